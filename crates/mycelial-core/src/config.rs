@@ -22,6 +22,7 @@ pub struct NodeConfig {
     pub logging: LoggingConfig,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for NodeConfig {
     fn default() -> Self {
         Self {
@@ -43,6 +44,7 @@ pub struct IdentityConfig {
     pub name: Option<String>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for IdentityConfig {
     fn default() -> Self {
         Self {
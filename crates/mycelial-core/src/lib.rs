@@ -63,7 +63,7 @@ pub use content::{Content, ContentId, ContentMetadata};
 pub use peer::{PeerId, PeerInfo};
 
 // Reputation re-exports
-pub use reputation::Reputation;
+pub use reputation::{Reputation, ReputationPolicy};
 
 // Credit re-exports
 pub use credit::CreditRelationship;
@@ -129,6 +129,19 @@ pub trait StateStore: Send + Sync {
 
     /// Update peer reputation
     async fn update_reputation(&self, id: &PeerId, reputation: &Reputation) -> Result<()>;
+
+    /// Store a credit relationship, replacing any between the same creditor and debtor
+    async fn store_credit_relationship(&self, relationship: &CreditRelationship) -> Result<()>;
+
+    /// Retrieve the credit relationship from `creditor` to `debtor`
+    async fn get_credit_relationship_between(
+        &self,
+        creditor: &PeerId,
+        debtor: &PeerId,
+    ) -> Result<Option<CreditRelationship>>;
+
+    /// List credit relationships where the peer is creditor or debtor
+    async fn list_credit_relationships_for(&self, id: &PeerId) -> Result<Vec<CreditRelationship>>;
 }
 
 /// Version information
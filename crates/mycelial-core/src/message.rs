@@ -1,10 +1,14 @@
 //! Message types for peer-to-peer communication
 
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Timelike, Utc};
 use uuid::Uuid;
+use crate::identity::{Keypair, KeypairExt, PublicKey, PublicKeyExt, SignatureBytes};
 use crate::peer::PeerId;
 
+/// Domain separator prefixed to the bytes a message signature covers
+const SIGNING_DOMAIN: &[u8] = b"mycelial/message/v1";
+
 /// A message in the mycelial network
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -16,6 +20,9 @@ pub struct Message {
     pub sender: PeerId,
     /// Optional specific recipient (None = broadcast)
     pub recipient: Option<PeerId>,
+    /// Recipients of a group message, which leaves `recipient` unset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<PeerId>,
     /// Message payload
     pub payload: Vec<u8>,
     /// When the message was created
@@ -51,6 +58,7 @@ impl Message {
             message_type,
             sender,
             recipient: None,
+            recipients: Vec::new(),
             payload,
             timestamp: Utc::now(),
             signature: None,
@@ -64,12 +72,132 @@ impl Message {
             message_type: MessageType::Direct,
             sender,
             recipient: Some(recipient),
+            recipients: Vec::new(),
+            payload,
+            timestamp: Utc::now(),
+            signature: None,
+        }
+    }
+
+    /// Create a direct message to several peers at once
+    ///
+    /// Duplicate recipients are dropped, keeping the first occurrence.
+    pub fn group(sender: PeerId, recipients: Vec<PeerId>, payload: Vec<u8>) -> Self {
+        let mut unique: Vec<PeerId> = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            if !unique.contains(&recipient) {
+                unique.push(recipient);
+            }
+        }
+        Self {
+            id: Uuid::new_v4(),
+            message_type: MessageType::Direct,
+            sender,
+            recipient: None,
+            recipients: unique,
             payload,
             timestamp: Utc::now(),
             signature: None,
         }
     }
 
+    /// Every peer the message is addressed to, empty for broadcasts
+    pub fn recipient_ids(&self) -> Vec<&PeerId> {
+        self.recipient.iter().chain(self.recipients.iter()).collect()
+    }
+
+    /// Identifier of the group a group message is sent within
+    ///
+    /// A hash of the sender and recipients in sorted order, so every member
+    /// writing to the same set of peers gets the same ID. `None` for
+    /// messages that aren't group messages.
+    pub fn group_id(&self) -> Option<String> {
+        if self.recipients.is_empty() {
+            return None;
+        }
+        let mut members: Vec<&str> = self.recipients.iter().chain([&self.sender]).map(PeerId::as_str).collect();
+        members.sort_unstable();
+        members.dedup();
+        Some(blake3::hash(members.join("\n").as_bytes()).to_hex().to_string())
+    }
+
+    /// Sign the message with the sender's keypair
+    ///
+    /// The timestamp is truncated to whole seconds first, since that is the
+    /// precision the store keeps; the signature then survives a round trip
+    /// through the database.
+    pub fn sign(&mut self, keypair: &Keypair) {
+        self.timestamp = self.timestamp.with_nanosecond(0).unwrap_or(self.timestamp);
+        let signature = keypair.sign_bytes(&self.signing_bytes());
+        self.signature = Some(signature.to_bytes().to_vec());
+    }
+
+    /// Check the signature against the given public key
+    ///
+    /// Returns false for unsigned messages, malformed signatures and any
+    /// message whose signed fields changed after signing.
+    pub fn verify(&self, public_key: &PublicKey) -> bool {
+        let Some(signature) = self.signature.as_deref() else {
+            return false;
+        };
+        let Ok(bytes) = <[u8; 64]>::try_from(signature) else {
+            return false;
+        };
+        // Sub-second precision isn't covered by the signature
+        if self.timestamp.nanosecond() != 0 {
+            return false;
+        }
+        public_key
+            .verify_bytes(&self.signing_bytes(), &SignatureBytes::from_bytes(bytes))
+            .is_ok()
+    }
+
+    /// Canonical bytes covered by the signature
+    ///
+    /// Layout, with all integers big-endian:
+    ///
+    /// | field          | encoding                                          |
+    /// |----------------|---------------------------------------------------|
+    /// | domain         | `b"mycelial/message/v1"`                          |
+    /// | `id`           | 16 raw UUID bytes                                 |
+    /// | `message_type` | 1 byte tag (see [`MessageType::signing_tag`])     |
+    /// | `sender`       | u32 length + UTF-8 bytes                          |
+    /// | `recipient`    | `0x00`, or `0x01` + u32 length + UTF-8 bytes      |
+    /// | `payload`      | u64 length + bytes                                |
+    /// | `timestamp`    | i64 Unix seconds                                  |
+    /// | `recipients`   | none if empty, else u32 count + each as `sender`  |
+    ///
+    /// Every variable-length field carries its length, so bytes can't be
+    /// shifted from one field to the next without changing the encoding.
+    /// Leaving out an empty `recipients` keeps signatures made before group
+    /// messages existed valid.
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            SIGNING_DOMAIN.len() + 16 + 1 + 4 + self.sender.0.len() + 5 + 8 + self.payload.len() + 8,
+        );
+        bytes.extend_from_slice(SIGNING_DOMAIN);
+        bytes.extend_from_slice(self.id.as_bytes());
+        bytes.push(self.message_type.signing_tag());
+        put_str(&mut bytes, &self.sender.0);
+        match &self.recipient {
+            None => bytes.push(0x00),
+            Some(recipient) => {
+                bytes.push(0x01);
+                put_str(&mut bytes, &recipient.0);
+            }
+        }
+        bytes.extend_from_slice(&(self.payload.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes.extend_from_slice(&self.timestamp.timestamp().to_be_bytes());
+        if !self.recipients.is_empty() {
+            bytes.extend_from_slice(&(self.recipients.len() as u32).to_be_bytes());
+            for recipient in &self.recipients {
+                put_str(&mut bytes, &recipient.0);
+            }
+        }
+        bytes
+    }
+
     /// Check if message is expired (older than max_age seconds)
     pub fn is_expired(&self, max_age_secs: i64) -> bool {
         let age = Utc::now().signed_duration_since(self.timestamp);
@@ -77,6 +205,50 @@ impl Message {
     }
 }
 
+impl std::str::FromStr for MessageType {
+    type Err = crate::MycelialError;
+
+    /// Parse the variant name, e.g. `Content`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Discovery" => Ok(MessageType::Discovery),
+            "Content" => Ok(MessageType::Content),
+            "Reputation" => Ok(MessageType::Reputation),
+            "Credit" => Ok(MessageType::Credit),
+            "Governance" => Ok(MessageType::Governance),
+            "Direct" => Ok(MessageType::Direct),
+            "System" => Ok(MessageType::System),
+            other => Err(crate::MycelialError::InvalidMessageFormat(format!(
+                "unknown message type: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl MessageType {
+    /// Stable one-byte tag used in the signed encoding
+    ///
+    /// Tags are part of the signature format; never renumber them.
+    fn signing_tag(&self) -> u8 {
+        match self {
+            MessageType::Discovery => 0,
+            MessageType::Content => 1,
+            MessageType::Reputation => 2,
+            MessageType::Credit => 3,
+            MessageType::Governance => 4,
+            MessageType::Direct => 5,
+            MessageType::System => 6,
+        }
+    }
+}
+
+/// Append a u32 length-prefixed string
+fn put_str(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,4 +266,102 @@ mod tests {
         assert_eq!(msg.message_type, MessageType::Content);
         assert!(msg.recipient.is_none());
     }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::generate();
+        let mut msg = Message::direct(
+            PeerId("sender".to_string()),
+            PeerId("recipient".to_string()),
+            b"Hello, world!".to_vec(),
+        );
+        assert!(!msg.verify(&keypair.public_key()));
+
+        msg.sign(&keypair);
+        assert!(msg.verify(&keypair.public_key()));
+
+        // Survives serialization
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: Message = serde_json::from_str(&json).unwrap();
+        assert!(decoded.verify(&keypair.public_key()));
+    }
+
+    #[test]
+    fn test_tampered_message_fails_verification() {
+        let keypair = Keypair::generate();
+        let mut msg = Message::new(MessageType::Content, PeerId("sender".to_string()), b"pay 10".to_vec());
+        msg.sign(&keypair);
+
+        let mut tampered = msg.clone();
+        tampered.payload = b"pay 99".to_vec();
+        assert!(!tampered.verify(&keypair.public_key()));
+
+        let mut retyped = msg.clone();
+        retyped.message_type = MessageType::Credit;
+        assert!(!retyped.verify(&keypair.public_key()));
+
+        let mut redirected = msg.clone();
+        redirected.recipient = Some(PeerId("someone".to_string()));
+        assert!(!redirected.verify(&keypair.public_key()));
+
+        let mut widened = msg.clone();
+        widened.recipients = vec![PeerId("someone".to_string())];
+        assert!(!widened.verify(&keypair.public_key()));
+
+        // Moving bytes between sender and payload changes the encoding
+        let mut shifted = msg;
+        shifted.sender = PeerId("sende".to_string());
+        shifted.payload = b"rpay 10".to_vec();
+        assert!(!shifted.verify(&keypair.public_key()));
+    }
+
+    #[test]
+    fn test_group_message() {
+        let keypair = Keypair::generate();
+        let alice = PeerId("alice".to_string());
+        let bob = PeerId("bob".to_string());
+        let mut msg = Message::group(
+            PeerId("sender".to_string()),
+            vec![alice.clone(), bob.clone(), alice.clone()],
+            b"hi all".to_vec(),
+        );
+        assert_eq!(msg.message_type, MessageType::Direct);
+        assert!(msg.recipient.is_none());
+        assert_eq!(msg.recipient_ids(), vec![&alice, &bob]);
+
+        msg.sign(&keypair);
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.recipients, vec![alice, bob]);
+        assert!(decoded.verify(&keypair.public_key()));
+    }
+
+    #[test]
+    fn test_group_id() {
+        let peer = |id: &str| PeerId(id.to_string());
+        let from_alice = Message::group(peer("alice"), vec![peer("bob"), peer("carol")], b"hi".to_vec());
+        let from_carol = Message::group(peer("carol"), vec![peer("alice"), peer("bob")], b"hey".to_vec());
+        let without_carol = Message::group(peer("alice"), vec![peer("bob")], b"psst".to_vec());
+
+        // Any member writing to the others reaches the same group
+        assert!(from_alice.group_id().is_some());
+        assert_eq!(from_alice.group_id(), from_carol.group_id());
+        assert_ne!(from_alice.group_id(), without_carol.group_id());
+
+        assert!(Message::direct(peer("alice"), peer("bob"), vec![]).group_id().is_none());
+    }
+
+    #[test]
+    fn test_wrong_key_fails_verification() {
+        let signer = Keypair::generate();
+        let other = Keypair::generate();
+        let mut msg = Message::new(MessageType::Content, PeerId("sender".to_string()), b"hi".to_vec());
+        msg.sign(&signer);
+
+        assert!(!msg.verify(&other.public_key()));
+
+        // A truncated signature is rejected rather than panicking
+        msg.signature.as_mut().unwrap().truncate(10);
+        assert!(!msg.verify(&signer.public_key()));
+    }
 }
@@ -57,6 +57,11 @@ impl From<PublicKey> for PeerId {
     }
 }
 
+/// Leading bytes of a libp2p peer id that inlines an ed25519 key: an
+/// identity multihash (code 0x00, 36 bytes) of the protobuf-encoded key
+/// (key type Ed25519, then 32 bytes of key data)
+const LIBP2P_ED25519_ID_PREFIX: [u8; 6] = [0x00, 0x24, 0x08, 0x01, 0x12, 0x20];
+
 /// Information about a peer in the network
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfo {
@@ -119,6 +124,26 @@ impl PeerInfo {
             .map_err(|_| crate::MycelialError::InvalidSignature)
     }
 
+    /// Check that `id` is the one derived from `public_key`
+    ///
+    /// Both id schemes in use are accepted: the base58 ed25519 key itself, and
+    /// the libp2p peer id inlining that key (which nodes also store as the
+    /// public key, since the key can be recovered from it).
+    pub fn verify_id(&self) -> bool {
+        if let Ok(key) = self.get_public_key() {
+            if PeerId::from_public_key(&key) == self.id {
+                return true;
+            }
+        }
+
+        let Some(key) = libp2p_ed25519_key(&self.public_key) else {
+            return false;
+        };
+        let mut libp2p_id = LIBP2P_ED25519_ID_PREFIX.to_vec();
+        libp2p_id.extend_from_slice(&key);
+        bs58::encode(libp2p_id).into_string() == self.id.0
+    }
+
     /// Update the last_seen timestamp
     pub fn touch(&mut self) {
         self.last_seen = Utc::now();
@@ -131,6 +156,17 @@ impl PeerInfo {
     }
 }
 
+/// Raw ed25519 key from a base58 key or libp2p peer id that inlines one
+fn libp2p_ed25519_key(encoded: &str) -> Option<[u8; 32]> {
+    let bytes = bs58::decode(encoded).into_vec().ok()?;
+    let key = match bytes.len() {
+        32 => &bytes[..],
+        38 if bytes.starts_with(&LIBP2P_ED25519_ID_PREFIX) => &bytes[LIBP2P_ED25519_ID_PREFIX.len()..],
+        _ => return None,
+    };
+    key.try_into().ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +219,47 @@ mod tests {
         assert_eq!(info.public_key, keypair.public_key().to_base58());
         assert_eq!(info.id.as_str(), keypair.public_key().to_base58());
     }
+
+    #[test]
+    fn test_verify_id() {
+        let (info, _) = PeerInfo::generate(vec![]);
+        assert!(info.verify_id());
+
+        // Someone else's id with our key
+        let (other, _) = PeerInfo::generate(vec![]);
+        let spoofed = PeerInfo { id: other.id, ..info.clone() };
+        assert!(!spoofed.verify_id());
+
+        let garbage = PeerInfo { public_key: "not-a-key".to_string(), ..info };
+        assert!(!garbage.verify_id());
+    }
+
+    #[test]
+    fn test_verify_libp2p_id() {
+        // A libp2p peer id for an ed25519 key, stored as both id and key
+        let mut bytes = LIBP2P_ED25519_ID_PREFIX.to_vec();
+        bytes.extend_from_slice(&[7u8; 32]);
+        let libp2p_id = bs58::encode(&bytes).into_string();
+        assert!(libp2p_id.starts_with("12D3KooW"));
+
+        let now = Utc::now();
+        let info = PeerInfo {
+            id: PeerId(libp2p_id.clone()),
+            public_key: libp2p_id,
+            addresses: vec![],
+            first_seen: now,
+            last_seen: now,
+            name: None,
+        };
+        assert!(info.verify_id());
+
+        // The raw key maps to the same libp2p id
+        let raw = PeerInfo { public_key: bs58::encode([7u8; 32]).into_string(), ..info.clone() };
+        assert!(raw.verify_id());
+
+        // One flipped key byte changes the id
+        bytes[10] ^= 1;
+        let tampered = PeerInfo { public_key: bs58::encode(&bytes).into_string(), ..info };
+        assert!(!tampered.verify_id());
+    }
 }
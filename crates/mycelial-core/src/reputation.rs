@@ -1,7 +1,7 @@
 //! Reputation scoring and trust management
 
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 /// Reputation score for a peer
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +95,52 @@ impl Reputation {
     }
 }
 
+/// How a score is derived from a peer's interaction counters
+///
+/// The default policy is the plain success ratio
+/// `successful / (successful + failed)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReputationPolicy {
+    /// How much a failure counts relative to a success (above 1.0 punishes
+    /// failures harder)
+    pub failure_weight: f64,
+    /// Counters this old are trusted half as much, pulling the score toward
+    /// neutral 0.5 (`None` ignores age)
+    pub recency_half_life: Option<Duration>,
+}
+
+impl Default for ReputationPolicy {
+    fn default() -> Self {
+        Self {
+            failure_weight: 1.0,
+            recency_half_life: None,
+        }
+    }
+}
+
+impl ReputationPolicy {
+    /// Score for the given counters, last updated `age` ago (0.0 to 1.0)
+    ///
+    /// Peers without interactions score a neutral 0.5.
+    pub fn score(&self, successful: u64, failed: u64, age: Duration) -> f64 {
+        let successful = successful as f64;
+        let failed = failed as f64 * self.failure_weight.max(0.0);
+        if successful + failed <= 0.0 {
+            return 0.5;
+        }
+        let ratio = successful / (successful + failed);
+
+        match self.recency_half_life {
+            Some(half_life) if half_life > Duration::zero() && age > Duration::zero() => {
+                let confidence =
+                    0.5f64.powf(age.num_milliseconds() as f64 / half_life.num_milliseconds() as f64);
+                (0.5 + (ratio - 0.5) * confidence).clamp(0.0, 1.0)
+            }
+            _ => ratio,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +158,33 @@ mod tests {
         rep.update(false, 0.4, 0.6);
         assert!(rep.score < rep.history.last().unwrap().score);
     }
+
+    #[test]
+    fn test_reputation_policy_weights() {
+        let plain = ReputationPolicy::default();
+        assert_eq!(plain.score(3, 1, Duration::zero()), 0.75);
+        assert_eq!(plain.score(0, 0, Duration::zero()), 0.5);
+
+        // Failures counting triple drag the same record down to 0.5
+        let strict = ReputationPolicy {
+            failure_weight: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(strict.score(3, 1, Duration::zero()), 0.5);
+        assert!(strict.score(9, 1, Duration::zero()) < plain.score(9, 1, Duration::zero()));
+        // Weighting can't help a peer that never failed
+        assert_eq!(strict.score(4, 0, Duration::zero()), plain.score(4, 0, Duration::zero()));
+    }
+
+    #[test]
+    fn test_reputation_policy_recency() {
+        let policy = ReputationPolicy {
+            recency_half_life: Some(Duration::hours(1)),
+            ..Default::default()
+        };
+        assert_eq!(policy.score(3, 1, Duration::zero()), 0.75);
+        // One half-life later the score is halfway back to neutral
+        assert!((policy.score(3, 1, Duration::hours(1)) - 0.625).abs() < 1e-9);
+        assert!((policy.score(1, 3, Duration::hours(1)) - 0.375).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,182 @@
+//! Per-peer bandwidth accounting
+//!
+//! libp2p's bandwidth metrics only report totals for the whole transport, but
+//! resource-contribution economics need bytes per peer. The transport output
+//! is wrapped in a muxer that counts bytes read from and written to every
+//! substream, attributed to the peer on the other end of the connection.
+//! Counts include everything above the multiplexer (protocol negotiation,
+//! identify, gossipsub, Kademlia) but not Noise/Yamux framing overhead.
+
+use futures::{AsyncRead, AsyncWrite};
+use libp2p::core::muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox};
+use libp2p::core::transport::Boxed;
+use libp2p::core::Transport;
+use libp2p::PeerId;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Byte counters for one peer, shared by all its connections
+#[derive(Debug, Default)]
+pub struct PeerBandwidth {
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+}
+
+impl PeerBandwidth {
+    /// Bytes received from the peer
+    pub fn inbound(&self) -> u64 {
+        self.inbound.load(Ordering::Relaxed)
+    }
+
+    /// Bytes sent to the peer
+    pub fn outbound(&self) -> u64 {
+        self.outbound.load(Ordering::Relaxed)
+    }
+}
+
+/// Bandwidth counters for every peer with an open connection
+#[derive(Debug, Default)]
+pub struct BandwidthTracker {
+    peers: Mutex<HashMap<PeerId, Arc<PeerBandwidth>>>,
+}
+
+impl BandwidthTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counters for a peer, created on first use
+    pub fn peer(&self, peer_id: &PeerId) -> Arc<PeerBandwidth> {
+        self.peers.lock().entry(*peer_id).or_default().clone()
+    }
+
+    /// Totals for a peer as `(bytes_in, bytes_out)`
+    pub fn totals(&self, peer_id: &PeerId) -> Option<(u64, u64)> {
+        self.peers
+            .lock()
+            .get(peer_id)
+            .map(|counters| (counters.inbound(), counters.outbound()))
+    }
+
+    /// Totals for every tracked peer
+    pub fn snapshot(&self) -> Vec<(PeerId, u64, u64)> {
+        self.peers
+            .lock()
+            .iter()
+            .map(|(peer_id, counters)| (*peer_id, counters.inbound(), counters.outbound()))
+            .collect()
+    }
+
+    /// Stop tracking a peer, returning its final totals
+    pub fn remove(&self, peer_id: &PeerId) -> Option<(u64, u64)> {
+        self.peers
+            .lock()
+            .remove(peer_id)
+            .map(|counters| (counters.inbound(), counters.outbound()))
+    }
+}
+
+/// Wrap a transport so every connection's traffic is counted per peer
+pub(crate) fn track_bandwidth(
+    transport: Boxed<(PeerId, StreamMuxerBox)>,
+    tracker: Arc<BandwidthTracker>,
+) -> Boxed<(PeerId, StreamMuxerBox)> {
+    transport
+        .map(move |(peer_id, muxer), _| {
+            let counters = tracker.peer(&peer_id);
+            (peer_id, StreamMuxerBox::new(CountingMuxer { inner: muxer, counters }))
+        })
+        .boxed()
+}
+
+/// Muxer whose substreams count the bytes passing through them
+struct CountingMuxer {
+    inner: StreamMuxerBox,
+    counters: Arc<PeerBandwidth>,
+}
+
+impl StreamMuxer for CountingMuxer {
+    type Substream = CountingStream;
+    type Error = io::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner
+            .poll_inbound_unpin(cx)
+            .map_ok(|inner| CountingStream { inner, counters: this.counters.clone() })
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner
+            .poll_outbound_unpin(cx)
+            .map_ok(|inner| CountingStream { inner, counters: this.counters.clone() })
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_close_unpin(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.get_mut().inner.poll_unpin(cx)
+    }
+}
+
+/// Substream that adds the bytes it reads and writes to its peer's counters
+struct CountingStream {
+    inner: SubstreamBox,
+    counters: Arc<PeerBandwidth>,
+}
+
+impl AsyncRead for CountingStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            this.counters.inbound.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for CountingStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            this.counters.outbound.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
@@ -4,12 +4,13 @@
 //! gossipsub, kademlia, identify, and mDNS protocols.
 
 use libp2p::{
+    connection_limits::{self, ConnectionLimits},
     gossipsub::{self, IdentTopic, MessageAuthenticity, MessageId, ValidationMode},
     identify,
     identity::Keypair,
     kad::{self, store::MemoryStore},
     mdns,
-    swarm::NetworkBehaviour,
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
     PeerId,
 };
 use sha2::{Digest, Sha256};
@@ -17,19 +18,25 @@ use std::time::Duration;
 
 use crate::config::NetworkConfig;
 use crate::error::NetworkError;
+use crate::gate;
+use crate::rate_limit::ConnectionRateLimiter;
 
 /// Combined network behaviour for the mycelial network
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "MycelialBehaviourEvent")]
 pub struct MycelialBehaviour {
+    /// Caps simultaneous connections at `max_connections`
+    pub limits: connection_limits::Behaviour,
+    /// Refuses blocked peers and peers over the connection rate limit
+    pub gate: gate::Behaviour,
     /// Gossipsub for pub/sub messaging
     pub gossipsub: gossipsub::Behaviour,
     /// Kademlia DHT for peer discovery and content routing
     pub kademlia: kad::Behaviour<MemoryStore>,
     /// Identify protocol for peer identification
     pub identify: identify::Behaviour,
-    /// mDNS for local peer discovery
-    pub mdns: mdns::tokio::Behaviour,
+    /// mDNS for local peer discovery (disabled unless `enable_mdns` is set)
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
 }
 
 /// Events emitted by the network behaviour
@@ -45,6 +52,13 @@ pub enum MycelialBehaviourEvent {
     Mdns(mdns::Event),
 }
 
+// Connection limits and the gate never emit events; they only deny connections
+impl From<void::Void> for MycelialBehaviourEvent {
+    fn from(event: void::Void) -> Self {
+        void::unreachable(event)
+    }
+}
+
 impl From<gossipsub::Event> for MycelialBehaviourEvent {
     fn from(event: gossipsub::Event) -> Self {
         MycelialBehaviourEvent::Gossipsub(event)
@@ -83,34 +97,56 @@ impl MycelialBehaviour {
         // Create Identify behaviour
         let identify = create_identify(keypair);
 
-        // Create mDNS behaviour
-        let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)
-            .map_err(|e| NetworkError::Config(e.to_string()))?;
+        // Create mDNS behaviour only when enabled, so nothing is announced
+        // on the LAN otherwise
+        let mdns = if config.enable_mdns {
+            Some(
+                mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)
+                    .map_err(|e| NetworkError::Config(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        let max_established = config
+            .max_connections
+            .map(|max| u32::try_from(max).unwrap_or(u32::MAX));
+        let limits = connection_limits::Behaviour::new(
+            ConnectionLimits::default().with_max_established(max_established),
+        );
+        let gate = gate::Behaviour::new(
+            config.max_connections_per_peer_per_minute.map(ConnectionRateLimiter::per_minute),
+        );
 
         Ok(Self {
+            limits,
+            gate,
             gossipsub,
             kademlia,
             identify,
-            mdns,
+            mdns: Toggle::from(mdns),
         })
     }
 
-    /// Subscribe to a gossipsub topic
-    pub fn subscribe(&mut self, topic: &str) -> crate::error::Result<()> {
+    /// Whether mDNS discovery is running
+    pub fn mdns_enabled(&self) -> bool {
+        self.mdns.is_enabled()
+    }
+
+    /// Subscribe to a gossipsub topic, returns false if already subscribed
+    pub fn subscribe(&mut self, topic: &str) -> crate::error::Result<bool> {
         let topic = IdentTopic::new(topic);
         self.gossipsub
             .subscribe(&topic)
-            .map_err(|e| NetworkError::Gossipsub(format!("Failed to subscribe: {:?}", e)))?;
-        Ok(())
+            .map_err(|e| NetworkError::Gossipsub(format!("Failed to subscribe: {:?}", e)))
     }
 
-    /// Unsubscribe from a gossipsub topic
-    pub fn unsubscribe(&mut self, topic: &str) -> crate::error::Result<()> {
+    /// Unsubscribe from a gossipsub topic, returns false if it wasn't subscribed
+    pub fn unsubscribe(&mut self, topic: &str) -> crate::error::Result<bool> {
         let topic = IdentTopic::new(topic);
         self.gossipsub
             .unsubscribe(&topic)
-            .map_err(|e| NetworkError::Gossipsub(format!("Failed to unsubscribe: {:?}", e)))?;
-        Ok(())
+            .map_err(|e| NetworkError::Gossipsub(format!("Failed to unsubscribe: {:?}", e)))
     }
 
     /// Publish a message to a gossipsub topic
@@ -121,6 +157,22 @@ impl MycelialBehaviour {
             .map_err(|e| NetworkError::Gossipsub(format!("Failed to publish: {:?}", e)))
     }
 
+    /// Publish to every connected peer subscribed to the topic, mesh or not
+    ///
+    /// Gossipsub always sends to explicit peers, so the topic's subscribers
+    /// are made explicit for the duration of this one publish.
+    pub fn flood_publish(&mut self, topic: &str, data: Vec<u8>) -> crate::error::Result<MessageId> {
+        let peers = self.all_peers_on_topic(topic);
+        for peer in &peers {
+            self.gossipsub.add_explicit_peer(peer);
+        }
+        let result = self.publish(topic, data);
+        for peer in &peers {
+            self.gossipsub.remove_explicit_peer(peer);
+        }
+        result
+    }
+
     /// Get the mesh peers for a specific topic
     /// Returns the list of peer IDs that are in the gossipsub mesh for this topic
     pub fn mesh_peers(&self, topic: &str) -> Vec<PeerId> {
@@ -187,8 +239,8 @@ impl MycelialBehaviour {
     }
 }
 
-/// Create a gossipsub behaviour with the given configuration
-fn create_gossipsub(keypair: &Keypair, config: &NetworkConfig) -> crate::error::Result<gossipsub::Behaviour> {
+/// Build the gossipsub configuration for a network configuration
+pub(crate) fn gossipsub_config(config: &NetworkConfig) -> crate::error::Result<gossipsub::Config> {
     // Message ID function based on content hash
     let message_id_fn = |message: &gossipsub::Message| {
         let mut hasher = Sha256::new();
@@ -203,8 +255,21 @@ fn create_gossipsub(keypair: &Keypair, config: &NetworkConfig) -> crate::error::
     // mesh_n: target number of peers in the mesh (default=6, lowered to 2)
     // mesh_n_low: minimum mesh peers before trying to add more (default=4, lowered to 1)
     // mesh_n_high: maximum mesh peers before pruning (default=12, lowered to 4)
-    let gossipsub_config = gossipsub::ConfigBuilder::default()
-        .heartbeat_interval(Duration::from_secs(1))
+    let mut builder = gossipsub::ConfigBuilder::default();
+    if let Some(heartbeat) = config.gossipsub_heartbeat {
+        if heartbeat.is_zero() {
+            return Err(NetworkError::Config("Gossipsub heartbeat must be non-zero".to_string()));
+        }
+        builder.heartbeat_interval(heartbeat);
+    }
+    if config.flood_publish_below.is_some() {
+        // Flooding happens per message instead, see `flood_publish`
+        builder.flood_publish(false);
+    }
+    if config.validate_messages {
+        builder.validate_messages();
+    }
+    builder
         .validation_mode(ValidationMode::Strict)
         .message_id_fn(message_id_fn)
         .max_transmit_size(config.max_message_size)
@@ -218,14 +283,27 @@ fn create_gossipsub(keypair: &Keypair, config: &NetworkConfig) -> crate::error::
         .history_gossip(3)
         .duplicate_cache_time(Duration::from_secs(60))
         .build()
-        .map_err(|e| NetworkError::Config(format!("Gossipsub config error: {}", e)))?;
+        .map_err(|e| NetworkError::Config(format!("Gossipsub config error: {}", e)))
+}
+
+/// Create a gossipsub behaviour with the given configuration
+fn create_gossipsub(keypair: &Keypair, config: &NetworkConfig) -> crate::error::Result<gossipsub::Behaviour> {
+    let gossipsub_config = gossipsub_config(config)?;
 
     // Create behaviour with signing using the keypair
-    gossipsub::Behaviour::new(
+    let mut gossipsub = gossipsub::Behaviour::new(
         MessageAuthenticity::Signed(keypair.clone()),
         gossipsub_config,
     )
-    .map_err(|e| NetworkError::Config(format!("Gossipsub creation error: {}", e)))
+    .map_err(|e| NetworkError::Config(format!("Gossipsub creation error: {}", e)))?;
+
+    // Peer scoring, so reputation can demote peers (see `scoring`)
+    let (score_params, score_thresholds) = crate::scoring::peer_score_config();
+    gossipsub
+        .with_peer_score(score_params, score_thresholds)
+        .map_err(|e| NetworkError::Config(format!("Gossipsub peer score error: {}", e)))?;
+
+    Ok(gossipsub)
 }
 
 /// Create a Kademlia behaviour
@@ -268,6 +346,11 @@ pub mod topics {
     pub const GOVERNANCE: &str = "/mycelial/1.0.0/governance";
     /// System messages (peer discovery, health)
     pub const SYSTEM: &str = "/mycelial/1.0.0/system";
+    /// State digests exchanged between nodes
+    pub const SYNC: &str = "/mycelial/1.0.0/sync";
+    /// Liveness heartbeats, handled by the network service and never
+    /// delivered to the application
+    pub const HEARTBEAT: &str = "/mycelial/1.0.0/heartbeat";
 
     /// Get all standard topics
     pub fn all() -> Vec<&'static str> {
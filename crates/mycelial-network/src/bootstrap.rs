@@ -0,0 +1,287 @@
+//! Bootstrap peer reconnection
+//!
+//! Bootstrap peers are the configured entry points into the network, so a
+//! node that can't reach them may never find anyone else. Rather than giving
+//! up after the first failed dial, each address is retried with exponential
+//! backoff (capped at a maximum delay) until it connects or the attempt cap
+//! is reached. A successful connection resets the backoff, and losing that
+//! connection later starts a fresh round of attempts, so nodes recover when
+//! a bootstrap node restarts.
+
+use libp2p::swarm::ConnectionId;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Dial state for a single bootstrap address
+#[derive(Debug, Clone)]
+struct BootstrapTarget {
+    /// Address to dial
+    addr: Multiaddr,
+    /// Consecutive failed attempts since the last success
+    attempts: u32,
+    /// When the next attempt is due (None while dialing, connected or exhausted)
+    next_attempt: Option<Instant>,
+    /// Peer reached at this address, once connected
+    connected: Option<PeerId>,
+}
+
+/// What to do after a bootstrap dial failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootstrapFailure {
+    /// Another attempt is scheduled after `delay`
+    Retry {
+        /// Attempts made so far
+        attempt: u32,
+        /// Wait before the next attempt
+        delay: Duration,
+    },
+    /// The attempt cap was reached; no further dials until reconnected elsewhere
+    Exhausted {
+        /// Attempts made
+        attempts: u32,
+    },
+}
+
+/// Schedules backoff-limited dials of the configured bootstrap addresses
+#[derive(Debug)]
+pub struct BootstrapDialer {
+    /// Configured bootstrap addresses
+    targets: Vec<BootstrapTarget>,
+    /// Outstanding dials, mapped to their target
+    in_flight: HashMap<ConnectionId, usize>,
+    /// Maximum consecutive attempts per address (at least one)
+    max_attempts: u32,
+    /// Delay after the first failure; doubles on each further failure
+    base_delay: Duration,
+    /// Upper bound for the delay between attempts
+    max_delay: Duration,
+}
+
+impl BootstrapDialer {
+    /// Create a dialer whose first attempts are all due at `now`
+    pub fn new(
+        addrs: Vec<Multiaddr>,
+        max_attempts: u32,
+        base_delay: Duration,
+        max_delay: Duration,
+        now: Instant,
+    ) -> Self {
+        let targets = addrs
+            .into_iter()
+            .map(|addr| BootstrapTarget {
+                addr,
+                attempts: 0,
+                next_attempt: Some(now),
+                connected: None,
+            })
+            .collect();
+
+        Self {
+            targets,
+            in_flight: HashMap::new(),
+            max_attempts: max_attempts.max(1),
+            base_delay,
+            max_delay,
+        }
+    }
+
+    /// Delay before the attempt following `failures` consecutive failures
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(16);
+        (self.base_delay * 2u32.pow(exponent)).min(self.max_delay)
+    }
+
+    /// Earliest time an attempt is due, if any
+    pub fn next_due(&self) -> Option<Instant> {
+        self.targets.iter().filter_map(|t| t.next_attempt).min()
+    }
+
+    /// Take the targets whose next attempt is due
+    ///
+    /// Each returned index must be reported back through [`Self::dialing`] or
+    /// [`Self::dial_not_started`].
+    pub fn due(&mut self, now: Instant) -> Vec<(usize, Multiaddr)> {
+        let mut due = Vec::new();
+        for (index, target) in self.targets.iter_mut().enumerate() {
+            if target.next_attempt.is_some_and(|at| at <= now) {
+                target.next_attempt = None;
+                target.attempts += 1;
+                due.push((index, target.addr.clone()));
+            }
+        }
+        due
+    }
+
+    /// Record the connection id of a dial started for a target
+    pub fn dialing(&mut self, index: usize, connection_id: ConnectionId) {
+        self.in_flight.insert(connection_id, index);
+    }
+
+    /// Record a dial the swarm refused to start, counting it as a failure
+    pub fn dial_not_started(
+        &mut self,
+        index: usize,
+        now: Instant,
+    ) -> Option<(Multiaddr, BootstrapFailure)> {
+        self.fail(index, now)
+    }
+
+    /// Handle an established connection, returns true if it was a bootstrap dial
+    pub fn on_connected(&mut self, connection_id: ConnectionId, peer_id: PeerId) -> bool {
+        let Some(index) = self.in_flight.remove(&connection_id) else {
+            return false;
+        };
+        let target = &mut self.targets[index];
+        target.attempts = 0;
+        target.next_attempt = None;
+        target.connected = Some(peer_id);
+        true
+    }
+
+    /// Handle a failed dial, or None if it wasn't a bootstrap dial
+    pub fn on_dial_failed(
+        &mut self,
+        connection_id: ConnectionId,
+        now: Instant,
+    ) -> Option<(Multiaddr, BootstrapFailure)> {
+        let index = self.in_flight.remove(&connection_id)?;
+        self.fail(index, now)
+    }
+
+    /// Handle a peer's last connection closing, scheduling a fresh round of
+    /// attempts for any bootstrap address it was reached at
+    ///
+    /// Returns true if a dial was scheduled.
+    pub fn on_disconnected(&mut self, peer_id: &PeerId, now: Instant) -> bool {
+        let mut scheduled = false;
+        for target in &mut self.targets {
+            if target.connected.as_ref() == Some(peer_id) {
+                target.connected = None;
+                target.attempts = 0;
+                target.next_attempt = Some(now);
+                scheduled = true;
+            }
+        }
+        scheduled
+    }
+
+    /// Number of configured bootstrap addresses
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Check if no bootstrap addresses are configured
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    fn fail(&mut self, index: usize, now: Instant) -> Option<(Multiaddr, BootstrapFailure)> {
+        let attempts = self.targets.get(index)?.attempts;
+        let failure = if attempts >= self.max_attempts {
+            BootstrapFailure::Exhausted { attempts }
+        } else {
+            BootstrapFailure::Retry {
+                attempt: attempts,
+                delay: self.backoff(attempts),
+            }
+        };
+
+        let target = &mut self.targets[index];
+        target.next_attempt = match failure {
+            BootstrapFailure::Retry { delay, .. } => Some(now + delay),
+            BootstrapFailure::Exhausted { .. } => None,
+        };
+        Some((target.addr.clone(), failure))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn dialer(max_attempts: u32, now: Instant) -> BootstrapDialer {
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/9000".parse().unwrap();
+        BootstrapDialer::new(
+            vec![addr],
+            max_attempts,
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            now,
+        )
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let dialer = dialer(10, Instant::now());
+        let delays: Vec<u64> = (1..=5).map(|n| dialer.backoff(n).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn test_retries_until_exhausted() {
+        let now = Instant::now();
+        let mut dialer = dialer(3, now);
+
+        let mut failures = Vec::new();
+        let mut at = now;
+        loop {
+            let due = dialer.due(at);
+            assert_eq!(due.len(), 1);
+            let id = ConnectionId::new_unchecked(failures.len());
+            dialer.dialing(due[0].0, id);
+
+            let (_, failure) = dialer.on_dial_failed(id, at).unwrap();
+            failures.push(failure);
+            match failure {
+                BootstrapFailure::Retry { delay, .. } => {
+                    // Not due again until the backoff has elapsed
+                    assert!(dialer.due(at).is_empty());
+                    at += delay;
+                }
+                BootstrapFailure::Exhausted { .. } => break,
+            }
+        }
+
+        assert_eq!(
+            failures,
+            vec![
+                BootstrapFailure::Retry { attempt: 1, delay: Duration::from_secs(1) },
+                BootstrapFailure::Retry { attempt: 2, delay: Duration::from_secs(2) },
+                BootstrapFailure::Exhausted { attempts: 3 },
+            ]
+        );
+        assert_eq!(dialer.next_due(), None);
+    }
+
+    #[test]
+    fn test_success_resets_and_disconnect_redials() {
+        let now = Instant::now();
+        let mut dialer = dialer(3, now);
+        let peer = Keypair::generate_ed25519().public().to_peer_id();
+
+        // One failure, then success
+        let (index, _) = dialer.due(now)[0].clone();
+        dialer.dialing(index, ConnectionId::new_unchecked(1));
+        dialer.on_dial_failed(ConnectionId::new_unchecked(1), now);
+        let later = now + Duration::from_secs(1);
+        let (index, _) = dialer.due(later)[0].clone();
+        dialer.dialing(index, ConnectionId::new_unchecked(2));
+        assert!(dialer.on_connected(ConnectionId::new_unchecked(2), peer));
+        assert_eq!(dialer.next_due(), None);
+
+        // Unrelated connections are ignored
+        assert!(!dialer.on_connected(ConnectionId::new_unchecked(3), peer));
+        assert_eq!(dialer.on_dial_failed(ConnectionId::new_unchecked(3), later), None);
+
+        // Losing the bootstrap peer starts over from the first attempt
+        assert!(dialer.on_disconnected(&peer, later));
+        assert_eq!(dialer.due(later).len(), 1);
+        dialer.dialing(0, ConnectionId::new_unchecked(4));
+        assert_eq!(
+            dialer.on_dial_failed(ConnectionId::new_unchecked(4), later).map(|(_, f)| f),
+            Some(BootstrapFailure::Retry { attempt: 1, delay: Duration::from_secs(1) })
+        );
+    }
+}
@@ -1,8 +1,58 @@
 //! Network configuration types
 
+use libp2p::{multiaddr::Protocol, Multiaddr};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use std::time::Duration;
 
+/// Which transports the node listens and dials on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportSelection {
+    /// TCP with Noise and Yamux only
+    TcpOnly,
+    /// QUIC (UDP) only
+    QuicOnly,
+    /// Both TCP and QUIC
+    #[default]
+    Both,
+}
+
+impl TransportSelection {
+    /// Check if TCP is selected
+    pub fn uses_tcp(&self) -> bool {
+        matches!(self, TransportSelection::TcpOnly | TransportSelection::Both)
+    }
+
+    /// Check if QUIC is selected
+    pub fn uses_quic(&self) -> bool {
+        matches!(self, TransportSelection::QuicOnly | TransportSelection::Both)
+    }
+
+    /// Check if a listen address runs over a selected transport
+    pub fn supports(&self, addr: &Multiaddr) -> bool {
+        let is_quic = addr
+            .iter()
+            .any(|p| matches!(p, Protocol::Quic | Protocol::QuicV1));
+        let is_tcp = addr.iter().any(|p| matches!(p, Protocol::Tcp(_)));
+
+        (is_quic && self.uses_quic()) || (is_tcp && self.uses_tcp())
+    }
+}
+
+impl FromStr for TransportSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" | "tcp-only" | "tcp_only" => Ok(TransportSelection::TcpOnly),
+            "quic" | "quic-only" | "quic_only" => Ok(TransportSelection::QuicOnly),
+            "both" => Ok(TransportSelection::Both),
+            other => Err(format!("unknown transport '{}' (expected tcp, quic or both)", other)),
+        }
+    }
+}
+
 /// Network configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
@@ -14,16 +64,108 @@ pub struct NetworkConfig {
     pub enable_mdns: bool,
     /// Enable Kademlia DHT
     pub enable_kademlia: bool,
-    /// Maximum number of connections
-    pub max_connections: u32,
+    /// Maximum simultaneous established connections (None is unlimited)
+    #[serde(default = "default_max_connections")]
+    pub max_connections: Option<usize>,
     /// Maximum message size in bytes
     pub max_message_size: usize,
     /// Connection idle timeout in seconds
     pub idle_timeout_secs: u64,
-    /// Enable TCP transport
-    pub enable_tcp: bool,
-    /// Enable QUIC transport
-    pub enable_quic: bool,
+    /// Transports to listen and dial on
+    #[serde(default)]
+    pub transports: TransportSelection,
+    /// Maximum redial attempts for a disconnected trusted peer (0 disables)
+    pub redial_max_attempts: u32,
+    /// Initial delay between redial attempts in milliseconds (doubles per failure)
+    pub redial_base_delay_ms: u64,
+    /// Maximum consecutive dial attempts per bootstrap peer before reporting failure
+    #[serde(default = "default_bootstrap_max_attempts")]
+    pub bootstrap_max_attempts: u32,
+    /// Delay before retrying a failed bootstrap dial in milliseconds (doubles per failure)
+    #[serde(default = "default_bootstrap_base_delay_ms")]
+    pub bootstrap_base_delay_ms: u64,
+    /// Upper bound for the bootstrap retry delay in milliseconds
+    #[serde(default = "default_bootstrap_max_delay_ms")]
+    pub bootstrap_max_delay_ms: u64,
+    /// Maximum connections accepted from one peer per minute (None disables)
+    #[serde(default)]
+    pub max_connections_per_peer_per_minute: Option<u32>,
+    /// How long received message IDs are remembered to drop duplicates
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: Duration,
+    /// Seconds between per-peer bandwidth reports (0 disables)
+    #[serde(default = "default_bandwidth_report_interval_secs")]
+    pub bandwidth_report_interval_secs: u64,
+    /// Seconds between checks for subscribed topics with an empty mesh (0 disables)
+    #[serde(default = "default_topic_health_interval_secs")]
+    pub topic_health_interval_secs: u64,
+    /// Topics this node may subscribe to and receive (`None` allows all);
+    /// an entry ending in `*` matches every topic with that prefix
+    #[serde(default)]
+    pub allowed_topics: Option<Vec<String>>,
+    /// Gossipsub heartbeat interval (`None` keeps the libp2p default of 1s)
+    ///
+    /// Each heartbeat grafts and prunes mesh peers and emits gossip. A short
+    /// interval repairs meshes faster, which suits small low-latency test
+    /// networks, at the cost of more control traffic per peer; large networks
+    /// may want a longer one.
+    #[serde(default)]
+    pub gossipsub_heartbeat: Option<Duration>,
+    /// Publish to every subscribed peer when a topic's mesh has fewer peers
+    /// than this (`None` keeps the libp2p default of always flooding)
+    ///
+    /// Setting it switches gossipsub to publishing through the mesh only,
+    /// which saves bandwidth on well-connected topics, and falls back to
+    /// flooding where the mesh is too sparse to be relied on.
+    #[serde(default)]
+    pub flood_publish_below: Option<usize>,
+    /// Seconds between liveness heartbeats to connected peers (0 disables)
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    /// Heartbeat intervals a peer may stay silent before it is reported unresponsive
+    #[serde(default = "default_heartbeat_miss_threshold")]
+    pub heartbeat_miss_threshold: u32,
+    /// Forward received messages only once the application accepts them with
+    /// [`NetworkHandle::report_message`](crate::NetworkHandle::report_message)
+    /// (false forwards them on receipt)
+    #[serde(default)]
+    pub validate_messages: bool,
+}
+
+fn default_max_connections() -> Option<usize> {
+    Some(100)
+}
+
+fn default_dedup_window() -> Duration {
+    Duration::from_secs(120)
+}
+
+fn default_bootstrap_max_attempts() -> u32 {
+    10
+}
+
+fn default_bootstrap_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_bootstrap_max_delay_ms() -> u64 {
+    60_000
+}
+
+fn default_bandwidth_report_interval_secs() -> u64 {
+    10
+}
+
+fn default_topic_health_interval_secs() -> u64 {
+    30
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    15
+}
+
+fn default_heartbeat_miss_threshold() -> u32 {
+    3
 }
 
 impl Default for NetworkConfig {
@@ -36,11 +178,25 @@ impl Default for NetworkConfig {
             bootstrap_peers: Vec::new(),
             enable_mdns: true,
             enable_kademlia: true,
-            max_connections: 100,
+            max_connections: default_max_connections(),
             max_message_size: 1024 * 1024, // 1 MB
             idle_timeout_secs: 30,
-            enable_tcp: true,
-            enable_quic: true,
+            transports: TransportSelection::Both,
+            redial_max_attempts: 5,
+            redial_base_delay_ms: 1000,
+            bootstrap_max_attempts: default_bootstrap_max_attempts(),
+            bootstrap_base_delay_ms: default_bootstrap_base_delay_ms(),
+            bootstrap_max_delay_ms: default_bootstrap_max_delay_ms(),
+            max_connections_per_peer_per_minute: None,
+            dedup_window: default_dedup_window(),
+            bandwidth_report_interval_secs: default_bandwidth_report_interval_secs(),
+            topic_health_interval_secs: default_topic_health_interval_secs(),
+            allowed_topics: None,
+            gossipsub_heartbeat: None,
+            flood_publish_below: None,
+            heartbeat_interval_secs: default_heartbeat_interval_secs(),
+            heartbeat_miss_threshold: default_heartbeat_miss_threshold(),
+            validate_messages: false,
         }
     }
 }
@@ -53,11 +209,25 @@ impl NetworkConfig {
             bootstrap_peers: Vec::new(),
             enable_mdns: true,
             enable_kademlia: true,
-            max_connections: 50,
+            max_connections: Some(50),
             max_message_size: 1024 * 1024,
             idle_timeout_secs: 30,
-            enable_tcp: true,
-            enable_quic: false, // Simpler for testing
+            transports: TransportSelection::TcpOnly, // Simpler for testing
+            redial_max_attempts: 5,
+            redial_base_delay_ms: 1000,
+            bootstrap_max_attempts: default_bootstrap_max_attempts(),
+            bootstrap_base_delay_ms: default_bootstrap_base_delay_ms(),
+            bootstrap_max_delay_ms: default_bootstrap_max_delay_ms(),
+            max_connections_per_peer_per_minute: None,
+            dedup_window: default_dedup_window(),
+            bandwidth_report_interval_secs: default_bandwidth_report_interval_secs(),
+            topic_health_interval_secs: default_topic_health_interval_secs(),
+            allowed_topics: None,
+            gossipsub_heartbeat: None,
+            flood_publish_below: None,
+            heartbeat_interval_secs: default_heartbeat_interval_secs(),
+            heartbeat_miss_threshold: default_heartbeat_miss_threshold(),
+            validate_messages: false,
         }
     }
 
@@ -65,4 +235,56 @@ impl NetworkConfig {
     pub fn idle_timeout(&self) -> Duration {
         Duration::from_secs(self.idle_timeout_secs)
     }
+
+    /// Get the bandwidth report interval, if reporting is enabled
+    pub fn bandwidth_report_interval(&self) -> Option<Duration> {
+        (self.bandwidth_report_interval_secs > 0)
+            .then(|| Duration::from_secs(self.bandwidth_report_interval_secs))
+    }
+
+    /// Get the topic health check interval, if checks are enabled
+    pub fn topic_health_interval(&self) -> Option<Duration> {
+        (self.topic_health_interval_secs > 0)
+            .then(|| Duration::from_secs(self.topic_health_interval_secs))
+    }
+
+    /// Get the liveness heartbeat interval, if heartbeats are enabled
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        (self.heartbeat_interval_secs > 0)
+            .then(|| Duration::from_secs(self.heartbeat_interval_secs))
+    }
+
+    /// Check a topic against the allow-list
+    pub fn is_topic_allowed(&self, topic: &str) -> bool {
+        topic_allowed(self.allowed_topics.as_deref(), topic)
+    }
+
+    /// Get the initial redial delay as a Duration
+    pub fn redial_base_delay(&self) -> Duration {
+        Duration::from_millis(self.redial_base_delay_ms)
+    }
+
+    /// Get the initial bootstrap retry delay as a Duration
+    pub fn bootstrap_base_delay(&self) -> Duration {
+        Duration::from_millis(self.bootstrap_base_delay_ms)
+    }
+
+    /// Get the maximum bootstrap retry delay as a Duration
+    pub fn bootstrap_max_delay(&self) -> Duration {
+        Duration::from_millis(self.bootstrap_max_delay_ms)
+    }
+}
+
+/// Check a topic against an allow-list, `None` allowing everything
+///
+/// Entries match exactly, except that a trailing `*` matches any topic
+/// starting with the rest of the entry (`/mycelial/1.0.0/*`).
+pub(crate) fn topic_allowed(allowed: Option<&[String]>, topic: &str) -> bool {
+    let Some(allowed) = allowed else {
+        return true;
+    };
+    allowed.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => topic == pattern,
+    })
 }
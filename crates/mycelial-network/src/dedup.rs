@@ -0,0 +1,133 @@
+//! Deduplication of received gossipsub messages
+//!
+//! Gossipsub already suppresses duplicates for its own duplicate-cache
+//! lifetime, but a message that reaches us again after that (or through a
+//! republish) would be delivered twice. The deduplicator remembers message IDs
+//! for a configurable window and drops repeats before they reach the
+//! application. Memory is bounded: once the capacity is reached the oldest IDs
+//! are forgotten early.
+
+use libp2p::gossipsub::MessageId;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Default number of message IDs remembered
+pub const DEFAULT_DEDUP_CAPACITY: usize = 10_000;
+
+/// Time-windowed set of recently seen message IDs
+#[derive(Debug)]
+pub struct MessageDeduplicator {
+    /// When each remembered ID was first seen
+    seen: HashMap<MessageId, Instant>,
+    /// IDs in the order they were first seen, oldest first
+    order: VecDeque<(MessageId, Instant)>,
+    /// How long an ID is remembered
+    window: Duration,
+    /// Maximum IDs remembered at once
+    capacity: usize,
+    /// Duplicates dropped so far
+    duplicates: u64,
+}
+
+impl MessageDeduplicator {
+    /// Create a deduplicator remembering IDs for `window`
+    pub fn new(window: Duration) -> Self {
+        Self::with_capacity(window, DEFAULT_DEDUP_CAPACITY)
+    }
+
+    /// Create a deduplicator with an explicit capacity
+    pub fn with_capacity(window: Duration, capacity: usize) -> Self {
+        Self {
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            window,
+            capacity: capacity.max(1),
+            duplicates: 0,
+        }
+    }
+
+    /// Record a message ID, returns false if it was already seen in the window
+    pub fn check(&mut self, message_id: &MessageId, now: Instant) -> bool {
+        self.expire(now);
+
+        if self.seen.contains_key(message_id) {
+            self.duplicates += 1;
+            return false;
+        }
+
+        while self.order.len() >= self.capacity {
+            if let Some((oldest, _)) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        self.seen.insert(message_id.clone(), now);
+        self.order.push_back((message_id.clone(), now));
+        true
+    }
+
+    /// Number of duplicates dropped so far
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Number of IDs currently remembered
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Check if no IDs are remembered
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Forget IDs that left the window
+    fn expire(&mut self, now: Instant) {
+        while let Some((id, seen_at)) = self.order.front() {
+            if now.saturating_duration_since(*seen_at) < self.window {
+                break;
+            }
+            self.seen.remove(id);
+            self.order.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(s: &str) -> MessageId {
+        MessageId::from(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_duplicates_dropped_within_window() {
+        let mut dedup = MessageDeduplicator::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(dedup.check(&id("a"), start));
+        assert!(!dedup.check(&id("a"), start + Duration::from_secs(30)));
+        assert!(dedup.check(&id("b"), start + Duration::from_secs(30)));
+        assert_eq!(dedup.duplicates(), 1);
+
+        // Once the window has passed the ID is accepted again
+        assert!(dedup.check(&id("a"), start + Duration::from_secs(60)));
+        assert_eq!(dedup.duplicates(), 1);
+    }
+
+    #[test]
+    fn test_capacity_bound() {
+        let mut dedup = MessageDeduplicator::with_capacity(Duration::from_secs(60), 2);
+        let now = Instant::now();
+
+        assert!(dedup.check(&id("a"), now));
+        assert!(dedup.check(&id("b"), now));
+        assert!(dedup.check(&id("c"), now));
+        assert_eq!(dedup.len(), 2);
+
+        // "a" was evicted to make room
+        assert!(dedup.check(&id("a"), now));
+        assert!(!dedup.check(&id("c"), now));
+    }
+}
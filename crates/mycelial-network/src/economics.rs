@@ -7,7 +7,7 @@
 //! - Resource: Resource sharing metrics
 
 use mycelial_protocol::{
-    topics,
+    topics, MessageCodec, PayloadCodec,
     VouchMessage, CreditMessage, GovernanceMessage, ResourceMessage,
 };
 use tokio::sync::broadcast;
@@ -36,13 +36,21 @@ pub struct EconomicsHandler {
     network: NetworkHandle,
     /// Event sender for economics events
     event_tx: broadcast::Sender<EconomicsEvent>,
+    /// Encoding for published messages; either is accepted on receipt
+    codec: PayloadCodec,
 }
 
 impl EconomicsHandler {
     /// Create a new economics handler
     pub fn new(network: NetworkHandle) -> (Self, broadcast::Receiver<EconomicsEvent>) {
         let (event_tx, event_rx) = broadcast::channel(256);
-        (Self { network, event_tx }, event_rx)
+        (Self { network, event_tx, codec: PayloadCodec::default() }, event_rx)
+    }
+
+    /// Publish with the given codec instead of JSON
+    pub fn with_codec(mut self, codec: PayloadCodec) -> Self {
+        self.codec = codec;
+        self
     }
 
     /// Handle a network event, parsing economics messages
@@ -50,7 +58,7 @@ impl EconomicsHandler {
         if let NetworkEvent::MessageReceived { topic, data, .. } = event {
             match topic.as_str() {
                 t if t == topics::VOUCH => {
-                    match serde_json::from_slice::<VouchMessage>(data) {
+                    match self.codec.decode::<VouchMessage>(data) {
                         Ok(msg) => {
                             debug!("Received vouch message: {:?}", msg);
                             let event = EconomicsEvent::Vouch(msg);
@@ -61,7 +69,7 @@ impl EconomicsHandler {
                     }
                 }
                 t if t == topics::CREDIT => {
-                    match serde_json::from_slice::<CreditMessage>(data) {
+                    match self.codec.decode::<CreditMessage>(data) {
                         Ok(msg) => {
                             debug!("Received credit message: {:?}", msg);
                             let event = EconomicsEvent::Credit(msg);
@@ -72,7 +80,7 @@ impl EconomicsHandler {
                     }
                 }
                 t if t == topics::GOVERNANCE => {
-                    match serde_json::from_slice::<GovernanceMessage>(data) {
+                    match self.codec.decode::<GovernanceMessage>(data) {
                         Ok(msg) => {
                             debug!("Received governance message: {:?}", msg);
                             let event = EconomicsEvent::Governance(msg);
@@ -83,7 +91,7 @@ impl EconomicsHandler {
                     }
                 }
                 t if t == topics::RESOURCE => {
-                    match serde_json::from_slice::<ResourceMessage>(data) {
+                    match self.codec.decode::<ResourceMessage>(data) {
                         Ok(msg) => {
                             debug!("Received resource message: {:?}", msg);
                             let event = EconomicsEvent::Resource(msg);
@@ -101,53 +109,57 @@ impl EconomicsHandler {
 
     /// Publish a vouch message
     pub async fn publish_vouch(&self, msg: &VouchMessage) -> Result<()> {
-        let data = serde_json::to_vec(msg)
+        let data = self.codec.encode(msg)
             .map_err(|e| NetworkError::Serialization(e.to_string()))?;
-        self.network.publish(topics::VOUCH, data).await
+        self.network.publish(topics::VOUCH, data).await?;
+        Ok(())
     }
 
     /// Publish a credit message
     pub async fn publish_credit(&self, msg: &CreditMessage) -> Result<()> {
-        let data = serde_json::to_vec(msg)
+        let data = self.codec.encode(msg)
             .map_err(|e| NetworkError::Serialization(e.to_string()))?;
-        self.network.publish(topics::CREDIT, data).await
+        self.network.publish(topics::CREDIT, data).await?;
+        Ok(())
     }
 
     /// Publish a governance message
     pub async fn publish_governance(&self, msg: &GovernanceMessage) -> Result<()> {
-        let data = serde_json::to_vec(msg)
+        let data = self.codec.encode(msg)
             .map_err(|e| NetworkError::Serialization(e.to_string()))?;
-        self.network.publish(topics::GOVERNANCE, data).await
+        self.network.publish(topics::GOVERNANCE, data).await?;
+        Ok(())
     }
 
     /// Publish a resource message
     pub async fn publish_resource(&self, msg: &ResourceMessage) -> Result<()> {
-        let data = serde_json::to_vec(msg)
+        let data = self.codec.encode(msg)
             .map_err(|e| NetworkError::Serialization(e.to_string()))?;
-        self.network.publish(topics::RESOURCE, data).await
+        self.network.publish(topics::RESOURCE, data).await?;
+        Ok(())
     }
 }
 
-/// Parse a network message into an economics event
+/// Parse a network message into an economics event, in either payload codec
 pub fn parse_economics_message(topic: &str, data: &[u8]) -> Option<EconomicsEvent> {
     match topic {
         t if t == topics::VOUCH => {
-            serde_json::from_slice::<VouchMessage>(data)
+            PayloadCodec::default().decode::<VouchMessage>(data)
                 .ok()
                 .map(EconomicsEvent::Vouch)
         }
         t if t == topics::CREDIT => {
-            serde_json::from_slice::<CreditMessage>(data)
+            PayloadCodec::default().decode::<CreditMessage>(data)
                 .ok()
                 .map(EconomicsEvent::Credit)
         }
         t if t == topics::GOVERNANCE => {
-            serde_json::from_slice::<GovernanceMessage>(data)
+            PayloadCodec::default().decode::<GovernanceMessage>(data)
                 .ok()
                 .map(EconomicsEvent::Governance)
         }
         t if t == topics::RESOURCE => {
-            serde_json::from_slice::<ResourceMessage>(data)
+            PayloadCodec::default().decode::<ResourceMessage>(data)
                 .ok()
                 .map(EconomicsEvent::Resource)
         }
@@ -224,6 +236,13 @@ mod tests {
         } else {
             panic!("Wrong variant");
         }
+
+        // Nodes publishing bincode are understood too
+        let data = PayloadCodec::Bincode.encode(&msg).unwrap();
+        assert!(matches!(
+            parse_economics_message(topics::CREDIT, &data),
+            Some(EconomicsEvent::Credit(CreditMessage::CreateLine(_)))
+        ));
     }
 
     #[test]
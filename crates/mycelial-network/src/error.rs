@@ -1,6 +1,8 @@
 //! Network-specific error types
 
-use libp2p::TransportError;
+use libp2p::{swarm::DialError, TransportError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 
 /// Network-specific errors
@@ -38,6 +40,10 @@ pub enum NetworkError {
     #[error("Not subscribed to topic: {0}")]
     NotSubscribed(String),
 
+    /// Topic outside the configured allow-list
+    #[error("Topic not allowed: {0}")]
+    TopicNotAllowed(String),
+
     /// Peer not found
     #[error("Peer not found: {0}")]
     PeerNotFound(String),
@@ -102,3 +108,122 @@ impl From<libp2p::swarm::ConnectionDenied> for NetworkError {
 
 /// Result type for network operations
 pub type Result<T> = std::result::Result<T, NetworkError>;
+
+/// Display text libp2p uses when multistream-select finds no common protocol
+const MULTISTREAM_SELECT_FAILED: &str = "Multistream select failed";
+
+/// Classification of connection setup failures caused by protocol negotiation
+///
+/// These usually point at version or transport incompatibilities between
+/// nodes rather than at the remote being unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiationFailure {
+    /// The address requires a transport we don't have enabled
+    UnsupportedTransport,
+    /// The remote speaks none of our security protocols
+    SecurityMismatch,
+    /// The remote speaks none of our stream multiplexers
+    MuxerMismatch,
+}
+
+impl NegotiationFailure {
+    /// All classifications, in a stable order
+    pub const ALL: [NegotiationFailure; 3] = [
+        NegotiationFailure::UnsupportedTransport,
+        NegotiationFailure::SecurityMismatch,
+        NegotiationFailure::MuxerMismatch,
+    ];
+
+    /// Short machine-readable code
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NegotiationFailure::UnsupportedTransport => "unsupported_transport",
+            NegotiationFailure::SecurityMismatch => "security_mismatch",
+            NegotiationFailure::MuxerMismatch => "muxer_mismatch",
+        }
+    }
+
+    /// Classify a dial error, returning `None` for non-negotiation failures
+    pub fn classify(error: &DialError) -> Option<Self> {
+        let DialError::Transport(errors) = error else {
+            return None;
+        };
+
+        errors.iter().find_map(|(_, err)| match err {
+            TransportError::MultiaddrNotSupported(_) => {
+                Some(NegotiationFailure::UnsupportedTransport)
+            }
+            TransportError::Other(err) => {
+                let message = err.to_string();
+                Self::ALL
+                    .into_iter()
+                    .find(|failure| message.contains(&failure.to_string()))
+            }
+        })
+    }
+
+    /// Tag an upgrade error from the transport stack with this classification
+    ///
+    /// Only multistream-select failures are tagged; handshake errors past
+    /// negotiation are passed through unchanged.
+    pub(crate) fn tag<E>(self, err: E) -> std::io::Error
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if err.to_string() == MULTISTREAM_SELECT_FAILED {
+            std::io::Error::other(format!("{}: {}", self, err))
+        } else {
+            std::io::Error::other(err)
+        }
+    }
+}
+
+impl fmt::Display for NegotiationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NegotiationFailure::UnsupportedTransport => write!(f, "unsupported transport"),
+            NegotiationFailure::SecurityMismatch => write!(f, "security protocol mismatch"),
+            NegotiationFailure::MuxerMismatch => write!(f, "muxer protocol mismatch"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::Multiaddr;
+
+    #[test]
+    fn test_classify_unsupported_transport() {
+        let addr: Multiaddr = "/ip4/127.0.0.1/udp/4001/quic-v1".parse().unwrap();
+        let error = DialError::Transport(vec![(
+            addr.clone(),
+            TransportError::MultiaddrNotSupported(addr),
+        )]);
+
+        assert_eq!(
+            NegotiationFailure::classify(&error),
+            Some(NegotiationFailure::UnsupportedTransport)
+        );
+    }
+
+    #[test]
+    fn test_tag_only_negotiation_errors() {
+        let tagged = NegotiationFailure::MuxerMismatch
+            .tag(std::io::Error::other(MULTISTREAM_SELECT_FAILED));
+        assert_eq!(
+            tagged.to_string(),
+            "muxer protocol mismatch: Multistream select failed"
+        );
+
+        let untagged = NegotiationFailure::MuxerMismatch
+            .tag(std::io::Error::other("Handshake failed"));
+        assert_eq!(untagged.to_string(), "Handshake failed");
+
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        let error = DialError::Transport(vec![(addr, TransportError::Other(untagged))]);
+        assert_eq!(NegotiationFailure::classify(&error), None);
+        assert_eq!(NegotiationFailure::classify(&DialError::Aborted), None);
+    }
+}
@@ -6,6 +6,10 @@
 use chrono::{DateTime, Utc};
 use libp2p::{gossipsub::MessageId, Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::NegotiationFailure;
+use crate::transport::{AddressScope, AddressTransport};
 
 /// Events emitted by the network service
 #[derive(Debug, Clone)]
@@ -25,6 +29,16 @@ pub enum NetworkEvent {
     ListeningOn {
         /// The address we're listening on
         address: Multiaddr,
+        /// Transport the address uses
+        transport: AddressTransport,
+        /// Who can reach the address
+        scope: AddressScope,
+    },
+
+    /// A listen address is no longer available (e.g. an interface went down)
+    ListenAddressExpired {
+        /// The address we stopped listening on
+        address: Multiaddr,
     },
 
     /// A new peer connected
@@ -99,6 +113,26 @@ pub enum NetworkEvent {
         topic: String,
     },
 
+    /// The gossipsub mesh for a topic gained or lost peers (graft/prune)
+    MeshUpdated {
+        /// The topic
+        topic: String,
+        /// Number of peers now in the topic mesh
+        mesh_peers: usize,
+    },
+
+    /// A subscribed topic has no mesh peers, so publishes reach nobody
+    TopicIsolated {
+        /// The topic
+        topic: String,
+    },
+
+    /// A previously isolated topic has mesh peers again
+    TopicConnected {
+        /// The topic
+        topic: String,
+    },
+
     /// DHT record found
     RecordFound {
         /// The key
@@ -139,6 +173,60 @@ pub enum NetworkEvent {
         error: String,
     },
 
+    /// A bootstrap peer could not be reached and will be dialed again
+    ///
+    /// `DialFailed` is only emitted for bootstrap peers once every attempt
+    /// has failed.
+    BootstrapDialRetry {
+        /// Bootstrap address that failed
+        address: Multiaddr,
+        /// Failed attempts so far
+        attempt: u32,
+        /// Wait before the next attempt
+        delay: Duration,
+    },
+
+    /// The connection limit was hit and further connections are being refused
+    ///
+    /// Emitted once when the first connection is refused, and again only
+    /// after the node has dropped back below the limit.
+    ConnectionLimitReached {
+        /// Configured maximum number of connections
+        limit: usize,
+    },
+
+    /// A connection was refused because the peer exceeded its rate limit
+    ConnectionThrottled {
+        /// The throttled peer
+        peer_id: PeerId,
+        /// Connections accepted from this peer in the current window
+        recent_connections: usize,
+    },
+
+    /// A blocked peer's connection was closed or refused
+    ConnectionBlocked {
+        /// The blocked peer
+        peer_id: PeerId,
+    },
+
+    /// A connected peer missed too many liveness heartbeats in a row
+    ///
+    /// The connection may be half-open; callers will usually disconnect.
+    PeerUnresponsive {
+        /// The silent peer
+        peer_id: PeerId,
+    },
+
+    /// Bytes exchanged with a connected peer since it connected
+    BandwidthReport {
+        /// The peer
+        peer_id: PeerId,
+        /// Bytes received from the peer
+        bytes_in: u64,
+        /// Bytes sent to the peer
+        bytes_out: u64,
+    },
+
     /// Connection established (inbound or outbound)
     ConnectionEstablished {
         /// The peer's ID
@@ -170,6 +258,9 @@ impl NetworkEvent {
                 | NetworkEvent::PeerIdentified { .. }
                 | NetworkEvent::ConnectionEstablished { .. }
                 | NetworkEvent::ConnectionClosed { .. }
+                | NetworkEvent::ConnectionThrottled { .. }
+                | NetworkEvent::ConnectionBlocked { .. }
+                | NetworkEvent::PeerUnresponsive { .. }
         )
     }
 
@@ -199,6 +290,9 @@ impl NetworkEvent {
             NetworkEvent::Dialing { peer_id } => Some(peer_id),
             NetworkEvent::ConnectionEstablished { peer_id, .. } => Some(peer_id),
             NetworkEvent::ConnectionClosed { peer_id, .. } => Some(peer_id),
+            NetworkEvent::ConnectionThrottled { peer_id, .. } => Some(peer_id),
+            NetworkEvent::ConnectionBlocked { peer_id } => Some(peer_id),
+            NetworkEvent::BandwidthReport { peer_id, .. } => Some(peer_id),
             NetworkEvent::MessageReceived { source, .. } => source.as_ref(),
             _ => None,
         }
@@ -212,8 +306,12 @@ pub struct NetworkStats {
     pub connected_peers: usize,
     /// Total messages received
     pub messages_received: u64,
+    /// Received messages dropped as duplicates
+    pub messages_deduplicated: u64,
     /// Total messages sent
     pub messages_sent: u64,
+    /// Sent messages flooded to all subscribers because the mesh was sparse
+    pub messages_flood_published: u64,
     /// Bytes received
     pub bytes_received: u64,
     /// Bytes sent
@@ -222,4 +320,33 @@ pub struct NetworkStats {
     pub subscribed_topics: usize,
     /// Uptime in seconds
     pub uptime_secs: u64,
+    /// Dial failures caused by protocol negotiation, by classification
+    pub negotiation_failures: NegotiationFailureCounts,
+}
+
+/// Aggregate counts of classified negotiation failures
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NegotiationFailureCounts {
+    /// Dials to addresses whose transport isn't enabled
+    pub unsupported_transport: u64,
+    /// Dials where no security protocol could be agreed
+    pub security_mismatch: u64,
+    /// Dials where no stream multiplexer could be agreed
+    pub muxer_mismatch: u64,
+}
+
+impl NegotiationFailureCounts {
+    /// Record a classified failure
+    pub fn record(&mut self, failure: NegotiationFailure) {
+        match failure {
+            NegotiationFailure::UnsupportedTransport => self.unsupported_transport += 1,
+            NegotiationFailure::SecurityMismatch => self.security_mismatch += 1,
+            NegotiationFailure::MuxerMismatch => self.muxer_mismatch += 1,
+        }
+    }
+
+    /// Total number of classified failures
+    pub fn total(&self) -> u64 {
+        self.unsupported_transport + self.security_mismatch + self.muxer_mismatch
+    }
 }
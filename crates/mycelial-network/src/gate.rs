@@ -0,0 +1,198 @@
+//! Connection admission
+//!
+//! Blocked peers and peers over their connection rate limit are turned away
+//! in the swarm's connection hooks, like the limits of
+//! [`libp2p::connection_limits`]. A denied connection is never handed to
+//! the other protocols or reported as established; the swarm reports it as
+//! an incoming or outgoing connection error whose cause downcasts to
+//! [`Denied`]. Dials to a blocked peer whose ID is known are refused before
+//! anything is sent.
+
+use libp2p::{
+    core::{transport::PortUse, Endpoint},
+    swarm::{
+        dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+        THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+use std::collections::HashSet;
+use std::fmt;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use void::Void;
+
+use crate::rate_limit::ConnectionRateLimiter;
+
+/// Why the gate turned a connection away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// The peer is blocked
+    Blocked { peer_id: PeerId },
+    /// The peer connected too often within the rate-limit window
+    Throttled {
+        peer_id: PeerId,
+        /// Connections accepted from the peer in the current window
+        recent_connections: usize,
+    },
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Denied::Blocked { peer_id } => write!(f, "peer {} is blocked", peer_id),
+            Denied::Throttled { peer_id, recent_connections } => write!(
+                f,
+                "connection rate limit exceeded by {} ({} recent connections)",
+                peer_id, recent_connections
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Denied {}
+
+/// Denies connections from blocked and throttled peers
+#[derive(Debug)]
+pub struct Behaviour {
+    /// Peers whose connections are refused
+    blocked: HashSet<PeerId>,
+    /// Per-peer connection rate limiting (None when disabled)
+    rate_limiter: Option<ConnectionRateLimiter>,
+}
+
+impl Behaviour {
+    /// Create a gate, rate-limiting connections if `rate_limiter` is set
+    pub fn new(rate_limiter: Option<ConnectionRateLimiter>) -> Self {
+        Self { blocked: HashSet::new(), rate_limiter }
+    }
+
+    /// Refuse the peer's connections from now on, returns false if it already was
+    pub fn block(&mut self, peer_id: PeerId) -> bool {
+        self.blocked.insert(peer_id)
+    }
+
+    /// Accept the peer's connections again, returns false if it wasn't blocked
+    pub fn unblock(&mut self, peer_id: &PeerId) -> bool {
+        self.blocked.remove(peer_id)
+    }
+
+    /// Forget rate-limit history that left the window
+    pub fn prune(&mut self, now: Instant) {
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            limiter.prune(now);
+        }
+    }
+
+    /// Admit an established connection, counting it against the rate limit
+    fn admit(&mut self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.blocked.contains(&peer_id) {
+            return Err(ConnectionDenied::new(Denied::Blocked { peer_id }));
+        }
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            let now = Instant::now();
+            if !limiter.check(peer_id, now) {
+                let recent_connections = limiter.recent(&peer_id, now);
+                return Err(ConnectionDenied::new(Denied::Throttled { peer_id, recent_connections }));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Void;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        match maybe_peer {
+            Some(peer_id) if self.blocked.contains(&peer_id) => {
+                Err(ConnectionDenied::new(Denied::Blocked { peer_id }))
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.admit(peer)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.admit(peer)?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    fn denial(result: Result<(), ConnectionDenied>) -> Option<Denied> {
+        result.err().map(|cause| *cause.downcast_ref::<Denied>().unwrap())
+    }
+
+    #[test]
+    fn test_blocked_and_throttled_peers_denied() {
+        let mut gate = Behaviour::new(Some(ConnectionRateLimiter::per_minute(1)));
+        let (blocked, noisy) = (random_peer_id(), random_peer_id());
+
+        gate.block(blocked);
+        assert_eq!(denial(gate.admit(blocked)), Some(Denied::Blocked { peer_id: blocked }));
+        let dial = gate.handle_pending_outbound_connection(
+            ConnectionId::new_unchecked(0),
+            Some(blocked),
+            &[],
+            Endpoint::Dialer,
+        );
+        assert!(dial.is_err());
+
+        assert_eq!(denial(gate.admit(noisy)), None);
+        assert_eq!(
+            denial(gate.admit(noisy)),
+            Some(Denied::Throttled { peer_id: noisy, recent_connections: 1 })
+        );
+
+        assert!(gate.unblock(&blocked));
+        assert_eq!(denial(gate.admit(blocked)), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Liveness heartbeats for connected peers
+//!
+//! A half-open connection can linger without libp2p ever reporting a
+//! disconnect. Every node that has heartbeats enabled publishes a small
+//! message on the reserved [`HEARTBEAT`](crate::behaviour::topics::HEARTBEAT)
+//! topic once per interval. Peers subscribed to that topic are expected to do
+//! the same; one that stays silent for a number of intervals in a row is
+//! reported as unresponsive. Peers that never subscribed (heartbeats disabled,
+//! or an older version) are not watched.
+//!
+//! A heartbeat also lists the sender's connected peers, from which
+//! [`TopologyTracker`](crate::topology::TopologyTracker) builds a picture of
+//! the wider mesh.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+
+/// Heartbeat state of a watched peer
+#[derive(Debug, Default)]
+struct Liveness {
+    /// Intervals in a row the peer has been silent for
+    missed: u32,
+    /// Whether a heartbeat arrived during the current interval
+    heard: bool,
+}
+
+/// Counts missed heartbeat intervals per watched peer
+#[derive(Debug)]
+pub struct HeartbeatMonitor {
+    /// Watched peers
+    peers: HashMap<PeerId, Liveness>,
+    /// Missed intervals after which a peer is reported
+    threshold: u32,
+}
+
+impl HeartbeatMonitor {
+    /// Create a monitor reporting peers after `threshold` silent intervals
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            peers: HashMap::new(),
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// Start watching a peer that subscribed to the heartbeat topic
+    pub fn watch(&mut self, peer_id: PeerId) {
+        self.peers.entry(peer_id).or_default();
+    }
+
+    /// Stop watching a peer (unsubscribed or disconnected)
+    pub fn forget(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+    }
+
+    /// Record a heartbeat from a watched peer
+    pub fn on_heartbeat(&mut self, peer_id: &PeerId) {
+        if let Some(liveness) = self.peers.get_mut(peer_id) {
+            liveness.heard = true;
+        }
+    }
+
+    /// Close an interval, returning peers that just reached the threshold
+    ///
+    /// A peer is reported once; it is reported again only after it has sent
+    /// a heartbeat and then gone silent for another `threshold` intervals.
+    pub fn tick(&mut self) -> Vec<PeerId> {
+        let mut unresponsive = Vec::new();
+        for (peer_id, liveness) in self.peers.iter_mut() {
+            if std::mem::take(&mut liveness.heard) {
+                liveness.missed = 0;
+                continue;
+            }
+            liveness.missed = liveness.missed.saturating_add(1);
+            if liveness.missed == self.threshold {
+                unresponsive.push(*peer_id);
+            }
+        }
+        unresponsive
+    }
+
+    /// Number of peers being watched
+    pub fn watched_count(&self) -> usize {
+        self.peers.len()
+    }
+}
+
+/// Most connected peers listed in a heartbeat
+pub const MAX_HEARTBEAT_NEIGHBOURS: usize = 256;
+
+/// Heartbeat payload for a node's `seq`-th interval
+///
+/// Message IDs are content hashes, so the payload carries the sender and a
+/// sequence number to keep every heartbeat distinct. The sender's connected
+/// peers follow, each peer ID prefixed with its length.
+pub(crate) fn heartbeat_payload(local_peer_id: &PeerId, seq: u64, connected: &[PeerId]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    push_peer_id(&mut payload, local_peer_id);
+    payload.extend_from_slice(&seq.to_be_bytes());
+    for peer_id in connected.iter().take(MAX_HEARTBEAT_NEIGHBOURS) {
+        push_peer_id(&mut payload, peer_id);
+    }
+    payload
+}
+
+/// Connected peers listed in a heartbeat payload, None if it is malformed
+pub(crate) fn heartbeat_neighbours(payload: &[u8]) -> Option<Vec<PeerId>> {
+    let (_sender, rest) = take_peer_id(payload)?;
+    let mut rest = rest.get(8..)?;
+    let mut neighbours = Vec::new();
+    while !rest.is_empty() {
+        let (peer_id, remaining) = take_peer_id(rest)?;
+        neighbours.push(peer_id);
+        rest = remaining;
+    }
+    Some(neighbours)
+}
+
+fn push_peer_id(payload: &mut Vec<u8>, peer_id: &PeerId) {
+    let bytes = peer_id.to_bytes();
+    payload.push(bytes.len() as u8);
+    payload.extend_from_slice(&bytes);
+}
+
+fn take_peer_id(data: &[u8]) -> Option<(PeerId, &[u8])> {
+    let (&len, rest) = data.split_first()?;
+    let len = len as usize;
+    let peer_id = PeerId::from_bytes(rest.get(..len)?).ok()?;
+    Some((peer_id, &rest[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    #[test]
+    fn test_silent_peer_reported_after_threshold() {
+        let mut monitor = HeartbeatMonitor::new(3);
+        let alive = random_peer_id();
+        let silent = random_peer_id();
+        monitor.watch(alive);
+        monitor.watch(silent);
+
+        // Both answer at first, then `silent` stops
+        monitor.on_heartbeat(&alive);
+        monitor.on_heartbeat(&silent);
+        assert!(monitor.tick().is_empty());
+
+        for _ in 0..2 {
+            monitor.on_heartbeat(&alive);
+            assert!(monitor.tick().is_empty());
+        }
+        monitor.on_heartbeat(&alive);
+        assert_eq!(monitor.tick(), vec![silent]);
+
+        // Reported once, not on every later interval
+        monitor.on_heartbeat(&alive);
+        assert!(monitor.tick().is_empty());
+
+        // A heartbeat resets the count
+        monitor.on_heartbeat(&silent);
+        for _ in 0..3 {
+            monitor.on_heartbeat(&alive);
+            assert!(monitor.tick().is_empty());
+        }
+        monitor.on_heartbeat(&alive);
+        assert_eq!(monitor.tick(), vec![silent]);
+    }
+
+    #[test]
+    fn test_unwatched_peers_ignored() {
+        let mut monitor = HeartbeatMonitor::new(1);
+        let peer = random_peer_id();
+
+        // Heartbeats from peers nobody watches don't start tracking them
+        monitor.on_heartbeat(&peer);
+        assert_eq!(monitor.watched_count(), 0);
+
+        monitor.watch(peer);
+        monitor.forget(&peer);
+        assert!(monitor.tick().is_empty());
+    }
+
+    #[test]
+    fn test_payloads_distinct() {
+        let a = random_peer_id();
+        let b = random_peer_id();
+        assert_ne!(heartbeat_payload(&a, 1, &[]), heartbeat_payload(&a, 2, &[]));
+        assert_ne!(heartbeat_payload(&a, 1, &[]), heartbeat_payload(&b, 1, &[]));
+    }
+
+    #[test]
+    fn test_payload_lists_neighbours() {
+        let sender = random_peer_id();
+        let connected = vec![random_peer_id(), random_peer_id()];
+
+        let payload = heartbeat_payload(&sender, 7, &connected);
+        assert_eq!(heartbeat_neighbours(&payload), Some(connected));
+        assert_eq!(heartbeat_neighbours(&heartbeat_payload(&sender, 1, &[])), Some(vec![]));
+
+        // Truncated payloads are rejected rather than misread
+        assert_eq!(heartbeat_neighbours(&payload[..payload.len() - 1]), None);
+        assert_eq!(heartbeat_neighbours(&[]), None);
+    }
+}
@@ -49,29 +49,47 @@
 //! }
 //! ```
 
+pub mod bandwidth;
 pub mod behaviour;
+pub mod bootstrap;
 pub mod config;
+pub mod dedup;
 pub mod economics;
 pub mod error;
 pub mod event;
+pub mod gate;
+pub mod heartbeat;
 pub mod peer;
+pub mod rate_limit;
+pub mod redial;
+pub mod scoring;
 pub mod service;
+pub mod topology;
 pub mod transport;
 
 // Re-exports
+pub use bandwidth::BandwidthTracker;
 pub use behaviour::{MycelialBehaviour, MycelialBehaviourEvent, topics};
-pub use config::NetworkConfig;
+pub use bootstrap::{BootstrapDialer, BootstrapFailure};
+pub use config::{NetworkConfig, TransportSelection};
+pub use dedup::MessageDeduplicator;
 pub use economics::{EconomicsEvent, EconomicsHandler, economics_topics, is_economics_topic, parse_economics_message};
-pub use error::{NetworkError, Result};
-pub use event::{NetworkEvent, NetworkStats};
+pub use error::{NegotiationFailure, NetworkError, Result};
+pub use event::{NegotiationFailureCounts, NetworkEvent, NetworkStats};
+pub use heartbeat::HeartbeatMonitor;
 pub use peer::{ConnectionState, PeerInfo, PeerManager};
-pub use service::{NetworkCommand, NetworkHandle, NetworkService};
-pub use transport::{TransportConfig, create_transport, parse_multiaddr, extract_peer_id};
+pub use rate_limit::ConnectionRateLimiter;
+pub use redial::RedialScheduler;
+pub use scoring::reputation_to_app_score;
+pub use service::{DialOutcome, NetworkCommand, NetworkHandle, NetworkService};
+pub use topology::{GraphEdge, NetworkGraph, TopologyTracker};
+pub use transport::{AddressScope, AddressTransport, ListenAddress, TransportConfig, classify_address, create_transport, parse_multiaddr, extract_peer_id};
 
 // Re-export libp2p types commonly used
 pub use libp2p::identity::Keypair;
 pub use libp2p::PeerId as Libp2pPeerId;
 pub use libp2p::Multiaddr;
+pub use libp2p::gossipsub::{MessageAcceptance, MessageId};
 
 #[cfg(test)]
 mod tests {
@@ -90,4 +108,36 @@ mod tests {
         let config = NetworkConfig::local_test(5000);
         assert_eq!(config.listen_addresses[0], "/ip4/127.0.0.1/tcp/5000");
     }
+
+    #[test]
+    fn test_topic_allowed() {
+        let allowed = vec!["/mycelial/1.0.0/chat".to_string(), "economics/*".to_string()];
+
+        assert!(config::topic_allowed(None, "/anything"));
+        assert!(config::topic_allowed(Some(&allowed), "/mycelial/1.0.0/chat"));
+        assert!(!config::topic_allowed(Some(&allowed), "/mycelial/1.0.0/chat/extra"));
+        assert!(config::topic_allowed(Some(&allowed), "economics/credit"));
+        assert!(config::topic_allowed(Some(&allowed), "economics/"));
+        assert!(!config::topic_allowed(Some(&allowed), "economics"));
+        assert!(!config::topic_allowed(Some(&[]), "/mycelial/1.0.0/chat"));
+    }
+
+    #[test]
+    fn test_transport_selection() {
+        let tcp: libp2p::Multiaddr = "/ip4/127.0.0.1/tcp/9000".parse().unwrap();
+        let quic: libp2p::Multiaddr = "/ip4/127.0.0.1/udp/9001/quic-v1".parse().unwrap();
+
+        assert_eq!(NetworkConfig::default().transports, TransportSelection::Both);
+        assert!(TransportSelection::Both.supports(&tcp));
+        assert!(TransportSelection::Both.supports(&quic));
+        assert!(TransportSelection::TcpOnly.supports(&tcp));
+        assert!(!TransportSelection::TcpOnly.supports(&quic));
+        assert!(!TransportSelection::QuicOnly.supports(&tcp));
+        assert!(TransportSelection::QuicOnly.supports(&quic));
+
+        assert_eq!("tcp".parse(), Ok(TransportSelection::TcpOnly));
+        assert_eq!("QUIC".parse(), Ok(TransportSelection::QuicOnly));
+        assert_eq!("both".parse(), Ok(TransportSelection::Both));
+        assert!("udp".parse::<TransportSelection>().is_err());
+    }
 }
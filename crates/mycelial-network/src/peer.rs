@@ -239,6 +239,17 @@ impl PeerManager {
             .collect()
     }
 
+    /// Check if a known peer is trusted and not banned
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.peers
+            .read()
+            .get(peer_id)
+            .map(|info| {
+                info.state != ConnectionState::Banned && info.is_trusted(self.trust_threshold)
+            })
+            .unwrap_or(false)
+    }
+
     /// Count connected peers
     pub fn connected_count(&self) -> usize {
         self.peers
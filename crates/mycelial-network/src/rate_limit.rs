@@ -0,0 +1,145 @@
+//! Per-peer connection rate limiting
+//!
+//! Misbehaving peers can reconnect in a tight loop, which costs a full
+//! handshake each time. The limiter keeps a sliding window of accepted
+//! connection times per remote peer and refuses new ones once the window is
+//! full. Timestamps older than the window are dropped as they age out, and
+//! peers with no recent connections are forgotten entirely.
+
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Sliding-window limiter for connections per remote peer
+#[derive(Debug)]
+pub struct ConnectionRateLimiter {
+    /// Accepted connection times per peer, oldest first
+    history: HashMap<PeerId, VecDeque<Instant>>,
+    /// Maximum connections per peer within the window
+    max_per_window: u32,
+    /// Length of the sliding window
+    window: Duration,
+}
+
+impl ConnectionRateLimiter {
+    /// Create a limiter allowing `max_per_window` connections per `window`
+    pub fn new(max_per_window: u32, window: Duration) -> Self {
+        Self {
+            history: HashMap::new(),
+            max_per_window,
+            window,
+        }
+    }
+
+    /// Create a limiter with a one-minute window
+    pub fn per_minute(max_per_minute: u32) -> Self {
+        Self::new(max_per_minute, Duration::from_secs(60))
+    }
+
+    /// Record a connection attempt, returns false if it should be refused
+    ///
+    /// Refused attempts are not recorded, so a throttled peer regains access
+    /// as soon as its oldest accepted connection leaves the window.
+    pub fn check(&mut self, peer_id: PeerId, now: Instant) -> bool {
+        let window = self.window;
+        let times = self.history.entry(peer_id).or_default();
+        while times
+            .front()
+            .is_some_and(|&t| now.saturating_duration_since(t) >= window)
+        {
+            times.pop_front();
+        }
+
+        if times.len() >= self.max_per_window as usize {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+
+    /// Number of connections from a peer within the window ending at `now`
+    pub fn recent(&self, peer_id: &PeerId, now: Instant) -> usize {
+        self.history
+            .get(peer_id)
+            .map(|times| {
+                times
+                    .iter()
+                    .filter(|&&t| now.saturating_duration_since(t) < self.window)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Drop timestamps that left the window and forget idle peers
+    pub fn prune(&mut self, now: Instant) {
+        let window = self.window;
+        self.history.retain(|_, times| {
+            times.retain(|&t| now.saturating_duration_since(t) < window);
+            !times.is_empty()
+        });
+    }
+
+    /// Number of peers currently tracked
+    pub fn tracked_peers(&self) -> usize {
+        self.history.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    #[test]
+    fn test_rapid_connections_are_throttled() {
+        let mut limiter = ConnectionRateLimiter::per_minute(3);
+        let noisy = random_peer_id();
+        let quiet = random_peer_id();
+        let start = Instant::now();
+
+        // Ten reconnects within a second: only the first three get through
+        let accepted = (0..10)
+            .filter(|i| limiter.check(noisy, start + Duration::from_millis(i * 100)))
+            .count();
+        assert_eq!(accepted, 3);
+        assert_eq!(limiter.recent(&noisy, start + Duration::from_secs(1)), 3);
+
+        // Other peers are unaffected
+        assert!(limiter.check(quiet, start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_window_slides() {
+        let mut limiter = ConnectionRateLimiter::new(2, Duration::from_secs(60));
+        let peer = random_peer_id();
+        let start = Instant::now();
+
+        assert!(limiter.check(peer, start));
+        assert!(limiter.check(peer, start + Duration::from_secs(30)));
+        assert!(!limiter.check(peer, start + Duration::from_secs(59)));
+
+        // The first connection ages out, freeing one slot
+        assert!(limiter.check(peer, start + Duration::from_secs(60)));
+        assert!(!limiter.check(peer, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_prune_forgets_idle_peers() {
+        let mut limiter = ConnectionRateLimiter::per_minute(5);
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.check(random_peer_id(), start);
+        }
+        assert_eq!(limiter.tracked_peers(), 10);
+
+        limiter.prune(start + Duration::from_secs(30));
+        assert_eq!(limiter.tracked_peers(), 10);
+
+        limiter.prune(start + Duration::from_secs(60));
+        assert_eq!(limiter.tracked_peers(), 0);
+    }
+}
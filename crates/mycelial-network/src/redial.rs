@@ -0,0 +1,192 @@
+//! Automatic redialing of trusted peers
+//!
+//! When a trusted peer drops off we try to get it back right away instead of
+//! waiting for passive rediscovery. Attempts back off exponentially and stop
+//! once the peer reconnects or the attempt cap is reached. This is separate
+//! from bootstrap reconnection, which covers configured entry points.
+
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Redial state for a single peer
+#[derive(Debug, Clone)]
+struct RedialState {
+    /// Attempts made so far
+    attempts: u32,
+    /// Earliest time the next attempt may be made
+    next_attempt: Instant,
+}
+
+/// Schedules backoff-limited redials of recently disconnected trusted peers
+#[derive(Debug)]
+pub struct RedialScheduler {
+    /// Peers awaiting a redial
+    pending: HashMap<PeerId, RedialState>,
+    /// Peers we disconnected on purpose and must not redial
+    suppressed: HashSet<PeerId>,
+    /// Maximum attempts per disconnect (0 disables redialing)
+    max_attempts: u32,
+    /// Delay before the second attempt; doubles on each failure
+    base_delay: Duration,
+}
+
+impl RedialScheduler {
+    /// Create a new scheduler
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            pending: HashMap::new(),
+            suppressed: HashSet::new(),
+            max_attempts,
+            base_delay,
+        }
+    }
+
+    /// Handle a peer disconnecting, returns true if a redial was scheduled
+    ///
+    /// Only trusted peers are redialed; the first attempt is due immediately.
+    pub fn on_disconnected(&mut self, peer_id: PeerId, trusted: bool, now: Instant) -> bool {
+        if self.suppressed.remove(&peer_id) || !trusted || self.max_attempts == 0 {
+            return false;
+        }
+
+        self.pending.insert(
+            peer_id,
+            RedialState {
+                attempts: 0,
+                next_attempt: now,
+            },
+        );
+        true
+    }
+
+    /// Handle a peer (re)connecting, cancelling any pending redial
+    pub fn on_connected(&mut self, peer_id: &PeerId) {
+        self.pending.remove(peer_id);
+        self.suppressed.remove(peer_id);
+    }
+
+    /// Handle a failed redial, backing off or giving up at the cap
+    pub fn on_dial_failed(&mut self, peer_id: &PeerId, now: Instant) {
+        if let Some(state) = self.pending.get_mut(peer_id) {
+            if state.attempts >= self.max_attempts {
+                self.pending.remove(peer_id);
+            } else {
+                let exponent = state.attempts.saturating_sub(1).min(16);
+                state.next_attempt = now + self.base_delay * 2u32.pow(exponent);
+            }
+        }
+    }
+
+    /// Don't redial this peer on its next disconnect (e.g. we closed it)
+    pub fn suppress(&mut self, peer_id: PeerId) {
+        self.pending.remove(&peer_id);
+        self.suppressed.insert(peer_id);
+    }
+
+    /// Drop any pending redial of this peer (e.g. it was blocked)
+    pub fn cancel(&mut self, peer_id: &PeerId) {
+        self.pending.remove(peer_id);
+    }
+
+    /// Take the peers whose next attempt is due, counting the attempt
+    pub fn due(&mut self, now: Instant) -> Vec<PeerId> {
+        let mut due = Vec::new();
+        for (peer_id, state) in self.pending.iter_mut() {
+            if state.next_attempt <= now && state.attempts < self.max_attempts {
+                state.attempts += 1;
+                // Hold off further attempts until this one resolves
+                state.next_attempt = now + self.base_delay * 2u32.pow(state.attempts.min(16));
+                due.push(*peer_id);
+            }
+        }
+        due
+    }
+
+    /// Check if a redial is pending for a peer
+    pub fn is_pending(&self, peer_id: &PeerId) -> bool {
+        self.pending.contains_key(peer_id)
+    }
+
+    /// Number of peers awaiting a redial
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    #[test]
+    fn test_trusted_disconnect_triggers_redial() {
+        let mut scheduler = RedialScheduler::new(3, Duration::from_secs(1));
+        let trusted = random_peer_id();
+        let untrusted = random_peer_id();
+        let now = Instant::now();
+
+        assert!(scheduler.on_disconnected(trusted, true, now));
+        assert!(!scheduler.on_disconnected(untrusted, false, now));
+
+        // First attempt is immediate and only for the trusted peer
+        assert_eq!(scheduler.due(now), vec![trusted]);
+        assert!(!scheduler.is_pending(&untrusted));
+
+        // Reconnecting stops further attempts
+        scheduler.on_connected(&trusted);
+        assert_eq!(scheduler.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_redial_backoff_and_cap() {
+        let mut scheduler = RedialScheduler::new(2, Duration::from_secs(1));
+        let peer = random_peer_id();
+        let now = Instant::now();
+
+        scheduler.on_disconnected(peer, true, now);
+        assert_eq!(scheduler.due(now).len(), 1);
+
+        // Failure backs off before the next attempt
+        scheduler.on_dial_failed(&peer, now);
+        assert!(scheduler.due(now).is_empty());
+        let later = now + Duration::from_secs(1);
+        assert_eq!(scheduler.due(later), vec![peer]);
+
+        // Cap reached: give up
+        scheduler.on_dial_failed(&peer, later);
+        assert!(!scheduler.is_pending(&peer));
+    }
+
+    #[test]
+    fn test_suppressed_disconnect_not_redialed() {
+        let mut scheduler = RedialScheduler::new(3, Duration::from_secs(1));
+        let peer = random_peer_id();
+
+        scheduler.suppress(peer);
+        assert!(!scheduler.on_disconnected(peer, true, Instant::now()));
+        assert!(!scheduler.is_pending(&peer));
+    }
+
+    #[test]
+    fn test_cancelled_redial_dropped() {
+        let mut scheduler = RedialScheduler::new(3, Duration::from_secs(1));
+        let peer = random_peer_id();
+        let now = Instant::now();
+
+        assert!(scheduler.on_disconnected(peer, true, now));
+        scheduler.cancel(&peer);
+        assert!(!scheduler.is_pending(&peer));
+        assert!(scheduler.due(now).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_scheduler() {
+        let mut scheduler = RedialScheduler::new(0, Duration::from_secs(1));
+        assert!(!scheduler.on_disconnected(random_peer_id(), true, Instant::now()));
+    }
+}
@@ -0,0 +1,60 @@
+//! Reputation-driven gossipsub peer scoring
+//!
+//! Gossipsub scores every peer and drops negatively scored peers from the
+//! mesh at each heartbeat; peers below the thresholds stop receiving gossip,
+//! then our publishes, then are ignored entirely. Only the app-specific term
+//! (P5) is driven by us: the node's 0.0–1.0 reputation for a peer is mapped
+//! linearly onto -1.0..=1.0, centred on the neutral default of 0.5, and
+//! weighted by [`APP_SPECIFIC_WEIGHT`].
+//!
+//! | reputation | app score | score contribution |
+//! |------------|-----------|--------------------|
+//! | 0.0        | -1.0      | -10.0              |
+//! | 0.5        |  0.0      |   0.0              |
+//! | 1.0        |  1.0      | +10.0              |
+//!
+//! A zero-reputation peer on its own sits exactly at the default gossip
+//! threshold (-10): it is pruned from our meshes but still gossiped with.
+//! Reaching the publish (-50) or graylist (-80) thresholds takes protocol
+//! misbehaviour penalties on top.
+
+use libp2p::gossipsub::{PeerScoreParams, PeerScoreThresholds};
+
+/// Weight of the reputation-derived app-specific score
+pub const APP_SPECIFIC_WEIGHT: f64 = 10.0;
+
+/// Map a 0.0–1.0 reputation onto gossipsub's app-specific score
+///
+/// Out-of-range (and NaN) reputations are clamped, so a bad value from the
+/// network can't produce an unbounded score.
+pub fn reputation_to_app_score(reputation: f64) -> f64 {
+    let reputation = if reputation.is_nan() { 0.5 } else { reputation.clamp(0.0, 1.0) };
+    (reputation - 0.5) * 2.0
+}
+
+/// Score parameters enabling the reputation term with default thresholds
+pub(crate) fn peer_score_config() -> (PeerScoreParams, PeerScoreThresholds) {
+    let params = PeerScoreParams {
+        app_specific_weight: APP_SPECIFIC_WEIGHT,
+        ..Default::default()
+    };
+    (params, PeerScoreThresholds::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reputation_mapping() {
+        assert_eq!(reputation_to_app_score(0.0), -1.0);
+        assert_eq!(reputation_to_app_score(0.5), 0.0);
+        assert_eq!(reputation_to_app_score(1.0), 1.0);
+        assert_eq!(reputation_to_app_score(0.75), 0.5);
+
+        // Out-of-range input is clamped
+        assert_eq!(reputation_to_app_score(-3.0), -1.0);
+        assert_eq!(reputation_to_app_score(7.0), 1.0);
+        assert_eq!(reputation_to_app_score(f64::NAN), 0.0);
+    }
+}
@@ -5,37 +5,72 @@
 
 use futures::StreamExt;
 use libp2p::{
-    gossipsub, identify, kad, mdns,
-    swarm::SwarmEvent,
+    connection_limits, gossipsub::{self, MessageAcceptance, MessageId}, identify, kad, mdns,
+    swarm::{
+        dial_opts::{DialOpts, PeerCondition},
+        ConnectionDenied,
+        ConnectionId,
+        DialError,
+        ListenError,
+        SwarmEvent,
+    },
+    core::transport::ListenerId,
     Multiaddr, PeerId, Swarm,
 };
 use parking_lot::RwLock;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, info, warn};
 
-use crate::behaviour::{MycelialBehaviour, MycelialBehaviourEvent};
-use crate::config::NetworkConfig;
-use crate::error::{NetworkError, Result};
+/// How long a delivered message can wait for its validation result; gossipsub
+/// forgets it from its message cache well before
+const UNVALIDATED_MESSAGE_TTL: Duration = Duration::from_secs(30);
+
+use crate::bandwidth::{self, BandwidthTracker};
+use crate::behaviour::{topics, MycelialBehaviour, MycelialBehaviourEvent};
+use crate::bootstrap::{BootstrapDialer, BootstrapFailure};
+use crate::config::{topic_allowed, NetworkConfig};
+use crate::dedup::MessageDeduplicator;
+use crate::error::{NegotiationFailure, NetworkError, Result};
 use crate::event::{NetworkEvent, NetworkStats};
+use crate::gate;
+use crate::heartbeat::{self, HeartbeatMonitor};
 use crate::peer::{ConnectionState, PeerManager};
-use crate::transport::{self, TransportConfig};
+use crate::redial::RedialScheduler;
+use crate::scoring;
+use crate::topology::{NetworkGraph, TopologyTracker};
+use crate::transport::{self, ListenAddress, TransportConfig};
+
+/// Result of a dial: the peer reached, or why the dial failed
+pub type DialOutcome = std::result::Result<PeerId, String>;
 
 /// Commands sent to the network service
 #[derive(Debug)]
 pub enum NetworkCommand {
-    /// Dial a peer
-    Dial { address: Multiaddr },
+    /// Dial a peer, reporting whether the dial could be started
+    Dial { address: Multiaddr, response: tokio::sync::oneshot::Sender<Result<()>> },
+    /// Dial a peer, reporting whether a connection was established
+    DialAndWait { address: Multiaddr, response: tokio::sync::oneshot::Sender<DialOutcome> },
     /// Disconnect from a peer
     Disconnect { peer_id: PeerId },
-    /// Subscribe to a topic
-    Subscribe { topic: String },
-    /// Unsubscribe from a topic
-    Unsubscribe { topic: String },
-    /// Publish a message
-    Publish { topic: String, data: Vec<u8> },
+    /// Refuse a peer's connections, closing any it has open
+    BlockPeer { peer_id: PeerId },
+    /// Accept a blocked peer's connections again
+    UnblockPeer { peer_id: PeerId },
+    /// Subscribe to a topic, reporting whether it wasn't subscribed before
+    Subscribe { topic: String, response: tokio::sync::oneshot::Sender<Result<bool>> },
+    /// Unsubscribe from a topic, reporting whether it was subscribed
+    Unsubscribe { topic: String, response: tokio::sync::oneshot::Sender<Result<bool>> },
+    /// Unsubscribe from every subscribed topic
+    UnsubscribeAll,
+    /// Publish a message, reporting the id gossipsub assigned it
+    Publish {
+        topic: String,
+        data: Vec<u8>,
+        response: tokio::sync::oneshot::Sender<Result<MessageId>>,
+    },
     /// Store a value in the DHT
     PutRecord { key: Vec<u8>, value: Vec<u8> },
     /// Get a value from the DHT
@@ -44,6 +79,16 @@ pub enum NetworkCommand {
     GetPeers { response: tokio::sync::oneshot::Sender<Vec<PeerId>> },
     /// Get network stats
     GetStats { response: tokio::sync::oneshot::Sender<NetworkStats> },
+    /// Get the observed connections between peers
+    GetGraph { response: tokio::sync::oneshot::Sender<NetworkGraph> },
+    /// Feed a peer's 0.0–1.0 reputation into its gossipsub score
+    SetPeerScore { peer_id: PeerId, reputation: f64 },
+    /// Report the validation result of a received message
+    ReportMessage { message_id: MessageId, acceptance: MessageAcceptance },
+    /// Get a peer's current gossipsub score
+    GetPeerScore { peer_id: PeerId, response: tokio::sync::oneshot::Sender<Option<f64>> },
+    /// Get the peers in a topic's gossipsub mesh
+    GetMeshPeers { topic: String, response: tokio::sync::oneshot::Sender<Vec<PeerId>> },
     /// Shutdown
     Shutdown,
 }
@@ -53,6 +98,8 @@ pub enum NetworkCommand {
 pub struct NetworkHandle {
     command_tx: mpsc::Sender<NetworkCommand>,
     local_peer_id: PeerId,
+    /// Topic allow-list from the config (`None` allows all)
+    allowed_topics: Option<Arc<[String]>>,
 }
 
 impl NetworkHandle {
@@ -62,11 +109,35 @@ impl NetworkHandle {
     }
 
     /// Dial a peer by multiaddr
+    ///
+    /// Returns once the dial has started; fails with
+    /// [`NetworkError::DialFailed`] if it couldn't be (e.g. no transport for
+    /// the address). Use [`Self::dial_and_wait`] to wait for the connection.
     pub async fn dial(&self, address: Multiaddr) -> Result<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.command_tx
+            .send(NetworkCommand::Dial { address, response: tx })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send dial command".into()))?;
+
+        rx.await
+            .map_err(|_| NetworkError::Channel("Failed to receive dial result".into()))?
+    }
+
+    /// Dial a peer by multiaddr and wait for the outcome
+    ///
+    /// Resolves with the connected peer's ID once the connection is
+    /// established, or fails with [`NetworkError::DialFailed`].
+    pub async fn dial_and_wait(&self, address: Multiaddr) -> Result<PeerId> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
         self.command_tx
-            .send(NetworkCommand::Dial { address })
+            .send(NetworkCommand::DialAndWait { address: address.clone(), response: tx })
             .await
-            .map_err(|_| NetworkError::Channel("Failed to send dial command".into()))
+            .map_err(|_| NetworkError::Channel("Failed to send dial command".into()))?;
+
+        rx.await
+            .map_err(|_| NetworkError::Channel("Failed to receive dial outcome".into()))?
+            .map_err(|reason| NetworkError::DialFailed { peer: address.to_string(), reason })
     }
 
     /// Disconnect from a peer
@@ -77,28 +148,82 @@ impl NetworkHandle {
             .map_err(|_| NetworkError::Channel("Failed to send disconnect command".into()))
     }
 
+    /// Block a peer: close its connections and refuse new ones
+    ///
+    /// Each closed or refused connection is reported as
+    /// [`NetworkEvent::ConnectionBlocked`].
+    pub async fn block_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.command_tx
+            .send(NetworkCommand::BlockPeer { peer_id })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send block command".into()))
+    }
+
+    /// Unblock a peer previously blocked with [`Self::block_peer`]
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<()> {
+        self.command_tx
+            .send(NetworkCommand::UnblockPeer { peer_id })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send unblock command".into()))
+    }
+
     /// Subscribe to a gossipsub topic
-    pub async fn subscribe(&self, topic: impl Into<String>) -> Result<()> {
+    ///
+    /// Returns false if the node was already subscribed. Fails with
+    /// [`NetworkError::TopicNotAllowed`] for topics outside the configured
+    /// allow-list.
+    pub async fn subscribe(&self, topic: impl Into<String>) -> Result<bool> {
+        let topic = topic.into();
+        if !topic_allowed(self.allowed_topics.as_deref(), &topic) {
+            return Err(NetworkError::TopicNotAllowed(topic));
+        }
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
         self.command_tx
-            .send(NetworkCommand::Subscribe { topic: topic.into() })
+            .send(NetworkCommand::Subscribe { topic, response: tx })
             .await
-            .map_err(|_| NetworkError::Channel("Failed to send subscribe command".into()))
+            .map_err(|_| NetworkError::Channel("Failed to send subscribe command".into()))?;
+
+        rx.await
+            .map_err(|_| NetworkError::Channel("Failed to receive subscribe result".into()))?
     }
 
-    /// Unsubscribe from a gossipsub topic
-    pub async fn unsubscribe(&self, topic: impl Into<String>) -> Result<()> {
+    /// Unsubscribe from a gossipsub topic, returns false if it wasn't subscribed
+    pub async fn unsubscribe(&self, topic: impl Into<String>) -> Result<bool> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
         self.command_tx
-            .send(NetworkCommand::Unsubscribe { topic: topic.into() })
+            .send(NetworkCommand::Unsubscribe { topic: topic.into(), response: tx })
             .await
-            .map_err(|_| NetworkError::Channel("Failed to send unsubscribe command".into()))
+            .map_err(|_| NetworkError::Channel("Failed to send unsubscribe command".into()))?;
+
+        rx.await
+            .map_err(|_| NetworkError::Channel("Failed to receive unsubscribe result".into()))?
     }
 
-    /// Publish a message to a gossipsub topic
-    pub async fn publish(&self, topic: impl Into<String>, data: Vec<u8>) -> Result<()> {
+    /// Leave every subscribed gossipsub topic
+    ///
+    /// An `Unsubscribed` event is emitted for each topic left.
+    pub async fn unsubscribe_all(&self) -> Result<()> {
         self.command_tx
-            .send(NetworkCommand::Publish { topic: topic.into(), data })
+            .send(NetworkCommand::UnsubscribeAll)
             .await
-            .map_err(|_| NetworkError::Channel("Failed to send publish command".into()))
+            .map_err(|_| NetworkError::Channel("Failed to send unsubscribe all command".into()))
+    }
+
+    /// Publish a message to a gossipsub topic, returning its message id
+    ///
+    /// Fails if gossipsub refuses the message, e.g. when no peer is
+    /// subscribed to the topic yet. The id matches the `message_id` other
+    /// nodes see in their `MessageReceived` events.
+    pub async fn publish(&self, topic: impl Into<String>, data: Vec<u8>) -> Result<MessageId> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.command_tx
+            .send(NetworkCommand::Publish { topic: topic.into(), data, response: tx })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send publish command".into()))?;
+
+        rx.await
+            .map_err(|_| NetworkError::Channel("Failed to receive publish result".into()))?
     }
 
     /// Store a value in the DHT
@@ -139,6 +264,65 @@ impl NetworkHandle {
         rx.await.map_err(|_| NetworkError::Channel("Failed to receive stats".into()))
     }
 
+    /// Get the peers this node believes are connected to each other
+    ///
+    /// Always includes the local node's own connections; other peers'
+    /// connections appear as far as their heartbeats reported them.
+    pub async fn network_graph(&self) -> Result<NetworkGraph> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.command_tx
+            .send(NetworkCommand::GetGraph { response: tx })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send get_graph command".into()))?;
+
+        rx.await.map_err(|_| NetworkError::Channel("Failed to receive network graph".into()))
+    }
+
+    /// Set a peer's reputation (0.0–1.0), which drives its gossipsub score
+    ///
+    /// See [`crate::scoring`] for how reputation maps onto the score. Only
+    /// connected peers are scored, so call this again after they reconnect.
+    pub async fn set_peer_score(&self, peer_id: PeerId, reputation: f64) -> Result<()> {
+        self.command_tx
+            .send(NetworkCommand::SetPeerScore { peer_id, reputation })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send set_peer_score command".into()))
+    }
+
+    /// Report whether a received message is valid, when `validate_messages` is set
+    ///
+    /// Accepted messages are forwarded to other peers. Rejected ones are
+    /// dropped and count against the peer that passed them on; ignored ones
+    /// are just dropped.
+    pub async fn report_message(&self, message_id: MessageId, acceptance: MessageAcceptance) -> Result<()> {
+        self.command_tx
+            .send(NetworkCommand::ReportMessage { message_id, acceptance })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send report_message command".into()))
+    }
+
+    /// Get a peer's gossipsub score, if it is connected
+    pub async fn peer_score(&self, peer_id: PeerId) -> Result<Option<f64>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.command_tx
+            .send(NetworkCommand::GetPeerScore { peer_id, response: tx })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send get_peer_score command".into()))?;
+
+        rx.await.map_err(|_| NetworkError::Channel("Failed to receive peer score".into()))
+    }
+
+    /// Get the peers in a topic's gossipsub mesh
+    pub async fn mesh_peers(&self, topic: impl Into<String>) -> Result<Vec<PeerId>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.command_tx
+            .send(NetworkCommand::GetMeshPeers { topic: topic.into(), response: tx })
+            .await
+            .map_err(|_| NetworkError::Channel("Failed to send get_mesh_peers command".into()))?;
+
+        rx.await.map_err(|_| NetworkError::Channel("Failed to receive mesh peers".into()))
+    }
+
     /// Shutdown the network service
     pub async fn shutdown(&self) -> Result<()> {
         self.command_tx
@@ -165,6 +349,34 @@ pub struct NetworkService {
     command_tx: mpsc::Sender<NetworkCommand>,
     /// Subscribed topics
     subscribed_topics: HashSet<String>,
+    /// Last reported gossipsub mesh size per topic
+    mesh_sizes: HashMap<String, usize>,
+    /// Subscribed topics last seen with an empty mesh
+    isolated_topics: HashSet<String>,
+    /// Redial scheduling for disconnected trusted peers
+    redial: RedialScheduler,
+    /// Backoff-limited dialing of the configured bootstrap peers
+    bootstrap: BootstrapDialer,
+    /// Recently delivered message IDs, to drop duplicates
+    dedup: MessageDeduplicator,
+    /// Delivered messages awaiting validation, with the peer that passed each on
+    unvalidated: HashMap<MessageId, (PeerId, Instant)>,
+    /// Bytes exchanged per connected peer
+    bandwidth: Arc<BandwidthTracker>,
+    /// Missed liveness heartbeats per peer (None when heartbeats are disabled)
+    heartbeat: Option<HeartbeatMonitor>,
+    /// Heartbeats published so far
+    heartbeat_seq: u64,
+    /// Connections other peers reported in their heartbeats
+    topology: TopologyTracker,
+    /// Dials whose outcome a caller is waiting for
+    pending_dials: HashMap<ConnectionId, tokio::sync::oneshot::Sender<DialOutcome>>,
+    /// Whether `ConnectionLimitReached` was emitted since we were last below the limit
+    connection_limit_reported: bool,
+    /// Listeners opened for the configured listen addresses
+    listeners: Vec<ListenerId>,
+    /// Node-wide shutdown signal; on receipt the service stops listening
+    shutdown_rx: Option<broadcast::Receiver<()>>,
     /// Statistics
     stats: Arc<RwLock<NetworkStats>>,
     /// Start time
@@ -184,11 +396,15 @@ impl NetworkService {
 
         // Create transport
         let transport_config = TransportConfig {
-            enable_tcp: config.enable_tcp,
-            enable_quic: config.enable_quic,
+            enable_tcp: config.transports.uses_tcp(),
+            enable_quic: config.transports.uses_quic(),
             ..Default::default()
         };
-        let transport = transport::create_transport(&keypair, &transport_config)?;
+        let bandwidth = Arc::new(BandwidthTracker::new());
+        let transport = bandwidth::track_bandwidth(
+            transport::create_transport(&keypair, &transport_config)?,
+            bandwidth.clone(),
+        );
 
         // Create behaviour
         let behaviour = MycelialBehaviour::new(&keypair, &config)?;
@@ -208,8 +424,37 @@ impl NetworkService {
         let handle = NetworkHandle {
             command_tx: command_tx.clone(),
             local_peer_id,
+            allowed_topics: config.allowed_topics.clone().map(Arc::from),
         };
 
+        let redial = RedialScheduler::new(config.redial_max_attempts, config.redial_base_delay());
+        let bootstrap_addrs = config
+            .bootstrap_peers
+            .iter()
+            .filter_map(|addr_str| match addr_str.parse::<Multiaddr>() {
+                Ok(addr) => Some(addr),
+                Err(e) => {
+                    warn!("Invalid bootstrap address {}: {}", addr_str, e);
+                    None
+                }
+            })
+            .collect();
+        let bootstrap = BootstrapDialer::new(
+            bootstrap_addrs,
+            config.bootstrap_max_attempts,
+            config.bootstrap_base_delay(),
+            config.bootstrap_max_delay(),
+            Instant::now(),
+        );
+        let dedup = MessageDeduplicator::new(config.dedup_window);
+        let heartbeat = config
+            .heartbeat_interval()
+            .map(|_| HeartbeatMonitor::new(config.heartbeat_miss_threshold));
+        // A report stays valid as long as its sender would count as alive
+        let topology = TopologyTracker::new(
+            config.heartbeat_interval().unwrap_or_default() * config.heartbeat_miss_threshold.max(1),
+        );
+
         let service = Self {
             swarm,
             config,
@@ -218,6 +463,20 @@ impl NetworkService {
             command_rx,
             command_tx,
             subscribed_topics: HashSet::new(),
+            mesh_sizes: HashMap::new(),
+            isolated_topics: HashSet::new(),
+            redial,
+            bootstrap,
+            dedup,
+            unvalidated: HashMap::new(),
+            bandwidth,
+            heartbeat,
+            heartbeat_seq: 0,
+            topology,
+            pending_dials: HashMap::new(),
+            connection_limit_reported: false,
+            listeners: Vec::new(),
+            shutdown_rx: None,
             stats: Arc::new(RwLock::new(NetworkStats::default())),
             start_time: Instant::now(),
             running: false,
@@ -226,6 +485,15 @@ impl NetworkService {
         Ok((service, handle, event_rx))
     }
 
+    /// Stop accepting connections when the node starts shutting down
+    ///
+    /// The service keeps running after the signal so pending messages can
+    /// still be published; it exits on [`NetworkHandle::shutdown`].
+    pub fn with_shutdown(mut self, shutdown_rx: broadcast::Receiver<()>) -> Self {
+        self.shutdown_rx = Some(shutdown_rx);
+        self
+    }
+
     /// Get a reference to the peer manager
     pub fn peer_manager(&self) -> &Arc<PeerManager> {
         &self.peer_manager
@@ -240,11 +508,17 @@ impl NetworkService {
             let addr: Multiaddr = addr_str.parse()
                 .map_err(|e| NetworkError::InvalidMultiaddr(format!("{}: {}", addr_str, e)))?;
 
-            self.swarm.listen_on(addr.clone())
+            if !self.config.transports.supports(&addr) {
+                info!("Skipping {} (transport not selected: {:?})", addr, self.config.transports);
+                continue;
+            }
+
+            let listener = self.swarm.listen_on(addr.clone())
                 .map_err(|e| NetworkError::ListenFailed {
                     address: addr_str.clone(),
                     reason: e.to_string(),
                 })?;
+            self.listeners.push(listener);
 
             info!("Listening on {}", addr);
         }
@@ -259,6 +533,7 @@ impl NetworkService {
             "/mycelial/1.0.0/announce",
             "/mycelial/1.0.0/reputation",
             "/mycelial/1.0.0/direct",
+            "/mycelial/1.0.0/sync",
             // Economics protocol topics (Phase 7)
             "/mycelial/1.0.0/vouch",      // Vouch/reputation delegation
             "/mycelial/1.0.0/credit",     // Mutual credit transactions
@@ -266,6 +541,10 @@ impl NetworkService {
             "/mycelial/1.0.0/resource",   // Resource sharing metrics
         ];
         for topic_str in topics {
+            if !self.config.is_topic_allowed(topic_str) {
+                info!("Not subscribing to {} (not in allowed topics)", topic_str);
+                continue;
+            }
             let topic = libp2p::gossipsub::IdentTopic::new(topic_str);
             match self.swarm.behaviour_mut().gossipsub.subscribe(&topic) {
                 Ok(true) => {
@@ -281,23 +560,18 @@ impl NetworkService {
             }
         }
 
-        // Connect to bootstrap peers
-        for addr_str in &self.config.bootstrap_peers.clone() {
-            let addr: Multiaddr = match addr_str.parse() {
-                Ok(a) => a,
-                Err(e) => {
-                    warn!("Invalid bootstrap address {}: {}", addr_str, e);
-                    continue;
-                }
-            };
-
-            if let Err(e) = self.swarm.dial(addr.clone()) {
-                warn!("Failed to dial bootstrap peer {}: {:?}", addr, e);
-            } else {
-                info!("Dialing bootstrap peer {}", addr);
+        // The heartbeat topic is internal, so it isn't reported as subscribed
+        if self.heartbeat.is_some() {
+            let topic = gossipsub::IdentTopic::new(topics::HEARTBEAT);
+            if let Err(e) = self.swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+                warn!("Failed to subscribe to heartbeats, disabling them: {:?}", e);
+                self.heartbeat = None;
             }
         }
 
+        // Connect to bootstrap peers
+        self.process_bootstrap_dials();
+
         self.running = true;
 
         // Emit started event
@@ -306,14 +580,74 @@ impl NetworkService {
             listen_addresses: self.swarm.listeners().cloned().collect(),
         });
 
+        // Periodic check for due redials of trusted peers
+        let mut redial_tick = tokio::time::interval(Duration::from_secs(1));
+
+        // Periodic per-peer bandwidth reports
+        let report_interval = self.config.bandwidth_report_interval();
+        let report_period = report_interval.unwrap_or(Duration::from_secs(3600));
+        let mut bandwidth_tick =
+            tokio::time::interval_at(tokio::time::Instant::now() + report_period, report_period);
+
+        // Periodic check for subscribed topics nobody else is meshed on
+        let health_interval = self.config.topic_health_interval();
+        let health_period = health_interval.unwrap_or(Duration::from_secs(3600));
+        let mut topic_health_tick =
+            tokio::time::interval_at(tokio::time::Instant::now() + health_period, health_period);
+
+        // Periodic liveness heartbeats
+        let heartbeat_interval = self.config.heartbeat_interval().filter(|_| self.heartbeat.is_some());
+        let heartbeat_period = heartbeat_interval.unwrap_or(Duration::from_secs(3600));
+        let mut heartbeat_tick =
+            tokio::time::interval_at(tokio::time::Instant::now() + heartbeat_period, heartbeat_period);
+
+        let mut shutdown_rx = self.shutdown_rx.take();
+
         // Main event loop
         loop {
+            let bootstrap_due = self.bootstrap.next_due().map(tokio::time::Instant::from_std);
+
             tokio::select! {
                 // Handle swarm events
                 event = self.swarm.select_next_some() => {
                     self.handle_swarm_event(event).await;
                 }
 
+                // Redial disconnected trusted peers
+                _ = redial_tick.tick() => {
+                    self.process_redials();
+                    self.swarm.behaviour_mut().gate.prune(Instant::now());
+                    self.unvalidated.retain(|_, (_, at)| at.elapsed() < UNVALIDATED_MESSAGE_TTL);
+                }
+
+                // Retry bootstrap peers whose backoff has elapsed
+                _ = tokio::time::sleep_until(bootstrap_due.unwrap_or_else(tokio::time::Instant::now)),
+                    if bootstrap_due.is_some() =>
+                {
+                    self.process_bootstrap_dials();
+                }
+
+                // Report bytes exchanged with each peer
+                _ = bandwidth_tick.tick(), if report_interval.is_some() => {
+                    self.report_bandwidth();
+                }
+
+                // Flag topics whose mesh emptied or refilled
+                _ = topic_health_tick.tick(), if health_interval.is_some() => {
+                    self.check_topic_health();
+                }
+
+                // Send our heartbeat and flag peers that stopped sending theirs
+                _ = heartbeat_tick.tick(), if heartbeat_interval.is_some() => {
+                    self.process_heartbeats();
+                }
+
+                // Node is shutting down: refuse new inbound connections
+                _ = shutdown_signal(&mut shutdown_rx), if shutdown_rx.is_some() => {
+                    shutdown_rx = None;
+                    self.stop_listening();
+                }
+
                 // Handle commands
                 Some(cmd) = self.command_rx.recv() => {
                     if !self.handle_command(cmd).await {
@@ -322,6 +656,10 @@ impl NetworkService {
                 }
             }
 
+            // Gossipsub has no graft/prune events, so diff mesh sizes instead;
+            // the redial tick bounds how stale this can get after a heartbeat
+            self.check_mesh_changes();
+
             // Update stats
             {
                 let mut stats = self.stats.write();
@@ -338,6 +676,67 @@ impl NetworkService {
         Ok(())
     }
 
+    /// Close every listener opened in [`run`](Self::run)
+    fn stop_listening(&mut self) {
+        info!("Shutdown signalled, no longer accepting connections");
+        for listener in self.listeners.drain(..) {
+            self.swarm.remove_listener(listener);
+        }
+    }
+
+    /// Emit `MeshUpdated` for every topic whose mesh size changed
+    fn check_mesh_changes(&mut self) {
+        let gossipsub = &self.swarm.behaviour().gossipsub;
+        let current: HashMap<String, usize> = self
+            .subscribed_topics
+            .iter()
+            .map(|topic| {
+                let hash = libp2p::gossipsub::IdentTopic::new(topic.as_str()).hash();
+                (topic.clone(), gossipsub.mesh_peers(&hash).count())
+            })
+            .collect();
+
+        // Topics we left report an empty mesh once
+        for (topic, previous) in &self.mesh_sizes {
+            if *previous > 0 && !current.contains_key(topic) {
+                let _ = self.event_tx.send(NetworkEvent::MeshUpdated {
+                    topic: topic.clone(),
+                    mesh_peers: 0,
+                });
+            }
+        }
+
+        for (topic, &mesh_peers) in &current {
+            let previous = self.mesh_sizes.get(topic).copied().unwrap_or(0);
+            if mesh_peers != previous {
+                debug!("Mesh for {} changed: {} -> {} peers", topic, previous, mesh_peers);
+                let _ = self.event_tx.send(NetworkEvent::MeshUpdated {
+                    topic: topic.clone(),
+                    mesh_peers,
+                });
+            }
+        }
+
+        self.mesh_sizes = current;
+    }
+
+    /// Emit `TopicIsolated`/`TopicConnected` when a topic's mesh empties or refills
+    fn check_topic_health(&mut self) {
+        let gossipsub = &self.swarm.behaviour().gossipsub;
+        self.isolated_topics.retain(|topic| self.subscribed_topics.contains(topic));
+
+        for topic in &self.subscribed_topics {
+            let hash = libp2p::gossipsub::IdentTopic::new(topic.as_str()).hash();
+            let isolated = gossipsub.mesh_peers(&hash).next().is_none();
+
+            if isolated && self.isolated_topics.insert(topic.clone()) {
+                let _ = self.event_tx.send(NetworkEvent::TopicIsolated { topic: topic.clone() });
+            } else if !isolated && self.isolated_topics.remove(topic) {
+                let _ = self.event_tx.send(NetworkEvent::TopicConnected { topic: topic.clone() });
+            }
+        }
+    }
+
     /// Handle a swarm event
     async fn handle_swarm_event(&mut self, event: SwarmEvent<MycelialBehaviourEvent>) {
         match event {
@@ -347,16 +746,25 @@ impl NetworkService {
 
             SwarmEvent::ConnectionEstablished {
                 peer_id,
+                connection_id,
                 num_established,
                 endpoint,
                 ..
             } => {
                 debug!("Connection established with {}", peer_id);
+                let pending_dial = self.pending_dials.remove(&connection_id);
 
                 self.peer_manager.set_state(peer_id, ConnectionState::Connected);
+                self.redial.on_connected(&peer_id);
+                if self.bootstrap.on_connected(connection_id, peer_id) {
+                    info!("Connected to bootstrap peer {}", peer_id);
+                }
 
                 let addr = endpoint.get_remote_address();
                 self.peer_manager.add_address(peer_id, addr.clone());
+                if let Some(response) = pending_dial {
+                    let _ = response.send(Ok(peer_id));
+                }
 
                 let _ = self.event_tx.send(NetworkEvent::ConnectionEstablished {
                     peer_id,
@@ -380,13 +788,42 @@ impl NetworkService {
             } => {
                 debug!("Connection closed with {}: {:?}", peer_id, cause);
 
+                if let Some(limit) = self.config.max_connections {
+                    let established =
+                        self.swarm.network_info().connection_counters().num_established();
+                    if (established as usize) < limit {
+                        self.connection_limit_reported = false;
+                    }
+                }
+
                 if num_established == 0 {
                     self.peer_manager.set_state(peer_id, ConnectionState::Disconnected);
+                    if let Some(monitor) = self.heartbeat.as_mut() {
+                        monitor.forget(&peer_id);
+                    }
+
+                    // Final totals for the session, then stop tracking the peer
+                    if let Some((bytes_in, bytes_out)) = self.bandwidth.remove(&peer_id) {
+                        let _ = self.event_tx.send(NetworkEvent::BandwidthReport {
+                            peer_id,
+                            bytes_in,
+                            bytes_out,
+                        });
+                    }
 
                     let _ = self.event_tx.send(NetworkEvent::PeerDisconnected {
                         peer_id,
                         num_connections: self.peer_manager.connected_count(),
                     });
+
+                    let trusted = self.peer_manager.is_trusted(&peer_id);
+                    if self.redial.on_disconnected(peer_id, trusted, Instant::now()) {
+                        info!("Trusted peer {} disconnected, scheduling redial", peer_id);
+                        self.process_redials();
+                    }
+                    if self.bootstrap.on_disconnected(&peer_id, Instant::now()) {
+                        info!("Lost bootstrap peer {}, reconnecting", peer_id);
+                    }
                 }
 
                 let _ = self.event_tx.send(NetworkEvent::ConnectionClosed {
@@ -396,32 +833,314 @@ impl NetworkService {
                 });
             }
 
+            // Addresses can be bound at different times (QUIC often after
+            // TCP), so each one is announced as it appears
             SwarmEvent::NewListenAddr { address, .. } => {
-                info!("Listening on {}", address);
-                let _ = self.event_tx.send(NetworkEvent::ListeningOn { address });
+                let ListenAddress { address, transport, scope } = ListenAddress::new(address);
+                info!("Listening on {} ({:?}, {:?})", address, transport, scope);
+                let _ = self.event_tx.send(NetworkEvent::ListeningOn {
+                    address,
+                    transport,
+                    scope,
+                });
+            }
+
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                info!("No longer listening on {}", address);
+                let _ = self.event_tx.send(NetworkEvent::ListenAddressExpired { address });
+            }
+
+            SwarmEvent::ListenerClosed { addresses, .. } => {
+                for address in addresses {
+                    info!("No longer listening on {}", address);
+                    let _ = self.event_tx.send(NetworkEvent::ListenAddressExpired { address });
+                }
             }
 
-            SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+            SwarmEvent::IncomingConnectionError {
+                send_back_addr,
+                error,
+                ..
+            } => {
+                if let ListenError::Denied { cause } = &error {
+                    if self.note_gate_denied(cause) {
+                        return;
+                    }
+                    if self.note_connection_denied(cause) {
+                        debug!("Refused connection from {}: connection limit reached", send_back_addr);
+                        return;
+                    }
+                }
+                debug!("Incoming connection from {} failed: {}", send_back_addr, error);
+            }
+
+            SwarmEvent::OutgoingConnectionError {
+                connection_id,
+                peer_id,
+                error,
+            } => {
+                if let DialError::Denied { cause } = &error {
+                    if self.note_gate_denied(cause) {
+                        if let Some(response) = self.pending_dials.remove(&connection_id) {
+                            let _ = response.send(Err(error.to_string()));
+                        }
+                        if let Some((address, failure)) =
+                            self.bootstrap.on_dial_failed(connection_id, Instant::now())
+                        {
+                            self.report_bootstrap_failure(address, failure, error.to_string());
+                        }
+                        return;
+                    }
+                    self.note_connection_denied(cause);
+                }
+
                 if let Some(peer_id) = peer_id {
                     warn!("Dial error for {}: {:?}", peer_id, error);
                     self.peer_manager.set_state(peer_id, ConnectionState::Failed);
+                    self.redial.on_dial_failed(&peer_id, Instant::now());
                 }
 
-                let _ = self.event_tx.send(NetworkEvent::DialFailed {
-                    peer_id,
-                    error: error.to_string(),
+                // Surface negotiation failures distinctly so incompatible peers are diagnosable
+                let error = match NegotiationFailure::classify(&error) {
+                    Some(failure) => {
+                        warn!("Negotiation failure ({}) dialing {:?}", failure, peer_id);
+                        self.stats.write().negotiation_failures.record(failure);
+                        format!("[{}] {}", failure.as_str(), error)
+                    }
+                    None => error.to_string(),
+                };
+
+                if let Some(response) = self.pending_dials.remove(&connection_id) {
+                    let _ = response.send(Err(error.clone()));
+                }
+
+                if let Some((address, failure)) =
+                    self.bootstrap.on_dial_failed(connection_id, Instant::now())
+                {
+                    self.report_bootstrap_failure(address, failure, error);
+                    return;
+                }
+
+                let _ = self.event_tx.send(NetworkEvent::DialFailed { peer_id, error });
+            }
+
+            SwarmEvent::Dialing { peer_id: Some(peer_id), .. } => {
+                debug!("Dialing {}", peer_id);
+                self.peer_manager.set_state(peer_id, ConnectionState::Connecting);
+                let _ = self.event_tx.send(NetworkEvent::Dialing { peer_id });
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Dial trusted peers whose redial is due, using their known addresses
+    fn process_redials(&mut self) {
+        let now = Instant::now();
+        for peer_id in self.redial.due(now) {
+            let addresses: Vec<Multiaddr> = self
+                .peer_manager
+                .get(&peer_id)
+                .map(|info| info.addresses.iter().filter_map(|a| a.parse().ok()).collect())
+                .unwrap_or_default();
+
+            if addresses.is_empty() {
+                debug!("No known addresses to redial {}", peer_id);
+                self.redial.on_dial_failed(&peer_id, now);
+                continue;
+            }
+
+            let opts = DialOpts::peer_id(peer_id)
+                .addresses(addresses)
+                .condition(PeerCondition::Disconnected)
+                .build();
+
+            match self.swarm.dial(opts) {
+                Ok(()) => info!("Redialing trusted peer {}", peer_id),
+                Err(e) => {
+                    debug!("Redial of {} not started: {:?}", peer_id, e);
+                    self.redial.on_dial_failed(&peer_id, now);
+                }
+            }
+        }
+    }
+
+    /// Check whether a denied connection hit the connection limit, emitting
+    /// `ConnectionLimitReached` the first time it happens
+    fn note_connection_denied(&mut self, cause: &ConnectionDenied) -> bool {
+        if cause.downcast_ref::<connection_limits::Exceeded>().is_none() {
+            return false;
+        }
+
+        if !self.connection_limit_reported {
+            self.connection_limit_reported = true;
+            let limit = self.config.max_connections.unwrap_or_default();
+            warn!("Connection limit of {} reached, refusing new connections", limit);
+            let _ = self.event_tx.send(NetworkEvent::ConnectionLimitReached { limit });
+        }
+        true
+    }
+
+    /// Check whether the gate denied a connection, reporting the blocked or
+    /// throttled peer
+    fn note_gate_denied(&mut self, cause: &ConnectionDenied) -> bool {
+        match cause.downcast_ref::<gate::Denied>() {
+            Some(gate::Denied::Blocked { peer_id }) => {
+                info!("Refused connection with blocked peer {}", peer_id);
+                let _ = self.event_tx.send(NetworkEvent::ConnectionBlocked { peer_id: *peer_id });
+                true
+            }
+            Some(gate::Denied::Throttled { peer_id, recent_connections }) => {
+                warn!(
+                    "Throttling {}: {} connections in the last minute",
+                    peer_id, recent_connections
+                );
+                let _ = self.event_tx.send(NetworkEvent::ConnectionThrottled {
+                    peer_id: *peer_id,
+                    recent_connections: *recent_connections,
                 });
+                true
             }
+            None => false,
+        }
+    }
 
-            SwarmEvent::Dialing { peer_id, .. } => {
-                if let Some(peer_id) = peer_id {
-                    debug!("Dialing {}", peer_id);
-                    self.peer_manager.set_state(peer_id, ConnectionState::Connecting);
-                    let _ = self.event_tx.send(NetworkEvent::Dialing { peer_id });
+    /// Dial bootstrap peers whose next attempt is due
+    fn process_bootstrap_dials(&mut self) {
+        let now = Instant::now();
+        for (index, addr) in self.bootstrap.due(now) {
+            let opts = DialOpts::unknown_peer_id().address(addr.clone()).build();
+            let connection_id = opts.connection_id();
+
+            match self.swarm.dial(opts) {
+                Ok(()) => {
+                    info!("Dialing bootstrap peer {}", addr);
+                    self.bootstrap.dialing(index, connection_id);
+                }
+                Err(e) => {
+                    warn!("Failed to dial bootstrap peer {}: {:?}", addr, e);
+                    if let Some((address, failure)) = self.bootstrap.dial_not_started(index, now) {
+                        self.report_bootstrap_failure(address, failure, e.to_string());
+                    }
                 }
             }
+        }
+    }
 
-            _ => {}
+    /// Announce a retry, or a dial failure once a bootstrap peer is given up on
+    fn report_bootstrap_failure(&self, address: Multiaddr, failure: BootstrapFailure, error: String) {
+        match failure {
+            BootstrapFailure::Retry { attempt, delay } => {
+                info!(
+                    "Bootstrap peer {} unreachable (attempt {}), retrying in {:?}",
+                    address, attempt, delay
+                );
+                let _ = self.event_tx.send(NetworkEvent::BootstrapDialRetry {
+                    address,
+                    attempt,
+                    delay,
+                });
+            }
+            BootstrapFailure::Exhausted { attempts } => {
+                warn!(
+                    "Giving up on bootstrap peer {} after {} attempts: {}",
+                    address, attempts, error
+                );
+                let _ = self.event_tx.send(NetworkEvent::DialFailed {
+                    peer_id: None,
+                    error,
+                });
+            }
+        }
+    }
+
+    /// Publish this interval's heartbeat and report peers that went silent
+    fn process_heartbeats(&mut self) {
+        let Some(monitor) = self.heartbeat.as_mut() else {
+            return;
+        };
+        for peer_id in monitor.tick() {
+            warn!("Peer {} missed {} heartbeats", peer_id, self.config.heartbeat_miss_threshold);
+            let _ = self.event_tx.send(NetworkEvent::PeerUnresponsive { peer_id });
+        }
+
+        self.heartbeat_seq += 1;
+        let connected: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+        let payload =
+            heartbeat::heartbeat_payload(self.swarm.local_peer_id(), self.heartbeat_seq, &connected);
+        // Every subscriber must hear from us, not only mesh peers, or peers
+        // outside the mesh would count our heartbeats as missed. Fails
+        // harmlessly while no peer is subscribed.
+        if let Err(e) = self.swarm.behaviour_mut().flood_publish(topics::HEARTBEAT, payload) {
+            debug!("Heartbeat not sent: {}", e);
+        }
+    }
+
+    /// Emit a bandwidth report for every connected peer
+    fn report_bandwidth(&self) {
+        for (peer_id, bytes_in, bytes_out) in self.bandwidth.snapshot() {
+            // Connections refused after the handshake never get a close event
+            if !self.swarm.is_connected(&peer_id) {
+                self.bandwidth.remove(&peer_id);
+                continue;
+            }
+            let _ = self.event_tx.send(NetworkEvent::BandwidthReport {
+                peer_id,
+                bytes_in,
+                bytes_out,
+            });
+        }
+    }
+
+    /// Emit a received gossipsub message unless it was already delivered
+    fn deliver_message(&mut self, message_id: MessageId, propagation_source: PeerId, message: gossipsub::Message) {
+        debug!(
+            "Received message on topic {} from {:?}",
+            message.topic, message.source
+        );
+
+        if !self.config.is_topic_allowed(message.topic.as_str()) {
+            debug!("Dropping message on disallowed topic {}", message.topic);
+            self.validate(&message_id, &propagation_source, MessageAcceptance::Ignore);
+            return;
+        }
+
+        let is_new = self.dedup.check(&message_id, Instant::now());
+        {
+            let mut stats = self.stats.write();
+            stats.messages_received += 1;
+            stats.bytes_received += message.data.len() as u64;
+            stats.messages_deduplicated = self.dedup.duplicates();
+        }
+
+        if !is_new {
+            debug!("Dropping duplicate message {}", message_id);
+            self.validate(&message_id, &propagation_source, MessageAcceptance::Ignore);
+            return;
+        }
+
+        if self.config.validate_messages {
+            self.unvalidated.insert(message_id.clone(), (propagation_source, Instant::now()));
+        }
+        let _ = self.event_tx.send(NetworkEvent::MessageReceived {
+            message_id,
+            topic: message.topic.to_string(),
+            source: message.source,
+            data: message.data,
+            timestamp: chrono::Utc::now(),
+        });
+    }
+
+    /// Report a message's validation result to gossipsub, if messages are validated
+    fn validate(&mut self, message_id: &MessageId, propagation_source: &PeerId, acceptance: MessageAcceptance) {
+        if !self.config.validate_messages {
+            return;
+        }
+        let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+        match gossipsub.report_message_validation_result(message_id, propagation_source, acceptance) {
+            Ok(true) => {}
+            Ok(false) => debug!("Message {} left the cache before it was validated", message_id),
+            Err(e) => debug!("Failed to forward message {}: {}", message_id, e),
         }
     }
 
@@ -429,28 +1148,41 @@ impl NetworkService {
     async fn handle_behaviour_event(&mut self, event: MycelialBehaviourEvent) {
         match event {
             MycelialBehaviourEvent::Gossipsub(gossipsub::Event::Message {
-                propagation_source: _,
+                propagation_source,
                 message_id,
                 message,
             }) => {
-                debug!(
-                    "Received message on topic {} from {:?}",
-                    message.topic, message.source
-                );
+                if message.topic.as_str() == topics::HEARTBEAT {
+                    // Only the peer that handed it over is evidently alive; the
+                    // author may have gone silent since, with others relaying
+                    if let Some(monitor) = self.heartbeat.as_mut() {
+                        monitor.on_heartbeat(&propagation_source);
+                    }
+                    if let (Some(source), Some(neighbours)) =
+                        (message.source, heartbeat::heartbeat_neighbours(&message.data))
+                    {
+                        self.topology.record(source, neighbours, Instant::now());
+                    }
+                    self.validate(&message_id, &propagation_source, MessageAcceptance::Accept);
+                    return;
+                }
+                self.deliver_message(message_id, propagation_source, message);
+            }
 
-                {
-                    let mut stats = self.stats.write();
-                    stats.messages_received += 1;
-                    stats.bytes_received += message.data.len() as u64;
+            MycelialBehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic })
+                if topic.as_str() == topics::HEARTBEAT =>
+            {
+                if let Some(monitor) = self.heartbeat.as_mut() {
+                    monitor.watch(peer_id);
                 }
+            }
 
-                let _ = self.event_tx.send(NetworkEvent::MessageReceived {
-                    message_id,
-                    topic: message.topic.to_string(),
-                    source: message.source,
-                    data: message.data,
-                    timestamp: chrono::Utc::now(),
-                });
+            MycelialBehaviourEvent::Gossipsub(gossipsub::Event::Unsubscribed { peer_id, topic })
+                if topic.as_str() == topics::HEARTBEAT =>
+            {
+                if let Some(monitor) = self.heartbeat.as_mut() {
+                    monitor.forget(&peer_id);
+                }
             }
 
             MycelialBehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic }) => {
@@ -562,37 +1294,114 @@ impl NetworkService {
     /// Handle a command, returns false if should shutdown
     async fn handle_command(&mut self, cmd: NetworkCommand) -> bool {
         match cmd {
-            NetworkCommand::Dial { address } => {
-                if let Err(e) = self.swarm.dial(address.clone()) {
-                    warn!("Failed to dial {}: {:?}", address, e);
-                } else {
-                    debug!("Dialing {}", address);
+            NetworkCommand::Dial { address, response } => {
+                let result = match self.swarm.dial(address.clone()) {
+                    Ok(()) => {
+                        debug!("Dialing {}", address);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        warn!("Failed to dial {}: {:?}", address, e);
+                        Err(NetworkError::DialFailed { peer: address.to_string(), reason: e.to_string() })
+                    }
+                };
+                let _ = response.send(result);
+            }
+
+            NetworkCommand::DialAndWait { address, response } => {
+                let opts = DialOpts::unknown_peer_id().address(address.clone()).build();
+                let connection_id = opts.connection_id();
+                match self.swarm.dial(opts) {
+                    Ok(()) => {
+                        debug!("Dialing {}", address);
+                        self.pending_dials.insert(connection_id, response);
+                    }
+                    Err(e) => {
+                        warn!("Failed to dial {}: {:?}", address, e);
+                        let _ = response.send(Err(e.to_string()));
+                    }
                 }
             }
 
             NetworkCommand::Disconnect { peer_id } => {
+                // Deliberate disconnects must not trigger a redial
+                self.redial.suppress(peer_id);
                 let _ = self.swarm.disconnect_peer_id(peer_id);
             }
 
-            NetworkCommand::Subscribe { topic } => {
-                if let Err(e) = self.swarm.behaviour_mut().subscribe(&topic) {
-                    warn!("Failed to subscribe to {}: {:?}", topic, e);
-                } else {
-                    self.subscribed_topics.insert(topic.clone());
-                    let _ = self.event_tx.send(NetworkEvent::Subscribed { topic });
+            NetworkCommand::BlockPeer { peer_id } => {
+                if self.swarm.behaviour_mut().gate.block(peer_id) {
+                    info!("Blocked peer {}", peer_id);
+                }
+                // A blocked peer that already dropped off must not be redialed either
+                self.redial.cancel(&peer_id);
+                if self.swarm.is_connected(&peer_id) {
+                    self.redial.suppress(peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    let _ = self.event_tx.send(NetworkEvent::ConnectionBlocked { peer_id });
+                }
+            }
+
+            NetworkCommand::UnblockPeer { peer_id } => {
+                if self.swarm.behaviour_mut().gate.unblock(&peer_id) {
+                    info!("Unblocked peer {}", peer_id);
                 }
             }
 
-            NetworkCommand::Unsubscribe { topic } => {
-                if let Err(e) = self.swarm.behaviour_mut().unsubscribe(&topic) {
-                    warn!("Failed to unsubscribe from {}: {:?}", topic, e);
+            NetworkCommand::Subscribe { topic, response } => {
+                let result = if !self.config.is_topic_allowed(&topic) {
+                    warn!("Refusing to subscribe to disallowed topic {}", topic);
+                    Err(NetworkError::TopicNotAllowed(topic))
                 } else {
-                    self.subscribed_topics.remove(&topic);
-                    let _ = self.event_tx.send(NetworkEvent::Unsubscribed { topic });
+                    match self.swarm.behaviour_mut().subscribe(&topic) {
+                        Ok(true) => {
+                            self.subscribed_topics.insert(topic.clone());
+                            let _ = self.event_tx.send(NetworkEvent::Subscribed { topic });
+                            Ok(true)
+                        }
+                        Ok(false) => {
+                            debug!("Already subscribed to {}", topic);
+                            Ok(false)
+                        }
+                        Err(e) => {
+                            warn!("Failed to subscribe to {}: {:?}", topic, e);
+                            Err(e)
+                        }
+                    }
+                };
+                let _ = response.send(result);
+            }
+
+            NetworkCommand::Unsubscribe { topic, response } => {
+                let result = match self.swarm.behaviour_mut().unsubscribe(&topic) {
+                    Ok(true) => {
+                        self.subscribed_topics.remove(&topic);
+                        let _ = self.event_tx.send(NetworkEvent::Unsubscribed { topic });
+                        Ok(true)
+                    }
+                    Ok(false) => Ok(false),
+                    Err(e) => {
+                        warn!("Failed to unsubscribe from {}: {:?}", topic, e);
+                        Err(e)
+                    }
+                };
+                let _ = response.send(result);
+            }
+
+            NetworkCommand::UnsubscribeAll => {
+                let mut topics: Vec<String> = self.subscribed_topics.iter().cloned().collect();
+                topics.sort();
+                for topic in topics {
+                    if let Err(e) = self.swarm.behaviour_mut().unsubscribe(&topic) {
+                        warn!("Failed to unsubscribe from {}: {:?}", topic, e);
+                    } else {
+                        self.subscribed_topics.remove(&topic);
+                        let _ = self.event_tx.send(NetworkEvent::Unsubscribed { topic });
+                    }
                 }
             }
 
-            NetworkCommand::Publish { topic, data } => {
+            NetworkCommand::Publish { topic, data, response } => {
                 // Log mesh status before publishing for debugging
                 let mesh_peers = self.swarm.behaviour().mesh_peers(&topic);
                 let all_peers = self.swarm.behaviour().all_peers_on_topic(&topic);
@@ -614,12 +1423,32 @@ impl NetworkService {
                     debug!("Mesh peers for '{}': {:?}", topic, mesh_peers);
                 }
 
-                match self.swarm.behaviour_mut().publish(&topic, data.clone()) {
+                let data_len = data.len();
+                let flood = self
+                    .config
+                    .flood_publish_below
+                    .is_some_and(|min_mesh| mesh_peers.len() < min_mesh);
+                let result = if flood {
+                    self.swarm.behaviour_mut().flood_publish(&topic, data)
+                } else {
+                    self.swarm.behaviour_mut().publish(&topic, data)
+                };
+                match &result {
                     Ok(msg_id) => {
-                        info!("Published message {} to '{}' via {} mesh peers", msg_id, topic, mesh_peers.len());
+                        if flood {
+                            info!("Published message {} to '{}' by flooding {} subscribers", msg_id, topic, all_peers.len());
+                        } else {
+                            info!("Published message {} to '{}' via {} mesh peers", msg_id, topic, mesh_peers.len());
+                        }
+                        // Our own message relayed back to us is a duplicate,
+                        // never something received from others
+                        self.dedup.check(msg_id, Instant::now());
                         let mut stats = self.stats.write();
                         stats.messages_sent += 1;
-                        stats.bytes_sent += data.len() as u64;
+                        if flood {
+                            stats.messages_flood_published += 1;
+                        }
+                        stats.bytes_sent += data_len as u64;
                     }
                     Err(e) => {
                         warn!(
@@ -628,6 +1457,7 @@ impl NetworkService {
                         );
                     }
                 }
+                let _ = response.send(result);
             }
 
             NetworkCommand::PutRecord { key, value } => {
@@ -650,6 +1480,35 @@ impl NetworkService {
                 let _ = response.send(stats);
             }
 
+            NetworkCommand::GetGraph { response } => {
+                let connected: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+                let graph = self.topology.graph(*self.swarm.local_peer_id(), &connected, Instant::now());
+                let _ = response.send(graph);
+            }
+
+            NetworkCommand::SetPeerScore { peer_id, reputation } => {
+                let score = scoring::reputation_to_app_score(reputation);
+                let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+                if gossipsub.set_application_score(&peer_id, score) {
+                    debug!("Peer {} reputation {:.2} -> app score {:.2}", peer_id, reputation, score);
+                } else {
+                    debug!("Peer {} is not scored yet, ignoring reputation {:.2}", peer_id, reputation);
+                }
+            }
+
+            NetworkCommand::ReportMessage { message_id, acceptance } => match self.unvalidated.remove(&message_id) {
+                Some((propagation_source, _)) => self.validate(&message_id, &propagation_source, acceptance),
+                None => debug!("No message {} awaits validation", message_id),
+            },
+
+            NetworkCommand::GetPeerScore { peer_id, response } => {
+                let _ = response.send(self.swarm.behaviour().gossipsub.peer_score(&peer_id));
+            }
+
+            NetworkCommand::GetMeshPeers { topic, response } => {
+                let _ = response.send(self.swarm.behaviour().mesh_peers(&topic));
+            }
+
             NetworkCommand::Shutdown => {
                 info!("Shutdown requested");
                 return false;
@@ -659,3 +1518,972 @@ impl NetworkService {
         true
     }
 }
+
+/// Resolve once the node-wide shutdown signal fires (or its sender is gone)
+async fn shutdown_signal(shutdown_rx: &mut Option<broadcast::Receiver<()>>) {
+    match shutdown_rx {
+        Some(rx) => {
+            let _ = rx.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TransportSelection;
+    use libp2p::multiaddr::Protocol;
+
+    #[tokio::test]
+    async fn test_tcp_only_listens_on_tcp() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.transports = TransportSelection::TcpOnly;
+        config.listen_addresses = vec![
+            "/ip4/127.0.0.1/tcp/0".to_string(),
+            "/ip4/127.0.0.1/udp/0/quic-v1".to_string(),
+        ];
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+        let task = tokio::spawn(service.run());
+
+        // Collect listen addresses until the swarm goes quiet
+        let mut listeners = Vec::new();
+        while let Ok(event) =
+            tokio::time::timeout(Duration::from_millis(500), event_rx.recv()).await
+        {
+            if let NetworkEvent::ListeningOn { address, .. } = event.unwrap() {
+                listeners.push(address);
+            }
+        }
+
+        assert_eq!(listeners.len(), 1);
+        assert!(listeners[0].iter().any(|p| matches!(p, Protocol::Tcp(_))));
+        assert!(!listeners[0].iter().any(|p| matches!(p, Protocol::QuicV1)));
+
+        handle.shutdown().await.unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_report_after_traffic() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.bandwidth_report_interval_secs = 1;
+            config
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_a.dial(addr_b).await.unwrap();
+
+        // Identify and gossipsub subscriptions flow as soon as we connect
+        let report = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::BandwidthReport { peer_id, bytes_in, bytes_out } =
+                    events_a.recv().await.unwrap()
+                {
+                    if peer_id == peer_b && bytes_in > 0 && bytes_out > 0 {
+                        break (bytes_in, bytes_out);
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(report.is_ok(), "no bandwidth report with traffic to {}", peer_b);
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_network_graph_includes_direct_connections() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+
+        let (node_a, handle_a, _events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        let peer_b = handle_a.dial_and_wait(addr_b).await.unwrap();
+
+        let graph = handle_a.network_graph().await.unwrap();
+        let local = handle_a.local_peer_id().to_string();
+        assert_eq!(graph.local_peer_id, local);
+        assert!(graph.nodes.contains(&peer_b.to_string()));
+        assert!(graph
+            .edges
+            .iter()
+            .any(|e| e.direct && e.source == local && e.target == peer_b.to_string()));
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocked_peer_disconnected_and_refused() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, _events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_a = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_a.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_b.dial_and_wait(addr_a.clone()).await.unwrap();
+
+        // Blocking a connected peer drops its connection
+        handle_a.block_peer(peer_b).await.unwrap();
+        let mut blocked = false;
+        let disconnected = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match events_a.recv().await.unwrap() {
+                    NetworkEvent::ConnectionBlocked { peer_id } if peer_id == peer_b => blocked = true,
+                    NetworkEvent::PeerDisconnected { .. } => break,
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        assert!(disconnected.is_ok(), "blocked peer still connected");
+        assert!(blocked);
+        assert!(!handle_a.get_peers().await.unwrap().contains(&peer_b));
+
+        // Its reconnects are refused and never announced
+        handle_b.dial(addr_a).await.unwrap();
+        let refused = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match events_a.recv().await.unwrap() {
+                    NetworkEvent::ConnectionBlocked { peer_id } if peer_id == peer_b => break true,
+                    NetworkEvent::PeerConnected { peer_id, .. } if peer_id == peer_b => break false,
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        assert_eq!(refused, Ok(true));
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_rate_limit_throttles_reconnects() {
+        let test_config = |per_minute| {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.max_connections_per_peer_per_minute = per_minute;
+            config
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(Some(1))).unwrap();
+        let (node_b, handle_b, _events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(None)).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_a = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_a.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_b.dial(addr_a.clone()).await.unwrap();
+
+        // The first connection is accepted; the reconnect within the minute
+        // is denied and never announced
+        let throttled = tokio::time::timeout(Duration::from_secs(10), async {
+            let mut redialed = false;
+            loop {
+                match events_a.recv().await.unwrap() {
+                    NetworkEvent::PeerConnected { peer_id, .. } if peer_id == peer_b => {
+                        if redialed {
+                            break None;
+                        }
+                        handle_a.disconnect(peer_b).await.unwrap();
+                    }
+                    NetworkEvent::PeerDisconnected { peer_id, .. } if peer_id == peer_b && !redialed => {
+                        redialed = true;
+                        handle_b.dial(addr_a.clone()).await.unwrap();
+                    }
+                    NetworkEvent::ConnectionThrottled { peer_id, recent_connections } if peer_id == peer_b => {
+                        break Some(recent_connections);
+                    }
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        assert_eq!(throttled, Ok(Some(1)));
+        assert!(!handle_a.get_peers().await.unwrap().contains(&peer_b));
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit_refuses_excess_connections() {
+        let test_config = |max_connections| {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.max_connections = max_connections;
+            config
+        };
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (hub, hub_handle, mut hub_events) =
+            NetworkService::new(keypair, test_config(Some(1))).unwrap();
+        tokio::spawn(hub.run());
+        let hub_addr = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = hub_events.recv().await.unwrap() {
+                break address;
+            }
+        };
+
+        let mut spokes = Vec::new();
+        for _ in 0..3 {
+            let keypair = libp2p::identity::Keypair::generate_ed25519();
+            let (spoke, handle, _) = NetworkService::new(keypair, test_config(None)).unwrap();
+            tokio::spawn(spoke.run());
+            handle.dial(hub_addr.clone()).await.unwrap();
+            spokes.push(handle);
+        }
+
+        // One spoke gets in, the others are refused with a single notification
+        let mut connected = Vec::new();
+        let mut limit_events = 0;
+        while let Ok(event) = tokio::time::timeout(Duration::from_secs(2), hub_events.recv()).await {
+            match event.unwrap() {
+                NetworkEvent::PeerConnected { num_connections, .. } => {
+                    connected.push(num_connections)
+                }
+                NetworkEvent::ConnectionLimitReached { limit } => {
+                    assert_eq!(limit, 1);
+                    limit_events += 1;
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(connected, vec![1]);
+        assert_eq!(limit_events, 1);
+        assert_eq!(hub_handle.get_peers().await.unwrap().len(), 1);
+
+        hub_handle.shutdown().await.unwrap();
+        for handle in spokes {
+            handle.shutdown().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_silent_peer_reported_unresponsive() {
+        let test_config = |heartbeat_interval_secs| {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.heartbeat_interval_secs = heartbeat_interval_secs;
+            config.heartbeat_miss_threshold = 3;
+            config
+        };
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (hub, hub_handle, mut hub_events) = NetworkService::new(keypair, test_config(1)).unwrap();
+        tokio::spawn(hub.run());
+        let hub_addr = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = hub_events.recv().await.unwrap() {
+                break address;
+            }
+        };
+
+        // One peer sends heartbeats; the other joins the topic but never sends
+        let (responsive, responsive_handle, _) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(1)).unwrap();
+        let (silent, silent_handle, _) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(0)).unwrap();
+        tokio::spawn(responsive.run());
+        tokio::spawn(silent.run());
+        silent_handle.subscribe(topics::HEARTBEAT).await.unwrap();
+        responsive_handle.dial(hub_addr.clone()).await.unwrap();
+        silent_handle.dial(hub_addr).await.unwrap();
+
+        let (responsive_peer, silent_peer) = (responsive_handle.local_peer_id(), silent_handle.local_peer_id());
+        let reported = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerUnresponsive { peer_id } = hub_events.recv().await.unwrap() {
+                    assert_ne!(peer_id, responsive_peer, "a peer sending heartbeats was reported");
+                    if peer_id == silent_peer {
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(reported.is_ok(), "the silent peer was never reported");
+
+        hub_handle.shutdown().await.unwrap();
+        responsive_handle.shutdown().await.unwrap();
+        silent_handle.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_reach_peers_outside_mesh() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.heartbeat_interval_secs = 1;
+            config.heartbeat_miss_threshold = 2;
+            // No gossipsub heartbeat runs during the test to repair the mesh
+            config.gossipsub_heartbeat = Some(Duration::from_secs(60));
+            config.flood_publish_below = Some(2);
+            config
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_a.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerSubscribed { peer_id, .. } = events_a.recv().await.unwrap() {
+                    if peer_id == peer_b {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        // B leaving and rejoining makes A prune it and back it off, so B's
+        // graft is refused and it stays subscribed outside A's heartbeat mesh
+        assert!(handle_b.unsubscribe(topics::HEARTBEAT).await.unwrap());
+        assert!(handle_b.subscribe(topics::HEARTBEAT).await.unwrap());
+        let reported = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                tokio::select! {
+                    event = events_a.recv() => {
+                        if let NetworkEvent::PeerUnresponsive { .. } = event.unwrap() {
+                            break;
+                        }
+                    }
+                    event = events_b.recv() => {
+                        if let NetworkEvent::PeerUnresponsive { .. } = event.unwrap() {
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(reported.is_err(), "a peer outside the mesh was reported unresponsive");
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mesh_updated_on_graft_and_unsubscribe() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.gossipsub_heartbeat = Some(Duration::from_millis(200));
+            config
+        };
+        let topic = "/test/mesh";
+        let mesh_size = |events: &mut broadcast::Receiver<NetworkEvent>, expected: usize| {
+            let mut events = events.resubscribe();
+            async move {
+                tokio::time::timeout(Duration::from_secs(10), async move {
+                    loop {
+                        if let NetworkEvent::MeshUpdated { topic: updated, mesh_peers } = events.recv().await.unwrap() {
+                            if updated == topic && mesh_peers == expected {
+                                break;
+                            }
+                        }
+                    }
+                })
+                .await
+                .is_ok()
+            }
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        let grafted_a = mesh_size(&mut events_a, 1);
+        let grafted_b = mesh_size(&mut events_b, 1);
+        handle_a.subscribe(topic).await.unwrap();
+        handle_b.subscribe(topic).await.unwrap();
+        handle_a.dial(addr_b).await.unwrap();
+        assert!(grafted_a.await, "A never reported B grafted");
+        assert!(grafted_b.await, "B never reported A grafted");
+
+        // B leaving empties both meshes: A prunes B, and B's own mesh is gone
+        let pruned_a = mesh_size(&mut events_a, 0);
+        let pruned_b = mesh_size(&mut events_b, 0);
+        assert!(handle_b.unsubscribe(topic).await.unwrap());
+        assert!(pruned_a.await, "A never reported B pruned");
+        assert!(pruned_b.await, "B never reported its mesh emptied");
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_messages_not_forwarded() {
+        let test_config = |validate_messages| {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config.gossipsub_heartbeat = Some(Duration::from_millis(200));
+            config.validate_messages = validate_messages;
+            config
+        };
+        let topic = "/test/validated";
+
+        // A and C both only reach each other through B, which validates
+        let (node_a, handle_a, _events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(false)).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(true)).unwrap();
+        let (node_c, handle_c, mut events_c) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config(false)).unwrap();
+        let (peer_a, peer_b, peer_c) = (handle_a.local_peer_id(), handle_b.local_peer_id(), handle_c.local_peer_id());
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+        tokio::spawn(node_c.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        for handle in [&handle_a, &handle_b, &handle_c] {
+            handle.subscribe(topic).await.unwrap();
+        }
+        handle_a.dial(addr_b.clone()).await.unwrap();
+        handle_c.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let mesh = handle_b.mesh_peers(topic).await.unwrap();
+                let a_knows_b = handle_a.mesh_peers(topic).await.unwrap().contains(&peer_b);
+                if mesh.contains(&peer_a) && mesh.contains(&peer_c) && a_knows_b {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("mesh did not form");
+
+        // B rejects the first message and accepts the second
+        for (data, acceptance) in [(b"rejected", MessageAcceptance::Reject), (b"accepted", MessageAcceptance::Accept)] {
+            handle_a.publish(topic, data.to_vec()).await.unwrap();
+            let message_id = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    if let NetworkEvent::MessageReceived { message_id, .. } = events_b.recv().await.unwrap() {
+                        break message_id;
+                    }
+                }
+            })
+            .await
+            .unwrap();
+            handle_b.report_message(message_id, acceptance).await.unwrap();
+        }
+
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let NetworkEvent::MessageReceived { data, .. } = events_c.recv().await.unwrap() {
+                    break data;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, b"accepted");
+
+        for handle in [handle_a, handle_b, handle_c] {
+            handle.shutdown().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_isolated_topic_reported() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.topic_health_interval_secs = 1;
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+        let task = tokio::spawn(service.run());
+        handle.subscribe("/mycelial/1.0.0/lonely").await.unwrap();
+
+        let isolated = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let NetworkEvent::TopicIsolated { topic } = event_rx.recv().await.unwrap() {
+                    if topic == "/mycelial/1.0.0/lonely" {
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(isolated.is_ok(), "no TopicIsolated event for a topic without peers");
+
+        handle.shutdown().await.unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_peer_score_adjusts_gossipsub_score() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_a.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerConnected { peer_id, .. } = events_a.recv().await.unwrap() {
+                    if peer_id == peer_b {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        // Neutral reputation leaves the score untouched
+        handle_a.set_peer_score(peer_b, 0.5).await.unwrap();
+        assert_eq!(handle_a.peer_score(peer_b).await.unwrap(), Some(0.0));
+
+        handle_a.set_peer_score(peer_b, 0.0).await.unwrap();
+        let low = handle_a.peer_score(peer_b).await.unwrap().unwrap();
+        assert!(low < 0.0, "low reputation should score negatively, got {}", low);
+
+        handle_a.set_peer_score(peer_b, 1.0).await.unwrap();
+        let high = handle_a.peer_score(peer_b).await.unwrap().unwrap();
+        assert!(high > 0.0, "high reputation should score positively, got {}", high);
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_publish_returns_message_id() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+        let topic = "/mycelial/1.0.0/chat";
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        // Nobody to publish to yet
+        let err = handle_a.publish(topic, b"too early".to_vec()).await.unwrap_err();
+        assert!(matches!(err, NetworkError::Gossipsub(_)), "{:?}", err);
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_a.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerSubscribed { peer_id, topic: subscribed } =
+                    events_a.recv().await.unwrap()
+                {
+                    if peer_id == peer_b && subscribed == topic {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        let message_id = handle_a.publish(topic, b"hello".to_vec()).await.unwrap();
+        assert!(!message_id.0.is_empty());
+
+        // The receiver sees the same id
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::MessageReceived { message_id, .. } = events_b.recv().await.unwrap() {
+                    break message_id;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, message_id);
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flood_publish_without_mesh() {
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            // No heartbeat runs during the test to repair the mesh
+            config.gossipsub_heartbeat = Some(Duration::from_secs(60));
+            config.flood_publish_below = Some(2);
+            config
+        };
+        let topic = "/mycelial/1.0.0/chat";
+
+        let (node_a, handle_a, mut events_a) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, handle_b, mut events_b) =
+            NetworkService::new(libp2p::identity::Keypair::generate_ed25519(), test_config()).unwrap();
+        let peer_b = handle_b.local_peer_id();
+        let gossipsub = crate::behaviour::gossipsub_config(&node_a.config).unwrap();
+        assert!(!gossipsub.flood_publish());
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        handle_a.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerSubscribed { peer_id, topic: subscribed } =
+                    events_a.recv().await.unwrap()
+                {
+                    if peer_id == peer_b && subscribed == topic {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        // B was grafted when it subscribed; leaving and rejoining prunes it
+        // and backs it off, so it stays subscribed but outside the mesh
+        assert_eq!(handle_a.mesh_peers(topic).await.unwrap(), vec![peer_b]);
+        assert!(handle_a.unsubscribe(topic).await.unwrap());
+        assert!(handle_a.subscribe(topic).await.unwrap());
+        assert!(handle_a.mesh_peers(topic).await.unwrap().is_empty());
+        let message_id = handle_a.publish(topic, b"flooded".to_vec()).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::MessageReceived { message_id, .. } = events_b.recv().await.unwrap() {
+                    break message_id;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, message_id);
+        assert_eq!(handle_a.get_stats().await.unwrap().messages_flood_published, 1);
+
+        // Without the option gossipsub keeps flooding every message itself
+        let default = crate::behaviour::gossipsub_config(&NetworkConfig::local_test(0)).unwrap();
+        assert!(default.flood_publish());
+
+        handle_a.shutdown().await.unwrap();
+        handle_b.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mdns_disabled() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, _handle, _events) = NetworkService::new(keypair, config).unwrap();
+        assert!(!service.swarm.behaviour().mdns_enabled());
+
+        let config = NetworkConfig::local_test(0);
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, _handle, _events) = NetworkService::new(keypair, config).unwrap();
+        assert!(service.swarm.behaviour().mdns_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_custom_gossipsub_heartbeat() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.gossipsub_heartbeat = Some(Duration::from_millis(250));
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, _handle, _events) = NetworkService::new(keypair, config).unwrap();
+        let gossipsub = crate::behaviour::gossipsub_config(&service.config).unwrap();
+        assert_eq!(gossipsub.heartbeat_interval(), Duration::from_millis(250));
+
+        // None keeps the libp2p default
+        let default = crate::behaviour::gossipsub_config(&NetworkConfig::local_test(0)).unwrap();
+        assert_eq!(default.heartbeat_interval(), Duration::from_secs(1));
+
+        let mut config = NetworkConfig::local_test(0);
+        config.gossipsub_heartbeat = Some(Duration::ZERO);
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        assert!(NetworkService::new(keypair, config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_retries_with_backoff() {
+        // Nothing listens on port 1, so every dial is refused quickly
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.bootstrap_peers = vec!["/ip4/127.0.0.1/tcp/1".to_string()];
+        config.bootstrap_max_attempts = 4;
+        config.bootstrap_base_delay_ms = 50;
+        config.bootstrap_max_delay_ms = 150;
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+        let service_task = tokio::spawn(service.run());
+
+        let started = Instant::now();
+        let mut retries = Vec::new();
+        let failure = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match event_rx.recv().await.unwrap() {
+                    NetworkEvent::BootstrapDialRetry { attempt, delay, .. } => {
+                        retries.push((attempt, delay, started.elapsed()));
+                    }
+                    NetworkEvent::DialFailed { peer_id, error } => break (peer_id, error),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .expect("bootstrap dialing never gave up");
+
+        // DialFailed only once every attempt is used up
+        assert_eq!(failure.0, None);
+        let attempts: Vec<u32> = retries.iter().map(|(attempt, _, _)| *attempt).collect();
+        assert_eq!(attempts, vec![1, 2, 3]);
+
+        // Delays double and are capped at the maximum
+        let delays: Vec<u64> = retries.iter().map(|(_, delay, _)| delay.as_millis() as u64).collect();
+        assert_eq!(delays, vec![50, 100, 150]);
+
+        // Retries actually waited out the backoff
+        let gaps: Vec<Duration> = retries.windows(2).map(|w| w[1].2 - w[0].2).collect();
+        assert!(gaps[0] >= Duration::from_millis(50));
+        assert!(gaps[1] >= Duration::from_millis(100));
+
+        handle.shutdown().await.unwrap();
+        service_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_topic_allow_list() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.allowed_topics = Some(vec![
+            "/mycelial/1.0.0/chat".to_string(),
+            "/mycelial/1.0.0/economics/*".to_string(),
+        ]);
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (mut service, handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+
+        // Exact and wildcard matches are accepted, anything else refused
+        assert!(subscribe_via(&mut service, "/mycelial/1.0.0/chat").await.unwrap());
+        assert!(subscribe_via(&mut service, "/mycelial/1.0.0/economics/credit").await.unwrap());
+        let err = handle.subscribe("/mycelial/1.0.0/content").await.unwrap_err();
+        assert!(
+            matches!(err, NetworkError::TopicNotAllowed(topic) if topic == "/mycelial/1.0.0/content")
+        );
+        let err = subscribe_via(&mut service, "/mycelial/1.0.0/content").await.unwrap_err();
+        assert!(matches!(err, NetworkError::TopicNotAllowed(_)));
+
+        // Messages on disallowed topics never reach the application
+        let topics = ["/mycelial/1.0.0/content", "/mycelial/1.0.0/economics/vouch"];
+        for (i, topic) in topics.iter().enumerate() {
+            let message = gossipsub::Message {
+                source: None,
+                data: b"hello".to_vec(),
+                sequence_number: Some(i as u64),
+                topic: gossipsub::IdentTopic::new(*topic).hash(),
+            };
+            service.deliver_message(MessageId::from(vec![i as u8]), PeerId::random(), message);
+        }
+
+        let mut received = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let NetworkEvent::MessageReceived { topic, .. } = event {
+                received.push(topic);
+            }
+        }
+        assert_eq!(received, vec!["/mycelial/1.0.0/economics/vouch".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_all() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (mut service, _handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+
+        let topics = ["/test/a", "/test/b", "/test/c"];
+        for topic in topics {
+            subscribe_via(&mut service, topic).await.unwrap();
+        }
+        assert_eq!(service.subscribed_topics.len(), 3);
+
+        service.handle_command(NetworkCommand::UnsubscribeAll).await;
+        assert!(service.subscribed_topics.is_empty());
+
+        let mut left = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let NetworkEvent::Unsubscribed { topic } = event {
+                left.push(topic);
+            }
+        }
+        assert_eq!(left, topics.map(String::from).to_vec());
+
+        // Nothing left to leave
+        service.handle_command(NetworkCommand::UnsubscribeAll).await;
+        assert!(event_rx.try_recv().is_err());
+    }
+
+    /// Run a subscribe command on a service that isn't running
+    async fn subscribe_via(service: &mut NetworkService, topic: &str) -> Result<bool> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        service
+            .handle_command(NetworkCommand::Subscribe { topic: topic.to_string(), response: tx })
+            .await;
+        rx.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reports_outcome() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (service, handle, _event_rx) = NetworkService::new(keypair, config).unwrap();
+        let task = tokio::spawn(service.run());
+
+        let topic = "/mycelial/1.0.0/outcome";
+        assert!(handle.subscribe(topic).await.unwrap());
+        assert!(!handle.subscribe(topic).await.unwrap());
+        assert!(handle.unsubscribe(topic).await.unwrap());
+        assert!(!handle.unsubscribe(topic).await.unwrap());
+
+        // Publishing where nobody, not even this node, listens is refused
+        let err = handle.publish("/mycelial/1.0.0/nobody", b"hello".to_vec()).await.unwrap_err();
+        assert!(matches!(err, NetworkError::Gossipsub(_)), "{:?}", err);
+
+        handle.shutdown().await.unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_message_emitted_once() {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config.dedup_window = Duration::from_secs(60);
+
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let (mut service, _handle, mut event_rx) = NetworkService::new(keypair, config).unwrap();
+
+        let message = gossipsub::Message {
+            source: None,
+            data: b"hello".to_vec(),
+            sequence_number: Some(1),
+            topic: gossipsub::IdentTopic::new("test").hash(),
+        };
+        let message_id = MessageId::from(b"same-id".to_vec());
+
+        service.deliver_message(message_id.clone(), PeerId::random(), message.clone());
+        service.deliver_message(message_id, PeerId::random(), message);
+
+        let mut received = 0;
+        while let Ok(event) = event_rx.try_recv() {
+            if matches!(event, NetworkEvent::MessageReceived { .. }) {
+                received += 1;
+            }
+        }
+        assert_eq!(received, 1);
+
+        let stats = service.stats.read().clone();
+        assert_eq!(stats.messages_received, 2);
+        assert_eq!(stats.messages_deduplicated, 1);
+    }
+}
@@ -0,0 +1,202 @@
+//! Observed mesh topology
+//!
+//! A node knows its own connections for certain. libp2p doesn't surface what
+//! gossipsub peer exchange or identify learn about other peers' connections,
+//! so instead every liveness heartbeat carries the sender's connected peers
+//! (see [`heartbeat`](crate::heartbeat)). [`TopologyTracker`] keeps the latest
+//! such report per peer and combines them with the local connections into a
+//! [`NetworkGraph`]. Reports expire, so a peer that stops sending heartbeats
+//! drops out of the graph; without heartbeats only direct connections are
+//! reported.
+
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Peers this node believes are connected to each other
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkGraph {
+    /// The local node's peer ID
+    pub local_peer_id: String,
+    /// Every peer appearing in an edge, plus the local node
+    pub nodes: Vec<String>,
+    /// Connections, each reported once
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A connection between two peers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    /// One end (the local node for direct connections)
+    pub source: String,
+    /// The other end
+    pub target: String,
+    /// Whether this is one of the local node's own connections
+    pub direct: bool,
+}
+
+/// A peer's latest report of its connections
+#[derive(Debug)]
+struct Report {
+    neighbours: HashSet<PeerId>,
+    received_at: Instant,
+}
+
+/// Latest connection reports received from other peers
+#[derive(Debug)]
+pub struct TopologyTracker {
+    reports: HashMap<PeerId, Report>,
+    /// Age after which a report is ignored
+    max_age: Duration,
+}
+
+impl TopologyTracker {
+    /// Create a tracker ignoring reports older than `max_age`
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            reports: HashMap::new(),
+            max_age,
+        }
+    }
+
+    /// Replace `peer`'s report of its connections
+    pub fn record(&mut self, peer: PeerId, neighbours: impl IntoIterator<Item = PeerId>, now: Instant) {
+        let max_age = self.max_age;
+        self.reports
+            .retain(|_, report| now.saturating_duration_since(report.received_at) <= max_age);
+        self.reports.insert(
+            peer,
+            Report {
+                neighbours: neighbours.into_iter().filter(|n| *n != peer).collect(),
+                received_at: now,
+            },
+        );
+    }
+
+    /// Number of peers with a report on file, including expired ones
+    pub fn report_count(&self) -> usize {
+        self.reports.len()
+    }
+
+    /// Build the graph from the local connections and unexpired reports
+    ///
+    /// The local node's own connections come from `connected` only; what
+    /// other peers claim about their connections to us is ignored.
+    pub fn graph(&self, local: PeerId, connected: &[PeerId], now: Instant) -> NetworkGraph {
+        let mut nodes = BTreeSet::from([local.to_string()]);
+        let mut edges = Vec::new();
+
+        for peer in connected {
+            nodes.insert(peer.to_string());
+            edges.push(GraphEdge {
+                source: local.to_string(),
+                target: peer.to_string(),
+                direct: true,
+            });
+        }
+
+        let mut seen = BTreeSet::new();
+        for (peer, report) in &self.reports {
+            if now.saturating_duration_since(report.received_at) > self.max_age {
+                continue;
+            }
+            for neighbour in &report.neighbours {
+                if *peer == local || *neighbour == local {
+                    continue;
+                }
+                let (a, b) = if peer < neighbour { (peer, neighbour) } else { (neighbour, peer) };
+                if seen.insert((*a, *b)) {
+                    nodes.insert(a.to_string());
+                    nodes.insert(b.to_string());
+                }
+            }
+        }
+        edges.extend(seen.into_iter().map(|(a, b)| GraphEdge {
+            source: a.to_string(),
+            target: b.to_string(),
+            direct: false,
+        }));
+
+        NetworkGraph {
+            local_peer_id: local.to_string(),
+            nodes: nodes.into_iter().collect(),
+            edges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+
+    fn random_peer_id() -> PeerId {
+        Keypair::generate_ed25519().public().to_peer_id()
+    }
+
+    fn has_edge(graph: &NetworkGraph, a: &PeerId, b: &PeerId) -> bool {
+        let (a, b) = (a.to_string(), b.to_string());
+        graph
+            .edges
+            .iter()
+            .any(|e| (e.source == a && e.target == b) || (e.source == b && e.target == a))
+    }
+
+    #[test]
+    fn test_direct_connections_are_edges() {
+        let tracker = TopologyTracker::new(Duration::from_secs(60));
+        let local = random_peer_id();
+        let peers = [random_peer_id(), random_peer_id()];
+
+        let graph = tracker.graph(local, &peers, Instant::now());
+        assert_eq!(graph.local_peer_id, local.to_string());
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 2);
+        for peer in &peers {
+            assert!(graph.edges.iter().any(|e| e.direct
+                && e.source == local.to_string()
+                && e.target == peer.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_reported_adjacency() {
+        let mut tracker = TopologyTracker::new(Duration::from_secs(60));
+        let local = random_peer_id();
+        let (a, b, c) = (random_peer_id(), random_peer_id(), random_peer_id());
+        let now = Instant::now();
+
+        // a and b both report their link, which appears once
+        tracker.record(a, [local, b], now);
+        tracker.record(b, [a, c], now);
+
+        let graph = tracker.graph(local, &[a], now);
+        assert!(has_edge(&graph, &local, &a));
+        assert!(has_edge(&graph, &a, &b));
+        assert!(has_edge(&graph, &b, &c));
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(graph.edges.iter().filter(|e| e.direct).count(), 1);
+        assert_eq!(graph.nodes.len(), 4);
+
+        // Claims about links to the local node don't override its own view
+        let graph = tracker.graph(local, &[], now);
+        assert!(!has_edge(&graph, &local, &a));
+    }
+
+    #[test]
+    fn test_reports_expire() {
+        let mut tracker = TopologyTracker::new(Duration::from_secs(60));
+        let local = random_peer_id();
+        let (a, b, c) = (random_peer_id(), random_peer_id(), random_peer_id());
+        let start = Instant::now();
+        tracker.record(a, [b], start);
+
+        let later = start + Duration::from_secs(61);
+        assert!(tracker.graph(local, &[], later).edges.is_empty());
+
+        // Expired reports are dropped when the next one arrives
+        tracker.record(c, [b], later);
+        assert_eq!(tracker.report_count(), 1);
+    }
+}
@@ -3,14 +3,18 @@
 //! This module provides transport configuration for TCP, QUIC, and WebSocket
 //! with Noise encryption and Yamux multiplexing.
 
+use either::Either;
 use libp2p::{
-    core::upgrade,
+    core::{transport::timeout::TransportTimeoutError, upgrade},
     identity::Keypair,
-    noise, yamux, PeerId, Transport,
+    multiaddr::Protocol,
+    noise, yamux, Multiaddr, PeerId, Transport,
 };
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
-use crate::error::{NetworkError, Result};
+use crate::error::{NegotiationFailure, NetworkError, Result};
 
 /// Transport configuration
 #[derive(Debug, Clone)]
@@ -53,13 +57,30 @@ pub fn create_tcp_transport(
 /// Create the full transport stack
 ///
 /// This creates a transport that supports:
-/// - TCP with Noise encryption and Yamux multiplexing
+/// - TCP with Noise encryption and Yamux multiplexing (if enabled)
 /// - QUIC (if enabled)
 /// - DNS resolution
 pub fn create_transport(
     keypair: &Keypair,
     config: &TransportConfig,
 ) -> Result<libp2p::core::transport::Boxed<(PeerId, libp2p::core::muxing::StreamMuxerBox)>> {
+    if !config.enable_tcp && !config.enable_quic {
+        return Err(NetworkError::Config("At least one of TCP or QUIC must be enabled".into()));
+    }
+
+    // QUIC only: TLS and multiplexing are built into the protocol
+    if !config.enable_tcp {
+        let quic = libp2p::quic::tokio::Transport::new(libp2p::quic::Config::new(keypair))
+            .map(|(peer_id, muxer), _| {
+                (peer_id, libp2p::core::muxing::StreamMuxerBox::new(muxer))
+            });
+
+        let dns_transport = libp2p::dns::tokio::Transport::system(quic)
+            .map_err(|e| NetworkError::Config(format!("DNS config error: {:?}", e)))?;
+
+        return Ok(dns_transport.boxed());
+    }
+
     // Create TCP transport
     let tcp = libp2p::tcp::tokio::Transport::new(
         libp2p::tcp::Config::default().nodelay(true)
@@ -77,7 +98,8 @@ pub fn create_transport(
         .upgrade(upgrade::Version::V1)
         .authenticate(noise_config)
         .multiplex(yamux_config)
-        .timeout(config.connection_timeout);
+        .timeout(config.connection_timeout)
+        .map_err(classify_upgrade_error);
 
     // Optionally add QUIC
     if config.enable_quic {
@@ -114,6 +136,120 @@ pub fn create_transport(
     }
 }
 
+/// Tag security and muxer negotiation failures so they survive boxing
+///
+/// The upgrade stack nests errors as `Either<Either<transport, security>, muxer>`,
+/// which is the only place the failing stage is still known.
+fn classify_upgrade_error<S, M>(
+    err: TransportTimeoutError<Either<Either<std::io::Error, S>, M>>,
+) -> std::io::Error
+where
+    S: std::error::Error + Send + Sync + 'static,
+    M: std::error::Error + Send + Sync + 'static,
+{
+    match err {
+        TransportTimeoutError::Other(Either::Left(Either::Left(e))) => e,
+        TransportTimeoutError::Other(Either::Left(Either::Right(e))) => {
+            NegotiationFailure::SecurityMismatch.tag(e)
+        }
+        TransportTimeoutError::Other(Either::Right(e)) => NegotiationFailure::MuxerMismatch.tag(e),
+        TransportTimeoutError::Timeout => {
+            std::io::Error::new(std::io::ErrorKind::TimedOut, "connection upgrade timed out")
+        }
+        TransportTimeoutError::TimerError(e) => e,
+    }
+}
+
+/// Transport a listen address is reached over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressTransport {
+    Tcp,
+    Quic,
+    Other,
+}
+
+/// Who can reach an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressScope {
+    /// Only this machine
+    Loopback,
+    /// Private, link-local or carrier-grade NAT ranges
+    Lan,
+    /// Anything else, including DNS names
+    Public,
+}
+
+/// A bound listen address with its classification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenAddress {
+    pub address: Multiaddr,
+    pub transport: AddressTransport,
+    pub scope: AddressScope,
+}
+
+impl ListenAddress {
+    /// Classify a listen address
+    pub fn new(address: Multiaddr) -> Self {
+        let (transport, scope) = classify_address(&address);
+        Self { address, transport, scope }
+    }
+}
+
+/// Classify a multiaddr by transport and reachability
+///
+/// This is a heuristic on the address alone: a "public" address may still
+/// sit behind a firewall.
+pub fn classify_address(addr: &Multiaddr) -> (AddressTransport, AddressScope) {
+    let mut transport = AddressTransport::Other;
+    let mut scope = AddressScope::Public;
+
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::QuicV1 | Protocol::Quic => transport = AddressTransport::Quic,
+            Protocol::Tcp(_) if transport == AddressTransport::Other => {
+                transport = AddressTransport::Tcp
+            }
+            Protocol::Ip4(ip) => scope = ipv4_scope(ip),
+            Protocol::Ip6(ip) => scope = ipv6_scope(ip),
+            Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name)
+                if name == "localhost" =>
+            {
+                scope = AddressScope::Loopback
+            }
+            _ => {}
+        }
+    }
+
+    (transport, scope)
+}
+
+fn ipv4_scope(ip: Ipv4Addr) -> AddressScope {
+    // 100.64.0.0/10 (carrier-grade NAT) isn't covered by `is_private`
+    let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+    if ip.is_loopback() {
+        AddressScope::Loopback
+    } else if ip.is_private() || ip.is_link_local() || ip.is_unspecified() || shared {
+        AddressScope::Lan
+    } else {
+        AddressScope::Public
+    }
+}
+
+fn ipv6_scope(ip: Ipv6Addr) -> AddressScope {
+    let segment = ip.segments()[0];
+    let unique_local = (segment & 0xfe00) == 0xfc00;
+    let link_local = (segment & 0xffc0) == 0xfe80;
+    if ip.is_loopback() {
+        AddressScope::Loopback
+    } else if unique_local || link_local || ip.is_unspecified() {
+        AddressScope::Lan
+    } else {
+        AddressScope::Public
+    }
+}
+
 /// Parse a multiaddr string
 pub fn parse_multiaddr(addr: &str) -> Result<libp2p::Multiaddr> {
     addr.parse()
@@ -130,3 +266,76 @@ pub fn extract_peer_id(addr: &libp2p::Multiaddr) -> Option<PeerId> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::{
+        transport::{DialOpts, PortUse},
+        Endpoint,
+    };
+    use libp2p::swarm::DialError;
+    use libp2p::TransportError;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_classify_address() {
+        let cases = [
+            ("/ip4/127.0.0.1/tcp/9000", AddressTransport::Tcp, AddressScope::Loopback),
+            ("/ip4/192.168.1.20/udp/9000/quic-v1", AddressTransport::Quic, AddressScope::Lan),
+            ("/ip4/10.0.0.5/tcp/9000", AddressTransport::Tcp, AddressScope::Lan),
+            ("/ip4/100.72.1.1/tcp/9000", AddressTransport::Tcp, AddressScope::Lan),
+            ("/ip4/169.254.3.3/tcp/9000", AddressTransport::Tcp, AddressScope::Lan),
+            ("/ip4/203.0.113.7/udp/4001/quic-v1", AddressTransport::Quic, AddressScope::Public),
+            ("/ip6/::1/tcp/9000", AddressTransport::Tcp, AddressScope::Loopback),
+            ("/ip6/fe80::1/udp/9000/quic-v1", AddressTransport::Quic, AddressScope::Lan),
+            ("/ip6/fd12:3456::1/tcp/9000", AddressTransport::Tcp, AddressScope::Lan),
+            ("/ip6/2001:db8::1/tcp/9000", AddressTransport::Tcp, AddressScope::Public),
+            ("/dns4/localhost/tcp/9000", AddressTransport::Tcp, AddressScope::Loopback),
+            ("/dns4/node.example.com/tcp/443/ws", AddressTransport::Tcp, AddressScope::Public),
+            ("/ip4/8.8.8.8/udp/53", AddressTransport::Other, AddressScope::Public),
+        ];
+
+        for (addr, transport, scope) in cases {
+            let addr: Multiaddr = addr.parse().unwrap();
+            assert_eq!(classify_address(&addr), (transport, scope), "{}", addr);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protocol_mismatch_dial_is_classified() {
+        // A listener that speaks multistream-select but rejects every protocol
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 256];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket.write_all(b"\x13/multistream/1.0.0\n\x03na\n").await;
+            let _ = socket.read(&mut buf).await;
+        });
+
+        let keypair = Keypair::generate_ed25519();
+        let config = TransportConfig {
+            enable_quic: false,
+            ..Default::default()
+        };
+        let mut transport = create_transport(&keypair, &config).unwrap();
+
+        let addr: libp2p::Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap();
+        let opts = DialOpts {
+            role: Endpoint::Dialer,
+            port_use: PortUse::New,
+        };
+        let err = match transport.dial(addr.clone(), opts).unwrap().await {
+            Ok(_) => panic!("dial should fail protocol negotiation"),
+            Err(e) => e,
+        };
+
+        let error = DialError::Transport(vec![(addr, TransportError::Other(err))]);
+        assert_eq!(
+            NegotiationFailure::classify(&error),
+            Some(NegotiationFailure::SecurityMismatch)
+        );
+    }
+}
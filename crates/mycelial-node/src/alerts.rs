@@ -0,0 +1,54 @@
+//! Reputation threshold alerts
+//!
+//! Operators can pick a reputation boundary with `--reputation-alert-threshold`.
+//! The node remembers which side of it each peer was last reported on and
+//! sends a [`WsMessage::ReputationAlert`](crate::server::messages::WsMessage)
+//! only when a peer moves to the other side, not on every update, so a score
+//! hovering on one side doesn't keep alerting.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+use crate::server::messages::ThresholdSide;
+
+/// Tracks the side of the alert threshold each peer was last seen on
+#[derive(Debug, Default)]
+pub struct ReputationAlerts {
+    /// Score boundary (None disables alerts)
+    threshold: Option<f64>,
+    /// Last notified side per peer
+    sides: Mutex<HashMap<String, ThresholdSide>>,
+}
+
+impl ReputationAlerts {
+    /// Create a tracker alerting on crossings of `threshold`
+    pub fn new(threshold: Option<f64>) -> Self {
+        Self {
+            threshold,
+            sides: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a peer's score, returning the side it crossed to
+    ///
+    /// The first score seen for a peer only records where it started.
+    pub fn observe(&self, peer_id: &str, score: f64) -> Option<ThresholdSide> {
+        let threshold = self.threshold?;
+        let side_of = |score: f64| {
+            if score < threshold {
+                ThresholdSide::Below
+            } else {
+                ThresholdSide::Above
+            }
+        };
+
+        let side = side_of(score);
+        let mut sides = self.sides.lock();
+        let last = sides.entry(peer_id.to_string()).or_insert(side);
+        if *last == side {
+            return None;
+        }
+        *last = side;
+        Some(side)
+    }
+}
@@ -0,0 +1,65 @@
+//! Delivery receipts for direct messages
+//!
+//! Direct messages queued for an offline peer are published again once it
+//! reconnects, but a publish only means the message left this node: the
+//! recipient may not be in the mesh yet. A queued message is therefore kept
+//! until the recipient answers with a receipt, a `System` message addressed
+//! to the sender and naming the delivered message's ID. Recipients answer
+//! every copy they get, so a lost receipt is made up for on redelivery.
+
+use mycelial_core::message::{Message, MessageType};
+use mycelial_core::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Payload of a delivery receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryAck {
+    /// ID of the direct message that arrived
+    pub delivered: Uuid,
+}
+
+/// Receipt to publish for `message`, if it is a direct message to `local`
+pub fn receipt_for(message: &Message, local: &PeerId) -> Option<Message> {
+    if message.message_type != MessageType::Direct || message.recipient.as_ref() != Some(local) {
+        return None;
+    }
+
+    let payload = serde_json::to_vec(&DeliveryAck { delivered: message.id }).ok()?;
+    let mut receipt = Message::new(MessageType::System, local.clone(), payload);
+    receipt.recipient = Some(message.sender.clone());
+    Some(receipt)
+}
+
+/// The acknowledgement `message` carries, if it is a receipt sent to `local`
+pub fn acknowledgement(message: &Message, local: &PeerId) -> Option<DeliveryAck> {
+    if message.message_type != MessageType::System || message.recipient.as_ref() != Some(local) {
+        return None;
+    }
+    serde_json::from_slice(&message.payload).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_round_trip() {
+        let alice = PeerId("alice".to_string());
+        let bob = PeerId("bob".to_string());
+        let dm = Message::direct(alice.clone(), bob.clone(), b"hi".to_vec());
+
+        // Only the recipient answers
+        assert!(receipt_for(&dm, &alice).is_none());
+        let receipt = receipt_for(&dm, &bob).unwrap();
+
+        // ... and only the sender takes it as an acknowledgement
+        assert!(acknowledgement(&receipt, &bob).is_none());
+        assert_eq!(acknowledgement(&receipt, &alice), Some(DeliveryAck { delivered: dm.id }));
+
+        // Broadcasts aren't acknowledged
+        let broadcast = Message::new(MessageType::Content, alice.clone(), b"hi".to_vec());
+        assert!(receipt_for(&broadcast, &bob).is_none());
+        assert!(acknowledgement(&broadcast, &alice).is_none());
+    }
+}
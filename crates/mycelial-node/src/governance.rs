@@ -0,0 +1,78 @@
+//! Votes that arrive before their proposal
+//!
+//! Gossip doesn't keep messages in order, so a vote can reach this node
+//! before the proposal it is cast on. Such votes are held here and recorded
+//! once the proposal arrives. The buffer is bounded in size and age so
+//! votes on proposals that never show up don't pile up.
+
+use chrono::{DateTime, Duration, Utc};
+use mycelial_protocol::CastVote;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+/// Most votes held at once; the oldest are dropped first
+const MAX_EARLY_VOTES: usize = 1024;
+
+/// How long a vote waits for its proposal
+const EARLY_VOTE_TTL_SECS: i64 = 60 * 60;
+
+/// Votes waiting for their proposal, oldest first
+#[derive(Debug, Default)]
+pub struct EarlyVotes {
+    votes: Mutex<VecDeque<(DateTime<Utc>, CastVote)>>,
+}
+
+impl EarlyVotes {
+    /// Hold a vote whose proposal isn't known yet
+    pub fn hold(&self, vote: CastVote, now: DateTime<Utc>) {
+        let mut votes = self.votes.lock();
+        Self::expire(&mut votes, now);
+        if votes.len() >= MAX_EARLY_VOTES {
+            votes.pop_front();
+        }
+        votes.push_back((now, vote));
+    }
+
+    /// Take the held votes on `proposal_id`, oldest first
+    pub fn take(&self, proposal_id: &Uuid, now: DateTime<Utc>) -> Vec<CastVote> {
+        let mut votes = self.votes.lock();
+        Self::expire(&mut votes, now);
+        let (taken, kept) = std::mem::take(&mut *votes)
+            .into_iter()
+            .partition(|(_, vote)| vote.proposal_id == *proposal_id);
+        *votes = kept;
+        taken.into_iter().map(|(_, vote)| vote).collect()
+    }
+
+    fn expire(votes: &mut VecDeque<(DateTime<Utc>, CastVote)>, now: DateTime<Utc>) {
+        let cutoff = now - Duration::seconds(EARLY_VOTE_TTL_SECS);
+        while votes.front().is_some_and(|(held_at, _)| *held_at < cutoff) {
+            votes.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mycelial_protocol::Vote;
+
+    #[test]
+    fn test_votes_wait_for_their_proposal() {
+        let early = EarlyVotes::default();
+        let (proposal, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        early.hold(CastVote::new(proposal, "alice".to_string(), Vote::For, 1.0), now);
+        early.hold(CastVote::new(other, "bob".to_string(), Vote::Against, 1.0), now);
+        early.hold(CastVote::new(proposal, "carol".to_string(), Vote::Abstain, 1.0), now);
+
+        let voters: Vec<_> = early.take(&proposal, now).into_iter().map(|vote| vote.voter).collect();
+        assert_eq!(voters, ["alice", "carol"]);
+        assert!(early.take(&proposal, now).is_empty());
+
+        // Votes whose proposal never came are dropped eventually
+        let later = now + Duration::seconds(EARLY_VOTE_TTL_SECS + 1);
+        assert!(early.take(&other, later).is_empty());
+    }
+}
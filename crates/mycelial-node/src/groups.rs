@@ -0,0 +1,239 @@
+//! Group conversations
+//!
+//! A group message is published once, on a topic derived from its members
+//! (see [`Message::group_id`]), so only members subscribed to that topic
+//! receive it. Members join the topic when they write to the group, and
+//! each group message is preceded by an invite on the direct topic: a
+//! `System` message naming the group's members, with no content, which
+//! makes members that haven't joined yet join.
+//!
+//! Gossipsub refuses to publish on a topic nobody else has joined yet, so
+//! messages sent before any other member joined are held and published as
+//! soon as one does. Members joining later only see messages sent after.
+//!
+//! Invites are only taken from peers this node is connected to or already
+//! knows, and only while it is in fewer than [`MAX_JOINED_GROUPS`] groups.
+//! Groups without a message for [`GROUP_IDLE_SECS`] are left.
+
+use chrono::{DateTime, Duration, Utc};
+use mycelial_core::message::{Message, MessageType};
+use mycelial_core::peer::PeerId;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Prefix of the topics group messages are published on
+pub const GROUP_TOPIC_PREFIX: &str = "/mycelial/1.0.0/group/";
+
+/// Most messages held at once; the oldest are dropped first
+const MAX_HELD_MESSAGES: usize = 256;
+
+/// How long a message waits for another member to join
+const HELD_MESSAGE_TTL_SECS: i64 = 10 * 60;
+
+/// Most groups joined at once on other peers' invites
+pub const MAX_JOINED_GROUPS: usize = 64;
+
+/// How long a group may go without messages before it is left
+pub const GROUP_IDLE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Topic a group message is published on, `None` if it isn't one
+pub fn topic_for(message: &Message) -> Option<String> {
+    message.group_id().map(|group| format!("{}{}", GROUP_TOPIC_PREFIX, group))
+}
+
+/// Payload of a group invite
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupInvite {
+    /// ID of the group, see [`Message::group_id`]
+    pub group: String,
+}
+
+/// Invite to publish for the group `message` is sent within
+pub fn invite_for(message: &Message) -> Option<Message> {
+    let group = message.group_id()?;
+    let payload = serde_json::to_vec(&GroupInvite { group }).ok()?;
+    let mut invite = Message::new(MessageType::System, message.sender.clone(), payload);
+    invite.recipients = message.recipients.clone();
+    Some(invite)
+}
+
+/// Topic to join if `message` is an invite to a group `local` belongs to
+pub fn invitation(message: &Message, local: &PeerId) -> Option<String> {
+    if message.message_type != MessageType::System || !message.recipients.contains(local) {
+        return None;
+    }
+    let invite: GroupInvite = serde_json::from_slice(&message.payload).ok()?;
+    // The group must be the one the invite's own members make up
+    if message.group_id().as_ref() != Some(&invite.group) {
+        return None;
+    }
+    topic_for(message)
+}
+
+/// Whether a group message received on `topic` is one `local` should take
+///
+/// It must be on its own group's topic and name `local` as a recipient.
+pub fn is_member_message(message: &Message, topic: &str, local: &PeerId) -> bool {
+    topic_for(message).as_deref() == Some(topic) && message.recipients.contains(local)
+}
+
+/// An encoded group message and the topic it waits to be published on
+#[derive(Debug)]
+struct HeldMessage {
+    held_at: DateTime<Utc>,
+    topic: String,
+    data: Vec<u8>,
+}
+
+/// Group messages waiting for another member to join their topic
+#[derive(Debug, Default)]
+pub struct HeldGroupMessages {
+    held: Mutex<VecDeque<HeldMessage>>,
+}
+
+impl HeldGroupMessages {
+    /// Hold an encoded message until someone else joins `topic`
+    pub fn hold(&self, topic: String, data: Vec<u8>, now: DateTime<Utc>) {
+        let mut held = self.held.lock();
+        Self::expire(&mut held, now);
+        if held.len() >= MAX_HELD_MESSAGES {
+            held.pop_front();
+        }
+        held.push_back(HeldMessage { held_at: now, topic, data });
+    }
+
+    /// Take the messages held for `topic`, oldest first
+    pub fn take(&self, topic: &str, now: DateTime<Utc>) -> Vec<Vec<u8>> {
+        let mut held = self.held.lock();
+        Self::expire(&mut held, now);
+        let (taken, kept) = std::mem::take(&mut *held)
+            .into_iter()
+            .partition(|message: &HeldMessage| message.topic == topic);
+        *held = kept;
+        taken.into_iter().map(|message: HeldMessage| message.data).collect()
+    }
+
+    fn expire(held: &mut VecDeque<HeldMessage>, now: DateTime<Utc>) {
+        let cutoff = now - Duration::seconds(HELD_MESSAGE_TTL_SECS);
+        while held.front().is_some_and(|message| message.held_at < cutoff) {
+            held.pop_front();
+        }
+    }
+}
+
+/// Group topics this node is in and when each last had a message
+#[derive(Debug, Default)]
+pub struct JoinedGroups {
+    last_active: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl JoinedGroups {
+    /// Record a group this node opened or was already in
+    pub fn join(&self, topic: &str, now: DateTime<Utc>) {
+        self.last_active.lock().insert(topic.to_string(), now);
+    }
+
+    /// Record a group joined on an invite, unless that would go past
+    /// [`MAX_JOINED_GROUPS`]
+    ///
+    /// Returns false if the invite has to be turned down.
+    pub fn join_invited(&self, topic: &str, now: DateTime<Utc>) -> bool {
+        let mut last_active = self.last_active.lock();
+        if !last_active.contains_key(topic) && last_active.len() >= MAX_JOINED_GROUPS {
+            return false;
+        }
+        last_active.insert(topic.to_string(), now);
+        true
+    }
+
+    /// Note a message in a joined group
+    pub fn touch(&self, topic: &str, now: DateTime<Utc>) {
+        if let Some(last) = self.last_active.lock().get_mut(topic) {
+            *last = now;
+        }
+    }
+
+    /// Forget the groups idle for longer than [`GROUP_IDLE_SECS`], returning
+    /// their topics to leave
+    pub fn take_idle(&self, now: DateTime<Utc>) -> Vec<String> {
+        let cutoff = now - Duration::seconds(GROUP_IDLE_SECS);
+        let mut idle = Vec::new();
+        self.last_active.lock().retain(|topic, last| {
+            let keep = *last >= cutoff;
+            if !keep {
+                idle.push(topic.clone());
+            }
+            keep
+        });
+        idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(id: &str) -> PeerId {
+        PeerId(id.to_string())
+    }
+
+    #[test]
+    fn test_invite_round_trip() {
+        let message = Message::group(peer("alice"), vec![peer("bob"), peer("carol")], b"hi".to_vec());
+        let invite = invite_for(&message).unwrap();
+        let payload: GroupInvite = serde_json::from_slice(&invite.payload).unwrap();
+        assert_eq!(Some(payload.group), message.group_id());
+
+        // Every member but the sender is invited to the message's topic
+        let topic = topic_for(&message).unwrap();
+        assert_eq!(invitation(&invite, &peer("bob")), Some(topic.clone()));
+        assert_eq!(invitation(&invite, &peer("carol")), Some(topic.clone()));
+        assert_eq!(invitation(&invite, &peer("dave")), None);
+
+        // An invite can't point members at another group's topic
+        let mut redirected = invite.clone();
+        redirected.payload = serde_json::to_vec(&GroupInvite { group: "elsewhere".to_string() }).unwrap();
+        assert_eq!(invitation(&redirected, &peer("bob")), None);
+
+        assert!(is_member_message(&message, &topic, &peer("bob")));
+        assert!(!is_member_message(&message, &topic, &peer("dave")));
+        assert!(!is_member_message(&message, "/mycelial/1.0.0/chat", &peer("bob")));
+    }
+
+    #[test]
+    fn test_held_messages() {
+        let held = HeldGroupMessages::default();
+        let now = Utc::now();
+        held.hold("a".to_string(), b"1".to_vec(), now);
+        held.hold("b".to_string(), b"2".to_vec(), now);
+        held.hold("a".to_string(), b"3".to_vec(), now);
+
+        assert_eq!(held.take("a", now), vec![b"1".to_vec(), b"3".to_vec()]);
+        assert!(held.take("a", now).is_empty());
+
+        let later = now + Duration::seconds(HELD_MESSAGE_TTL_SECS + 1);
+        assert!(held.take("b", later).is_empty());
+    }
+
+    #[test]
+    fn test_joined_groups_capped_and_expired() {
+        let groups = JoinedGroups::default();
+        let now = Utc::now();
+        for i in 0..MAX_JOINED_GROUPS {
+            assert!(groups.join_invited(&format!("group-{}", i), now));
+        }
+
+        // Full: new invites are turned down, groups already joined aren't
+        assert!(!groups.join_invited("one-too-many", now));
+        assert!(groups.join_invited("group-0", now));
+
+        // Only groups without messages for the idle period are left
+        let later = now + Duration::seconds(GROUP_IDLE_SECS + 1);
+        groups.touch("group-1", later);
+        let idle = groups.take_idle(later);
+        assert_eq!(idle.len(), MAX_JOINED_GROUPS - 1);
+        assert!(!idle.contains(&"group-1".to_string()));
+        assert!(groups.join_invited("one-too-many", later));
+    }
+}
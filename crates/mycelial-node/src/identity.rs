@@ -0,0 +1,130 @@
+//! Persistent node identity
+//!
+//! The libp2p keypair determines the node's peer id, and reputation and
+//! credit are tied to that id. With `--identity <path>` the keypair is loaded
+//! from disk (protobuf-encoded, as libp2p serializes it) or generated and
+//! saved there on first run, so the peer id survives restarts.
+
+use anyhow::{bail, Context};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+use mycelial_network::Keypair;
+
+/// Load the keypair at `path`, generating and saving a new one if absent
+pub fn load_or_generate(path: &Path) -> anyhow::Result<Keypair> {
+    if path.exists() {
+        warn_if_world_readable(path);
+        let keypair = load(path)?;
+        info!("Loaded node identity from {}", path.display());
+        return Ok(keypair);
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    save(&keypair, path)?;
+    info!("Generated new node identity at {}", path.display());
+    Ok(keypair)
+}
+
+/// Read a protobuf-encoded ed25519 keypair
+pub fn load(path: &Path) -> anyhow::Result<Keypair> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read identity file {}", path.display()))?;
+    let keypair = Keypair::from_protobuf_encoding(&bytes)
+        .with_context(|| format!("Invalid identity file {}", path.display()))?;
+    if keypair.clone().try_into_ed25519().is_err() {
+        bail!("Identity file {} is not an ed25519 key", path.display());
+    }
+    Ok(keypair)
+}
+
+/// Write a keypair readable only by its owner
+///
+/// The file must not already exist, so an existing identity is never
+/// overwritten.
+pub fn save(keypair: &Keypair, path: &Path) -> anyhow::Result<()> {
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .context("Failed to encode node identity")?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create identity file {}", path.display()))?;
+    file.write_all(&bytes)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write identity file {}", path.display()))?;
+    Ok(())
+}
+
+/// Anyone who can read the key can impersonate the node
+#[cfg(unix)]
+fn warn_if_world_readable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o004 != 0 => {
+            warn!(
+                "!!! Identity file {} is world-readable; anyone on this machine can impersonate \
+                 this node. Run `chmod 600 {}` !!!",
+                path.display(),
+                path.display()
+            );
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to check permissions of {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_world_readable(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("identity.key");
+
+        let generated = load_or_generate(&path).unwrap();
+        let reloaded = load_or_generate(&path).unwrap();
+        assert_eq!(generated.public().to_peer_id(), reloaded.public().to_peer_id());
+
+        // Saving never clobbers an existing identity
+        assert!(save(&Keypair::generate_ed25519(), &path).is_err());
+        assert_eq!(load(&path).unwrap().public().to_peer_id(), generated.public().to_peer_id());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_load_rejects_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        fs::write(&path, b"not a key").unwrap();
+
+        let err = load(&path).unwrap_err();
+        assert!(err.to_string().contains("Invalid identity file"));
+    }
+}
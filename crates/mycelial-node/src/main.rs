@@ -5,23 +5,82 @@
 //! - WebSocket server for real-time dashboard updates
 //! - REST API for peer and network information
 
+mod alerts;
+mod delivery;
+mod governance;
+mod groups;
+mod identity;
+mod replay;
 mod server;
+mod shutdown;
+mod state_exchange;
 
 use clap::Parser;
 use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::sync::atomic::AtomicU64;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
-use tracing::{info, warn, error, Level};
+use tracing::{debug, info, warn, error, Level};
 use tracing_subscriber::FmtSubscriber;
 
 use mycelial_core::peer::{PeerId, PeerInfo};
-use mycelial_core::reputation::Reputation;
-use mycelial_network::{NetworkService, NetworkHandle, NetworkConfig, NetworkEvent, Keypair, Libp2pPeerId};
+use mycelial_network::{NetworkService, NetworkHandle, NetworkConfig, NetworkEvent, Keypair, Libp2pPeerId, ListenAddress, MessageAcceptance, MessageId, TransportSelection};
 use mycelial_network::{is_economics_topic, parse_economics_message, EconomicsEvent};
-use mycelial_state::SqliteStore;
-use server::messages::{WsMessage, ContributorEntry};
+use mycelial_protocol::PayloadCodec;
+use mycelial_state::{CompactionConfig, SqliteStore, StateCache, StateSnapshot, StateSync, StateUpdate};
+use alerts::ReputationAlerts;
+use governance::EarlyVotes;
+use groups::{HeldGroupMessages, JoinedGroups};
+use replay::{Replay, ReplayGuard};
+use state_exchange::{DigestAnswer, PendingDiffs, SnapshotBootstrap, SnapshotPage, SyncMessage, SYNC_PAGE_BYTES};
+use server::messages::{ChatRecipients, WsMessage, ContributorEntry};
+
+/// Topic direct messages and their delivery receipts are published on
+pub const DIRECT_TOPIC: &str = "/mycelial/1.0.0/direct";
+
+/// How long a direct message waits for an offline recipient before it's dropped
+pub const PENDING_DM_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Maximum queued direct messages per offline recipient
+pub const PENDING_DM_LIMIT: usize = 100;
+
+/// Database file used when `--db` is not given
+const DEFAULT_DB_PATH: &str = "mycelial.db";
+
+/// Peers preloaded into the cache at startup
+const CACHE_WARM_PEERS: usize = 1000;
+
+/// Messages preloaded into the cache at startup
+const CACHE_WARM_MESSAGES: usize = 1000;
+
+/// How often expired pending direct messages are swept
+const PENDING_DM_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often newly seen message IDs are written to the store
+const SEEN_MESSAGES_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often seen message IDs past the replay window are pruned
+const SEEN_MESSAGES_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How often the reputation compaction schedule is checked
+const COMPACTION_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often proposals past their deadline are tallied
+const PROPOSAL_TALLY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the lifetime message count is saved
+const MESSAGE_COUNT_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often groups are checked for having gone idle
+const IDLE_GROUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Environment variable holding the bearer token for `/api/maintenance/*`
+///
+/// Maintenance endpoints are disabled unless it is set.
+const ADMIN_TOKEN_ENV: &str = "MYCELIAL_ADMIN_TOKEN";
 
 #[derive(Parser)]
 #[command(name = "mycelial-node")]
@@ -39,21 +98,111 @@ struct Args {
     #[arg(long)]
     port: Option<u16>,
 
+    /// P2P transports to use: tcp, quic or both
+    #[arg(long, default_value = "both")]
+    transport: TransportSelection,
+
+    /// Disable mDNS local peer discovery (avoids announcing the node on the LAN)
+    #[arg(long)]
+    no_mdns: bool,
+
+    /// Refuse connections beyond this many simultaneous ones (0 = unlimited)
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    max_connections: usize,
+
     /// Dashboard HTTP server port (0 = auto-assign, bootstrap default: 8080, peer default: 0)
     #[arg(long)]
     http_port: Option<u16>,
 
+    /// Interface the dashboard HTTP server listens on (0.0.0.0 exposes it on every interface)
+    #[arg(long, value_name = "IP", default_value = "127.0.0.1")]
+    bind: IpAddr,
+
     /// Display name for this node
     #[arg(long, short, default_value = "Anonymous")]
     name: String,
 
-    /// Database path
-    #[arg(long, default_value = "mycelial.db")]
-    db: String,
+    /// Database path [default: mycelial.db]
+    #[arg(long)]
+    db: Option<String>,
+
+    /// Ed25519 keypair file; created if missing, keeps the peer id stable across restarts
+    #[arg(long)]
+    identity: Option<String>,
+
+    /// Keep all state in memory; nothing is written to disk
+    #[arg(long)]
+    in_memory: bool,
+
+    /// Checkpoint the database WAL every N minutes (off by default)
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_interval: Option<u64>,
+
+    /// Events buffered per WebSocket client before a slow client starts missing them
+    #[arg(long, default_value_t = 256)]
+    ws_buffer: usize,
+
+    /// Ignore messages from peers whose reputation is below this score (0.0-1.0)
+    #[arg(long, value_name = "SCORE")]
+    min_relay_reputation: Option<f64>,
+
+    /// With --min-relay-reputation, whether to accept or drop messages from peers with no known reputation
+    #[arg(long, value_enum, default_value = "accept")]
+    unknown_peers: UnknownPeerPolicy,
+
+    /// Alert dashboards when a peer's reputation crosses this score (0.0-1.0)
+    #[arg(long, value_name = "SCORE")]
+    reputation_alert_threshold: Option<f64>,
+
+    /// Reject messages timestamped more than this many seconds from the local clock (0 disables)
+    #[arg(long, value_name = "SECS", default_value_t = 300)]
+    max_message_age: u64,
+
+    /// Compact reputation counters every this many days (0 disables)
+    #[arg(long, value_name = "DAYS", default_value_t = 7)]
+    compaction_interval_days: u64,
+
+    /// Factor compaction scales reputation counters by (0.0-1.0, exclusive of 0)
+    #[arg(long, value_name = "FACTOR", default_value_t = 0.5, value_parser = parse_compaction_factor)]
+    compaction_factor: f64,
+
+    /// Encoding for published economics messages: json or bincode (either is accepted from peers)
+    #[arg(long, value_name = "CODEC", default_value = "json")]
+    payload_codec: PayloadCodec,
 
     /// Enable verbose logging
     #[arg(long, short)]
     verbose: bool,
+
+    /// PEM certificate chain for serving the dashboard over TLS (requires --tls-key)
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<String>,
+
+    /// PEM private key for the TLS certificate (requires --tls-cert)
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<String>,
+}
+
+/// Treatment of messages from peers with no stored reputation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum UnknownPeerPolicy {
+    /// Handle them like any other message
+    #[default]
+    Accept,
+    /// Ignore them
+    Drop,
+}
+
+/// Minimum reputation a message's author needs for the node to handle it
+///
+/// Gossipsub forwards messages before the node sees them, so this only keeps
+/// low-reputation traffic out of the store and the dashboard.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReputationGate {
+    /// Lowest accepted score (None accepts everyone)
+    pub min_reputation: Option<f64>,
+    /// What to do when the author's reputation is unknown
+    pub unknown_peers: UnknownPeerPolicy,
 }
 
 /// Application state shared across handlers
@@ -64,16 +213,46 @@ pub struct AppState {
     pub network: NetworkHandle,
     /// State storage
     pub store: SqliteStore,
+    /// State updates waiting to be gossiped
+    pub sync: StateSync,
     /// Broadcast channel for WebSocket events
     pub event_tx: broadcast::Sender<WsMessage>,
-    /// Message counter
+    /// Messages received since the node started
     pub message_count: AtomicU64,
+    /// Messages received in earlier runs, loaded from the store
+    pub previous_message_count: u64,
+    /// Messages received per topic
+    pub topic_message_counts: RwLock<HashMap<String, u64>>,
     /// Node start time
     pub start_time: Instant,
     /// Node name
     pub node_name: String,
     /// Subscribed topics
     pub subscribed_topics: RwLock<Vec<String>>,
+    /// Addresses the P2P node is currently listening on
+    pub listen_addresses: RwLock<Vec<ListenAddress>>,
+    /// Set once the network service has started, cleared when it stops
+    pub network_ready: AtomicBool,
+    /// Bearer token required by maintenance endpoints (None disables them)
+    pub admin_token: Option<String>,
+    /// Reputation needed for received messages to be stored or shown
+    pub reputation_gate: ReputationGate,
+    /// Last side of the alert threshold each peer was reported on
+    pub reputation_alerts: ReputationAlerts,
+    /// Rejects stale and replayed messages
+    pub replay_guard: ReplayGuard,
+    /// Encoding for published economics messages
+    pub payload_codec: PayloadCodec,
+    /// State digest comparisons waiting for the peer's answer
+    pub pending_diffs: PendingDiffs,
+    /// Snapshot this node asks for after joining
+    pub snapshot_bootstrap: SnapshotBootstrap,
+    /// Votes received before their proposal
+    pub early_votes: EarlyVotes,
+    /// Group messages waiting for another member to join their topic
+    pub held_group_messages: HeldGroupMessages,
+    /// Groups this node is in, left once idle
+    pub joined_groups: JoinedGroups,
 }
 
 #[tokio::main]
@@ -93,13 +272,22 @@ async fn main() -> anyhow::Result<()> {
     let p2p_port = args.port.unwrap_or(if args.bootstrap { 9000 } else { 0 });
     let http_port = args.http_port.unwrap_or(if args.bootstrap { 8080 } else { 0 });
 
+    // Validate TLS material before anything else starts
+    let tls_config = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(server::tls::load_config(cert, key).await?),
+        _ => None,
+    };
+
     info!("Starting Mycelial Node: {}", args.name);
     if args.bootstrap {
         info!("Running as BOOTSTRAP node");
     }
 
-    // Generate keypair
-    let keypair = Keypair::generate_ed25519();
+    // Load the persistent identity, or use a throwaway one
+    let keypair = match &args.identity {
+        Some(path) => identity::load_or_generate(std::path::Path::new(path))?,
+        None => Keypair::generate_ed25519(),
+    };
     let libp2p_peer_id = keypair.public().to_peer_id();
 
     // Convert to mycelial-core PeerId (base58 encoded)
@@ -108,22 +296,54 @@ async fn main() -> anyhow::Result<()> {
     info!("Local peer ID: {}", local_peer_id);
 
     // Initialize state store
-    let db_url = format!("sqlite:{}?mode=rwc", args.db);
-    let store = SqliteStore::new(&db_url).await?;
-    info!("Database initialized: {}", args.db);
+    if args.in_memory && args.db.is_some() {
+        warn!("--in-memory given, ignoring --db");
+    }
+    let db_url = database_url(&args);
+    // Without a cache the store reads every peer and credit line from SQLite,
+    // and /api/stats has no cache usage to report
+    let cache = Arc::new(StateCache::new());
+    let store = SqliteStore::new(&db_url).await?.with_cache(cache.clone());
+    info!("Database initialized: {}", db_url);
+    if let Err(e) = cache.warm_from_store(&store, CACHE_WARM_PEERS, CACHE_WARM_MESSAGES).await {
+        warn!("Failed to warm caches: {}", e);
+    }
+    let previous_message_count = store.load_message_count().await.unwrap_or_else(|e| {
+        warn!("Failed to load message count: {}", e);
+        0
+    });
 
     // Configure network
     // Port 0 tells the OS to assign an available port automatically
-    let mut config = NetworkConfig::default();
-    config.listen_addresses = vec![
-        format!("/ip4/0.0.0.0/tcp/{}", p2p_port),
-        format!("/ip4/0.0.0.0/udp/{}/quic-v1", if p2p_port == 0 { 0 } else { p2p_port + 1 }),
-    ];
+    let mut config = NetworkConfig {
+        transports: args.transport,
+        enable_mdns: !args.no_mdns,
+        max_connections: Some(args.max_connections).filter(|max| *max > 0),
+        validate_messages: true,
+        listen_addresses: Vec::new(),
+        ..NetworkConfig::default()
+    };
+    if args.no_mdns {
+        info!("mDNS discovery disabled");
+    }
+    let quic_port = if p2p_port == 0 { 0 } else { p2p_port + 1 };
+    if args.transport.uses_tcp() {
+        config.listen_addresses.push(format!("/ip4/0.0.0.0/tcp/{}", p2p_port));
+    }
+    if args.transport.uses_quic() {
+        config.listen_addresses.push(format!("/ip4/0.0.0.0/udp/{}/quic-v1", quic_port));
+    }
 
     if p2p_port == 0 {
         info!("P2P port: auto-assign (OS will select available port)");
     } else {
-        info!("P2P port: {} (TCP), {} (QUIC)", p2p_port, p2p_port + 1);
+        match args.transport {
+            TransportSelection::TcpOnly => info!("P2P port: {} (TCP)", p2p_port),
+            TransportSelection::QuicOnly => info!("P2P port: {} (QUIC)", quic_port),
+            TransportSelection::Both => {
+                info!("P2P port: {} (TCP), {} (QUIC)", p2p_port, quic_port)
+            }
+        }
     }
 
     if let Some(ref addr) = args.connect {
@@ -131,28 +351,73 @@ async fn main() -> anyhow::Result<()> {
         info!("Will connect to bootstrap peer: {}", addr);
     }
 
+    // Fired once on SIGINT/SIGTERM; the HTTP server and network service stop
+    // accepting connections when they see it
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+
     // Create network service
     let (network_service, network_handle, mut event_rx) = NetworkService::new(keypair.clone(), config)?;
+    let network_service = network_service.with_shutdown(shutdown_tx.subscribe());
 
     info!("Network service created");
 
     // Create broadcast channel for WebSocket events
-    let (event_tx, _) = broadcast::channel(256);
+    let (event_tx, _) = broadcast::channel(args.ws_buffer.max(1));
 
     // Create shared state
     let state = Arc::new(AppState {
         local_peer_id: local_peer_id.clone(),
         network: network_handle.clone(),
         store,
+        sync: StateSync::new(local_peer_id.to_string(), cache).with_compaction(compaction_config(&args)),
         event_tx: event_tx.clone(),
         message_count: AtomicU64::new(0),
+        previous_message_count,
+        topic_message_counts: RwLock::new(HashMap::new()),
         start_time: Instant::now(),
         node_name: args.name.clone(),
         subscribed_topics: RwLock::new(Vec::new()),
+        listen_addresses: RwLock::new(Vec::new()),
+        network_ready: AtomicBool::new(false),
+        admin_token: std::env::var(ADMIN_TOKEN_ENV).ok().filter(|token| !token.is_empty()),
+        reputation_gate: ReputationGate {
+            min_reputation: args.min_relay_reputation,
+            unknown_peers: args.unknown_peers,
+        },
+        reputation_alerts: ReputationAlerts::new(args.reputation_alert_threshold),
+        replay_guard: ReplayGuard::new(
+            Some(args.max_message_age)
+                .filter(|secs| *secs > 0)
+                .map(|secs| chrono::Duration::seconds(secs as i64)),
+        ),
+        payload_codec: args.payload_codec,
+        pending_diffs: PendingDiffs::default(),
+        snapshot_bootstrap: SnapshotBootstrap::default(),
+        early_votes: EarlyVotes::default(),
+        held_group_messages: HeldGroupMessages::default(),
+        joined_groups: JoinedGroups::default(),
+    });
+    if state.admin_token.is_none() {
+        info!("Maintenance endpoints disabled ({} not set)", ADMIN_TOKEN_ENV);
+    }
+    state.sync.restore_compaction(&state.store).await?;
+
+    // Messages accepted before a restart must not be accepted again
+    match state.store.unexpired_seen_messages(chrono::Utc::now()).await {
+        Ok(seen) => state.replay_guard.restore(seen),
+        Err(e) => warn!("Failed to load seen message IDs: {}", e),
+    }
+
+    // Load subscriptions from the previous run before the network starts, so
+    // saving the default topics as they are subscribed keeps the saved ones
+    let saved_topics = state.store.load_subscriptions().await.unwrap_or_else(|e| {
+        warn!("Failed to load saved subscriptions: {}", e);
+        Vec::new()
     });
+    state.subscribed_topics.write().extend(saved_topics.iter().cloned());
 
     // Spawn network service
-    tokio::spawn(async move {
+    let network_task = tokio::spawn(async move {
         if let Err(e) = network_service.run().await {
             error!("Network error: {}", e);
         }
@@ -167,26 +432,748 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
-    // Start HTTP server - bind to requested port (0 = auto-assign)
-    let http_bind_addr = format!("0.0.0.0:{}", http_port);
-    let listener = tokio::net::TcpListener::bind(&http_bind_addr).await?;
+    // Restore subscriptions from the previous run
+    for topic in saved_topics {
+        if topic.starts_with(groups::GROUP_TOPIC_PREFIX) {
+            state.joined_groups.join(&topic, chrono::Utc::now());
+        }
+        if let Err(e) = state.network.subscribe(topic.as_str()).await {
+            warn!("Dropping saved subscription {}: {}", topic, e);
+            let topics = {
+                let mut topics = state.subscribed_topics.write();
+                topics.retain(|t| t != &topic);
+                topics.clone()
+            };
+            if let Err(e) = state.store.save_subscriptions(&topics).await {
+                warn!("Failed to save subscriptions: {}", e);
+            }
+        }
+    }
+
+    // Re-apply blocks from previous runs
+    match state.store.blocked_peers().await {
+        Ok(peers) => {
+            for peer_id in peers {
+                match peer_id.parse::<Libp2pPeerId>() {
+                    Ok(id) => {
+                        if let Err(e) = state.network.block_peer(id).await {
+                            warn!("Failed to block {}: {}", peer_id, e);
+                        }
+                    }
+                    Err(_) => debug!("Blocked peer {} has no libp2p peer id", peer_id),
+                }
+            }
+        }
+        Err(e) => warn!("Failed to load blocklist: {}", e),
+    }
+
+    // Periodically drop direct messages whose recipient never came back,
+    // and resend those a connected recipient hasn't acknowledged yet
+    let sweep_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PENDING_DM_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            expire_pending_dms(&sweep_state).await;
+            if let Ok(peers) = sweep_state.network.get_peers().await {
+                for peer_id in peers {
+                    deliver_pending_dms(&sweep_state, &peer_id.to_base58()).await;
+                }
+            }
+        }
+    });
+
+    // Store seen message IDs in batches rather than one write per message
+    let seen_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SEEN_MESSAGES_SAVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            save_seen_messages(&seen_state).await;
+        }
+    });
+
+    // Forget seen message IDs once they're too old to be replayed
+    let seen_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SEEN_MESSAGES_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = seen_state.store.prune_seen_messages(chrono::Utc::now()).await {
+                warn!("Failed to prune seen message IDs: {}", e);
+            }
+        }
+    });
+
+    // Compact reputation counters when the schedule says so
+    if args.compaction_interval_days > 0 {
+        let compaction_state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(COMPACTION_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = compaction_state.sync.maybe_compact_reputations(&compaction_state.store).await {
+                    warn!("Reputation compaction failed: {}", e);
+                }
+            }
+        });
+    } else {
+        info!("Scheduled reputation compaction is off");
+    }
+
+    // Decide proposals once their deadline passes
+    let tally_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PROPOSAL_TALLY_INTERVAL);
+        loop {
+            interval.tick().await;
+            tally_due_proposals(&tally_state).await;
+        }
+    });
+
+    // Leave groups nobody has written to in a while
+    let group_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_GROUP_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            for topic in group_state.joined_groups.take_idle(chrono::Utc::now()) {
+                info!("Leaving idle group {}", topic);
+                if let Err(e) = group_state.network.unsubscribe(topic.as_str()).await {
+                    warn!("Failed to leave group {}: {}", topic, e);
+                }
+            }
+        }
+    });
+
+    // Save the lifetime message count so a crash loses at most a minute of it
+    let count_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MESSAGE_COUNT_SAVE_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            save_message_count(&count_state).await;
+        }
+    });
+
+    // Keep the WAL from growing without bound on long-running nodes
+    if let Some(minutes) = args.checkpoint_interval {
+        let checkpoint_state = state.clone();
+        tokio::spawn(async move {
+            let period = Duration::from_secs(minutes * 60);
+            loop {
+                tokio::time::sleep(period).await;
+                if let Err(e) = checkpoint_state.store.checkpoint().await {
+                    warn!("WAL checkpoint failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // Start HTTP server - bind to requested interface and port (0 = auto-assign)
+    let listener = bind_http_listener(args.bind, http_port).await?;
 
     // Get the actual bound address (important when port was 0)
     let actual_http_addr = listener.local_addr()?;
-    let actual_http_port = actual_http_addr.port();
+    if !actual_http_addr.ip().is_loopback() {
+        warn!(
+            "Dashboard is reachable from other hosts on {}; use --bind 127.0.0.1 to keep it local",
+            actual_http_addr.ip()
+        );
+    }
+
+    let (http_scheme, ws_scheme) = if tls_config.is_some() { ("https", "wss") } else { ("http", "ws") };
 
     info!("═══════════════════════════════════════════════════════════");
-    info!("  Dashboard server listening on http://127.0.0.1:{}", actual_http_port);
-    info!("  WebSocket endpoint: ws://127.0.0.1:{}/ws", actual_http_port);
-    info!("  REST API: http://127.0.0.1:{}/api/", actual_http_port);
+    info!("  Dashboard server listening on {}://{}", http_scheme, actual_http_addr);
+    info!("  WebSocket endpoint: {}://{}/ws", ws_scheme, actual_http_addr);
+    info!("  REST API: {}://{}/api/", http_scheme, actual_http_addr);
     info!("═══════════════════════════════════════════════════════════");
 
-    let app = server::create_router(state);
-    axum::serve(listener, app).await?;
+    let app = server::create_router(state.clone());
+    let server_shutdown = shutdown::wait(shutdown_tx.subscribe());
+    let mut server_task = tokio::spawn(async move {
+        match tls_config {
+            Some(tls_config) => server::tls::serve(listener, tls_config, app, server_shutdown).await,
+            None => axum::serve(listener, app)
+                .with_graceful_shutdown(server_shutdown)
+                .await
+                .map_err(Into::into),
+        }
+    });
+
+    tokio::select! {
+        result = &mut server_task => {
+            result??;
+            return Ok(());
+        }
+        _ = shutdown::signal() => {}
+    }
+
+    info!("Shutting down");
+    let _ = shutdown_tx.send(());
+
+    // Let in-flight HTTP requests finish; long-lived WebSockets are cut off
+    if tokio::time::timeout(shutdown::SHUTDOWN_TIMEOUT, &mut server_task).await.is_err() {
+        warn!("HTTP connections still open after {:?}, closing them", shutdown::SHUTDOWN_TIMEOUT);
+        server_task.abort();
+    }
+
+    shutdown::flush_pending_updates(&state, shutdown::SHUTDOWN_TIMEOUT).await;
+    save_message_count(&state).await;
+    save_seen_messages(&state).await;
+
+    // The service emits NetworkEvent::Stopped as it exits
+    if let Err(e) = state.network.shutdown().await {
+        warn!("Failed to stop network service: {}", e);
+    }
+    if tokio::time::timeout(shutdown::SHUTDOWN_TIMEOUT, network_task).await.is_err() {
+        warn!("Network service did not stop within {:?}", shutdown::SHUTDOWN_TIMEOUT);
+    }
+
+    state.store.close().await;
+    info!("Shutdown complete");
+
+    Ok(())
+}
+
+/// Messages received over the node's lifetime, this session included
+pub fn lifetime_message_count(state: &AppState) -> u64 {
+    state.previous_message_count + state.message_count.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Persist the lifetime message count
+async fn save_message_count(state: &AppState) {
+    if let Err(e) = state.store.save_message_count(lifetime_message_count(state)).await {
+        warn!("Failed to save message count: {}", e);
+    }
+}
+
+/// Persist the message IDs accepted since the last save
+async fn save_seen_messages(state: &AppState) {
+    let unsaved = state.replay_guard.take_unsaved();
+    if let Err(e) = state.store.record_seen_messages(&unsaved).await {
+        warn!("Failed to record {} seen message IDs: {}", unsaved.len(), e);
+    }
+}
+
+/// SQLite URL for the store, honouring `--in-memory` over `--db`
+fn database_url(args: &Args) -> String {
+    if args.in_memory {
+        "sqlite::memory:".to_string()
+    } else {
+        let path = args.db.as_deref().unwrap_or(DEFAULT_DB_PATH);
+        format!("sqlite:{}?mode=rwc", path)
+    }
+}
+
+/// Reputation compaction schedule from the command line
+///
+/// With an interval of 0 no compaction is scheduled, but compactions
+/// announced by peers are still followed so counters stay comparable.
+fn compaction_config(args: &Args) -> CompactionConfig {
+    CompactionConfig {
+        interval: chrono::Duration::days(args.compaction_interval_days.max(1) as i64),
+        factor: args.compaction_factor,
+    }
+}
+
+fn parse_compaction_factor(s: &str) -> Result<f64, String> {
+    let factor: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if factor > 0.0 && factor <= 1.0 {
+        Ok(factor)
+    } else {
+        Err("must be greater than 0.0 and at most 1.0".to_string())
+    }
+}
+
+/// Listen for dashboard connections on `ip`, port 0 picking a free port
+async fn bind_http_listener(ip: IpAddr, port: u16) -> std::io::Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind(SocketAddr::new(ip, port)).await
+}
+
+/// Deliver direct messages queued while a peer was offline
+///
+/// There is no point-to-point protocol yet, so queued messages go out on the
+/// direct topic as they would have if the peer had been online. Their
+/// timestamp is moved to the time of delivery, as the recipient would
+/// otherwise reject a message queued longer than `--max-message-age`.
+///
+/// Messages stay queued until the recipient's delivery receipt arrives (see
+/// [`delivery`]), and are sent again on the next sweep until then.
+async fn deliver_pending_dms(state: &AppState, recipient: &str) {
+    let pending = match state.store.list_pending_dms(recipient).await {
+        Ok(pending) => pending,
+        Err(e) => {
+            warn!("Failed to load pending DMs for {}: {}", recipient, e);
+            return;
+        }
+    };
+
+    // Sent as queued: the recipient accepts direct messages as old as the queue allows
+    for dm in pending {
+        if let Err(e) = state.network.publish(DIRECT_TOPIC, dm.payload).await {
+            warn!("Failed to deliver pending DM {} to {}: {}", dm.id, recipient, e);
+            break;
+        }
+        debug!("Sent queued DM {} to {}, awaiting receipt", dm.id, recipient);
+    }
+}
+
+/// Answer a direct message addressed to this node with a delivery receipt
+async fn acknowledge_direct_message(state: &AppState, message: &mycelial_core::message::Message, local: &PeerId) {
+    let Some(receipt) = delivery::receipt_for(message, local) else {
+        return;
+    };
+    match serde_json::to_vec(&receipt) {
+        Ok(data) => {
+            if let Err(e) = state.network.publish(DIRECT_TOPIC, data).await {
+                warn!("Failed to acknowledge DM {} from {}: {}", message.id, message.sender, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize receipt for DM {}: {}", message.id, e),
+    }
+}
+
+/// Drop a queued direct message once its recipient acknowledges it
+///
+/// Only a receipt published by the recipient itself counts.
+async fn handle_delivery_ack(state: &AppState, ack: delivery::DeliveryAck, source: Option<&Libp2pPeerId>) {
+    let Some(recipient) = source.map(|peer_id| peer_id.to_base58()) else {
+        return;
+    };
+    let id = ack.delivered.to_string();
+    match state.store.list_pending_dms(&recipient).await {
+        Ok(pending) if pending.iter().any(|dm| dm.id == id) => {}
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Failed to load pending DMs for {}: {}", recipient, e);
+            return;
+        }
+    }
+
+    match state.store.delete_pending_dm(&id).await {
+        Ok(true) => {
+            info!("Delivered queued DM {} to {}", id, recipient);
+            let _ = state.event_tx.send(WsMessage::DirectMessageStatus {
+                id,
+                to: recipient,
+                status: "delivered".to_string(),
+            });
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Failed to remove delivered DM {}: {}", id, e),
+    }
+}
+
+/// Drop expired pending direct messages and report them to the dashboard
+async fn expire_pending_dms(state: &AppState) {
+    match state.store.expire_pending_dms().await {
+        Ok(expired) => {
+            for dm in expired {
+                info!("Queued DM {} to {} expired undelivered", dm.id, dm.recipient);
+                let _ = state.event_tx.send(WsMessage::DirectMessageStatus {
+                    id: dm.id,
+                    to: dm.recipient,
+                    status: "expired".to_string(),
+                });
+            }
+        }
+        Err(e) => warn!("Failed to expire pending DMs: {}", e),
+    }
+}
+
+/// Answer or complete a state exchange with another node
+///
+/// Messages for other nodes, and messages whose claimed sender isn't the
+/// peer that published them, are ignored. Anything else on the topic is
+/// taken as a gossiped state update.
+async fn handle_sync_message(state: &AppState, data: &[u8], source: Option<&Libp2pPeerId>, local_peer_id: Libp2pPeerId) {
+    let Ok(message) = serde_json::from_slice::<SyncMessage>(data) else {
+        apply_state_update(state, data, source).await;
+        return;
+    };
+    let local = local_peer_id.to_base58();
+    if message.to() != local {
+        return;
+    }
+    if !is_publisher(source, message.from()) {
+        warn!("Ignoring sync message claiming to be from {} published by {:?}", message.from(), source);
+        return;
+    }
+
+    match message {
+        SyncMessage::DigestRequest { id, from, .. } => {
+            let summary = match state.store.state_digest().await {
+                Ok(digest) => digest.summary(),
+                Err(e) => {
+                    warn!("Failed to compute state digest for {}: {}", from, e);
+                    return;
+                }
+            };
+            let answer = SyncMessage::Digest { id, from: local, to: from.clone(), summary };
+            if let Err(e) = send_sync_message(state, &answer).await {
+                warn!("Failed to send state digest to {}: {}", from, e);
+            }
+        }
+        SyncMessage::Digest { id, from, summary, .. } => {
+            if !state.pending_diffs.deliver(&from, id, DigestAnswer::Summary(summary)) {
+                debug!("Dropping unrequested state digest {} from {}", id, from);
+            }
+        }
+        SyncMessage::LeavesRequest { id, from, buckets, .. } => {
+            let pages = match state.store.state_digest().await {
+                Ok(digest) => digest.leaf_pages(&buckets, SYNC_PAGE_BYTES),
+                Err(e) => Err(e),
+            };
+            let pages = match pages {
+                Ok(pages) => pages,
+                Err(e) => {
+                    warn!("Failed to collect digest leaves for {}: {}", from, e);
+                    return;
+                }
+            };
+            for page in pages {
+                let answer = SyncMessage::Leaves { id, from: local.clone(), to: from.clone(), page };
+                if let Err(e) = send_sync_message(state, &answer).await {
+                    warn!("Failed to send digest leaves to {}: {}", from, e);
+                    return;
+                }
+            }
+        }
+        SyncMessage::Leaves { id, from, page, .. } => {
+            if !state.pending_diffs.deliver(&from, id, DigestAnswer::Leaves(page)) {
+                debug!("Dropping unrequested digest leaves {} from {}", id, from);
+            }
+        }
+        SyncMessage::SnapshotRequest { id, from, .. } => {
+            let sent = match state.sync.export_snapshot(&state.store).await {
+                Ok(snapshot) => send_snapshot(state, snapshot, id, &local, &from).await,
+                Err(e) => Err(format!("failed to export state snapshot: {}", e)),
+            };
+            if let Err(reason) = sent {
+                warn!("Couldn't send state snapshot to {}: {}", from, reason);
+                // Tell the requester, so it can ask another peer
+                let failed = SyncMessage::SnapshotFailed { id, from: local, to: from.clone(), reason };
+                if let Err(e) = send_sync_message(state, &failed).await {
+                    warn!("Failed to tell {} its snapshot failed: {}", from, e);
+                }
+            }
+        }
+        SyncMessage::Snapshot { id, from, page, pages, snapshot, .. } => {
+            let snapshot = match state.snapshot_bootstrap.receive(&from, id, page, pages, snapshot) {
+                SnapshotPage::Complete(snapshot) => snapshot,
+                SnapshotPage::Partial => return,
+                SnapshotPage::Unrequested => {
+                    debug!("Dropping unrequested state snapshot {} from {}", id, from);
+                    return;
+                }
+            };
+            match state.sync.import_snapshot(&snapshot, &state.store).await {
+                Ok(changed) => {
+                    info!("Bootstrapped from {}'s snapshot, {} records changed", from, changed);
+                    // Merged reputations change how we score those peers
+                    for (peer_id, _) in &snapshot.reputations {
+                        rescore_peer(state, peer_id).await;
+                    }
+                }
+                Err(e) => warn!("Failed to import state snapshot from {}: {}", from, e),
+            }
+        }
+        SyncMessage::SnapshotFailed { id, from, reason, .. } => {
+            if state.snapshot_bootstrap.fail(&from, id) {
+                warn!("{} couldn't send its state snapshot: {}", from, reason);
+            }
+        }
+    }
+}
 
+/// Send `snapshot` to `to` in pages that fit in a gossip message
+async fn send_snapshot(state: &AppState, snapshot: StateSnapshot, id: uuid::Uuid, local: &str, to: &str) -> Result<(), String> {
+    let pages = snapshot.into_pages(SYNC_PAGE_BYTES).map_err(|e| e.to_string())?;
+    let count = pages.len() as u32;
+    for (page, snapshot) in (0..).zip(pages) {
+        let answer = SyncMessage::Snapshot {
+            id,
+            from: local.to_string(),
+            to: to.to_string(),
+            page,
+            pages: count,
+            snapshot,
+        };
+        send_sync_message(state, &answer).await?;
+    }
     Ok(())
 }
 
+/// Publish a state exchange message on the sync topic
+async fn send_sync_message(state: &AppState, message: &SyncMessage) -> Result<(), String> {
+    let data = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+    state
+        .network
+        .publish(mycelial_network::topics::SYNC, data)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Apply a state update gossiped on the sync topic
+///
+/// Only credit updates are taken, and only from the node that made them:
+/// they carry the tallies of transfers recorded there.
+async fn apply_state_update(state: &AppState, data: &[u8], source: Option<&Libp2pPeerId>) {
+    let update = match StateSync::deserialize_update(data) {
+        Ok(update) => update,
+        Err(e) => {
+            debug!("Ignoring unreadable sync message: {}", e);
+            return;
+        }
+    };
+    let StateUpdate::CreditUpdate { creditor, debtor, origin, .. } = &update else {
+        debug!("Ignoring gossiped state update that isn't a credit update");
+        return;
+    };
+    if !is_publisher(source, origin) {
+        warn!("Ignoring credit update claiming to be from {} published by {:?}", origin, source);
+        return;
+    }
+
+    for peer_id in [creditor, debtor] {
+        if let Err(e) = remember_peer(state, &PeerId(peer_id.clone())).await {
+            warn!("Failed to store peer {}: {}", peer_id, e);
+            return;
+        }
+    }
+    match state.sync.apply_update_from(&update, origin, &state.store).await {
+        Ok(true) => debug!("Applied credit update for {}:{} from {}", creditor, debtor, origin),
+        Ok(false) => {}
+        Err(e) => warn!("Rejected credit update for {}:{} from {}: {}", creditor, debtor, origin, e),
+    }
+}
+
+/// Ask `peer` for a snapshot of its state, unless this node already has one
+async fn request_snapshot(state: &AppState, peer: &Libp2pPeerId, local_peer_id: Libp2pPeerId) {
+    let id = uuid::Uuid::new_v4();
+    let peer = peer.to_base58();
+    if !state.snapshot_bootstrap.start(&peer, id, chrono::Utc::now()) {
+        return;
+    }
+    let request = SyncMessage::SnapshotRequest { id, from: local_peer_id.to_base58(), to: peer.clone() };
+    match send_sync_message(state, &request).await {
+        Ok(_) => info!("Asked {} for a state snapshot", peer),
+        Err(e) => {
+            warn!("Failed to ask {} for a state snapshot: {}", peer, e);
+            state.snapshot_bootstrap.cancel(id);
+        }
+    }
+}
+
+/// Record a gossiped vote, holding it back if its proposal hasn't arrived
+///
+/// Returns false if the vote was dropped because the voter isn't the peer
+/// that published it.
+async fn handle_vote(state: &AppState, vote: &mycelial_protocol::CastVote, source: Option<&Libp2pPeerId>) -> bool {
+    if !is_publisher(source, &vote.voter) {
+        warn!("Ignoring vote claiming to be by {} published by {:?}", vote.voter, source);
+        return false;
+    }
+
+    match state.store.record_vote(vote).await {
+        Ok(_) => {}
+        Err(mycelial_state::StateError::NotFound { .. }) => {
+            debug!("Holding vote by {} until proposal {} arrives", vote.voter, vote.proposal_id);
+            state.early_votes.hold(vote.clone(), chrono::Utc::now());
+        }
+        Err(e) => warn!("Failed to record vote by {} on {}: {}", vote.voter, vote.proposal_id, e),
+    }
+    true
+}
+
+/// Record the votes that arrived before a proposal
+async fn record_early_votes(state: &AppState, proposal_id: &uuid::Uuid) {
+    for vote in state.early_votes.take(proposal_id, chrono::Utc::now()) {
+        if let Err(e) = state.store.record_vote(&vote).await {
+            warn!("Failed to record vote by {} on {}: {}", vote.voter, proposal_id, e);
+        }
+    }
+}
+
+/// Decide every open proposal whose deadline has passed
+async fn tally_due_proposals(state: &AppState) {
+    let due = match state.store.due_proposals(chrono::Utc::now()).await {
+        Ok(due) => due,
+        Err(e) => {
+            warn!("Failed to list proposals due for tallying: {}", e);
+            return;
+        }
+    };
+
+    for proposal_id in due {
+        match state.store.tally_proposal(&proposal_id).await {
+            Ok(outcome) => {
+                let _ = state.event_tx.send(WsMessage::ProposalDecided {
+                    id: proposal_id.to_string(),
+                    status: outcome.verdict.as_str().to_string(),
+                    votes_for: outcome.votes_for,
+                    votes_against: outcome.votes_against,
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                });
+            }
+            Err(e) => warn!("Failed to tally proposal {}: {}", proposal_id, e),
+        }
+    }
+}
+
+/// Leaderboard key for a resource type, e.g. `bandwidth`
+fn resource_type_name(resource_type: &mycelial_protocol::ResourceType) -> String {
+    use mycelial_protocol::ResourceType;
+    match resource_type {
+        ResourceType::Bandwidth => "bandwidth".to_string(),
+        ResourceType::Storage => "storage".to_string(),
+        ResourceType::Compute => "compute".to_string(),
+        ResourceType::Relay => "relay".to_string(),
+        ResourceType::Other(name) => name.to_lowercase(),
+    }
+}
+
+/// Whether a message's author meets the configured reputation gate
+async fn passes_reputation_gate(state: &AppState, source: Option<&Libp2pPeerId>) -> bool {
+    let gate = state.reputation_gate;
+    let Some(min_reputation) = gate.min_reputation else {
+        return true;
+    };
+
+    let score = match source {
+        Some(peer_id) => stored_reputation(state, &peer_id.to_base58()).await,
+        None => None,
+    };
+    match score {
+        Some(score) => score >= min_reputation,
+        None => gate.unknown_peers == UnknownPeerPolicy::Accept,
+    }
+}
+
+/// Tell the network whether to forward a received message
+async fn report_message(state: &AppState, message_id: &MessageId, acceptance: MessageAcceptance) {
+    if let Err(e) = state.network.report_message(message_id.clone(), acceptance).await {
+        warn!("Failed to report validation of message {}: {}", message_id, e);
+    }
+}
+
+/// Store a chat message, recording its sender first if it is new to us
+///
+/// Stored messages must name a known sender, and a message can arrive
+/// from a peer we never connected to.
+pub async fn store_chat_message(state: &AppState, message: &mycelial_core::message::Message) -> mycelial_state::Result<()> {
+    remember_peer(state, &message.sender).await?;
+    state.store.store_message(message).await
+}
+
+/// Store a peer known only by its ID, so records can refer to it
+pub async fn remember_peer(state: &AppState, peer_id: &PeerId) -> mycelial_state::Result<()> {
+    if state.store.get_peer(peer_id.as_str()).await?.is_none() {
+        let now = chrono::Utc::now();
+        let peer = PeerInfo {
+            id: peer_id.clone(),
+            public_key: peer_id.to_string(),
+            addresses: vec![],
+            first_seen: now,
+            last_seen: now,
+            name: None,
+        };
+        state.store.upsert_peer(&peer, None).await?;
+    }
+    Ok(())
+}
+
+/// Join the group an invite is for, if it really comes from the inviting member
+///
+/// The inviter must be connected or already known to this node, and the
+/// invite is turned down once this node is in [`groups::MAX_JOINED_GROUPS`]
+/// groups.
+async fn join_group(
+    state: &AppState,
+    invite: &mycelial_core::message::Message,
+    group_topic: String,
+    source: Option<&Libp2pPeerId>,
+) {
+    if !is_publisher(source, invite.sender.as_str()) {
+        warn!("Ignoring group invite claiming to be from {} published by {:?}", invite.sender, source);
+        return;
+    }
+    if state.subscribed_topics.read().contains(&group_topic) {
+        return;
+    }
+    let connected = match (source, state.network.get_peers().await) {
+        (Some(inviter), Ok(peers)) => peers.contains(inviter),
+        _ => false,
+    };
+    if !connected && stored_reputation(state, invite.sender.as_str()).await.is_none() {
+        debug!("Ignoring group invite from unknown peer {}", invite.sender);
+        return;
+    }
+    if !state.joined_groups.join_invited(&group_topic, chrono::Utc::now()) {
+        warn!("Turning down invite to {} from {}: already in too many groups", group_topic, invite.sender);
+        return;
+    }
+    info!("Joining group {} on {}'s invite", group_topic, invite.sender);
+    if let Err(e) = state.network.subscribe(&group_topic).await {
+        warn!("Failed to join group {}: {}", group_topic, e);
+    }
+}
+
+/// Whether `claimed` is the peer that published a message
+///
+/// Messages naming their author in the payload are only trusted if the
+/// gossipsub source, which is signed, is that same peer.
+fn is_publisher(source: Option<&Libp2pPeerId>, claimed: &str) -> bool {
+    source.map(|peer_id| peer_id.to_base58()).as_deref() == Some(claimed)
+}
+
+/// Reputation score this node holds for a peer, `None` if it doesn't know it
+async fn stored_reputation(state: &AppState, peer_id: &str) -> Option<f64> {
+    match state.store.get_peer(peer_id).await {
+        Ok(peer) => peer.map(|(_, reputation)| reputation.score),
+        Err(e) => {
+            warn!("Failed to look up reputation of {}: {}", peer_id, e);
+            None
+        }
+    }
+}
+
+/// Let gossipsub demote (or favour) a peer in our meshes by its stored reputation
+///
+/// Alerts go by the same record, once the mesh score follows it. Peers this
+/// node doesn't know are left alone.
+async fn rescore_peer(state: &AppState, peer_id: &str) {
+    let pid = match peer_id.parse::<Libp2pPeerId>() {
+        Ok(pid) => pid,
+        Err(e) => {
+            warn!("Can't score invalid peer ID {}: {}", peer_id, e);
+            return;
+        }
+    };
+    if let Some(score) = stored_reputation(state, peer_id).await {
+        match state.network.set_peer_score(pid, score).await {
+            Ok(()) => alert_on_crossing(state, peer_id, score),
+            Err(e) => warn!("Failed to update peer score for {}: {}", pid, e),
+        }
+    }
+}
+
+/// Tell clients if a peer's reputation moved across the alert threshold
+fn alert_on_crossing(state: &AppState, peer_id: &str, score: f64) {
+    if let Some(direction) = state.reputation_alerts.observe(peer_id, score) {
+        info!("Reputation of {} is now {:?} the alert threshold", peer_id, direction);
+        let _ = state.event_tx.send(WsMessage::ReputationAlert {
+            peer_id: peer_id.to_string(),
+            direction,
+            score,
+        });
+    }
+}
+
 /// Handle events from the P2P network
 async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_id: Libp2pPeerId) {
     match event {
@@ -207,16 +1194,25 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                 name: Some(format!("Peer-{}", short_id)),
             };
 
-            // Store peer with default reputation
-            if let Err(e) = state.store.upsert_peer(&peer_info, Some(&Reputation::default())).await {
-                warn!("Failed to store peer: {}", e);
+            // Store the peer, keeping the reputation we hold for a returning one
+            match state.store.get_peer(core_peer_id.as_str()).await {
+                Ok(stored) => {
+                    let reputation = stored.map(|(_, reputation)| reputation).unwrap_or_default();
+                    if let Err(e) = state.store.upsert_peer(&peer_info, Some(&reputation)).await {
+                        warn!("Failed to store peer: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to look up peer {}: {}", peer_id, e),
             }
+            rescore_peer(state, &peer_id.to_base58()).await;
 
             // Broadcast to dashboard
             let _ = state.event_tx.send(WsMessage::PeerJoined {
                 peer_id: peer_id.to_base58(),
                 name: peer_info.name.clone(),
             });
+
+            deliver_pending_dms(state, &peer_id.to_base58()).await;
         }
 
         NetworkEvent::PeerDisconnected { peer_id, num_connections } => {
@@ -226,9 +1222,83 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
             });
         }
 
+        NetworkEvent::PeerUnresponsive { peer_id } => {
+            // Likely a half-open connection; dropping it lets the peer reconnect cleanly
+            warn!("Peer {} stopped answering heartbeats, disconnecting", peer_id);
+            if let Err(e) = state.network.disconnect(peer_id).await {
+                warn!("Failed to disconnect unresponsive peer {}: {}", peer_id, e);
+            }
+        }
+
         NetworkEvent::MessageReceived { message_id, topic, source, data, timestamp } => {
+            if let Some(peer_id) = source {
+                if state.store.is_blocked(&peer_id.to_base58()).await.unwrap_or(false) {
+                    debug!("Dropping message on {} from blocked peer {}", topic, peer_id);
+                    report_message(state, &message_id, MessageAcceptance::Ignore).await;
+                    return;
+                }
+            }
+
+            // Gossipsub forwards the message once we accept it; messages by
+            // low-reputation authors are rejected, so they stop spreading here
+            let trusted = passes_reputation_gate(state, source.as_ref()).await;
+            let acceptance = if trusted { MessageAcceptance::Accept } else { MessageAcceptance::Reject };
+            report_message(state, &message_id, acceptance).await;
+
+            // Messages in the common envelope carry their own ID and send time
+            let envelope = serde_json::from_slice::<mycelial_core::message::Message>(&data).ok();
+            let (replay_id, sent_at) = match &envelope {
+                Some(message) => (message.id.to_string(), Some(message.timestamp)),
+                None => (message_id.to_string(), None),
+            };
+            let local = PeerId(local_peer_id.to_base58());
+            // Direct messages to us may have waited in the sender's queue
+            let queued_for_us = envelope.as_ref().is_some_and(|message| {
+                topic == DIRECT_TOPIC
+                    && message.message_type == mycelial_core::message::MessageType::Direct
+                    && message.recipient.as_ref() == Some(&local)
+            });
+            let checked = if queued_for_us {
+                state.replay_guard.check_direct(&replay_id, sent_at, timestamp)
+            } else {
+                state.replay_guard.check(&replay_id, sent_at, timestamp)
+            };
+            if let Err(replay) = checked {
+                debug!("Rejecting message {} on {} from {:?}: {:?}", replay_id, topic, source, replay);
+                // A direct message sent again means our receipt got lost
+                if let (Replay::Duplicate, Some(message)) = (replay, &envelope) {
+                    acknowledge_direct_message(state, message, &local).await;
+                }
+                return;
+            }
+
             // Update message count
             state.message_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            *state.topic_message_counts.write().entry(topic.clone()).or_insert(0) += 1;
+
+            if !trusted {
+                debug!("Ignoring message on {} from low-reputation peer {:?}", topic, source);
+                return;
+            }
+
+            // Delivery receipts and group invites are for this node only,
+            // never shown as chat
+            if let Some(message) = envelope.as_ref().filter(|_| topic == DIRECT_TOPIC) {
+                if message.message_type == mycelial_core::message::MessageType::System {
+                    if let Some(ack) = delivery::acknowledgement(message, &local) {
+                        handle_delivery_ack(state, ack, source.as_ref()).await;
+                    } else if let Some(group_topic) = groups::invitation(message, &local) {
+                        join_group(state, message, group_topic, source.as_ref()).await;
+                    }
+                    return;
+                }
+                acknowledge_direct_message(state, message, &local).await;
+            }
+
+            if topic == mycelial_network::topics::SYNC {
+                handle_sync_message(state, &data, source.as_ref(), local_peer_id).await;
+                return;
+            }
 
             let from_id = source.map(|p| p.to_base58()).unwrap_or_else(|| "unknown".to_string());
             let ts = timestamp.timestamp_millis();
@@ -241,6 +1311,13 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                             use mycelial_protocol::VouchMessage;
                             match vouch_msg {
                                 VouchMessage::VouchRequest(req) => {
+                                    if !is_publisher(source.as_ref(), &req.voucher) {
+                                        warn!("Ignoring vouch claiming to be by {} published by {:?}", req.voucher, source);
+                                        return;
+                                    }
+                                    if let Err(e) = state.store.save_vouch(&req).await {
+                                        warn!("Failed to save vouch {}: {}", req.id, e);
+                                    }
                                     let _ = state.event_tx.send(WsMessage::VouchRequest {
                                         id: req.id.to_string(),
                                         voucher: req.voucher,
@@ -250,6 +1327,13 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                                     });
                                 }
                                 VouchMessage::VouchAck(ack) => {
+                                    if !is_publisher(source.as_ref(), &ack.from) {
+                                        warn!("Ignoring vouch ack claiming to be from {} published by {:?}", ack.from, source);
+                                        return;
+                                    }
+                                    if let Err(e) = state.store.record_vouch_ack(&ack).await {
+                                        warn!("Failed to record vouch ack for {}: {}", ack.vouch_id, e);
+                                    }
                                     let _ = state.event_tx.send(WsMessage::VouchAck {
                                         id: message_id.to_string(),
                                         request_id: ack.vouch_id.to_string(),
@@ -259,6 +1343,8 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                                     });
                                 }
                                 VouchMessage::ReputationUpdate(update) => {
+                                    // The announced score is only a claim; rescore by our own record
+                                    rescore_peer(state, &update.peer_id).await;
                                     let _ = state.event_tx.send(WsMessage::ReputationUpdate {
                                         peer_id: update.peer_id,
                                         new_score: update.score,
@@ -303,6 +1389,10 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                             use mycelial_protocol::GovernanceMessage;
                             match gov_msg {
                                 GovernanceMessage::CreateProposal(proposal) => {
+                                    match state.store.save_proposal(&proposal).await {
+                                        Ok(()) => record_early_votes(state, &proposal.id).await,
+                                        Err(e) => warn!("Failed to save proposal {}: {}", proposal.id, e),
+                                    }
                                     // quorum is f64 (0.0-1.0), convert to percentage as u32
                                     let quorum_pct = (proposal.quorum * 100.0) as u32;
                                     let _ = state.event_tx.send(WsMessage::Proposal {
@@ -320,6 +1410,9 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                                     });
                                 }
                                 GovernanceMessage::CastVote(vote) => {
+                                    if !handle_vote(state, &vote, source.as_ref()).await {
+                                        return;
+                                    }
                                     let _ = state.event_tx.send(WsMessage::VoteCast {
                                         id: message_id.to_string(),
                                         proposal_id: vote.proposal_id.to_string(),
@@ -354,6 +1447,14 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                             use mycelial_protocol::ResourceMessage;
                             match res_msg {
                                 ResourceMessage::Contribution(contrib) => {
+                                    let resource_type = resource_type_name(&contrib.resource_type);
+                                    if let Err(e) = state
+                                        .store
+                                        .record_contribution(&contrib.peer_id, &resource_type, contrib.amount)
+                                        .await
+                                    {
+                                        warn!("Failed to record contribution from {}: {}", contrib.peer_id, e);
+                                    }
                                     let _ = state.event_tx.send(WsMessage::ResourceContribution {
                                         id: contrib.id.to_string(),
                                         peer_id: contrib.peer_id,
@@ -389,10 +1490,50 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                 }
             }
             // Try to parse as chat message (handles chat, content, direct, and room topics)
-            else if topic.contains("chat") || topic.contains("content") || topic.contains("direct") || topic.contains("room") {
+            else if topic.contains("chat")
+                || topic.contains("content")
+                || topic.contains("direct")
+                || topic.contains("room")
+                || topic.starts_with(groups::GROUP_TOPIC_PREFIX)
+            {
                 if let Ok(content) = String::from_utf8(data.clone()) {
                     let short_from = &from_id[..8.min(from_id.len())];
 
+                    // Direct messages to this node, from their sender, are kept
+                    // as conversation history
+                    let direct = envelope
+                        .as_ref()
+                        .filter(|message| queued_for_us && is_publisher(source.as_ref(), message.sender.as_str()));
+                    if let Some(message) = direct {
+                        if let Err(e) = store_chat_message(state, message).await {
+                            warn!("Failed to store direct message {}: {}", message.id, e);
+                        }
+                    }
+
+                    // Group messages name their recipients; keep a single copy.
+                    // They only count on their own group's topic, sent by a
+                    // member to this node.
+                    let group = envelope.filter(|message| !message.recipients.is_empty());
+                    if let Some(message) = &group {
+                        if !groups::is_member_message(message, &topic, &local)
+                            || !is_publisher(source.as_ref(), message.sender.as_str())
+                        {
+                            debug!("Ignoring group message {} on {} from {:?}", message.id, topic, source);
+                            return;
+                        }
+                    } else if topic.starts_with(groups::GROUP_TOPIC_PREFIX) {
+                        return;
+                    }
+                    if let Some(message) = &group {
+                        state.joined_groups.touch(&topic, chrono::Utc::now());
+                        if let Err(e) = store_chat_message(state, message).await {
+                            warn!("Failed to store group message {}: {}", message.id, e);
+                        }
+                    }
+                    let to = group.map(|message| {
+                        ChatRecipients::Group(message.recipients.into_iter().map(|peer| peer.0).collect())
+                    });
+
                     // Extract room_id from topic if it's a room message
                     // Topic format: /mycelial/1.0.0/room/{room_id}
                     let room_id = if topic.contains("/room/") {
@@ -405,7 +1546,7 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
                         id: message_id.to_string(),
                         from: from_id.clone(),
                         from_name: format!("Peer-{}", short_from),
-                        to: None,
+                        to,
                         room_id,
                         content,
                         timestamp: ts,
@@ -414,38 +1555,108 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
             }
         }
 
-        NetworkEvent::ListeningOn { address } => {
+        NetworkEvent::ListeningOn { address, transport, scope } => {
             // Print full multiaddr with peer ID so users know how to connect
             let full_multiaddr = format!("{}/p2p/{}", address, local_peer_id);
             info!("═══════════════════════════════════════════════════════════");
-            info!("  P2P Listening on: {}", address);
+            info!("  P2P Listening on: {} ({:?}, {:?})", address, transport, scope);
             info!("  Full multiaddr (use this to connect):");
             info!("    {}", full_multiaddr);
             info!("═══════════════════════════════════════════════════════════");
+
+            let mut addresses = state.listen_addresses.write();
+            if !addresses.iter().any(|a| a.address == address) {
+                addresses.push(ListenAddress { address, transport, scope });
+            }
+        }
+
+        NetworkEvent::ListenAddressExpired { address } => {
+            state.listen_addresses.write().retain(|a| a.address != address);
         }
 
         NetworkEvent::Subscribed { topic } => {
             info!("Subscribed to topic: {}", topic);
-            state.subscribed_topics.write().push(topic);
+            let topics = {
+                let mut topics = state.subscribed_topics.write();
+                if !topics.contains(&topic) {
+                    topics.push(topic);
+                }
+                topics.clone()
+            };
+            if let Err(e) = state.store.save_subscriptions(&topics).await {
+                warn!("Failed to save subscriptions: {}", e);
+            }
         }
 
         NetworkEvent::Unsubscribed { topic } => {
             info!("Unsubscribed from topic: {}", topic);
-            state.subscribed_topics.write().retain(|t| t != &topic);
+            let topics = {
+                let mut topics = state.subscribed_topics.write();
+                topics.retain(|t| t != &topic);
+                topics.clone()
+            };
+            if let Err(e) = state.store.save_subscriptions(&topics).await {
+                warn!("Failed to save subscriptions: {}", e);
+            }
         }
 
         NetworkEvent::Started { peer_id, listen_addresses: _ } => {
             info!("Network started for peer: {}", peer_id);
             info!("Listen addresses will be reported as they become available");
+            state.network_ready.store(true, std::sync::atomic::Ordering::Relaxed);
         }
 
         NetworkEvent::Stopped => {
             info!("Network stopped");
+            state.network_ready.store(false, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        NetworkEvent::DialFailed { peer_id: Some(pid), error } => {
+            warn!("Failed to dial {}: {}", pid, error);
+        }
+
+        NetworkEvent::MeshUpdated { topic, mesh_peers } => {
+            info!("Mesh for {} now has {} peers", topic, mesh_peers);
+            let _ = state.event_tx.send(WsMessage::MeshStatus { topic, mesh_peers });
+        }
+
+        NetworkEvent::TopicIsolated { topic } => {
+            warn!("Topic {} has no mesh peers; messages on it will not propagate", topic);
+            let _ = state.event_tx.send(WsMessage::TopicHealth { topic, isolated: true });
+        }
+
+        NetworkEvent::TopicConnected { topic } => {
+            info!("Topic {} has mesh peers again", topic);
+            let _ = state.event_tx.send(WsMessage::TopicHealth { topic, isolated: false });
+        }
+
+        NetworkEvent::BandwidthReport { peer_id, bytes_in, bytes_out } => {
+            let _ = state.event_tx.send(WsMessage::BandwidthUpdate {
+                peer_id: peer_id.to_base58(),
+                bytes_in,
+                bytes_out,
+            });
         }
 
-        NetworkEvent::DialFailed { peer_id, error } => {
-            if let Some(pid) = peer_id {
-                warn!("Failed to dial {}: {}", pid, error);
+        NetworkEvent::ConnectionBlocked { peer_id } => {
+            info!("Closed connection from blocked peer {}", peer_id);
+        }
+
+        NetworkEvent::PeerSubscribed { peer_id, topic } if topic == mycelial_network::topics::SYNC => {
+            // Updates queued while nobody was listening can go out now
+            shutdown::flush_pending_updates(state, shutdown::SHUTDOWN_TIMEOUT).await;
+            request_snapshot(state, &peer_id, local_peer_id).await;
+        }
+
+        NetworkEvent::PeerSubscribed { peer_id, topic } if topic.starts_with(groups::GROUP_TOPIC_PREFIX) => {
+            for data in state.held_group_messages.take(&topic, chrono::Utc::now()) {
+                match state.network.publish(&topic, data.clone()).await {
+                    Ok(_) => debug!("Published held group message on {} now that {} joined", topic, peer_id),
+                    Err(e) => {
+                        warn!("Failed to publish held group message on {}: {}", topic, e);
+                        state.held_group_messages.hold(topic.clone(), data, chrono::Utc::now());
+                    }
+                }
             }
         }
 
@@ -458,3 +1669,768 @@ async fn handle_network_event(event: NetworkEvent, state: &AppState, local_peer_
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mycelial_core::reputation::Reputation;
+    use server::testing;
+
+    #[test]
+    fn test_database_url() {
+        let args = Args::parse_from(["mycelial-node"]);
+        assert_eq!(database_url(&args), "sqlite:mycelial.db?mode=rwc");
+
+        let args = Args::parse_from(["mycelial-node", "--db", "other.db"]);
+        assert_eq!(database_url(&args), "sqlite:other.db?mode=rwc");
+
+        // --in-memory wins over --db
+        let args = Args::parse_from(["mycelial-node", "--in-memory", "--db", "other.db"]);
+        assert_eq!(database_url(&args), "sqlite::memory:");
+    }
+
+    #[test]
+    fn test_compaction_args() {
+        let args = Args::parse_from(["mycelial-node"]);
+        let config = compaction_config(&args);
+        assert_eq!(config.interval, chrono::Duration::days(7));
+        assert_eq!(config.factor, 0.5);
+
+        let args = Args::parse_from(["mycelial-node", "--compaction-interval-days", "30", "--compaction-factor", "0.8"]);
+        let config = compaction_config(&args);
+        assert_eq!(config.interval, chrono::Duration::days(30));
+        assert_eq!(config.factor, 0.8);
+
+        let args = Args::parse_from(["mycelial-node", "--compaction-interval-days", "0"]);
+        assert_eq!(args.compaction_interval_days, 0);
+        for factor in ["0", "1.5", "-0.5", "half"] {
+            assert!(Args::try_parse_from(["mycelial-node", "--compaction-factor", factor]).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_listener_binds_requested_interface() {
+        // Loopback unless another interface is asked for
+        let args = Args::parse_from(["mycelial-node"]);
+        assert_eq!(args.bind, IpAddr::from([127, 0, 0, 1]));
+        let args = Args::parse_from(["mycelial-node", "--bind", "0.0.0.0"]);
+        assert!(args.bind.is_unspecified());
+        assert!(Args::try_parse_from(["mycelial-node", "--bind", "localhost"]).is_err());
+
+        let listener = bind_http_listener(IpAddr::from([127, 0, 0, 1]), 0).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(addr.ip(), IpAddr::from([127, 0, 0, 1]));
+        assert_ne!(addr.port(), 0);
+
+        let listener = bind_http_listener(IpAddr::from([0, 0, 0, 0]), 0).await.unwrap();
+        assert!(listener.local_addr().unwrap().ip().is_unspecified());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_node_serves_api() {
+        let dir = std::env::temp_dir().join(format!("mycelial-in-memory-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let db_path = dir.join("node.db");
+        let db_arg = db_path.to_str().unwrap();
+
+        let args = Args::parse_from(["mycelial-node", "--in-memory", "--db", db_arg]);
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.store = SqliteStore::new(&database_url(&args)).await.unwrap();
+        assert_eq!(state.store.count_peers().await.unwrap(), 0);
+        let addr = testing::spawn_server(Arc::new(state)).await;
+
+        let (status, peers) = testing::get_json(addr, "/api/peers").await;
+        assert_eq!(status, 200);
+        assert_eq!(peers, serde_json::json!([]));
+        let (status, _) = testing::get_json(addr, "/api/stats").await;
+        assert_eq!(status, 200);
+
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ready_follows_network_lifecycle() {
+        let state = testing::app_state().await;
+        let addr = testing::spawn_server(state.clone()).await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+
+        // Liveness doesn't depend on the network
+        let (status, body) = testing::get_json(addr, "/health").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, serde_json::json!({"status": "ok"}));
+
+        let (status, _) = testing::get_json(addr, "/ready").await;
+        assert_eq!(status, 503);
+
+        let started = NetworkEvent::Started { peer_id: local_peer_id, listen_addresses: vec![] };
+        handle_network_event(started, &state, local_peer_id).await;
+        let (status, body) = testing::get_json(addr, "/ready").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "ready");
+
+        handle_network_event(NetworkEvent::Stopped, &state, local_peer_id).await;
+        let (status, _) = testing::get_json(addr, "/ready").await;
+        assert_eq!(status, 503);
+    }
+
+    #[tokio::test]
+    async fn test_topic_message_counts() {
+        let state = testing::app_state().await;
+        let addr = testing::spawn_server(state.clone()).await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+
+        for topic in ["/test/a", "/test/b", "/test/a", "/test/a"] {
+            let event = NetworkEvent::MessageReceived {
+                message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+                topic: topic.to_string(),
+                source: None,
+                data: b"payload".to_vec(),
+                timestamp: chrono::Utc::now(),
+            };
+            handle_network_event(event, &state, local_peer_id).await;
+        }
+
+        let (status, stats) = testing::get_json(addr, "/api/topics/stats").await;
+        assert_eq!(status, 200);
+        assert_eq!(stats["topics"], serde_json::json!({"/test/a": 3, "/test/b": 1}));
+        assert_eq!(stats["total"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_peer_messages_not_counted() {
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let blocked = Keypair::generate_ed25519().public().to_peer_id();
+        state.store.block_peer(&blocked.to_base58(), None).await.unwrap();
+
+        let event = NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: "/test/a".to_string(),
+            source: Some(blocked),
+            data: b"payload".to_vec(),
+            timestamp: chrono::Utc::now(),
+        };
+        handle_network_event(event, &state, local_peer_id).await;
+
+        assert_eq!(state.message_count.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert!(state.topic_message_counts.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_saved_subscriptions_survive_default_subscribes() {
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        state.store.save_subscriptions(&["/test/saved".to_string()]).await.unwrap();
+
+        // Loaded before the network starts, as at startup
+        let saved = state.store.load_subscriptions().await.unwrap();
+        state.subscribed_topics.write().extend(saved);
+        let event = NetworkEvent::Subscribed { topic: "/test/default".to_string() };
+        handle_network_event(event, &state, local_peer_id).await;
+
+        let mut saved = state.store.load_subscriptions().await.unwrap();
+        saved.sort();
+        assert_eq!(saved, vec!["/test/default".to_string(), "/test/saved".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_reputation_alert_once_per_crossing() {
+        use mycelial_protocol::{topics, ReputationChangeReason, ReputationUpdate, VouchMessage};
+        use server::messages::ThresholdSide;
+
+        let with_alerts = |state: Arc<AppState>| {
+            let mut state = Arc::into_inner(state).unwrap();
+            state.reputation_alerts = ReputationAlerts::new(Some(0.5));
+            state
+        };
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let alice = Keypair::generate_ed25519().public().to_peer_id().to_base58();
+        let peer = |id: &str| PeerInfo {
+            id: PeerId(id.to_string()),
+            public_key: id.to_string(),
+            addresses: vec![],
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            name: None,
+        };
+        // Our record moves to `score`, then a gossiped update claims otherwise
+        async fn observe(state: &AppState, peer_id: &str, score: f64, local_peer_id: Libp2pPeerId) {
+            state.store.update_peer_reputation(peer_id, &Reputation::new(score)).await.unwrap();
+            let update = VouchMessage::ReputationUpdate(ReputationUpdate {
+                peer_id: peer_id.to_string(),
+                score: 1.0 - score,
+                delta: 0.1,
+                reason: ReputationChangeReason::SuccessfulInteraction,
+                timestamp: chrono::Utc::now(),
+            });
+            let event = NetworkEvent::MessageReceived {
+                message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+                topic: topics::VOUCH.to_string(),
+                source: None,
+                data: serde_json::to_vec(&update).unwrap(),
+                timestamp: chrono::Utc::now(),
+            };
+            handle_network_event(event, state, local_peer_id).await;
+        }
+        let alerts = |events: &mut broadcast::Receiver<WsMessage>| {
+            let mut alerts = Vec::new();
+            while let Ok(message) = events.try_recv() {
+                if let WsMessage::ReputationAlert { peer_id, direction, score } = message {
+                    alerts.push((peer_id, direction, score));
+                }
+            }
+            alerts
+        };
+
+        let state = with_alerts(testing::app_state_with_network().await);
+        let mut events = state.event_tx.subscribe();
+        for id in [alice.as_str(), "not-a-peer-id"] {
+            state.store.upsert_peer(&peer(id), Some(&Reputation::new(0.6))).await.unwrap();
+        }
+        for score in [0.6, 0.55, 0.4, 0.3, 0.45, 0.6, 0.7, 0.5, 0.2] {
+            observe(&state, &alice, score, local_peer_id).await;
+        }
+        assert_eq!(
+            alerts(&mut events),
+            vec![
+                (alice.clone(), ThresholdSide::Below, 0.4),
+                (alice.clone(), ThresholdSide::Above, 0.6),
+                (alice.clone(), ThresholdSide::Below, 0.2),
+            ]
+        );
+
+        // No alerts for peer IDs the network can't score
+        for score in [0.6, 0.2] {
+            observe(&state, "not-a-peer-id", score, local_peer_id).await;
+        }
+        assert!(alerts(&mut events).is_empty());
+
+        // ... nor while the mesh score can't be updated
+        let state = with_alerts(testing::app_state().await);
+        let mut events = state.event_tx.subscribe();
+        state.store.upsert_peer(&peer(&alice), Some(&Reputation::new(0.6))).await.unwrap();
+        for score in [0.6, 0.2] {
+            observe(&state, &alice, score, local_peer_id).await;
+        }
+        assert!(alerts(&mut events).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconnected_peer_scored_by_stored_reputation() {
+        let mut state = Arc::into_inner(testing::app_state_with_network().await).unwrap();
+        state.reputation_alerts = ReputationAlerts::new(Some(0.5));
+        let mut events = state.event_tx.subscribe();
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let alice = Keypair::generate_ed25519().public().to_peer_id();
+        let connected = || NetworkEvent::PeerConnected { peer_id: alice, num_connections: 1 };
+
+        // A new peer starts out with the default reputation
+        handle_network_event(connected(), &state, local_peer_id).await;
+        let (_, reputation) = state.store.get_peer(&alice.to_base58()).await.unwrap().unwrap();
+        assert_eq!(reputation.score, Reputation::default().score);
+
+        // A returning one keeps what we hold for it, and is scored by it
+        state.store.update_peer_reputation(&alice.to_base58(), &Reputation::new(0.2)).await.unwrap();
+        handle_network_event(connected(), &state, local_peer_id).await;
+        let (_, reputation) = state.store.get_peer(&alice.to_base58()).await.unwrap().unwrap();
+        assert_eq!(reputation.score, 0.2);
+        let alerted = std::iter::from_fn(|| events.try_recv().ok())
+            .any(|message| matches!(message, WsMessage::ReputationAlert { score, .. } if score == 0.2));
+        assert!(alerted);
+    }
+
+    #[tokio::test]
+    async fn test_low_reputation_messages_dropped() {
+        use mycelial_core::message::Message;
+
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.reputation_gate = ReputationGate {
+            min_reputation: Some(0.3),
+            unknown_peers: UnknownPeerPolicy::Drop,
+        };
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+
+        let peer_with_score = |score: f64| {
+            let peer_id = Keypair::generate_ed25519().public().to_peer_id();
+            let info = PeerInfo {
+                id: PeerId(peer_id.to_base58()),
+                public_key: peer_id.to_base58(),
+                addresses: vec![],
+                first_seen: chrono::Utc::now(),
+                last_seen: chrono::Utc::now(),
+                name: None,
+            };
+            let reputation = Reputation { score, ..Reputation::default() };
+            (peer_id, info, reputation)
+        };
+        let low = peer_with_score(0.1);
+        let high = peer_with_score(0.9);
+        for (_, info, reputation) in [&low, &high] {
+            state.store.upsert_peer(info, Some(reputation)).await.unwrap();
+        }
+        let unknown = Keypair::generate_ed25519().public().to_peer_id();
+
+        let mut sent = Vec::new();
+        for source in [low.0, high.0, unknown] {
+            let message = Message::group(
+                PeerId(source.to_base58()),
+                vec![PeerId(local_peer_id.to_base58()), PeerId("bob".to_string())],
+                b"hello".to_vec(),
+            );
+            let event = NetworkEvent::MessageReceived {
+                message_id: mycelial_network::MessageId::new(message.id.as_bytes()),
+                topic: groups::topic_for(&message).unwrap(),
+                source: Some(source),
+                data: serde_json::to_vec(&message).unwrap(),
+                timestamp: chrono::Utc::now(),
+            };
+            handle_network_event(event, &state, local_peer_id).await;
+            sent.push(message.id);
+        }
+
+        assert!(state.store.get_message(&sent[0]).await.unwrap().is_none());
+        assert!(state.store.get_message(&sent[1]).await.unwrap().is_some());
+        assert!(state.store.get_message(&sent[2]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_votes_bound_to_source_and_held_for_proposal() {
+        use mycelial_protocol::{topics, CastVote, CreateProposal, GovernanceMessage, Vote};
+        use mycelial_state::ProposalVerdict;
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let voter = Keypair::generate_ed25519().public().to_peer_id();
+        let info = PeerInfo {
+            id: PeerId(voter.to_base58()),
+            public_key: voter.to_base58(),
+            addresses: vec![],
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            name: None,
+        };
+        let reputation = Reputation { score: 0.8, ..Reputation::default() };
+        state.store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+
+        let now = chrono::Utc::now();
+        let mut proposal = CreateProposal::new(voter.to_base58(), "Raise limits".to_string(), String::new())
+            .with_quorum(0.5)
+            .with_threshold(0.5)
+            .with_deadline(now - chrono::Duration::minutes(1));
+        proposal.timestamp = now - chrono::Duration::hours(1);
+        let vote_by = |name: String| {
+            let mut vote = CastVote::new(proposal.id, name, Vote::For, 1.0);
+            vote.timestamp = now - chrono::Duration::minutes(30);
+            vote
+        };
+        let publish = |message: GovernanceMessage, source: Libp2pPeerId| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: topics::GOVERNANCE.to_string(),
+            source: Some(source),
+            data: serde_json::to_vec(&message).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        // Published by someone else in the voter's name: dropped, though it
+        // would otherwise replace the voter's earlier vote
+        let forger = Keypair::generate_ed25519().public().to_peer_id();
+        let mut forged_vote = vote_by(voter.to_base58());
+        forged_vote.vote = Vote::Against;
+        forged_vote.timestamp = now - chrono::Duration::minutes(10);
+        let forged = publish(GovernanceMessage::CastVote(forged_vote), forger);
+        handle_network_event(forged, &state, local_peer_id).await;
+
+        // The voter's own vote, ahead of the proposal: held until it arrives
+        let genuine = publish(GovernanceMessage::CastVote(vote_by(voter.to_base58())), voter);
+        handle_network_event(genuine, &state, local_peer_id).await;
+        assert!(state.store.due_proposals(now).await.unwrap().is_empty());
+        let announced = publish(GovernanceMessage::CreateProposal(proposal.clone()), voter);
+        handle_network_event(announced, &state, local_peer_id).await;
+
+        // The scheduled tally decides it with the one genuine vote
+        assert_eq!(state.store.due_proposals(now).await.unwrap(), vec![proposal.id]);
+        let mut dashboard = state.event_tx.subscribe();
+        tally_due_proposals(&state).await;
+        let outcome = state.store.tally_proposal(&proposal.id).await.unwrap();
+        assert_eq!(outcome.verdict, ProposalVerdict::Passed);
+        assert_eq!(outcome.voter_count, 1);
+
+        // Dashboards get the outcome with the weights as they were tallied
+        match dashboard.try_recv().unwrap() {
+            WsMessage::ProposalDecided { id, status, votes_for, votes_against, .. } => {
+                assert_eq!(id, proposal.id.to_string());
+                assert_eq!(status, "passed");
+                assert_eq!(votes_for, outcome.votes_for);
+                assert_eq!(votes_against, 0.0);
+            }
+            other => panic!("unexpected dashboard event {:?}", other),
+        }
+        assert!(state.store.due_proposals(now).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gossiped_credit_updates_applied_from_origin() {
+        use mycelial_state::{CreditTallies, CreditTally};
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let [creditor, debtor, forger] = [(); 3].map(|_| Keypair::generate_ed25519().public().to_peer_id());
+        let mut tally = CreditTally::default();
+        tally.record(30.0);
+        let update = StateUpdate::CreditUpdate {
+            creditor: creditor.to_base58(),
+            debtor: debtor.to_base58(),
+            credit_limit: 100.0,
+            balance: 30.0,
+            active: true,
+            timestamp: chrono::Utc::now(),
+            origin: creditor.to_base58(),
+            tallies: Some(CreditTallies {
+                opening: None,
+                nodes: [(creditor.to_base58(), tally)].into(),
+            }),
+        };
+        let publish = |source: Libp2pPeerId| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: mycelial_network::topics::SYNC.to_string(),
+            source: Some(source),
+            data: state.sync.encode_update(&update).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        let balance = || async {
+            state
+                .store
+                .get_credit_relationship_between(&creditor.to_base58(), &debtor.to_base58())
+                .await
+                .unwrap()
+                .map(|line| line.balance)
+        };
+
+        // Relayed in the creditor's name by someone else: ignored
+        handle_network_event(publish(forger), &state, local_peer_id).await;
+        assert_eq!(balance().await, None);
+
+        handle_network_event(publish(creditor), &state, local_peer_id).await;
+        assert_eq!(balance().await, Some(30.0));
+        let tallies = state.store.credit_tallies(&creditor.to_base58(), &debtor.to_base58()).await.unwrap();
+        assert_eq!(tallies.nodes[&creditor.to_base58()], tally);
+    }
+
+    #[tokio::test]
+    async fn test_vouches_bound_to_source() {
+        use mycelial_protocol::{topics, VouchAck, VouchMessage, VouchRequest};
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let [voucher, vouchee, forger] = [(); 3].map(|_| Keypair::generate_ed25519().public().to_peer_id());
+        let publish = |message: VouchMessage, source: Libp2pPeerId| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: topics::VOUCH.to_string(),
+            source: Some(source),
+            data: serde_json::to_vec(&message).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        let ack = |vouch: &VouchRequest| VouchAck {
+            vouch_id: vouch.id,
+            from: vouchee.to_base58(),
+            accepted: true,
+            reason: None,
+            timestamp: chrono::Utc::now(),
+        };
+        let trusted = || async {
+            state.store.trust_path(&voucher.to_base58(), &vouchee.to_base58(), 1).await.unwrap().is_some()
+        };
+
+        // A vouch in the voucher's name published by someone else isn't stored
+        let forged = VouchRequest::new(voucher.to_base58(), vouchee.to_base58(), 0.5);
+        handle_network_event(publish(VouchMessage::VouchRequest(forged.clone()), forger), &state, local_peer_id).await;
+        handle_network_event(publish(VouchMessage::VouchAck(ack(&forged)), vouchee), &state, local_peer_id).await;
+        assert!(!trusted().await);
+
+        // Nor is an acceptance in the vouchee's name
+        let genuine = VouchRequest::new(voucher.to_base58(), vouchee.to_base58(), 0.5);
+        handle_network_event(publish(VouchMessage::VouchRequest(genuine.clone()), voucher), &state, local_peer_id).await;
+        handle_network_event(publish(VouchMessage::VouchAck(ack(&genuine)), forger), &state, local_peer_id).await;
+        assert!(!trusted().await);
+
+        handle_network_event(publish(VouchMessage::VouchAck(ack(&genuine)), vouchee), &state, local_peer_id).await;
+        assert!(trusted().await);
+    }
+
+    #[tokio::test]
+    async fn test_group_messages_checked_before_storing() {
+        use mycelial_core::message::Message;
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let local = PeerId(local_peer_id.to_base58());
+        let [sender, forger] = [(); 2].map(|_| Keypair::generate_ed25519().public().to_peer_id());
+        let group = |recipients: Vec<PeerId>| Message::group(PeerId(sender.to_base58()), recipients, b"hello".to_vec());
+        let receive = |message: &Message, topic: String, source: Libp2pPeerId| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(message.id.as_bytes()),
+            topic,
+            source: Some(source),
+            data: serde_json::to_vec(message).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        let stored = |message: &Message| {
+            let id = message.id;
+            let state = state.clone();
+            async move { state.store.get_message(&id).await.unwrap().is_some() }
+        };
+
+        // Sent to us on the group's own topic, by a sender we never met: stored
+        let genuine = group(vec![local.clone(), PeerId("bob".to_string())]);
+        let topic = groups::topic_for(&genuine).unwrap();
+        handle_network_event(receive(&genuine, topic.clone(), sender), &state, local_peer_id).await;
+        assert!(stored(&genuine).await);
+        assert!(state.store.get_peer(&sender.to_base58()).await.unwrap().is_some());
+
+        // On a shared topic instead of the group's
+        let elsewhere = group(vec![local.clone(), PeerId("bob".to_string())]);
+        handle_network_event(receive(&elsewhere, "/mycelial/1.0.0/chat".to_string(), sender), &state, local_peer_id).await;
+        assert!(!stored(&elsewhere).await);
+
+        // Published by someone other than the named sender
+        let forged = group(vec![local.clone(), PeerId("bob".to_string())]);
+        handle_network_event(receive(&forged, topic, forger), &state, local_peer_id).await;
+        assert!(!stored(&forged).await);
+
+        // Not addressed to us
+        let overheard = group(vec![PeerId("bob".to_string()), PeerId("carol".to_string())]);
+        let overheard_topic = groups::topic_for(&overheard).unwrap();
+        handle_network_event(receive(&overheard, overheard_topic, sender), &state, local_peer_id).await;
+        assert!(!stored(&overheard).await);
+    }
+
+    #[tokio::test]
+    async fn test_group_invites_only_from_known_peers() {
+        use mycelial_core::message::Message;
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let local = PeerId(local_peer_id.to_base58());
+        let inviter = Keypair::generate_ed25519().public().to_peer_id();
+        let invite = || {
+            let message = Message::group(PeerId(inviter.to_base58()), vec![local.clone()], b"hi".to_vec());
+            groups::invite_for(&message).unwrap()
+        };
+        let receive = |invite: &Message| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(invite.id.as_bytes()),
+            topic: DIRECT_TOPIC.to_string(),
+            source: Some(inviter),
+            data: serde_json::to_vec(invite).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        let joined = || state.joined_groups.take_idle(chrono::Utc::now() + chrono::Duration::days(365));
+
+        // Neither connected nor known: turned down
+        handle_network_event(receive(&invite()), &state, local_peer_id).await;
+        assert!(joined().is_empty());
+
+        remember_peer(&state, &PeerId(inviter.to_base58())).await.unwrap();
+        handle_network_event(receive(&invite()), &state, local_peer_id).await;
+        assert_eq!(joined().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_joining_node_bootstraps_from_snapshot() {
+        use mycelial_core::identity::Keypair as IdentityKeypair;
+
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+        let (node_a, network_a, events_a) = NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, network_b, mut events_b) = NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+
+        // A knows a peer B has never heard of
+        let node = |network| async move {
+            let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+            state.network = network;
+            Arc::new(state)
+        };
+        let (state_a, state_b) = (node(network_a.clone()).await, node(network_b).await);
+        let alice_key = IdentityKeypair::generate();
+        let alice = PeerId::from_public_key(&alice_key.public_key());
+        let info = PeerInfo {
+            public_key: alice.to_string(),
+            id: alice.clone(),
+            addresses: vec![],
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            name: Some("Alice".to_string()),
+        };
+        let update = state_a.sync.create_signed_peer_update(&info, &alice_key).unwrap();
+        assert!(state_a.sync.apply_update_from(&update, alice.as_str(), &state_a.store).await.unwrap());
+
+        for (state, mut events) in [(state_a.clone(), events_a), (state_b.clone(), events_b)] {
+            let local = state.network.local_peer_id();
+            tokio::spawn(async move {
+                while let Ok(event) = events.recv().await {
+                    handle_network_event(event, &state, local).await;
+                }
+            });
+        }
+
+        // B asks for a snapshot as soon as A shows up on the sync topic
+        network_a.dial(addr_b).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while state_b.store.get_peer(alice.as_str()).await.unwrap().is_none() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("B never imported A's snapshot");
+        let (imported, _) = state_b.store.get_peer(alice.as_str()).await.unwrap().unwrap();
+        assert_eq!(imported.name, Some("Alice".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_message_count_survives_restart() {
+        let mut first_run = Arc::into_inner(testing::app_state().await).unwrap();
+        first_run.previous_message_count = 10;
+        first_run.message_count.store(5, std::sync::atomic::Ordering::Relaxed);
+        save_message_count(&first_run).await;
+
+        // The next run starts its session count from zero on the same store
+        let mut second_run = Arc::into_inner(testing::app_state().await).unwrap();
+        second_run.store = first_run.store;
+        second_run.previous_message_count = second_run.store.load_message_count().await.unwrap();
+        second_run.message_count.store(2, std::sync::atomic::Ordering::Relaxed);
+        let addr = testing::spawn_server(Arc::new(second_run)).await;
+
+        let (status, stats) = testing::get_json(addr, "/api/stats").await;
+        assert_eq!(status, 200);
+        assert_eq!(stats["message_count"], 2);
+        assert_eq!(stats["lifetime_message_count"], 17);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_messages_not_counted() {
+        use mycelial_core::message::{Message, MessageType};
+        use std::sync::atomic::Ordering;
+
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.replay_guard = ReplayGuard::new(Some(chrono::Duration::seconds(300)));
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let receive = |message: &Message| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: "/test/a".to_string(),
+            source: None,
+            data: serde_json::to_vec(message).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        // A fresh message is accepted
+        let fresh = Message::new(MessageType::Content, PeerId("alice".to_string()), b"hi".to_vec());
+        handle_network_event(receive(&fresh), &state, local_peer_id).await;
+        assert_eq!(state.message_count.load(Ordering::Relaxed), 1);
+
+        // An old one is rejected
+        let mut old = Message::new(MessageType::Content, PeerId("alice".to_string()), b"hi".to_vec());
+        old.timestamp = chrono::Utc::now() - chrono::Duration::hours(1);
+        handle_network_event(receive(&old), &state, local_peer_id).await;
+        assert_eq!(state.message_count.load(Ordering::Relaxed), 1);
+
+        // So is the fresh message published again
+        handle_network_event(receive(&fresh), &state, local_peer_id).await;
+        assert_eq!(state.message_count.load(Ordering::Relaxed), 1);
+        assert_eq!(state.topic_message_counts.read().get("/test/a"), Some(&1));
+
+        // ... even after a restart
+        save_seen_messages(&state).await;
+        let seen = state.store.unexpired_seen_messages(chrono::Utc::now()).await.unwrap();
+        let restarted = ReplayGuard::new(Some(chrono::Duration::seconds(300)));
+        restarted.restore(seen);
+        assert_eq!(restarted.check(&fresh.id.to_string(), Some(fresh.timestamp), chrono::Utc::now()), Err(Replay::Duplicate));
+
+        // A direct message to us may have been queued for hours
+        let local = PeerId(local_peer_id.to_base58());
+        let mut queued = Message::direct(PeerId("alice".to_string()), local, b"hi".to_vec());
+        queued.timestamp = chrono::Utc::now() - chrono::Duration::hours(3);
+        let event = NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: DIRECT_TOPIC.to_string(),
+            source: None,
+            data: serde_json::to_vec(&queued).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        handle_network_event(event, &state, local_peer_id).await;
+        assert_eq!(state.message_count.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queued_dm_kept_until_receipt() {
+        use mycelial_core::message::Message;
+        use mycelial_state::PendingDirectMessage;
+
+        let state = testing::app_state().await;
+        let mut events = state.event_tx.subscribe();
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let recipient = Keypair::generate_ed25519().public().to_peer_id();
+        let local = PeerId(local_peer_id.to_base58());
+        let bob = PeerId(recipient.to_base58());
+
+        let dm = Message::direct(local.clone(), bob.clone(), b"hi".to_vec());
+        let now = chrono::Utc::now();
+        let pending = PendingDirectMessage {
+            id: dm.id.to_string(),
+            recipient: bob.0.clone(),
+            payload: serde_json::to_vec(&dm).unwrap(),
+            queued_at: now,
+            expires_at: now + chrono::Duration::seconds(PENDING_DM_TTL_SECS),
+        };
+        state.store.enqueue_pending_dm(&pending, PENDING_DM_LIMIT).await.unwrap();
+
+        // Sending it again doesn't drop it from the queue
+        deliver_pending_dms(&state, &bob.0).await;
+        assert_eq!(state.store.count_pending_dms(&bob.0).await.unwrap(), 1);
+
+        let receipt = delivery::receipt_for(&dm, &bob).unwrap();
+        let receive = |source: Libp2pPeerId| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: DIRECT_TOPIC.to_string(),
+            source: Some(source),
+            data: serde_json::to_vec(&receipt).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        // A receipt published by anyone but the recipient is ignored
+        let mallory = Keypair::generate_ed25519().public().to_peer_id();
+        handle_network_event(receive(mallory), &state, local_peer_id).await;
+        assert_eq!(state.store.count_pending_dms(&bob.0).await.unwrap(), 1);
+
+        // The recipient's own receipt removes it
+        let mut receipt_again = receipt.clone();
+        receipt_again.id = uuid::Uuid::new_v4();
+        let event = NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: DIRECT_TOPIC.to_string(),
+            source: Some(recipient),
+            data: serde_json::to_vec(&receipt_again).unwrap(),
+            timestamp: chrono::Utc::now(),
+        };
+        handle_network_event(event, &state, local_peer_id).await;
+        assert_eq!(state.store.count_pending_dms(&bob.0).await.unwrap(), 0);
+
+        let mut statuses = Vec::new();
+        while let Ok(message) = events.try_recv() {
+            match message {
+                WsMessage::DirectMessageStatus { id, status, .. } => statuses.push((id, status)),
+                WsMessage::ChatMessage { .. } => panic!("receipt shown as chat"),
+                _ => {}
+            }
+        }
+        assert_eq!(statuses, vec![(dm.id.to_string(), "delivered".to_string())]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Replay protection for received messages
+//!
+//! Gossipsub only remembers message IDs for a minute or so, after which a
+//! captured message can be published again and is processed a second time.
+//! [`ReplayGuard`] rejects messages whose own timestamp lies outside
+//! `--max-message-age` of the local clock, and remembers the IDs it accepted
+//! for as long so that a replay within the window is caught too.
+//!
+//! Payloads that are a [`Message`](mycelial_core::message::Message) are
+//! checked by their `id` and `timestamp`. Anything else (economics messages,
+//! plain chat) has no common envelope, so only its gossipsub message ID,
+//! a hash of the content, is remembered for the maximum age.
+//!
+//! A direct message to this node may have waited in its sender's queue
+//! until we came online, so it is accepted up to
+//! [`PENDING_DM_TTL_SECS`](crate::PENDING_DM_TTL_SECS) old and its ID is
+//! remembered that long. The node writes accepted IDs to the store in
+//! batches, so they survive a restart.
+
+use chrono::{DateTime, Duration, Utc};
+use mycelial_state::SeenMessage;
+use parking_lot::Mutex;
+use std::collections::{HashSet, VecDeque};
+
+/// How long IDs are remembered when the timestamp check is off
+const UNCHECKED_SEEN_WINDOW_SECS: i64 = crate::PENDING_DM_TTL_SECS;
+
+/// Most message IDs remembered at once; the soonest to expire are forgotten first
+const MAX_SEEN_IDS: usize = 100_000;
+
+/// Why a message was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replay {
+    /// Its timestamp is further from the local clock than the maximum age
+    OutsideWindow,
+    /// A message with the same ID was accepted within the window
+    Duplicate,
+}
+
+/// IDs accepted so far
+#[derive(Debug, Default)]
+struct Seen {
+    ids: HashSet<String>,
+    /// Expiry and ID of broadcast messages, in the order they expire
+    broadcast: VecDeque<(DateTime<Utc>, String)>,
+    /// Expiry and ID of direct messages to us, in the order they expire
+    direct: VecDeque<(DateTime<Utc>, String)>,
+    /// Accepted since the last [`ReplayGuard::take_unsaved`]
+    unsaved: Vec<SeenMessage>,
+}
+
+impl Seen {
+    /// Forget expired IDs, and the soonest to expire beyond [`MAX_SEEN_IDS`]
+    fn expire(&mut self, now: DateTime<Utc>) {
+        loop {
+            let full = self.ids.len() >= MAX_SEEN_IDS;
+            let queue = match (self.broadcast.front(), self.direct.front()) {
+                (Some((a, _)), Some((b, _))) if b < a => &mut self.direct,
+                (Some(_), _) => &mut self.broadcast,
+                (None, Some(_)) => &mut self.direct,
+                (None, None) => break,
+            };
+            match queue.front() {
+                Some((expires_at, _)) if full || *expires_at <= now => {
+                    if let Some((_, expired)) = queue.pop_front() {
+                        self.ids.remove(&expired);
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+/// Rejects stale and repeated messages
+#[derive(Debug)]
+pub struct ReplayGuard {
+    /// Largest accepted difference between a message's timestamp and now
+    /// (None accepts any timestamp)
+    max_age: Option<Duration>,
+    /// How long IDs of broadcast messages are remembered
+    window: Duration,
+    /// How long IDs of direct messages to us are remembered
+    direct_window: Duration,
+    seen: Mutex<Seen>,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ReplayGuard {
+    /// Create a guard rejecting messages more than `max_age` from now
+    pub fn new(max_age: Option<Duration>) -> Self {
+        let window = max_age.unwrap_or(Duration::seconds(UNCHECKED_SEEN_WINDOW_SECS));
+        Self {
+            max_age,
+            window,
+            direct_window: window.max(Duration::seconds(crate::PENDING_DM_TTL_SECS)),
+            seen: Mutex::new(Seen::default()),
+        }
+    }
+
+    /// Remember IDs accepted before a restart, the soonest to expire first
+    pub fn restore(&self, accepted: Vec<SeenMessage>) {
+        let mut seen = self.seen.lock();
+        for message in accepted {
+            if seen.ids.insert(message.id.clone()) {
+                let queue = if message.expires_at - message.seen_at > self.window {
+                    &mut seen.direct
+                } else {
+                    &mut seen.broadcast
+                };
+                queue.push_back((message.expires_at, message.id));
+            }
+        }
+    }
+
+    /// IDs accepted since the last call, for the node to store
+    pub fn take_unsaved(&self) -> Vec<SeenMessage> {
+        std::mem::take(&mut self.seen.lock().unsaved)
+    }
+
+    /// Check a received message, remembering its ID if it is accepted
+    ///
+    /// `sent_at` is the timestamp the message carries, if any. Timestamps
+    /// ahead of the local clock by more than the maximum age are rejected as
+    /// well, so a message can't be pre-dated to outlive the window.
+    pub fn check(&self, id: &str, sent_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), Replay> {
+        self.check_aged(id, sent_at, false, now)
+    }
+
+    /// Like [`Self::check`], for a direct message addressed to this node
+    ///
+    /// It is accepted up to [`PENDING_DM_TTL_SECS`](crate::PENDING_DM_TTL_SECS)
+    /// old, as long as a queued message waits for us.
+    pub fn check_direct(&self, id: &str, sent_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), Replay> {
+        self.check_aged(id, sent_at, true, now)
+    }
+
+    fn check_aged(
+        &self,
+        id: &str,
+        sent_at: Option<DateTime<Utc>>,
+        direct: bool,
+        now: DateTime<Utc>,
+    ) -> Result<(), Replay> {
+        let window = if direct { self.direct_window } else { self.window };
+        if let (Some(max_age), Some(sent_at)) = (self.max_age, sent_at) {
+            let age = now - sent_at;
+            if age > window || -age > max_age {
+                return Err(Replay::OutsideWindow);
+            }
+        }
+
+        let mut seen = self.seen.lock();
+        seen.expire(now);
+        if !seen.ids.insert(id.to_string()) {
+            return Err(Replay::Duplicate);
+        }
+        let expires_at = now + window;
+        let queue = if direct { &mut seen.direct } else { &mut seen.broadcast };
+        queue.push_back((expires_at, id.to_string()));
+        seen.unsaved.push(SeenMessage { id: id.to_string(), seen_at: now, expires_at });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_message_accepted() {
+        let guard = ReplayGuard::new(Some(Duration::seconds(300)));
+        let now = Utc::now();
+        assert_eq!(guard.check("a", Some(now - Duration::seconds(10)), now), Ok(()));
+        assert_eq!(guard.check("b", None, now), Ok(()));
+    }
+
+    #[test]
+    fn test_old_message_rejected() {
+        let guard = ReplayGuard::new(Some(Duration::seconds(300)));
+        let now = Utc::now();
+        assert_eq!(guard.check("old", Some(now - Duration::seconds(301)), now), Err(Replay::OutsideWindow));
+        assert_eq!(guard.check("future", Some(now + Duration::seconds(301)), now), Err(Replay::OutsideWindow));
+
+        // Without a maximum age only duplicates are rejected
+        let guard = ReplayGuard::new(None);
+        assert_eq!(guard.check("old", Some(now - Duration::days(30)), now), Ok(()));
+    }
+
+    #[test]
+    fn test_duplicate_id_rejected() {
+        let guard = ReplayGuard::new(Some(Duration::seconds(300)));
+        let now = Utc::now();
+        assert_eq!(guard.check("a", Some(now), now), Ok(()));
+        assert_eq!(guard.check("a", Some(now), now + Duration::seconds(60)), Err(Replay::Duplicate));
+
+        // Once the window has passed the ID is forgotten, but by then its
+        // timestamp is too old to be accepted anyway
+        let later = now + Duration::seconds(301);
+        assert_eq!(guard.check("a", Some(now), later), Err(Replay::OutsideWindow));
+        assert_eq!(guard.check("a", Some(later), later), Ok(()));
+    }
+
+    #[test]
+    fn test_queued_direct_message_accepted_late() {
+        let guard = ReplayGuard::new(Some(Duration::seconds(300)));
+        let now = Utc::now();
+        let queued = now - Duration::hours(3);
+        assert_eq!(guard.check("broadcast", Some(queued), now), Err(Replay::OutsideWindow));
+        assert_eq!(guard.check_direct("dm", Some(queued), now), Ok(()));
+        assert_eq!(guard.check_direct("dm", Some(queued), now + Duration::hours(20)), Err(Replay::Duplicate));
+
+        // Broadcast IDs are only kept for the maximum age, a DM's for a day
+        assert_eq!(guard.check("hash", None, now), Ok(()));
+        assert_eq!(guard.check("hash", None, now + Duration::seconds(299)), Err(Replay::Duplicate));
+        assert_eq!(guard.check("hash", None, now + Duration::seconds(301)), Ok(()));
+        assert_eq!(guard.check_direct("dm", Some(queued), now + Duration::hours(20)), Err(Replay::Duplicate));
+
+        // ... but not past the queue's own limit, nor from the future
+        let expired = now - Duration::seconds(crate::PENDING_DM_TTL_SECS + 1);
+        assert_eq!(guard.check_direct("expired", Some(expired), now), Err(Replay::OutsideWindow));
+        assert_eq!(guard.check_direct("early", Some(now + Duration::seconds(301)), now), Err(Replay::OutsideWindow));
+    }
+
+    #[test]
+    fn test_restored_ids_rejected() {
+        let guard = ReplayGuard::new(Some(Duration::seconds(300)));
+        let now = Utc::now();
+        assert_eq!(guard.check("a", Some(now), now), Ok(()));
+        assert_eq!(guard.check_direct("dm", Some(now), now), Ok(()));
+
+        // What was accepted is handed out once for storing
+        let unsaved = guard.take_unsaved();
+        assert_eq!(unsaved.iter().map(|seen| seen.expires_at - now).collect::<Vec<_>>(), [
+            Duration::seconds(300),
+            Duration::seconds(crate::PENDING_DM_TTL_SECS)
+        ]);
+        assert!(guard.take_unsaved().is_empty());
+
+        let restarted = ReplayGuard::new(Some(Duration::seconds(300)));
+        restarted.restore(unsaved);
+        let later = now + Duration::hours(1);
+        assert_eq!(restarted.check("a", Some(now), now), Err(Replay::Duplicate));
+        assert_eq!(restarted.check("a", Some(later), later), Ok(()));
+        assert_eq!(restarted.check_direct("dm", Some(now), later), Err(Replay::Duplicate));
+        assert_eq!(restarted.check("b", Some(now), now), Ok(()));
+    }
+}
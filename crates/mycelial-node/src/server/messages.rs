@@ -5,6 +5,7 @@
 //! governance, resource).
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use mycelial_core::peer::PeerInfo;
 
 /// Messages sent from server to client
@@ -22,23 +23,58 @@ pub enum WsMessage {
         peer_id: String,
     },
 
+    /// Gossipsub mesh size for a topic changed
+    MeshStatus {
+        topic: String,
+        mesh_peers: usize,
+    },
+
+    /// A subscribed topic lost or regained all of its mesh peers
+    TopicHealth {
+        topic: String,
+        isolated: bool,
+    },
+
+    /// Bytes exchanged with a connected peer since it connected
+    BandwidthUpdate {
+        peer_id: String,
+        bytes_in: u64,
+        bytes_out: u64,
+    },
+
     /// A chat message was received
     ChatMessage {
         id: String,
         from: String,
         from_name: String,
-        to: Option<String>,
+        to: Option<ChatRecipients>,
         room_id: Option<String>,
         content: String,
         timestamp: i64,
     },
 
+    /// Delivery status of a direct message to an offline peer
+    DirectMessageStatus {
+        id: String,
+        to: String,
+        /// One of "queued", "delivered" or "expired"
+        status: String,
+    },
+
     /// A peer's reputation was updated
     ReputationUpdate {
         peer_id: String,
         new_score: f64,
     },
 
+    /// A peer's reputation crossed the configured alert threshold
+    ReputationAlert {
+        peer_id: String,
+        /// Side of the threshold the peer moved to
+        direction: ThresholdSide,
+        score: f64,
+    },
+
     /// Full list of peers
     PeersList {
         peers: Vec<PeerListEntry>,
@@ -56,6 +92,12 @@ pub enum WsMessage {
         message: String,
     },
 
+    /// This client fell behind and missed events; it should re-fetch state.
+    /// Always delivered, regardless of the client's filter
+    Lagged {
+        skipped: u64,
+    },
+
     // ============ Economics Protocol Messages ============
 
     /// Vouch request received
@@ -111,6 +153,18 @@ pub enum WsMessage {
         timestamp: i64,
     },
 
+    /// Proposal decided at its deadline
+    ProposalDecided {
+        id: String,
+        /// `passed`, `failed` or `expired`
+        status: String,
+        /// Reputation-weighted votes in favour
+        votes_for: f64,
+        /// Reputation-weighted votes against
+        votes_against: f64,
+        timestamp: i64,
+    },
+
     /// Vote cast on a proposal
     VoteCast {
         id: String,
@@ -217,6 +271,17 @@ pub struct RoomEntry {
     pub created_at: i64,
 }
 
+/// Recipients of a direct chat message
+///
+/// A single recipient is a bare peer id and a group a list of them, so
+/// clients that only know one-to-one messages keep working.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ChatRecipients {
+    One(String),
+    Group(Vec<String>),
+}
+
 /// Messages sent from client to server
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -224,7 +289,7 @@ pub enum ClientMessage {
     /// Send a chat message
     SendChat {
         content: String,
-        to: Option<String>,
+        to: Option<ChatRecipients>,
         room_id: Option<String>,
     },
 
@@ -239,6 +304,9 @@ pub enum ClientMessage {
         topic: String,
     },
 
+    /// Leave every subscribed topic
+    UnsubscribeAll,
+
     // ============ Economics Protocol Client Messages ============
 
     /// Request to vouch for another peer
@@ -323,7 +391,8 @@ pub enum ClientMessage {
     JoinRoom {
         /// Room ID to join
         room_id: String,
-        /// Optional room name hint (for discovery)
+        /// Optional room name hint (for discovery, not used yet)
+        #[allow(dead_code)]
         room_name: Option<String>,
     },
 
@@ -336,3 +405,45 @@ pub enum ClientMessage {
     /// Get list of available rooms
     GetRooms,
 }
+
+/// Side of the reputation alert threshold a score is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdSide {
+    /// Dropped below the threshold
+    Below,
+    /// At or above the threshold
+    Above,
+}
+
+/// Per-connection filter sent by a client to receive only some message types
+///
+/// Example: `{"subscribe": ["ChatMessage", "PeerJoined"]}`. Names may be given
+/// as variant names or as the snake_case `type` tags.
+#[derive(Debug, Deserialize)]
+pub struct ClientFilter {
+    pub subscribe: Vec<String>,
+}
+
+impl ClientFilter {
+    /// The filter as a set of `type` tags
+    pub fn message_types(&self) -> HashSet<String> {
+        self.subscribe.iter().map(|name| to_snake_case(name)).collect()
+    }
+}
+
+/// Convert a variant name like `ChatMessage` to its `chat_message` tag
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -6,9 +6,10 @@
 pub mod websocket;
 pub mod rest;
 pub mod messages;
+pub mod tls;
 
 use axum::{
-    routing::get,
+    routing::{delete, get, post},
     Router,
 };
 use std::sync::Arc;
@@ -21,14 +22,37 @@ pub fn create_router(state: Arc<AppState>) -> Router {
     Router::new()
         // Health check
         .route("/health", get(rest::health))
+        .route("/ready", get(rest::ready))
         // Node info
         .route("/api/info", get(rest::node_info))
         // WebSocket endpoint
         .route("/ws", get(websocket::ws_handler))
         // REST endpoints
         .route("/api/peers", get(rest::list_peers))
+        .route("/api/peers/search", get(rest::search_peers))
+        .route("/api/peers/dial", post(rest::dial_peer))
+        .route("/api/peers/:peer_id", delete(rest::delete_peer))
+        .route("/api/peers/:peer_id/block", post(rest::block_peer).delete(rest::unblock_peer))
+        .route("/api/vouch", post(rest::submit_vouch))
         .route("/api/peer/:id", get(rest::get_peer))
+        .route("/api/peers/:peer_id/credit", get(rest::peer_credit))
+        .route("/api/peers/:peer_id/credit/summary", get(rest::peer_credit_summary))
+        .route("/api/peers/:peer_id/summary", get(rest::peer_summary))
+        .route("/api/peers/:peer_id/messages", get(rest::peer_messages))
+        .route("/api/peers/:peer_id/reputation/history", get(rest::reputation_history))
         .route("/api/stats", get(rest::get_stats))
+        .route("/api/topics/stats", get(rest::topic_stats))
+        .route("/api/sync/conflicts", get(rest::sync_conflicts))
+        .route("/api/sync/diff/:peer_id", get(rest::sync_diff))
+        .route("/api/network/graph", get(rest::network_graph))
+        .route("/api/listen_addresses", get(rest::listen_addresses))
+        .route("/api/messages", get(rest::list_messages))
+        .route("/api/messages/stats", get(rest::message_stats))
+        .route("/api/messages/export", get(rest::export_messages))
+        .route("/api/maintenance/prune", post(rest::prune))
+        .route("/api/maintenance/credit/deactivate", post(rest::deactivate_stale_credit))
+        .route("/api/credit/graph", get(rest::credit_graph))
+        .route("/api/resources/leaderboard", get(rest::resource_leaderboard))
         // CORS for dashboard
         .layer(
             CorsLayer::new()
@@ -38,3 +62,157 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         )
         .with_state(state)
 }
+
+#[cfg(test)]
+pub(crate) mod testing {
+    //! Helpers for exercising the server over real sockets
+
+    use super::*;
+    use mycelial_core::peer::PeerId;
+    use mycelial_network::{Keypair, NetworkConfig, NetworkService};
+    use mycelial_state::{SqliteStore, StateCache, StateSync};
+    use parking_lot::RwLock;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicBool, AtomicU64};
+    use std::time::Instant;
+    use tokio::sync::broadcast;
+
+    /// App state backed by an in-memory store and an idle network handle
+    pub async fn app_state() -> Arc<AppState> {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        let (_service, network, _events) =
+            NetworkService::new(Keypair::generate_ed25519(), config).unwrap();
+
+        Arc::new(AppState {
+            local_peer_id: PeerId("local".to_string()),
+            network,
+            store: SqliteStore::new(":memory:").await.unwrap(),
+            sync: StateSync::new("local".to_string(), Arc::new(StateCache::new())),
+            event_tx: broadcast::channel(64).0,
+            message_count: AtomicU64::new(0),
+            previous_message_count: 0,
+            topic_message_counts: RwLock::new(Default::default()),
+            start_time: Instant::now(),
+            node_name: "test".to_string(),
+            subscribed_topics: RwLock::new(Vec::new()),
+            listen_addresses: RwLock::new(Vec::new()),
+            network_ready: AtomicBool::new(false),
+            admin_token: None,
+            reputation_gate: Default::default(),
+            reputation_alerts: Default::default(),
+            replay_guard: Default::default(),
+            payload_codec: Default::default(),
+            pending_diffs: Default::default(),
+            snapshot_bootstrap: Default::default(),
+            early_votes: Default::default(),
+            held_group_messages: Default::default(),
+            joined_groups: Default::default(),
+        })
+    }
+
+    /// Like [`app_state`], but with the network service running
+    pub async fn app_state_with_network() -> Arc<AppState> {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        let (service, network, _events) =
+            NetworkService::new(Keypair::generate_ed25519(), config).unwrap();
+        tokio::spawn(service.run());
+
+        let mut state = Arc::into_inner(app_state().await).unwrap();
+        state.network = network;
+        Arc::new(state)
+    }
+
+    /// Serve the router on an ephemeral local port
+    pub async fn spawn_server(state: Arc<AppState>) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = create_router(state);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// Issue a GET request, returning the status code and JSON body
+    pub async fn get_json(addr: SocketAddr, path: &str) -> (u16, serde_json::Value) {
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        send_request(addr, request).await
+    }
+
+    /// Issue a POST request with a JSON body, returning the status code and JSON body
+    pub async fn post_json(addr: SocketAddr, path: &str, body: &str) -> (u16, serde_json::Value) {
+        post_json_with_headers(addr, path, "", body).await
+    }
+
+    /// Like [`post_json`], authenticating with a bearer token
+    pub async fn post_json_with_token(
+        addr: SocketAddr,
+        path: &str,
+        token: &str,
+        body: &str,
+    ) -> (u16, serde_json::Value) {
+        let headers = format!("Authorization: Bearer {}\r\n", token);
+        post_json_with_headers(addr, path, &headers, body).await
+    }
+
+    async fn post_json_with_headers(
+        addr: SocketAddr,
+        path: &str,
+        headers: &str,
+        body: &str,
+    ) -> (u16, serde_json::Value) {
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            headers,
+            body.len(),
+            body
+        );
+        send_request(addr, request).await
+    }
+
+    /// Issue a DELETE request authenticated with a bearer token, returning the status code
+    pub async fn delete_with_token(addr: SocketAddr, path: &str, token: &str) -> u16 {
+        let request = format!(
+            "DELETE {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Authorization: Bearer {}\r\n\r\n",
+            path, token
+        );
+        send_raw(addr, request).await.0
+    }
+
+    /// Issue a GET request, returning the status code and raw body
+    ///
+    /// Sent as HTTP/1.0 so streamed bodies arrive without chunked encoding.
+    pub async fn get_text(addr: SocketAddr, path: &str) -> (u16, String) {
+        let request = format!("GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path);
+        send_raw(addr, request).await
+    }
+
+    async fn send_request(addr: SocketAddr, request: String) -> (u16, serde_json::Value) {
+        let (status, body) = send_raw(addr, request).await;
+        let json = serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+
+    async fn send_raw(addr: SocketAddr, request: String) -> (u16, String) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .unwrap();
+        let body = response.split_once("\r\n\r\n").map(|(_, body)| body).unwrap_or("");
+        (status, body.to_string())
+    }
+}
@@ -1,22 +1,185 @@
 //! REST API endpoints
 
 use axum::{
-    extract::{Path, State},
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use serde::Serialize;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use mycelial_core::message::{Message, MessageType};
+use mycelial_core::peer::PeerInfo;
+use mycelial_network::{
+    AddressScope, AddressTransport, Libp2pPeerId, Multiaddr, NegotiationFailureCounts, NetworkError,
+};
+use mycelial_protocol::{topics, MessageCodec, VouchMessage, VouchRequest};
+use mycelial_state::{CacheStats, ConflictRecord, DigestDiff, GraphFormat, GraphWriter, SqliteStore, StateDigest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use crate::state_exchange::{DigestAnswer, SyncMessage};
 use crate::AppState;
 use super::messages::PeerListEntry;
 
-/// List all peers
+/// Query parameters for listing peers
+#[derive(Deserialize)]
+pub struct PeerListQuery {
+    /// Only list peers carrying this tag
+    pub tag: Option<String>,
+}
+
+/// List all peers, or those carrying a tag
 pub async fn list_peers(
     State(state): State<Arc<AppState>>,
-) -> Json<Vec<PeerListEntry>> {
-    let peers = state.store.list_peers().await.unwrap_or_default();
+    Query(query): Query<PeerListQuery>,
+) -> Response {
+    let peers = match query.tag.as_deref() {
+        Some(tag) => match state.store.list_peers_by_tag(tag).await {
+            Ok(peers) => peers,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+        None => state.store.list_peers().await.unwrap_or_default(),
+    };
     let entries: Vec<PeerListEntry> = peers.into_iter().map(Into::into).collect();
-    Json(entries)
+    Json(entries).into_response()
+}
+
+/// Default number of results returned by `/api/peers/search`
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// Most results returned by one `/api/peers/search` request
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Query parameters for searching peers by name
+#[derive(Deserialize)]
+pub struct PeerSearchQuery {
+    /// Display name prefix, matched case-insensitively
+    pub name: String,
+    pub limit: Option<u32>,
+}
+
+/// Find peers whose display name starts with a prefix
+pub async fn search_peers(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PeerSearchQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT);
+    match state.store.search_peers_by_name(&query.name, limit as i64).await {
+        Ok(peers) => {
+            let entries: Vec<PeerListEntry> = peers.into_iter().map(Into::into).collect();
+            Json(entries).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// How long `/api/peers/dial` waits for a connection
+const DIAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Body of a dial request
+#[derive(Deserialize)]
+pub struct DialRequest {
+    pub multiaddr: String,
+}
+
+/// Outcome of a dial request
+#[derive(Serialize)]
+pub struct DialResponse {
+    /// "connected" or "failed"
+    pub status: &'static str,
+    pub peer_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Dial a peer at runtime and report whether the connection succeeded
+///
+/// Returns 400 for an unparseable multiaddr, 502 if the dial failed and 504
+/// if it didn't complete within 10 seconds.
+pub async fn dial_peer(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DialRequest>,
+) -> Response {
+    let address: Multiaddr = match request.multiaddr.parse() {
+        Ok(address) => address,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, format!("Invalid multiaddr {}: {}", request.multiaddr, e))
+                .into_response()
+        }
+    };
+
+    let failed = |status: StatusCode, error: String| {
+        let body = DialResponse { status: "failed", peer_id: None, error: Some(error) };
+        (status, Json(body)).into_response()
+    };
+    match tokio::time::timeout(DIAL_TIMEOUT, state.network.dial_and_wait(address)).await {
+        Ok(Ok(peer_id)) => Json(DialResponse {
+            status: "connected",
+            peer_id: Some(peer_id.to_base58()),
+            error: None,
+        })
+        .into_response(),
+        Ok(Err(NetworkError::DialFailed { reason, .. })) => failed(StatusCode::BAD_GATEWAY, reason),
+        Ok(Err(e)) => failed(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+        Err(_) => failed(StatusCode::GATEWAY_TIMEOUT, "Dial timed out".to_string()),
+    }
+}
+
+/// Body of a vouch submission
+#[derive(Deserialize)]
+pub struct SubmitVouchRequest {
+    pub vouchee: String,
+    /// Reputation staked on the vouchee; values above 1.0 are capped
+    pub stake: f64,
+    pub message: Option<String>,
+}
+
+/// A published vouch
+#[derive(Serialize)]
+pub struct SubmitVouchResponse {
+    pub id: String,
+}
+
+/// Vouch for a known peer
+///
+/// The request is stored locally and published on the vouch topic; gossipsub
+/// signs it with the node's key.
+pub async fn submit_vouch(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SubmitVouchRequest>,
+) -> Response {
+    if !(request.stake.is_finite() && request.stake > 0.0) {
+        return (StatusCode::BAD_REQUEST, "stake must be positive").into_response();
+    }
+    if request.vouchee == state.local_peer_id.as_str() {
+        return (StatusCode::BAD_REQUEST, "cannot vouch for this node").into_response();
+    }
+    match state.store.get_peer(&request.vouchee).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, format!("Unknown peer {}", request.vouchee)).into_response()
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    let mut vouch = VouchRequest::new(state.local_peer_id.to_string(), request.vouchee, request.stake);
+    if let Some(message) = request.message {
+        vouch = vouch.with_message(message);
+    }
+    let id = vouch.id.to_string();
+    if let Err(e) = state.store.save_vouch(&vouch).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    let data = match state.payload_codec.encode(&VouchMessage::VouchRequest(vouch)) {
+        Ok(data) => data,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    match state.network.publish(topics::VOUCH, data).await {
+        Ok(_) => Json(SubmitVouchResponse { id }).into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    }
 }
 
 /// Get specific peer
@@ -30,32 +193,856 @@ pub async fn get_peer(
     }
 }
 
+/// Query parameters for deleting a peer
+#[derive(Deserialize)]
+pub struct DeletePeerQuery {
+    /// Also close any open connection to the peer
+    #[serde(default)]
+    pub disconnect: bool,
+}
+
+/// Forget a peer, along with its tags, credit relationships and messages
+///
+/// Needs the admin token. Answers 204, or 404 for an unknown peer.
+pub async fn delete_peer(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    Query(query): Query<DeletePeerQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(rejection) = authorize_admin(&state, &headers) {
+        return *rejection;
+    }
+
+    match state.store.delete_peer(&peer_id).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "unknown peer").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    if query.disconnect {
+        // The peer is already forgotten, so a failed disconnect is only logged
+        match peer_id.parse::<Libp2pPeerId>() {
+            Ok(id) => {
+                if let Err(e) = state.network.disconnect(id).await {
+                    tracing::warn!("Failed to disconnect deleted peer {}: {}", peer_id, e);
+                }
+            }
+            Err(_) => tracing::warn!("Deleted peer {} has no libp2p peer id to disconnect", peer_id),
+        }
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Body of a block request
+#[derive(Deserialize)]
+pub struct BlockPeerRequest {
+    /// Why the peer is blocked, for the operator's records
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Block a peer: refuse its connections and drop its messages
+///
+/// Needs the admin token. The peer doesn't have to be known. Answers 204.
+pub async fn block_peer(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<BlockPeerRequest>,
+) -> Response {
+    if let Err(rejection) = authorize_admin(&state, &headers) {
+        return *rejection;
+    }
+
+    if let Err(e) = state.store.block_peer(&peer_id, request.reason.as_deref()).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    // The block is stored and re-applied at startup, so a failure is only logged
+    match peer_id.parse::<Libp2pPeerId>() {
+        Ok(id) => {
+            if let Err(e) = state.network.block_peer(id).await {
+                tracing::warn!("Failed to drop connections of blocked peer {}: {}", peer_id, e);
+            }
+        }
+        Err(_) => tracing::warn!("Blocked peer {} has no libp2p peer id to refuse", peer_id),
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Lift a block. Needs the admin token; answers 204, or 404 if not blocked
+pub async fn unblock_peer(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(rejection) = authorize_admin(&state, &headers) {
+        return *rejection;
+    }
+
+    match state.store.unblock_peer(&peer_id).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "peer not blocked").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    if let Ok(id) = peer_id.parse::<Libp2pPeerId>() {
+        if let Err(e) = state.network.unblock_peer(id).await {
+            tracing::warn!("Failed to unblock {}: {}", peer_id, e);
+        }
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
 /// Network statistics
+///
+/// A single snapshot of everything the dashboard summarizes, so it doesn't
+/// have to stitch several calls together.
 #[derive(Serialize)]
 pub struct NetworkStats {
+    pub node_name: String,
     pub local_peer_id: String,
     pub peer_count: usize,
+    /// Messages received since the node started
     pub message_count: u64,
+    /// Messages received over all runs of the node
+    pub lifetime_message_count: u64,
+    pub deduplicated_messages: u64,
     pub uptime_seconds: u64,
     pub subscribed_topics: Vec<String>,
+    pub subscribed_topic_count: usize,
+    pub active_credit_relationships: usize,
+    /// Cache usage, if the store has a cache attached
+    pub cache: Option<CacheStats>,
+    pub negotiation_failures: NegotiationFailureCounts,
 }
 
 pub async fn get_stats(
     State(state): State<Arc<AppState>>,
 ) -> Json<NetworkStats> {
-    let peers = state.store.list_peers().await.unwrap_or_default();
+    let peer_count = state.store.count_peers().await.unwrap_or_default();
+    let active_credit_relationships = state
+        .store
+        .count_active_credit_relationships()
+        .await
+        .unwrap_or_default();
+    let network_stats = state.network.get_stats().await.unwrap_or_default();
+    let subscribed_topics = state.subscribed_topics.read().clone();
     Json(NetworkStats {
+        node_name: state.node_name.clone(),
         local_peer_id: state.local_peer_id.to_string(),
-        peer_count: peers.len(),
+        peer_count: peer_count as usize,
         message_count: state.message_count.load(std::sync::atomic::Ordering::Relaxed),
+        lifetime_message_count: crate::lifetime_message_count(&state),
+        deduplicated_messages: network_stats.messages_deduplicated,
         uptime_seconds: state.start_time.elapsed().as_secs(),
-        subscribed_topics: state.subscribed_topics.read().clone(),
+        subscribed_topic_count: subscribed_topics.len(),
+        subscribed_topics,
+        active_credit_relationships: active_credit_relationships as usize,
+        cache: state.store.cache().map(|cache| cache.stats()),
+        negotiation_failures: network_stats.negotiation_failures,
     })
 }
 
-/// Health check endpoint
-pub async fn health() -> &'static str {
-    "OK"
+/// Received message counts per topic
+#[derive(Serialize)]
+pub struct TopicStats {
+    pub topics: BTreeMap<String, u64>,
+    pub total: u64,
+}
+
+pub async fn topic_stats(
+    State(state): State<Arc<AppState>>,
+) -> Json<TopicStats> {
+    let topics: BTreeMap<String, u64> = state
+        .topic_message_counts
+        .read()
+        .iter()
+        .map(|(topic, count)| (topic.clone(), *count))
+        .collect();
+    let total = topics.values().sum();
+    Json(TopicStats { topics, total })
+}
+
+/// Recent concurrent writes found during state sync, newest first
+pub async fn sync_conflicts(
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<ConflictRecord>> {
+    Json(state.sync.recent_conflicts())
+}
+
+/// How long `/api/sync/diff/:peer_id` waits for the peer's digest
+const SYNC_DIFF_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Records that differ between this node and a peer
+#[derive(Serialize)]
+pub struct SyncDiffResponse {
+    pub peer_id: String,
+    #[serde(flatten)]
+    pub diff: DigestDiff,
+    pub total: usize,
+}
+
+/// Compare this node's state with a connected peer's, applying nothing
+///
+/// Asks the peer for its digest summary over the sync topic, then for the
+/// leaves of the buckets that differ, and reports how many peer, credit and
+/// key-value records differ. Returns 400 for an
+/// invalid peer ID, 404 if the peer isn't connected, 409 if a diff with it
+/// is already running and 504 if it doesn't answer within 10 seconds.
+pub async fn sync_diff(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+) -> Response {
+    let peer: Libp2pPeerId = match peer_id.parse() {
+        Ok(peer) => peer,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid peer ID {}: {}", peer_id, e)).into_response(),
+    };
+    match state.network.get_peers().await {
+        Ok(peers) if peers.contains(&peer) => {}
+        Ok(_) => return (StatusCode::NOT_FOUND, format!("Peer {} is not connected", peer_id)).into_response(),
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    }
+
+    let id = uuid::Uuid::new_v4();
+    let Some(mut pending) = state.pending_diffs.start(&peer_id, id) else {
+        return (StatusCode::CONFLICT, format!("A diff with {} is already running", peer_id)).into_response();
+    };
+
+    let local = match state.store.state_digest().await {
+        Ok(local) => local,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let local_peer_id = state.network.local_peer_id().to_base58();
+    let request = SyncMessage::DigestRequest { id, from: local_peer_id.clone(), to: peer_id.clone() };
+    if let Err(e) = crate::send_sync_message(&state, &request).await {
+        return (StatusCode::BAD_GATEWAY, e).into_response();
+    }
+
+    // Only the buckets whose hashes differ need their leaves compared
+    let exchange = async {
+        let summary = match pending.answer().await {
+            Some(DigestAnswer::Summary(summary)) => summary,
+            _ => return None,
+        };
+        let buckets = local.differing_buckets(&summary);
+        if buckets.is_empty() {
+            return Some(Ok(DigestDiff::default()));
+        }
+        let request = SyncMessage::LeavesRequest { id, from: local_peer_id, to: peer_id.clone(), buckets: buckets.clone() };
+        if let Err(e) = crate::send_sync_message(&state, &request).await {
+            return Some(Err(e));
+        }
+        let mut missing = buckets.clone();
+        let mut remote = StateDigest::default();
+        while !missing.is_empty() {
+            if let DigestAnswer::Leaves(page) = pending.answer().await? {
+                missing.remove(&page.buckets);
+                remote.extend(page.leaves);
+            }
+        }
+        Some(Ok(local.diff_buckets(&buckets, &remote)))
+    };
+    match tokio::time::timeout(SYNC_DIFF_TIMEOUT, exchange).await {
+        Ok(Some(Ok(diff))) => Json(SyncDiffResponse { peer_id, diff, total: diff.total() }).into_response(),
+        Ok(Some(Err(e))) => (StatusCode::BAD_GATEWAY, e).into_response(),
+        Ok(None) | Err(_) => {
+            (StatusCode::GATEWAY_TIMEOUT, format!("{} did not send its digest", peer_id)).into_response()
+        }
+    }
+}
+
+/// A credit relationship as returned by the REST API
+#[derive(Serialize)]
+pub struct CreditRelationshipEntry {
+    pub creditor: String,
+    pub debtor: String,
+    pub limit: f64,
+    pub balance: f64,
+    pub active: bool,
+}
+
+/// Query parameters for a peer's credit relationships
+#[derive(Deserialize)]
+pub struct PeerCreditQuery {
+    #[serde(default)]
+    pub active_only: bool,
+}
+
+/// List credit relationships where the peer is creditor or debtor
+pub async fn peer_credit(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    Query(query): Query<PeerCreditQuery>,
+) -> Response {
+    match state.store.get_peer(&peer_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, format!("Unknown peer: {}", peer_id)).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    match state.store.list_credit_relationships_for(&peer_id).await {
+        Ok(relationships) => {
+            let entries: Vec<CreditRelationshipEntry> = relationships
+                .into_iter()
+                .filter(|rel| rel.active || !query.active_only)
+                .map(|rel| CreditRelationshipEntry {
+                    creditor: rel.creditor.to_string(),
+                    debtor: rel.debtor.to_string(),
+                    limit: rel.credit_limit,
+                    balance: rel.balance,
+                    active: rel.active,
+                })
+                .collect();
+            Json(entries).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Total balances a peer is owed and owes across its credit relationships
+///
+/// `net` is positive when the peer is owed more than it owes; see
+/// [`CreditSummary`](mycelial_state::CreditSummary).
+pub async fn peer_credit_summary(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+) -> Response {
+    match state.store.get_peer(&peer_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, format!("Unknown peer: {}", peer_id)).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    match state.store.credit_summary(&peer_id).await {
+        Ok(summary) => Json(summary).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// How far back `/api/peers/:peer_id/summary` counts a peer's messages
+const RECENT_MESSAGE_WINDOW_HOURS: i64 = 24;
+
+/// A peer's reputation score and the interactions behind it
+#[derive(Serialize)]
+pub struct ReputationSummary {
+    pub score: f64,
+    pub successful_interactions: u64,
+    pub failed_interactions: u64,
+}
+
+/// Everything the dashboard shows on a peer's profile
+#[derive(Serialize)]
+pub struct PeerSummary {
+    pub peer: PeerInfo,
+    pub reputation: ReputationSummary,
+    /// Active credit relationships where the peer is creditor or debtor
+    pub credit: Vec<CreditRelationshipEntry>,
+    /// Messages received from the peer in the last 24 hours
+    pub recent_message_count: i64,
+}
+
+/// A peer's profile, reputation, active credit and recent activity in one call
+pub async fn peer_summary(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+) -> Response {
+    let (peer, reputation) = match state.store.get_peer(&peer_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return (StatusCode::NOT_FOUND, format!("Unknown peer: {}", peer_id)).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let relationships = match state.store.list_credit_relationships_for(&peer_id).await {
+        Ok(relationships) => relationships,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let since = chrono::Utc::now() - chrono::Duration::hours(RECENT_MESSAGE_WINDOW_HOURS);
+    let recent_message_count = match state.store.count_messages_from(&peer_id, since).await {
+        Ok(count) => count,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    Json(PeerSummary {
+        peer,
+        reputation: ReputationSummary {
+            score: reputation.score,
+            successful_interactions: reputation.successful_interactions,
+            failed_interactions: reputation.failed_interactions,
+        },
+        credit: relationships
+            .into_iter()
+            .filter(|rel| rel.active)
+            .map(|rel| CreditRelationshipEntry {
+                creditor: rel.creditor.to_string(),
+                debtor: rel.debtor.to_string(),
+                limit: rel.credit_limit,
+                balance: rel.balance,
+                active: rel.active,
+            })
+            .collect(),
+        recent_message_count,
+    })
+    .into_response()
+}
+
+/// One point of a peer's reputation over time
+#[derive(Serialize)]
+pub struct ReputationPoint {
+    /// Unix timestamp (seconds) of the snapshot
+    pub timestamp: i64,
+    pub score: f64,
+}
+
+/// Query parameters for a peer's reputation history
+#[derive(Deserialize)]
+pub struct ReputationHistoryQuery {
+    /// Only return snapshots taken at or after this unix timestamp (seconds)
+    pub since: Option<i64>,
+}
+
+/// Reputation snapshots for a peer, oldest first
+pub async fn reputation_history(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    Query(query): Query<ReputationHistoryQuery>,
+) -> Response {
+    let reputation = match state.store.get_peer(&peer_id).await {
+        Ok(Some((_, reputation))) => reputation,
+        Ok(None) => return (StatusCode::NOT_FOUND, format!("Unknown peer: {}", peer_id)).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let mut points: Vec<ReputationPoint> = reputation
+        .history
+        .iter()
+        .map(|snapshot| ReputationPoint {
+            timestamp: snapshot.timestamp.timestamp(),
+            score: snapshot.score,
+        })
+        .filter(|point| query.since.is_none_or(|since| point.timestamp >= since))
+        .collect();
+    points.sort_by_key(|point| point.timestamp);
+
+    Json(points).into_response()
+}
+
+/// Default number of messages returned by `/api/messages`
+const DEFAULT_MESSAGE_LIMIT: u32 = 50;
+
+/// Most messages returned by one `/api/messages` request
+const MAX_MESSAGE_LIMIT: u32 = 500;
+
+/// Query parameters for listing stored messages
+#[derive(Deserialize)]
+pub struct MessageQuery {
+    /// Message type name, e.g. `Content`
+    #[serde(rename = "type")]
+    pub message_type: Option<String>,
+    /// Sender peer ID
+    pub sender: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// A stored message, with binary fields base64-encoded
+#[derive(Serialize)]
+pub struct MessageEntry {
+    pub id: String,
+    pub message_type: String,
+    pub sender: String,
+    pub recipient: Option<String>,
+    /// Recipients of a group message
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    pub payload: String,
+    pub signature: Option<String>,
+    pub timestamp: i64,
+}
+
+impl From<Message> for MessageEntry {
+    fn from(message: Message) -> Self {
+        Self {
+            id: message.id.to_string(),
+            message_type: format!("{:?}", message.message_type),
+            sender: message.sender.0,
+            recipient: message.recipient.map(|peer| peer.0),
+            recipients: message.recipients.into_iter().map(|peer| peer.0).collect(),
+            payload: BASE64.encode(&message.payload),
+            signature: message.signature.map(|sig| BASE64.encode(sig)),
+            timestamp: message.timestamp.timestamp_millis(),
+        }
+    }
+}
+
+/// Recent messages, optionally filtered by type and/or sender
+pub async fn list_messages(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MessageQuery>,
+) -> Response {
+    let message_type = match query.message_type.as_deref().map(str::parse::<MessageType>) {
+        None => None,
+        Some(Ok(message_type)) => Some(message_type),
+        Some(Err(e)) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let limit = i64::from(query.limit.unwrap_or(DEFAULT_MESSAGE_LIMIT).min(MAX_MESSAGE_LIMIT));
+
+    let messages = match (&message_type, query.sender.as_deref()) {
+        (Some(message_type), Some(sender)) => {
+            state.store.list_messages_from_by_type(sender, message_type, limit).await
+        }
+        (Some(message_type), None) => state.store.list_messages_by_type(message_type, limit).await,
+        (None, Some(sender)) => state.store.list_messages_from(sender, limit).await,
+        (None, None) => state.store.list_recent_messages(limit).await,
+    };
+
+    match messages {
+        Ok(messages) => {
+            let entries: Vec<MessageEntry> = messages.into_iter().map(Into::into).collect();
+            Json(entries).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Whether a message was received by or sent from the local node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageDirection {
+    Inbound,
+    Outbound,
+}
+
+/// A message in a peer's conversation history
+#[derive(Serialize)]
+pub struct ConversationEntry {
+    #[serde(flatten)]
+    pub message: MessageEntry,
+    pub direction: MessageDirection,
+}
+
+/// Query parameters for a peer's message history
+#[derive(Deserialize)]
+pub struct ConversationQuery {
+    pub limit: Option<u32>,
+}
+
+/// Messages a peer sent or received, oldest first
+///
+/// Messages sent by this node are `outbound`, everything else `inbound`.
+pub async fn peer_messages(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    Query(query): Query<ConversationQuery>,
+) -> Response {
+    let limit = i64::from(query.limit.unwrap_or(DEFAULT_MESSAGE_LIMIT).min(MAX_MESSAGE_LIMIT));
+    match state.store.list_conversation(&peer_id, limit).await {
+        Ok(messages) => {
+            let entries: Vec<ConversationEntry> = messages
+                .into_iter()
+                .map(|message| {
+                    let direction = if message.sender == state.local_peer_id {
+                        MessageDirection::Outbound
+                    } else {
+                        MessageDirection::Inbound
+                    };
+                    ConversationEntry {
+                        message: message.into(),
+                        direction,
+                    }
+                })
+                .collect();
+            Json(entries).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Messages fetched from the store per chunk of an export
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+/// Every stored message as newline-delimited JSON, oldest first
+///
+/// Messages are read and written a batch at a time, so exporting a large
+/// table never holds more than one batch in memory.
+pub async fn export_messages(State(state): State<Arc<AppState>>) -> Response {
+    let chunks = futures::stream::unfold(Some(0), move |cursor| {
+        let state = state.clone();
+        async move {
+            let cursor = cursor?;
+            match export_batch(&state.store, cursor).await {
+                Ok((chunk, next_cursor)) => Some((Ok(chunk), next_cursor)),
+                Err(e) => Some((Err(e), None)),
+            }
+        }
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+/// Encode one batch of messages as NDJSON lines, with the cursor for the next
+async fn export_batch(store: &SqliteStore, cursor: i64) -> mycelial_state::Result<(Vec<u8>, Option<i64>)> {
+    let page = store.list_messages_page(cursor, EXPORT_BATCH_SIZE).await?;
+    let mut chunk = Vec::new();
+    for message in page.messages {
+        serde_json::to_writer(&mut chunk, &MessageEntry::from(message))?;
+        chunk.push(b'\n');
+    }
+    Ok((chunk, page.next_cursor))
+}
+
+/// Stored message counts by type and payload size
+pub async fn message_stats(State(state): State<Arc<AppState>>) -> Response {
+    match state.store.message_stats().await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Body of a prune request
+#[derive(Deserialize)]
+pub struct PruneRequest {
+    /// Delete records older than this many seconds
+    pub older_than_secs: i64,
+}
+
+/// Rows removed by a prune
+#[derive(Serialize)]
+pub struct PruneResponse {
+    pub messages: u64,
+    pub credit_transactions: u64,
+}
+
+/// Delete old messages and credit transactions
+///
+/// Requires `Authorization: Bearer <token>` matching the node's admin token.
+pub async fn prune(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<PruneRequest>,
+) -> Response {
+    if let Err(rejection) = authorize_admin(&state, &headers) {
+        return *rejection;
+    }
+    if request.older_than_secs < 0 {
+        return (StatusCode::BAD_REQUEST, "older_than_secs must not be negative").into_response();
+    }
+
+    let messages = match state.store.prune_messages(request.older_than_secs).await {
+        Ok(deleted) => deleted,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let credit_transactions = match state.store.prune_credit_transactions(request.older_than_secs).await {
+        Ok(deleted) => deleted,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    Json(PruneResponse { messages, credit_transactions }).into_response()
+}
+
+/// Credit relationships deactivated for inactivity
+#[derive(Serialize)]
+pub struct DeactivateCreditResponse {
+    pub deactivated: u64,
+}
+
+/// Deactivate credit relationships without a transaction for `older_than_secs`
+///
+/// Requires `Authorization: Bearer <token>` matching the node's admin token.
+pub async fn deactivate_stale_credit(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<PruneRequest>,
+) -> Response {
+    if let Err(rejection) = authorize_admin(&state, &headers) {
+        return *rejection;
+    }
+    if request.older_than_secs < 0 {
+        return (StatusCode::BAD_REQUEST, "older_than_secs must not be negative").into_response();
+    }
+
+    match state.store.deactivate_stale_credit(request.older_than_secs).await {
+        Ok(deactivated) => Json(DeactivateCreditResponse { deactivated }).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Check a request carries the admin bearer token
+///
+/// Maintenance endpoints answer 403 when no token is configured, and 401
+/// for a missing or wrong token. The rejection is boxed to keep the
+/// `Result` small.
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), Box<Response>> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(Box::new((StatusCode::FORBIDDEN, "maintenance endpoints are disabled").into_response()));
+    };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(Box::new(
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                "missing or invalid admin token",
+            )
+                .into_response(),
+        )),
+    }
+}
+
+/// Compare secrets without bailing out at the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Default number of entries on the resource leaderboard
+const DEFAULT_LEADERBOARD_LIMIT: u32 = 10;
+
+/// Query parameters for the resource leaderboard
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub resource_type: String,
+    pub limit: Option<u32>,
+}
+
+/// A peer's accumulated contribution of a resource
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub peer_id: String,
+    pub total: f64,
+}
+
+/// Top contributors of a resource type
+pub async fn resource_leaderboard(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT);
+    match state.store.top_contributors(&query.resource_type, limit).await {
+        Ok(top) => {
+            let entries: Vec<LeaderboardEntry> = top
+                .into_iter()
+                .map(|(peer_id, total)| LeaderboardEntry { peer_id, total })
+                .collect();
+            Json(entries).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Credit relationships fetched from the store per chunk of the graph export
+const GRAPH_EXPORT_BATCH_SIZE: i64 = 500;
+
+/// Export active credit relationships as GraphML or DOT (via `Accept`)
+///
+/// Relationships are read and written a batch at a time, like the message
+/// export; besides one batch only the IDs of peers already written are kept.
+pub async fn credit_graph(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    let format = GraphFormat::from_accept(accept);
+
+    let chunks = futures::stream::unfold(Some((GraphWriter::new(format), 0)), move |next| {
+        let state = state.clone();
+        async move {
+            let (mut writer, cursor) = next?;
+            let page = match state.store.credit_graph_page(cursor, GRAPH_EXPORT_BATCH_SIZE).await {
+                Ok(page) => page,
+                Err(e) => return Some((Err(e), None)),
+            };
+            let mut chunk = if cursor == 0 { writer.header().to_string() } else { String::new() };
+            chunk.push_str(&writer.page(&page.nodes, &page.edges));
+            match page.next_cursor {
+                Some(next_cursor) => Some((Ok(chunk), Some((writer, next_cursor)))),
+                None => {
+                    chunk.push_str(writer.footer());
+                    Some((Ok(chunk), None))
+                }
+            }
+        }
+    });
+
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+/// A listen address with the multiaddr peers should dial
+#[derive(Serialize)]
+pub struct ListenAddressEntry {
+    pub address: String,
+    pub transport: AddressTransport,
+    pub scope: AddressScope,
+    /// Address including `/p2p/<peer id>`, for `--connect`
+    pub connect: String,
+}
+
+/// Peers this node believes are connected to each other, for the mesh view
+pub async fn network_graph(
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    match state.network.network_graph().await {
+        Ok(graph) => Json(graph).into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    }
+}
+
+/// Addresses the P2P node is listening on, as bound so far
+pub async fn listen_addresses(
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<ListenAddressEntry>> {
+    let entries = state
+        .listen_addresses
+        .read()
+        .iter()
+        .map(|listen| ListenAddressEntry {
+            address: listen.address.to_string(),
+            transport: listen.transport,
+            scope: listen.scope,
+            connect: format!("{}/p2p/{}", listen.address, state.local_peer_id),
+        })
+        .collect();
+    Json(entries)
+}
+
+/// Liveness or readiness of the node
+#[derive(Serialize)]
+pub struct HealthStatus {
+    pub status: &'static str,
+}
+
+/// Liveness probe: answers whenever the HTTP server is serving
+pub async fn health() -> Json<HealthStatus> {
+    Json(HealthStatus { status: "ok" })
+}
+
+/// Readiness probe: 200 once the network service has started and the
+/// database answers, 503 otherwise
+pub async fn ready(State(state): State<Arc<AppState>>) -> Response {
+    if !state.network_ready.load(std::sync::atomic::Ordering::Relaxed) {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(HealthStatus { status: "starting" })).into_response();
+    }
+    if let Err(e) = state.store.ping().await {
+        tracing::warn!("Readiness check failed: {}", e);
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(HealthStatus { status: "database_unavailable" }))
+            .into_response();
+    }
+    Json(HealthStatus { status: "ready" }).into_response()
 }
 
 /// Node info endpoint
@@ -75,3 +1062,886 @@ pub async fn node_info(
         peer_id: state.local_peer_id.to_string(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::server::testing;
+    use chrono::{TimeZone, Utc};
+    use mycelial_core::credit::CreditRelationship;
+    use mycelial_core::message::{Message, MessageType};
+    use mycelial_core::peer::{PeerId, PeerInfo};
+    use mycelial_core::reputation::{Reputation, ReputationSnapshot};
+    use mycelial_network::ListenAddress;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_stats_snapshot() {
+        let state = testing::app_state().await;
+        state.subscribed_topics.write().push("/mycelial/1.0.0/chat".to_string());
+        state.message_count.store(7, std::sync::atomic::Ordering::Relaxed);
+        let addr = testing::spawn_server(state).await;
+
+        let (status, stats) = testing::get_json(addr, "/api/stats").await;
+        assert_eq!(status, 200);
+        assert_eq!(stats["node_name"], "test");
+        assert_eq!(stats["local_peer_id"], "local");
+        assert_eq!(stats["peer_count"], 0);
+        assert_eq!(stats["message_count"], 7);
+        assert_eq!(stats["lifetime_message_count"], 7);
+        assert!(stats["uptime_seconds"].is_u64());
+        assert_eq!(stats["subscribed_topic_count"], 1);
+        assert_eq!(stats["active_credit_relationships"], 0);
+        assert!(stats["cache"].is_null());
+        assert!(stats["negotiation_failures"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_list_messages_filters() {
+        let state = testing::app_state().await;
+        for peer in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(peer.to_string()),
+                public_key: peer.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        for (sender, message_type, payload) in [
+            ("alice", MessageType::Content, vec![0xff, 0x00]),
+            ("alice", MessageType::Credit, b"credit".to_vec()),
+            ("bob", MessageType::Content, b"post".to_vec()),
+        ] {
+            let message = Message::new(message_type, PeerId(sender.to_string()), payload);
+            state.store.store_message(&message).await.unwrap();
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let fetch = |path: &'static str| async move {
+            let (status, body) = testing::get_json(addr, path).await;
+            assert_eq!(status, 200, "{}", path);
+            body.as_array().unwrap().clone()
+        };
+
+        assert_eq!(fetch("/api/messages").await.len(), 3);
+        assert_eq!(fetch("/api/messages?limit=2").await.len(), 2);
+
+        let content = fetch("/api/messages?type=Content").await;
+        assert_eq!(content.len(), 2);
+        assert!(content.iter().all(|m| m["message_type"] == "Content"));
+
+        let from_alice = fetch("/api/messages?sender=alice").await;
+        assert_eq!(from_alice.len(), 2);
+        assert!(from_alice.iter().all(|m| m["sender"] == "alice"));
+
+        // Both filters together, with the binary payload base64-encoded
+        let both = fetch("/api/messages?type=Content&sender=alice").await;
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0]["payload"], "/wA=");
+
+        let (status, _) = testing::get_json(addr, "/api/messages?type=Spam").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_export_messages() {
+        let state = testing::app_state().await;
+        let info = PeerInfo {
+            id: PeerId("alice".to_string()),
+            public_key: "alice".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        state.store.upsert_peer(&info, None).await.unwrap();
+        for i in 0..5u8 {
+            let message = Message::new(MessageType::Content, PeerId("alice".to_string()), vec![i]);
+            state.store.store_message(&message).await.unwrap();
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_text(addr, "/api/messages/export").await;
+        assert_eq!(status, 200);
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["payload"], "AA==");
+        assert!(lines.iter().all(|m| m["sender"] == "alice"));
+    }
+
+    #[tokio::test]
+    async fn test_credit_graph_export() {
+        let state = testing::app_state().await;
+        for id in ["alice", "bob", "carol"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(id.to_uppercase()),
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        for (creditor, debtor) in [("alice", "bob"), ("bob", "carol")] {
+            let rel = CreditRelationship::new(PeerId(creditor.to_string()), PeerId(debtor.to_string()), 100.0);
+            state.store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_text(addr, "/api/credit/graph").await;
+        assert_eq!(status, 200);
+        assert!(body.starts_with("<?xml"));
+        assert_eq!(body.matches("<node ").count(), 3);
+        assert_eq!(body.matches("<edge ").count(), 2);
+        assert!(body.contains("<node id=\"bob\"><data key=\"label\">BOB</data></node>"));
+        assert!(body.ends_with("</graphml>\n"));
+    }
+
+    #[tokio::test]
+    async fn test_peer_summary() {
+        let state = testing::app_state().await;
+        for id in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(id.to_string()),
+            };
+            let reputation = Reputation {
+                score: 0.8,
+                successful_interactions: 12,
+                failed_interactions: 3,
+                ..Default::default()
+            };
+            state.store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+        }
+        let mut credit = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        credit.balance = 25.0;
+        state.store.upsert_credit_relationship(&credit).await.unwrap();
+        let mut closed = CreditRelationship::new(PeerId("bob".to_string()), PeerId("alice".to_string()), 10.0);
+        closed.active = false;
+        state.store.upsert_credit_relationship(&closed).await.unwrap();
+
+        let recent = Message::new(MessageType::Content, PeerId("alice".to_string()), b"hi".to_vec());
+        state.store.store_message(&recent).await.unwrap();
+        let mut old = Message::new(MessageType::Content, PeerId("alice".to_string()), b"old".to_vec());
+        old.timestamp = Utc::now() - chrono::Duration::days(2);
+        state.store.store_message(&old).await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let (status, summary) = testing::get_json(addr, "/api/peers/alice/summary").await;
+        assert_eq!(status, 200);
+        assert_eq!(summary["peer"]["id"], "alice");
+        assert_eq!(summary["peer"]["name"], "alice");
+        assert_eq!(summary["reputation"]["score"], 0.8);
+        assert_eq!(summary["reputation"]["successful_interactions"], 12);
+        assert_eq!(summary["reputation"]["failed_interactions"], 3);
+        let credit = summary["credit"].as_array().unwrap();
+        assert_eq!(credit.len(), 1);
+        assert_eq!(credit[0]["debtor"], "bob");
+        assert_eq!(credit[0]["balance"], 25.0);
+        assert_eq!(summary["recent_message_count"], 1);
+
+        let (status, _) = testing::get_json(addr, "/api/peers/nobody/summary").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_peer_messages_with_direction() {
+        let state = testing::app_state().await;
+        for id in ["local", "alice"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        let (local, alice) = (PeerId("local".to_string()), PeerId("alice".to_string()));
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let messages = [
+            Message::direct(local.clone(), alice.clone(), b"hello".to_vec()),
+            Message::direct(alice.clone(), local.clone(), b"hi".to_vec()),
+            Message::direct(local.clone(), alice.clone(), b"bye".to_vec()),
+        ];
+        for (i, mut message) in messages.into_iter().enumerate() {
+            message.timestamp = start + chrono::Duration::minutes(i as i64);
+            state.store.store_message(&message).await.unwrap();
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_json(addr, "/api/peers/alice/messages").await;
+        assert_eq!(status, 200);
+        let entries = body.as_array().unwrap();
+        // Payloads are base64: "hello", "hi", "bye"
+        let summary: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e["payload"].as_str().unwrap(), e["direction"].as_str().unwrap()))
+            .collect();
+        assert_eq!(summary, [("aGVsbG8=", "outbound"), ("aGk=", "inbound"), ("Ynll", "outbound")]);
+        assert_eq!(entries[1]["sender"], "alice");
+
+        let (_, body) = testing::get_json(addr, "/api/peers/alice/messages?limit=1").await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["direction"], "outbound");
+    }
+
+    #[tokio::test]
+    async fn test_chat_makes_conversation_history() {
+        use crate::server::messages::{ChatRecipients, ClientMessage};
+        use mycelial_network::{Keypair, NetworkEvent};
+
+        let state = testing::app_state().await;
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let bob_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let bob = PeerId(bob_peer_id.to_base58());
+
+        // Bob is offline, so this one is queued, and still kept
+        let send = ClientMessage::SendChat {
+            content: "hello".to_string(),
+            to: Some(ChatRecipients::One(bob.0.clone())),
+            room_id: None,
+        };
+        crate::server::websocket::handle_client_message(send, &state).await;
+
+        let mut reply = Message::direct(bob.clone(), PeerId(local_peer_id.to_base58()), b"hi".to_vec());
+        reply.timestamp = Utc::now() + chrono::Duration::seconds(1);
+        let receive = |reply: &Message, source| NetworkEvent::MessageReceived {
+            message_id: mycelial_network::MessageId::new(uuid::Uuid::new_v4().as_bytes()),
+            topic: crate::DIRECT_TOPIC.to_string(),
+            source: Some(source),
+            data: serde_json::to_vec(reply).unwrap(),
+            timestamp: Utc::now(),
+        };
+        // Only the sender's own copy counts
+        let mallory = Keypair::generate_ed25519().public().to_peer_id();
+        let mut forged = reply.clone();
+        forged.id = uuid::Uuid::new_v4();
+        crate::handle_network_event(receive(&forged, mallory), &state, local_peer_id).await;
+        crate::handle_network_event(receive(&reply, bob_peer_id), &state, local_peer_id).await;
+
+        let addr = testing::spawn_server(state).await;
+        let (status, body) = testing::get_json(addr, &format!("/api/peers/{}/messages", bob)).await;
+        assert_eq!(status, 200);
+        let summary: Vec<(&str, &str)> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["payload"].as_str().unwrap(), e["direction"].as_str().unwrap()))
+            .collect();
+        // Payloads are base64: "hello", "hi"
+        assert_eq!(summary, [("aGVsbG8=", "outbound"), ("aGk=", "inbound")]);
+    }
+
+    #[tokio::test]
+    async fn test_peer_credit() {
+        let state = testing::app_state().await;
+        for id in ["alice", "bob", "carol", "dave"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        // alice extends credit to bob, and owes carol on a closed line
+        let extended = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        state.store.upsert_credit_relationship(&extended).await.unwrap();
+        let mut closed = CreditRelationship::new(PeerId("carol".to_string()), PeerId("alice".to_string()), 50.0);
+        closed.active = false;
+        state.store.upsert_credit_relationship(&closed).await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let pairs = |body: serde_json::Value| -> Vec<(String, String, bool)> {
+            let mut pairs: Vec<_> = body
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| {
+                    let id = |field: &str| e[field].as_str().unwrap().to_string();
+                    (id("creditor"), id("debtor"), e["active"].as_bool().unwrap())
+                })
+                .collect();
+            pairs.sort();
+            pairs
+        };
+        let (status, body) = testing::get_json(addr, "/api/peers/alice/credit").await;
+        assert_eq!(status, 200);
+        assert_eq!(pairs(body), [
+            ("alice".to_string(), "bob".to_string(), true),
+            ("carol".to_string(), "alice".to_string(), false),
+        ]);
+
+        let (status, body) = testing::get_json(addr, "/api/peers/alice/credit?active_only=true").await;
+        assert_eq!(status, 200);
+        assert_eq!(pairs(body), [("alice".to_string(), "bob".to_string(), true)]);
+
+        // A known peer without credit lines gets an empty list
+        let (status, body) = testing::get_json(addr, "/api/peers/dave/credit").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, serde_json::json!([]));
+
+        let (status, _) = testing::get_json(addr, "/api/peers/nobody/credit").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_peer_credit_summary() {
+        let state = testing::app_state().await;
+        for id in ["alice", "bob", "carol"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        // bob owes alice 40, alice owes carol 15
+        let mut extended = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        extended.balance = 40.0;
+        state.store.upsert_credit_relationship(&extended).await.unwrap();
+        let mut owed = CreditRelationship::new(PeerId("carol".to_string()), PeerId("alice".to_string()), 50.0);
+        owed.balance = 15.0;
+        state.store.upsert_credit_relationship(&owed).await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let (status, summary) = testing::get_json(addr, "/api/peers/alice/credit/summary").await;
+        assert_eq!(status, 200);
+        assert_eq!(summary["total_extended"], 40.0);
+        assert_eq!(summary["total_owed"], 15.0);
+        assert_eq!(summary["net"], 25.0);
+        assert_eq!(summary["relationship_count"], 2);
+
+        let (status, _) = testing::get_json(addr, "/api/peers/nobody/credit/summary").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_dial_peer() {
+        let state = testing::app_state_with_network().await;
+        let addr = testing::spawn_server(state).await;
+
+        // Nothing listens on port 1, so the dial is refused
+        let (status, body) =
+            testing::post_json(addr, "/api/peers/dial", r#"{"multiaddr": "/ip4/127.0.0.1/tcp/1"}"#).await;
+        assert_eq!(status, 502);
+        assert_eq!(body["status"], "failed");
+        assert!(body["error"].is_string());
+
+        let (status, _) = testing::post_json(addr, "/api/peers/dial", r#"{"multiaddr": "not an address"}"#).await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_submit_vouch() {
+        use mycelial_network::{Keypair, NetworkConfig, NetworkEvent, NetworkService};
+        use mycelial_protocol::{topics, VouchMessage};
+        use std::time::Duration;
+
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+        let (node, network, mut events) = NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        let (remote, remote_network, mut remote_events) =
+            NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        let remote_peer = remote_network.local_peer_id();
+        tokio::spawn(node.run());
+        tokio::spawn(remote.run());
+
+        // The remote node must subscribe to the vouch topic before anything can be published
+        let remote_addr = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = remote_events.recv().await.unwrap() {
+                break address;
+            }
+        };
+        network.dial(remote_addr).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::PeerSubscribed { peer_id, topic } = events.recv().await.unwrap() {
+                    if peer_id == remote_peer && topic == topics::VOUCH {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.network = network;
+        let info = PeerInfo {
+            id: PeerId("bob".to_string()),
+            public_key: "bob".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        state.store.upsert_peer(&info, None).await.unwrap();
+        let addr = testing::spawn_server(Arc::new(state)).await;
+
+        let (status, _) = testing::post_json(addr, "/api/vouch", r#"{"vouchee": "bob", "stake": 0}"#).await;
+        assert_eq!(status, 400);
+        let (status, _) = testing::post_json(addr, "/api/vouch", r#"{"vouchee": "nobody", "stake": 0.5}"#).await;
+        assert_eq!(status, 404);
+
+        let (status, body) = testing::post_json(addr, "/api/vouch", r#"{"vouchee": "bob", "stake": 1.5}"#).await;
+        assert_eq!(status, 200);
+        let id = body["id"].as_str().unwrap().to_string();
+
+        let data = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let NetworkEvent::MessageReceived { topic, data, .. } = remote_events.recv().await.unwrap() {
+                    if topic == topics::VOUCH {
+                        break data;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+        match serde_json::from_slice::<VouchMessage>(&data).unwrap() {
+            VouchMessage::VouchRequest(request) => {
+                assert_eq!(request.id.to_string(), id);
+                assert_eq!(request.voucher, "local");
+                assert_eq!(request.vouchee, "bob");
+                // Stakes are capped at the voucher's full reputation
+                assert_eq!(request.stake, 1.0);
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_diff_between_nodes() {
+        use mycelial_network::{topics, Keypair, NetworkConfig, NetworkEvent, NetworkService};
+        use std::time::Duration;
+
+        let test_config = || {
+            let mut config = NetworkConfig::local_test(0);
+            config.enable_mdns = false;
+            config
+        };
+        let (node_a, network_a, mut events_a) = NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        let (node_b, network_b, mut events_b) = NetworkService::new(Keypair::generate_ed25519(), test_config()).unwrap();
+        let (peer_a, peer_b) = (network_a.local_peer_id(), network_b.local_peer_id());
+        tokio::spawn(node_a.run());
+        tokio::spawn(node_b.run());
+
+        // Both sides publish on the sync topic, so each must see the other subscribe
+        let addr_b = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = events_b.recv().await.unwrap() {
+                break address;
+            }
+        };
+        network_a.dial(addr_b).await.unwrap();
+        for (events, remote) in [(&mut events_a, peer_b), (&mut events_b, peer_a)] {
+            tokio::time::timeout(Duration::from_secs(10), async {
+                loop {
+                    if let NetworkEvent::PeerSubscribed { peer_id, topic } = events.recv().await.unwrap() {
+                        if peer_id == remote && topic == topics::SYNC {
+                            break;
+                        }
+                    }
+                }
+            })
+            .await
+            .unwrap();
+        }
+
+        let node = |network| async move {
+            let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+            state.network = network;
+            Arc::new(state)
+        };
+        let (state_a, state_b) = (node(network_a).await, node(network_b).await);
+        for (state, mut events, local) in [(state_a.clone(), events_a, peer_a), (state_b.clone(), events_b, peer_b)] {
+            tokio::spawn(async move {
+                while let Ok(event) = events.recv().await {
+                    crate::handle_network_event(event, &state, local).await;
+                }
+            });
+        }
+
+        // Shared peers and kv, then a peer only on A, a credit line only on B
+        // and a kv entry that differs
+        let peer = |id: &str| PeerInfo {
+            id: PeerId(id.to_string()),
+            public_key: id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        for state in [&state_a, &state_b] {
+            for id in ["alice", "bob"] {
+                state.store.upsert_peer(&peer(id), None).await.unwrap();
+            }
+            state.store.set_sync_value("app:shared", b"same").await.unwrap();
+        }
+        state_a.store.upsert_peer(&peer("carol"), None).await.unwrap();
+        let line = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 10.0);
+        state_b.store.upsert_credit_relationship(&line).await.unwrap();
+        state_b.store.set_sync_value("app:shared", b"changed").await.unwrap();
+
+        let addr = testing::spawn_server(state_a.clone()).await;
+        let path = format!("/api/sync/diff/{}", peer_b);
+        let (status, body) = testing::get_json(addr, &path).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["peer_id"], peer_b.to_base58());
+        assert_eq!((body["peers"].as_u64(), body["credits"].as_u64(), body["kv"].as_u64()), (Some(1), Some(1), Some(1)));
+        assert_eq!(body["total"], 3);
+
+        // Nothing was applied on either side
+        assert!(state_b.store.get_peer("carol").await.unwrap().is_none());
+        assert!(state_a.store.list_active_credit_relationships().await.unwrap().is_empty());
+
+        // One diff per peer at a time
+        let running = state_a.pending_diffs.start(&peer_b.to_base58(), uuid::Uuid::new_v4()).unwrap();
+        let (status, _) = testing::get_json(addr, &path).await;
+        assert_eq!(status, 409);
+        drop(running);
+
+        let stranger = Keypair::generate_ed25519().public().to_peer_id();
+        let (status, _) = testing::get_json(addr, &format!("/api/sync/diff/{}", stranger)).await;
+        assert_eq!(status, 404);
+        let (status, _) = testing::get_json(addr, "/api/sync/diff/not-a-peer").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_search_peers() {
+        let state = testing::app_state().await;
+        for (id, name) in [("p1", "Alice"), ("p2", "Bob")] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(name.to_string()),
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_json(addr, "/api/peers/search?name=al").await;
+        assert_eq!(status, 200);
+        let peers = body.as_array().unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0]["name"], "Alice");
+
+        // The name parameter is required
+        let (status, _) = testing::get_json(addr, "/api/peers/search").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_prune_endpoint() {
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.admin_token = Some("secret".to_string());
+        let info = PeerInfo {
+            id: PeerId("alice".to_string()),
+            public_key: "alice".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        state.store.upsert_peer(&info, None).await.unwrap();
+        for age_days in [0, 2, 3] {
+            let mut message = Message::new(MessageType::Content, PeerId("alice".to_string()), vec![]);
+            message.timestamp = Utc::now() - chrono::Duration::days(age_days);
+            state.store.store_message(&message).await.unwrap();
+        }
+        let addr = testing::spawn_server(Arc::new(state)).await;
+        let body = r#"{"older_than_secs": 86400}"#;
+
+        // No token, or the wrong one, is refused
+        let (status, _) = testing::post_json(addr, "/api/maintenance/prune", body).await;
+        assert_eq!(status, 401);
+        let (status, _) =
+            testing::post_json_with_token(addr, "/api/maintenance/prune", "guess", body).await;
+        assert_eq!(status, 401);
+
+        let (status, pruned) =
+            testing::post_json_with_token(addr, "/api/maintenance/prune", "secret", body).await;
+        assert_eq!(status, 200);
+        assert_eq!(pruned["messages"], 2);
+        assert_eq!(pruned["credit_transactions"], 0);
+
+        let (_, stats) = testing::get_json(addr, "/api/messages/stats").await;
+        assert_eq!(stats["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_peer() {
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.admin_token = Some("secret".to_string());
+        let info = PeerInfo {
+            id: PeerId("alice".to_string()),
+            public_key: "alice".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        state.store.upsert_peer(&info, None).await.unwrap();
+        state.store.add_tag("alice", "blocked").await.unwrap();
+        let state = Arc::new(state);
+        let addr = testing::spawn_server(state.clone()).await;
+
+        assert_eq!(testing::delete_with_token(addr, "/api/peers/alice", "guess").await, 401);
+        assert!(state.store.get_peer("alice").await.unwrap().is_some());
+
+        // Disconnecting is best effort and doesn't change the outcome
+        let status = testing::delete_with_token(addr, "/api/peers/alice?disconnect=true", "secret").await;
+        assert_eq!(status, 204);
+        assert!(state.store.get_peer("alice").await.unwrap().is_none());
+        assert!(state.store.peer_tags("alice").await.unwrap().is_empty());
+
+        assert_eq!(testing::delete_with_token(addr, "/api/peers/alice", "secret").await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_block_peer() {
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let addr = testing::spawn_server(state.clone()).await;
+        let path = "/api/peers/mallory/block";
+
+        let (status, _) = testing::post_json_with_token(addr, path, "guess", "{}").await;
+        assert_eq!(status, 401);
+        assert!(!state.store.is_blocked("mallory").await.unwrap());
+
+        // Peers can be blocked before they're ever seen
+        let (status, _) = testing::post_json_with_token(addr, path, "secret", r#"{"reason": "spam"}"#).await;
+        assert_eq!(status, 204);
+        assert!(state.store.is_blocked("mallory").await.unwrap());
+
+        assert_eq!(testing::delete_with_token(addr, path, "secret").await, 204);
+        assert!(!state.store.is_blocked("mallory").await.unwrap());
+        assert_eq!(testing::delete_with_token(addr, path, "secret").await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_deactivate_stale_credit_endpoint() {
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.admin_token = Some("secret".to_string());
+        for id in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        let mut rel = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 50.0);
+        rel.last_transaction = Utc::now() - chrono::Duration::days(30);
+        state.store.upsert_credit_relationship(&rel).await.unwrap();
+        let addr = testing::spawn_server(Arc::new(state)).await;
+        let body = r#"{"older_than_secs": 604800}"#;
+
+        let (status, _) = testing::post_json(addr, "/api/maintenance/credit/deactivate", body).await;
+        assert_eq!(status, 401);
+
+        let (status, result) =
+            testing::post_json_with_token(addr, "/api/maintenance/credit/deactivate", "secret", body).await;
+        assert_eq!(status, 200);
+        assert_eq!(result["deactivated"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_prune_disabled_without_token() {
+        let addr = testing::spawn_server(testing::app_state().await).await;
+        let (status, _) = testing::post_json_with_token(
+            addr,
+            "/api/maintenance/prune",
+            "anything",
+            r#"{"older_than_secs": 0}"#,
+        )
+        .await;
+        assert_eq!(status, 403);
+    }
+
+    #[tokio::test]
+    async fn test_list_peers_by_tag() {
+        let state = testing::app_state().await;
+        for id in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+        }
+        state.store.add_tag("bob", "friend").await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let (_, all) = testing::get_json(addr, "/api/peers").await;
+        assert_eq!(all.as_array().unwrap().len(), 2);
+
+        let (status, friends) = testing::get_json(addr, "/api/peers?tag=friend").await;
+        assert_eq!(status, 200);
+        let friends = friends.as_array().unwrap();
+        assert_eq!(friends.len(), 1);
+        assert_eq!(friends[0]["id"], "bob");
+
+        let (status, blocked) = testing::get_json(addr, "/api/peers?tag=blocked").await;
+        assert_eq!(status, 200);
+        assert!(blocked.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_listen_addresses() {
+        let state = testing::app_state().await;
+        for addr in ["/ip4/127.0.0.1/tcp/9000", "/ip4/192.168.1.20/udp/9000/quic-v1"] {
+            let listen = ListenAddress::new(addr.parse().unwrap());
+            state.listen_addresses.write().push(listen);
+        }
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_json(addr, "/api/listen_addresses").await;
+        assert_eq!(status, 200);
+        let entries = body.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["transport"], "tcp");
+        assert_eq!(entries[0]["scope"], "loopback");
+        assert_eq!(entries[0]["connect"], "/ip4/127.0.0.1/tcp/9000/p2p/local");
+        assert_eq!(entries[1]["transport"], "quic");
+        assert_eq!(entries[1]["scope"], "lan");
+    }
+
+    #[tokio::test]
+    async fn test_network_graph() {
+        // Unavailable while the network service isn't running
+        let addr = testing::spawn_server(testing::app_state().await).await;
+        let (status, _) = testing::get_json(addr, "/api/network/graph").await;
+        assert_eq!(status, 503);
+
+        // A node with no connections is a lone vertex
+        let state = testing::app_state_with_network().await;
+        let local = state.network.local_peer_id().to_string();
+        let addr = testing::spawn_server(state).await;
+        let (status, body) = testing::get_json(addr, "/api/network/graph").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["local_peer_id"], local);
+        assert_eq!(body["nodes"], serde_json::json!([local]));
+        assert_eq!(body["edges"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_sync_conflicts() {
+        use mycelial_state::{SqliteStore, StateCache, StateSync, StateUpdate};
+
+        let state = testing::app_state().await;
+        let remote_store = SqliteStore::new(":memory:").await.unwrap();
+        for id in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            state.store.upsert_peer(&info, None).await.unwrap();
+            remote_store.upsert_peer(&info, None).await.unwrap();
+        }
+        let remote = StateSync::new("remote".to_string(), Arc::new(StateCache::new()));
+        let now = Utc::now();
+        let credit_update = |origin: &str, secs: i64| StateUpdate::CreditUpdate {
+            creditor: "alice".to_string(),
+            debtor: "bob".to_string(),
+            credit_limit: 100.0,
+            balance: 10.0,
+            active: true,
+            timestamp: now + chrono::Duration::seconds(secs),
+            origin: origin.to_string(),
+            tallies: None,
+        };
+        state.sync.apply_update(&credit_update("local", 1), &state.store).await.unwrap();
+        remote.apply_update(&credit_update("remote", 0), &remote_store).await.unwrap();
+        let response = remote.handle_sync_request(&state.sync.get_clock());
+        state.sync.apply_sync_response(&response, "remote", &state.store).await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_json(addr, "/api/sync/conflicts").await;
+        assert_eq!(status, 200);
+        let conflicts = body.as_array().unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0]["key"], "credit:alice:bob");
+        assert_eq!(conflicts[0]["resolution"], "kept_local");
+    }
+
+    #[tokio::test]
+    async fn test_reputation_history() {
+        let state = testing::app_state().await;
+        let info = PeerInfo {
+            id: PeerId("alice".to_string()),
+            public_key: "alice".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        let reputation = Reputation {
+            history: [(1_700_000_000, 0.5), (1_700_000_100, 0.6), (1_700_000_200, 0.4)]
+                .into_iter()
+                .map(|(ts, score)| ReputationSnapshot {
+                    score,
+                    timestamp: Utc.timestamp_opt(ts, 0).unwrap(),
+                })
+                .collect(),
+            ..Reputation::default()
+        };
+        state.store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+        let addr = testing::spawn_server(state).await;
+
+        let (status, body) = testing::get_json(addr, "/api/peers/alice/reputation/history").await;
+        assert_eq!(status, 200);
+        let points: Vec<(i64, f64)> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["timestamp"].as_i64().unwrap(), p["score"].as_f64().unwrap()))
+            .collect();
+        assert_eq!(
+            points,
+            vec![(1_700_000_000, 0.5), (1_700_000_100, 0.6), (1_700_000_200, 0.4)]
+        );
+
+        let (_, body) =
+            testing::get_json(addr, "/api/peers/alice/reputation/history?since=1700000100").await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (status, _) = testing::get_json(addr, "/api/peers/nobody/reputation/history").await;
+        assert_eq!(status, 404);
+    }
+}
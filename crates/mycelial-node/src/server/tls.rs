@@ -0,0 +1,128 @@
+//! Optional TLS termination for the dashboard server
+//!
+//! When a certificate and key are configured the dashboard is served over
+//! HTTPS (rustls) and the WebSocket endpoint becomes `wss`. Plain HTTP stays
+//! the default.
+
+use anyhow::{bail, Context};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use std::future::Future;
+use std::path::Path;
+
+/// Load and validate a PEM certificate chain and private key
+///
+/// Runs at startup so a bad path or malformed file stops the node with a
+/// clear error instead of failing on the first connection.
+pub async fn load_config(cert_path: &str, key_path: &str) -> anyhow::Result<RustlsConfig> {
+    // libp2p's QUIC transport enables rustls' ring backend and axum-server
+    // enables aws-lc-rs; with both compiled in rustls won't pick one itself.
+    // Fails harmlessly if a provider was already installed
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    for (kind, path) in [("certificate", cert_path), ("private key", key_path)] {
+        if !Path::new(path).is_file() {
+            bail!("TLS {} not found: {}", kind, path);
+        }
+    }
+
+    RustlsConfig::from_pem_file(cert_path, key_path)
+        .await
+        .with_context(|| {
+            format!(
+                "Invalid TLS certificate/key pair (cert: {}, key: {})",
+                cert_path, key_path
+            )
+        })
+}
+
+/// Serve the router over TLS on an already bound listener
+///
+/// Stops accepting connections once `shutdown` resolves and returns when the
+/// open ones have finished.
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    config: RustlsConfig,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let handle = Handle::new();
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        shutdown_handle.graceful_shutdown(None);
+    });
+
+    axum_server::from_tcp_rustls(listener.into_std()?, config)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    const TESTDATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/tls");
+
+    fn testdata(name: &str) -> String {
+        format!("{}/{}", TESTDATA, name)
+    }
+
+    #[tokio::test]
+    async fn test_load_config_fails_fast() {
+        let err = load_config(&testdata("missing.pem"), &testdata("key.pem"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("TLS certificate not found"));
+
+        // A certificate where the key should be is not a valid pair
+        let err = load_config(&testdata("cert.pem"), &testdata("ca.pem"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid TLS certificate/key pair"));
+    }
+
+    #[tokio::test]
+    async fn test_serves_routes_over_tls() {
+        let config = load_config(&testdata("cert.pem"), &testdata("key.pem"))
+            .await
+            .unwrap();
+        let app = Router::new().route("/health", get(|| async { "OK" }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, config, app, std::future::pending()));
+
+        let mut roots = RootCertStore::empty();
+        let ca = std::fs::read(testdata("ca.pem")).unwrap();
+        for cert in rustls_pemfile::certs(&mut ca.as_slice()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let client = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut tls = TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+
+        tls.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        tls.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("OK"));
+    }
+}
@@ -10,15 +10,19 @@ use axum::{
     },
     response::IntoResponse,
 };
-use futures::{SinkExt, StreamExt};
+use futures::{Sink, SinkExt, StreamExt};
+use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::sync::Arc;
-use tracing::{info, warn, error};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn, error};
 use uuid::Uuid;
 
 use crate::AppState;
-use super::messages::{WsMessage, ClientMessage, PeerListEntry};
+use super::messages::{ChatRecipients, WsMessage, ClientMessage, ClientFilter, PeerListEntry};
+use mycelial_state::PendingDirectMessage;
 use mycelial_protocol::{
-    topics,
+    topics, MessageCodec,
     VouchMessage, VouchRequest, VouchAck as ProtocolVouchAck,
     CreditMessage, CreateCreditLine as ProtocolCreateCreditLine, CreditTransfer as ProtocolCreditTransfer,
     GovernanceMessage, CreateProposal as ProtocolCreateProposal, CastVote as ProtocolCastVote, Vote,
@@ -39,7 +43,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let (mut sender, mut receiver) = socket.split();
 
     // Subscribe to broadcast events
-    let mut event_rx = state.event_tx.subscribe();
+    let event_rx = state.event_tx.subscribe();
 
     // Send initial peer list
     match state.store.list_peers().await {
@@ -47,7 +51,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             let entries: Vec<PeerListEntry> = peers.into_iter().map(Into::into).collect();
             let init_msg = WsMessage::PeersList { peers: entries };
             if let Ok(json) = serde_json::to_string(&init_msg) {
-                let _ = sender.send(Message::Text(json.into())).await;
+                let _ = sender.send(Message::Text(json)).await;
             }
         }
         Err(e) => {
@@ -55,16 +59,11 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
         }
     }
 
+    // Message types this client asked for; None forwards everything
+    let filter: Arc<RwLock<Option<HashSet<String>>>> = Arc::new(RwLock::new(None));
+
     // Spawn task to forward broadcast events to this client
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(event) = event_rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&event) {
-                if sender.send(Message::Text(json.into())).await.is_err() {
-                    break;
-                }
-            }
-        }
-    });
+    let mut send_task = tokio::spawn(forward_events(event_rx, sender, filter.clone()));
 
     // Handle incoming messages from client
     let state_clone = state.clone();
@@ -73,6 +72,11 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             match msg {
                 Message::Text(text) => {
                     info!("Received WebSocket text: {}", text);
+                    if let Ok(client_filter) = serde_json::from_str::<ClientFilter>(&text) {
+                        info!("WebSocket client filter: {:?}", client_filter.subscribe);
+                        *filter.write() = Some(client_filter.message_types());
+                        continue;
+                    }
                     match serde_json::from_str::<ClientMessage>(&text) {
                         Ok(client_msg) => {
                             handle_client_message(client_msg, &state_clone).await;
@@ -97,40 +101,249 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     info!("WebSocket connection closed");
 }
 
+/// Forward broadcast events to a client until the channel or the socket closes
+///
+/// A client too slow to keep up with the broadcast channel loses the oldest
+/// events. Rather than dropping them silently or disconnecting, the client is
+/// told how many it missed so it can resync.
+async fn forward_events<S>(
+    mut event_rx: broadcast::Receiver<WsMessage>,
+    mut sender: S,
+    filter: Arc<RwLock<Option<HashSet<String>>>>,
+) where
+    S: Sink<Message> + Unpin,
+{
+    loop {
+        let event = match event_rx.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("WebSocket client lagged behind, skipped {} events", skipped);
+                WsMessage::Lagged { skipped }
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let Ok(value) = serde_json::to_value(&event) else {
+            continue;
+        };
+        let wanted = matches!(event, WsMessage::Lagged { .. })
+            || match filter.read().as_ref() {
+                Some(types) => value
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .is_some_and(|t| types.contains(t)),
+                None => true,
+            };
+        if !wanted {
+            continue;
+        }
+        if sender.send(Message::Text(value.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Check whether a peer (base58 ID) currently has a live connection
+async fn is_peer_connected(state: &AppState, peer_id: &str) -> bool {
+    state
+        .network
+        .get_peers()
+        .await
+        .map(|peers| peers.iter().any(|p| p.to_base58() == peer_id))
+        .unwrap_or(false)
+}
+
+/// Set the limit of the credit line this node extends to `debtor`
+///
+/// The line is created if this node doesn't have it yet. Its transfers are
+/// checked against the limit kept here, and the change goes through
+/// [`StateSync`](mycelial_state::StateSync) like any other credit update, so
+/// it is returned for other nodes.
+async fn store_credit_line(
+    state: &AppState,
+    debtor: &str,
+    limit: f64,
+) -> mycelial_state::Result<mycelial_state::StateUpdate> {
+    let creditor = state.local_peer_id.clone();
+    let line = match state.store.get_credit_relationship_between(creditor.as_str(), debtor).await? {
+        Some(existing) => mycelial_core::credit::CreditRelationship { credit_limit: limit, ..existing },
+        None => {
+            let debtor = mycelial_core::peer::PeerId(debtor.to_string());
+            crate::remember_peer(state, &creditor).await?;
+            crate::remember_peer(state, &debtor).await?;
+            mycelial_core::credit::CreditRelationship::new(creditor, debtor, limit)
+        }
+    };
+    let update = state.sync.create_credit_update(&line);
+    state.sync.apply_update(&update, &state.store).await?;
+    Ok(update)
+}
+
+/// Send a state update this node made to the others
+///
+/// With nobody to send it to yet, it waits in the pending queue.
+async fn publish_state_update(state: &AppState, update: mycelial_state::StateUpdate) {
+    match state.sync.encode_update(&update) {
+        Ok(data) => {
+            if let Err(e) = state.network.publish(mycelial_network::topics::SYNC, data).await {
+                debug!("Queueing state update until it can be published: {}", e);
+                state.sync.queue_update(update);
+            }
+        }
+        Err(e) => error!("Failed to serialize state update: {}", e),
+    }
+}
+
+/// Queue a direct message for an offline recipient and report its status
+async fn queue_direct_message(
+    state: &AppState,
+    message_id: String,
+    recipient: &str,
+    data: Vec<u8>,
+    content: &str,
+    timestamp: i64,
+) {
+    let now = chrono::Utc::now();
+    let dm = PendingDirectMessage {
+        id: message_id.clone(),
+        recipient: recipient.to_string(),
+        payload: data,
+        queued_at: now,
+        expires_at: now + chrono::Duration::seconds(crate::PENDING_DM_TTL_SECS),
+    };
+
+    if let Err(e) = state.store.enqueue_pending_dm(&dm, crate::PENDING_DM_LIMIT).await {
+        error!("Failed to queue direct message for {}: {}", recipient, e);
+        let _ = state.event_tx.send(WsMessage::Error {
+            message: format!("Failed to queue direct message: {}", e),
+        });
+        return;
+    }
+
+    info!("Recipient {} offline, queued direct message {}", recipient, message_id);
+
+    let _ = state.event_tx.send(WsMessage::ChatMessage {
+        id: message_id.clone(),
+        from: state.local_peer_id.to_string(),
+        from_name: state.node_name.clone(),
+        to: Some(ChatRecipients::One(recipient.to_string())),
+        room_id: None,
+        content: content.to_string(),
+        timestamp,
+    });
+    let _ = state.event_tx.send(WsMessage::DirectMessageStatus {
+        id: message_id,
+        to: recipient.to_string(),
+        status: "queued".to_string(),
+    });
+}
+
+/// Join a group's topic and invite the other members to join it too
+async fn open_group(state: &AppState, message: &mycelial_core::message::Message, topic: &str) {
+    state.joined_groups.join(topic, chrono::Utc::now());
+    if let Err(e) = state.network.subscribe(topic).await {
+        warn!("Failed to join group topic {}: {}", topic, e);
+    }
+
+    let Some(invite) = crate::groups::invite_for(message) else {
+        return;
+    };
+    match serde_json::to_vec(&invite) {
+        Ok(data) => {
+            if let Err(e) = state.network.publish(crate::DIRECT_TOPIC, data).await {
+                warn!("Failed to invite members to group {}: {}", topic, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize group invite: {}", e),
+    }
+}
+
 /// Handle messages from the client
-async fn handle_client_message(msg: ClientMessage, state: &AppState) {
+pub(crate) async fn handle_client_message(msg: ClientMessage, state: &AppState) {
     info!("Received client message: {:?}", msg);
 
     match msg {
         ClientMessage::SendChat { content, to, room_id } => {
             info!("SendChat: content='{}', to={:?}, room_id={:?}", content, to, room_id);
 
-            // Generate message ID and timestamp for local echo
-            let message_id = Uuid::new_v4().to_string();
             let timestamp = chrono::Utc::now().timestamp_millis();
 
-            // Create chat message using core Message type
-            let chat_msg = mycelial_core::message::Message::new(
-                mycelial_core::message::MessageType::Content,
-                state.local_peer_id.clone(),
-                content.as_bytes().to_vec(),
-            );
+            // Create chat message using core Message type; a group message
+            // goes out once, naming all of its recipients
+            let chat_msg = match (&to, &room_id) {
+                (Some(ChatRecipients::One(recipient)), None) => mycelial_core::message::Message::direct(
+                    state.local_peer_id.clone(),
+                    mycelial_core::peer::PeerId(recipient.clone()),
+                    content.as_bytes().to_vec(),
+                ),
+                (Some(ChatRecipients::Group(recipients)), None) => {
+                    if recipients.is_empty() {
+                        let _ = state.event_tx.send(WsMessage::Error {
+                            message: "Group message needs at least one recipient".to_string(),
+                        });
+                        return;
+                    }
+                    mycelial_core::message::Message::group(
+                        state.local_peer_id.clone(),
+                        recipients.iter().cloned().map(mycelial_core::peer::PeerId).collect(),
+                        content.as_bytes().to_vec(),
+                    )
+                }
+                _ => mycelial_core::message::Message::new(
+                    mycelial_core::message::MessageType::Content,
+                    state.local_peer_id.clone(),
+                    content.as_bytes().to_vec(),
+                ),
+            };
+            // The local echo and any delivery receipt refer to the message's own ID
+            let message_id = chat_msg.id.to_string();
 
             // Serialize and publish to network
             match serde_json::to_vec(&chat_msg) {
                 Ok(data) => {
+                    // Direct and group messages make up conversation history
+                    if chat_msg.recipient.is_some() || !chat_msg.recipients.is_empty() {
+                        if let Err(e) = crate::store_chat_message(state, &chat_msg).await {
+                            warn!("Failed to store sent message {}: {}", chat_msg.id, e);
+                        }
+                    }
+
+                    // Hold direct messages for offline recipients until they reconnect
+                    if let (Some(ChatRecipients::One(recipient)), None) = (&to, &room_id) {
+                        if !is_peer_connected(state, recipient).await {
+                            queue_direct_message(state, message_id, recipient, data, &content, timestamp).await;
+                            return;
+                        }
+                    }
+
                     // Determine topic based on message target
-                    let topic = if room_id.is_some() {
-                        format!("/mycelial/1.0.0/room/{}", room_id.as_ref().unwrap())
+                    let group_topic = crate::groups::topic_for(&chat_msg);
+                    let topic = if let Some(room_id) = &room_id {
+                        format!("/mycelial/1.0.0/room/{}", room_id)
+                    } else if let Some(group_topic) = &group_topic {
+                        group_topic.clone()
                     } else if to.is_some() {
-                        "/mycelial/1.0.0/direct".to_string()
+                        crate::DIRECT_TOPIC.to_string()
                     } else {
                         "/mycelial/1.0.0/chat".to_string()
                     };
 
+                    if group_topic.is_some() {
+                        open_group(state, &chat_msg, &topic).await;
+                    }
+
                     info!("Publishing to topic: {}", topic);
 
-                    if let Err(e) = state.network.publish(&topic, data).await {
+                    let published = match state.network.publish(&topic, data.clone()).await {
+                        Ok(_) => Ok(()),
+                        // Nobody else has joined the group yet; send it once someone does
+                        Err(e) if group_topic.is_some() => {
+                            info!("Holding group message {} until a member joins: {}", message_id, e);
+                            state.held_group_messages.hold(topic.clone(), data, chrono::Utc::now());
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = published {
                         error!("Failed to publish chat: {}", e);
                     } else {
                         info!("Chat message published successfully");
@@ -182,6 +395,15 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
         ClientMessage::Subscribe { topic } => {
             if let Err(e) = state.network.subscribe(&topic).await {
                 error!("Failed to subscribe to topic {}: {}", topic, e);
+                let _ = state.event_tx.send(WsMessage::Error {
+                    message: format!("Failed to subscribe to {}: {}", topic, e),
+                });
+            }
+        }
+
+        ClientMessage::UnsubscribeAll => {
+            if let Err(e) = state.network.unsubscribe_all().await {
+                error!("Failed to unsubscribe from all topics: {}", e);
             }
         }
 
@@ -205,7 +427,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
             let vouch_msg = VouchMessage::VouchRequest(vouch_req);
 
             // Serialize and publish to network
-            match serde_json::to_vec(&vouch_msg) {
+            match state.payload_codec.encode(&vouch_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::VOUCH, data).await {
                         error!("Failed to publish vouch request: {}", e);
@@ -252,7 +474,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
                 timestamp: chrono::Utc::now(),
             });
 
-            match serde_json::to_vec(&ack_msg) {
+            match state.payload_codec.encode(&ack_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::VOUCH, data).await {
                         error!("Failed to publish vouch ack: {}", e);
@@ -278,13 +500,18 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
 
             let timestamp = chrono::Utc::now().timestamp_millis();
 
+            match store_credit_line(state, &debtor, limit).await {
+                Ok(update) => publish_state_update(state, update).await,
+                Err(e) => warn!("Failed to store credit line to {}: {}", debtor, e),
+            }
+
             let credit_msg = CreditMessage::CreateLine(ProtocolCreateCreditLine::new(
                 state.local_peer_id.to_string(),
                 debtor.clone(),
                 limit,
             ));
 
-            match serde_json::to_vec(&credit_msg) {
+            match state.payload_codec.encode(&credit_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::CREDIT, data).await {
                         error!("Failed to publish credit line: {}", e);
@@ -311,6 +538,31 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
 
             let timestamp = chrono::Utc::now().timestamp_millis();
 
+            // A line this node keeps is checked and tallied before anyone
+            // hears of the transfer; other nodes merge the tallies into their
+            // copy of the balance. Lines it doesn't keep are the other side's
+            // to check.
+            let creditor = state.local_peer_id.as_str();
+            let kept = match state.store.get_credit_relationship_between(creditor, &to).await {
+                Ok(line) => line.is_some(),
+                Err(e) => {
+                    warn!("Failed to look up credit line to {}: {}", to, e);
+                    false
+                }
+            };
+            if kept {
+                match state.sync.record_credit_transfer(creditor, &to, amount, &state.store).await {
+                    Ok(update) => publish_state_update(state, update).await,
+                    Err(e) => {
+                        warn!("Rejected credit transfer to {}: {}", to, e);
+                        let _ = state.event_tx.send(WsMessage::Error {
+                            message: format!("Credit transfer rejected: {}", e),
+                        });
+                        return;
+                    }
+                }
+            }
+
             // For transfers, we use a placeholder line_id - in practice, the client should
             // provide the actual credit line ID they want to use for the transfer
             let line_id = Uuid::new_v4(); // Placeholder - real impl would look up active credit line
@@ -325,7 +577,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
             }
             let transfer_msg = CreditMessage::Transfer(transfer);
 
-            match serde_json::to_vec(&transfer_msg) {
+            match state.payload_codec.encode(&transfer_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::CREDIT, data).await {
                         error!("Failed to publish credit transfer: {}", e);
@@ -358,7 +610,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
                 description.clone(),
             ));
 
-            match serde_json::to_vec(&proposal_msg) {
+            match state.payload_codec.encode(&proposal_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::GOVERNANCE, data).await {
                         error!("Failed to publish proposal: {}", e);
@@ -413,7 +665,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
                 1.0, // Default weight, could be based on reputation
             ));
 
-            match serde_json::to_vec(&vote_msg) {
+            match state.payload_codec.encode(&vote_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::GOVERNANCE, data).await {
                         error!("Failed to publish vote: {}", e);
@@ -454,7 +706,7 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
                 unit.clone(),
             ));
 
-            match serde_json::to_vec(&resource_msg) {
+            match state.payload_codec.encode(&resource_msg) {
                 Ok(data) => {
                     if let Err(e) = state.network.publish(topics::RESOURCE, data).await {
                         error!("Failed to publish resource contribution: {}", e);
@@ -595,3 +847,127 @@ async fn handle_client_message(msg: ClientMessage, state: &AppState) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::testing;
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite::Message as Frame;
+
+    fn message_type(frame: Frame) -> String {
+        let value: serde_json::Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+        value["type"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_client_filter() {
+        let state = testing::app_state().await;
+        let addr = testing::spawn_server(state.clone()).await;
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+
+        // The initial peer list is sent before any filter applies
+        let first = ws.next().await.unwrap().unwrap();
+        assert_eq!(message_type(first), "peers_list");
+
+        let mut events = state.event_tx.subscribe();
+        ws.send(Frame::Text(r#"{"subscribe": ["ChatMessage"]}"#.into())).await.unwrap();
+        ws.send(Frame::Text(r#"{"type": "get_peers"}"#.into())).await.unwrap();
+
+        // Messages are handled in order, so once the peer list is broadcast
+        // the filter is in place; the list itself is filtered out
+        loop {
+            if let WsMessage::PeersList { .. } = events.recv().await.unwrap() {
+                break;
+            }
+        }
+
+        state.event_tx.send(WsMessage::PeerLeft { peer_id: "gone".to_string() }).unwrap();
+        state
+            .event_tx
+            .send(WsMessage::ChatMessage {
+                id: "1".to_string(),
+                from: "peer".to_string(),
+                from_name: "Peer".to_string(),
+                to: None,
+                room_id: None,
+                content: "hi".to_string(),
+                timestamp: 0,
+            })
+            .unwrap();
+
+        let next = ws.next().await.unwrap().unwrap();
+        assert_eq!(message_type(next), "chat_message");
+
+        let quiet = tokio::time::timeout(Duration::from_millis(200), ws.next()).await;
+        assert!(quiet.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transfers_checked_against_credit_line() {
+        let state = testing::app_state().await;
+        let mut events = state.event_tx.subscribe();
+        let transfer = |amount| ClientMessage::TransferCredit { to: "bob".to_string(), amount, memo: None };
+
+        // Without a line kept here there is nothing to tally it on
+        handle_client_message(transfer(10.0), &state).await;
+        assert!(state.store.get_credit_relationship_between("local", "bob").await.unwrap().is_none());
+
+        // Past the line's limit it's refused
+        let line = |limit| ClientMessage::CreateCreditLine { debtor: "bob".to_string(), limit };
+        handle_client_message(line(50.0), &state).await;
+        handle_client_message(transfer(30.0), &state).await;
+        handle_client_message(transfer(30.0), &state).await;
+        let tallies = state.store.credit_tallies("local", "bob").await.unwrap();
+        assert_eq!(tallies.nodes["local"].increments, 30.0);
+
+        // Raising the limit keeps the balance
+        handle_client_message(line(100.0), &state).await;
+        handle_client_message(transfer(30.0), &state).await;
+        let line = state.store.get_credit_relationship_between("local", "bob").await.unwrap().unwrap();
+        assert_eq!((line.credit_limit, line.balance), (100.0, 60.0));
+
+        // With no peers to publish to, the updates wait to be sent
+        let pending = state.sync.drain_pending_updates();
+        assert_eq!(pending.len(), 4);
+        assert!(matches!(
+            pending.last(),
+            Some(mycelial_state::StateUpdate::CreditUpdate { balance, tallies: Some(_), .. }) if *balance == 60.0
+        ));
+
+        let mut rejected = 0;
+        while let Ok(event) = events.try_recv() {
+            if let WsMessage::Error { message } = event {
+                assert!(message.starts_with("Credit transfer rejected"), "{}", message);
+                rejected += 1;
+            }
+        }
+        assert_eq!(rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_client_is_notified() {
+        let (event_tx, event_rx) = broadcast::channel(4);
+        for i in 0..10 {
+            event_tx.send(WsMessage::PeerLeft { peer_id: i.to_string() }).unwrap();
+        }
+        drop(event_tx);
+
+        // Only chat messages wanted, but the lag notice still comes through
+        let filter = Arc::new(RwLock::new(Some(HashSet::from(["chat_message".to_string()]))));
+        let (sink, frames) = futures::channel::mpsc::unbounded();
+        forward_events(event_rx, sink, filter).await;
+
+        let frames: Vec<serde_json::Value> = frames
+            .map(|frame| match frame {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                other => panic!("unexpected frame {:?}", other),
+            })
+            .collect()
+            .await;
+        assert_eq!(frames, vec![serde_json::json!({"type": "lagged", "skipped": 6})]);
+    }
+}
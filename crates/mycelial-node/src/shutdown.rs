@@ -0,0 +1,181 @@
+//! Graceful shutdown
+//!
+//! On SIGINT/SIGTERM the node stops accepting HTTP and P2P connections,
+//! publishes any state updates still queued in [`StateSync`], then stops the
+//! network service and closes the database. Each step is bounded so a stuck
+//! peer or client can't keep the process alive.
+//!
+//! [`StateSync`]: mycelial_state::StateSync
+
+use mycelial_network::topics;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::AppState;
+
+/// Upper bound for each shutdown step (HTTP drain, flush, network stop)
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait for SIGINT (Ctrl+C) or, on Unix, SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Resolve once the shutdown broadcast fires (or its sender is dropped)
+pub async fn wait(mut shutdown_rx: broadcast::Receiver<()>) {
+    let _ = shutdown_rx.recv().await;
+}
+
+/// Publish every queued state update, giving up after `timeout`
+///
+/// The queue is drained up front, so updates that couldn't be published in
+/// time are dropped rather than left behind. Returns how many were handed to
+/// the network service.
+pub async fn flush_pending_updates(state: &AppState, timeout: Duration) -> usize {
+    let pending = state.sync.drain_pending_updates();
+    if pending.is_empty() {
+        return 0;
+    }
+
+    let total = pending.len();
+    let mut published = 0;
+    let flush = async {
+        for update in pending {
+            let data = match state.sync.encode_update(&update) {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("Dropping unserializable state update: {}", e);
+                    continue;
+                }
+            };
+            match state.network.publish(topics::SYNC, data).await {
+                Ok(_) => published += 1,
+                Err(e) => {
+                    warn!("Failed to publish pending updates: {}", e);
+                    break;
+                }
+            }
+        }
+    };
+
+    if tokio::time::timeout(timeout, flush).await.is_err() {
+        warn!("Timed out flushing pending updates after {:?}", timeout);
+    }
+
+    info!("Flushed {}/{} pending state updates", published, total);
+    published
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::testing;
+    use mycelial_network::{Keypair, NetworkConfig, NetworkEvent, NetworkService};
+    use std::sync::Arc;
+
+    fn config_without_mdns() -> NetworkConfig {
+        let mut config = NetworkConfig::local_test(0);
+        config.enable_mdns = false;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_pending_updates() {
+        let (service, network, mut events) =
+            NetworkService::new(Keypair::generate_ed25519(), config_without_mdns()).unwrap();
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let service_task = tokio::spawn(service.with_shutdown(shutdown_tx.subscribe()).run());
+        let server_shutdown = tokio::spawn(wait(shutdown_tx.subscribe()));
+
+        // Gossipsub only accepts a publish with someone to send it to
+        let (peer, peer_network, mut peer_events) =
+            NetworkService::new(Keypair::generate_ed25519(), config_without_mdns()).unwrap();
+        tokio::spawn(peer.run());
+        peer_network.subscribe(topics::SYNC).await.unwrap();
+        let peer_addr = loop {
+            if let NetworkEvent::ListeningOn { address, .. } = peer_events.recv().await.unwrap() {
+                break address;
+            }
+        };
+        network.dial(peer_addr).await.unwrap();
+        tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+            loop {
+                if let NetworkEvent::PeerSubscribed { topic, .. } = events.recv().await.unwrap() {
+                    if topic == topics::SYNC {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut state = Arc::into_inner(testing::app_state().await).unwrap();
+        state.network = network;
+        for version in 1..=3 {
+            let update = state.sync.create_kv_update("key", vec![version as u8], version);
+            state.sync.queue_update(update);
+        }
+
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(SHUTDOWN_TIMEOUT, server_shutdown).await.unwrap().unwrap();
+        assert_eq!(flush_pending_updates(&state, SHUTDOWN_TIMEOUT).await, 3);
+        assert!(state.sync.drain_pending_updates().is_empty());
+
+        // The peer gets every queued update
+        let received = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+            let mut received = 0;
+            while received < 3 {
+                if let NetworkEvent::MessageReceived { topic, .. } = peer_events.recv().await.unwrap() {
+                    if topic == topics::SYNC {
+                        received += 1;
+                    }
+                }
+            }
+            received
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, 3);
+
+        state.network.shutdown().await.unwrap();
+        tokio::time::timeout(SHUTDOWN_TIMEOUT, service_task)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        while !matches!(events.recv().await.unwrap(), NetworkEvent::Stopped) {}
+        peer_network.shutdown().await.unwrap();
+
+        state.store.close().await;
+        assert!(state.store.count_peers().await.is_err());
+    }
+}
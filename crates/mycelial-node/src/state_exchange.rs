@@ -0,0 +1,331 @@
+//! State exchange with other nodes
+//!
+//! There is no point-to-point protocol yet, so nodes ask each other for
+//! state over the sync topic. A request names the node it is meant for and
+//! only that node answers, addressing the answer back to the requester.
+//! Receivers check that the gossipsub source is the node a message claims
+//! to be from, so nobody can answer on another node's behalf.
+//!
+//! A diff first exchanges digest summaries, the root and bucket hashes of
+//! each record kind, then asks for the leaves of the buckets that differ,
+//! which arrive in pages of at most [`SYNC_PAGE_BYTES`].
+//!
+//! A node that just joined also asks the first peer it shares the sync
+//! topic with for a snapshot of its state, see [`SnapshotBootstrap`]. A
+//! snapshot can outgrow a gossip message, so it is sent in pages of at most
+//! [`SYNC_PAGE_BYTES`] as well; if it can't be sent, the answer says why.
+
+use chrono::{DateTime, Duration, Utc};
+use mycelial_state::{DigestBuckets, DigestPage, DigestSummary, StateSnapshot};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// A message on the sync topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncMessage {
+    /// Ask `to` for its digest summary
+    DigestRequest { id: Uuid, from: String, to: String },
+    /// Answer to a digest request
+    Digest { id: Uuid, from: String, to: String, summary: DigestSummary },
+    /// Ask `to` for the leaves of the buckets that differ
+    LeavesRequest { id: Uuid, from: String, to: String, buckets: DigestBuckets },
+    /// One page of the answer to a leaves request
+    Leaves { id: Uuid, from: String, to: String, page: DigestPage },
+    /// Ask `to` for a snapshot of its state
+    SnapshotRequest { id: Uuid, from: String, to: String },
+    /// One page of the answer to a snapshot request
+    Snapshot {
+        id: Uuid,
+        from: String,
+        to: String,
+        /// Index of this page, counting from 0
+        #[serde(default)]
+        page: u32,
+        /// Pages the snapshot was split into
+        #[serde(default)]
+        pages: u32,
+        snapshot: StateSnapshot,
+    },
+    /// Answer to a snapshot request that couldn't be served
+    SnapshotFailed { id: Uuid, from: String, to: String, reason: String },
+}
+
+impl SyncMessage {
+    /// Node the message claims to come from
+    pub fn from(&self) -> &str {
+        match self {
+            SyncMessage::DigestRequest { from, .. }
+            | SyncMessage::Digest { from, .. }
+            | SyncMessage::LeavesRequest { from, .. }
+            | SyncMessage::Leaves { from, .. }
+            | SyncMessage::SnapshotRequest { from, .. }
+            | SyncMessage::Snapshot { from, .. }
+            | SyncMessage::SnapshotFailed { from, .. } => from,
+        }
+    }
+
+    /// Node the message is addressed to
+    pub fn to(&self) -> &str {
+        match self {
+            SyncMessage::DigestRequest { to, .. }
+            | SyncMessage::Digest { to, .. }
+            | SyncMessage::LeavesRequest { to, .. }
+            | SyncMessage::Leaves { to, .. }
+            | SyncMessage::SnapshotRequest { to, .. }
+            | SyncMessage::Snapshot { to, .. }
+            | SyncMessage::SnapshotFailed { to, .. } => to,
+        }
+    }
+}
+
+/// Part of a peer's answer during a diff
+#[derive(Debug)]
+pub enum DigestAnswer {
+    Summary(DigestSummary),
+    Leaves(DigestPage),
+}
+
+/// Diffs waiting for answers, at most one per peer
+#[derive(Debug, Default)]
+pub struct PendingDiffs {
+    waiting: Mutex<HashMap<String, (Uuid, mpsc::UnboundedSender<DigestAnswer>)>>,
+}
+
+impl PendingDiffs {
+    /// Start waiting for `peer`'s answers to diff `id`
+    ///
+    /// Returns `None` if a diff with the peer is already running. The wait
+    /// ends when the returned [`PendingDiff`] is dropped.
+    pub fn start(&self, peer: &str, id: Uuid) -> Option<PendingDiff<'_>> {
+        let mut waiting = self.waiting.lock();
+        if waiting.contains_key(peer) {
+            return None;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        waiting.insert(peer.to_string(), (id, tx));
+        Some(PendingDiff { diffs: self, peer: peer.to_string(), id, answers: rx })
+    }
+
+    /// Hand part of `peer`'s answer to whoever is waiting for it
+    ///
+    /// Returns false if nobody waits for answers to `id` from the peer.
+    pub fn deliver(&self, peer: &str, id: Uuid, answer: DigestAnswer) -> bool {
+        match self.waiting.lock().get(peer) {
+            Some((waiting_id, tx)) if *waiting_id == id => tx.send(answer).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+/// A running diff with one peer, see [`PendingDiffs::start`]
+#[derive(Debug)]
+pub struct PendingDiff<'a> {
+    diffs: &'a PendingDiffs,
+    peer: String,
+    id: Uuid,
+    answers: mpsc::UnboundedReceiver<DigestAnswer>,
+}
+
+impl PendingDiff<'_> {
+    /// Wait for the next part of the peer's answer
+    pub async fn answer(&mut self) -> Option<DigestAnswer> {
+        self.answers.recv().await
+    }
+}
+
+impl Drop for PendingDiff<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.diffs.waiting.lock();
+        if waiting.get(&self.peer).is_some_and(|(id, _)| *id == self.id) {
+            waiting.remove(&self.peer);
+        }
+    }
+}
+
+/// How long a snapshot request waits before the next peer is asked
+const SNAPSHOT_REQUEST_TIMEOUT_SECS: i64 = 30;
+
+/// Largest encoded snapshot or digest page, half of gossipsub's 1 MB message
+/// limit so the sync message around it and the signature always fit
+pub const SYNC_PAGE_BYTES: usize = 512 * 1024;
+
+/// Where a joining node is with its snapshot
+#[derive(Debug, Default)]
+enum Bootstrap {
+    #[default]
+    Idle,
+    Requested { peer: String, id: Uuid, at: DateTime<Utc>, pages: BTreeMap<u32, StateSnapshot> },
+    Done,
+}
+
+/// What a received snapshot page amounts to
+#[derive(Debug)]
+pub enum SnapshotPage {
+    /// No such snapshot was asked for
+    Unrequested,
+    /// More pages are still to come
+    Partial,
+    /// The last missing page arrived
+    Complete(StateSnapshot),
+}
+
+/// The snapshot a joining node asks for, once per run
+///
+/// Only one request is out at a time. If it isn't answered in time, the
+/// next peer to join the sync topic is asked instead.
+#[derive(Debug, Default)]
+pub struct SnapshotBootstrap {
+    state: Mutex<Bootstrap>,
+}
+
+impl SnapshotBootstrap {
+    /// Start asking `peer` with request `id`
+    ///
+    /// Returns false if a snapshot already arrived or another request is
+    /// still waiting for its answer.
+    pub fn start(&self, peer: &str, id: Uuid, now: DateTime<Utc>) -> bool {
+        let mut state = self.state.lock();
+        match &*state {
+            Bootstrap::Done => return false,
+            Bootstrap::Requested { at, .. } if now - *at < Duration::seconds(SNAPSHOT_REQUEST_TIMEOUT_SECS) => {
+                return false;
+            }
+            _ => {}
+        }
+        *state = Bootstrap::Requested { peer: peer.to_string(), id, at: now, pages: BTreeMap::new() };
+        true
+    }
+
+    /// Give up on request `id`, e.g. because it couldn't be sent
+    pub fn cancel(&self, id: Uuid) {
+        let mut state = self.state.lock();
+        if matches!(&*state, Bootstrap::Requested { id: requested, .. } if *requested == id) {
+            *state = Bootstrap::Idle;
+        }
+    }
+
+    /// Accept page `page` of `pages` of `peer`'s snapshot answering `id`
+    ///
+    /// Pages may arrive in any order; the snapshot is complete once all of
+    /// them are in.
+    pub fn receive(&self, peer: &str, id: Uuid, page: u32, pages: u32, snapshot: StateSnapshot) -> SnapshotPage {
+        let mut state = self.state.lock();
+        let Bootstrap::Requested { peer: asked, id: requested, pages: received, .. } = &mut *state else {
+            return SnapshotPage::Unrequested;
+        };
+        let pages = pages.max(1);
+        if asked != peer || *requested != id || page >= pages {
+            return SnapshotPage::Unrequested;
+        }
+        received.insert(page, snapshot);
+        if received.len() < pages as usize {
+            return SnapshotPage::Partial;
+        }
+        let received = std::mem::take(received);
+        *state = Bootstrap::Done;
+        SnapshotPage::Complete(StateSnapshot::join(received.into_values()).expect("a page was received"))
+    }
+
+    /// Give up on `peer`'s snapshot answering `id`, which it couldn't send
+    ///
+    /// Returns false if no such snapshot was asked for. The next peer to
+    /// join the sync topic is asked instead.
+    pub fn fail(&self, peer: &str, id: Uuid) -> bool {
+        let mut state = self.state.lock();
+        match &*state {
+            Bootstrap::Requested { peer: asked, id: requested, .. } if asked == peer && *requested == id => {
+                *state = Bootstrap::Idle;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mycelial_state::VectorClock;
+
+    fn snapshot_page(reputations: &[&str]) -> StateSnapshot {
+        StateSnapshot {
+            origin: "dave".to_string(),
+            clock: VectorClock::new(),
+            epoch: 0,
+            peer_updates: Vec::new(),
+            reputations: reputations.iter().map(|id| (id.to_string(), Default::default())).collect(),
+            credit_relationships: Vec::new(),
+            credit_tallies: Vec::new(),
+            taken_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_one_diff_per_peer() {
+        let diffs = PendingDiffs::default();
+        let id = Uuid::new_v4();
+        let mut pending = diffs.start("bob", id).unwrap();
+        assert!(diffs.start("bob", Uuid::new_v4()).is_none());
+        assert!(diffs.start("carol", Uuid::new_v4()).is_some());
+
+        // Answers to other requests, or from other peers, are ignored
+        let page = || DigestAnswer::Leaves(DigestPage::default());
+        assert!(!diffs.deliver("bob", Uuid::new_v4(), page()));
+        assert!(!diffs.deliver("carol", id, page()));
+        assert!(diffs.deliver("bob", id, page()));
+        assert!(diffs.deliver("bob", id, page()));
+        assert!(matches!(pending.answer().await, Some(DigestAnswer::Leaves(_))));
+        assert!(matches!(pending.answer().await, Some(DigestAnswer::Leaves(_))));
+
+        // Finishing frees the peer for the next diff
+        drop(pending);
+        assert!(diffs.start("bob", Uuid::new_v4()).is_some());
+    }
+
+    #[test]
+    fn test_one_snapshot_per_run() {
+        let bootstrap = SnapshotBootstrap::default();
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        assert!(bootstrap.start("bob", id, now));
+        assert!(!bootstrap.start("carol", Uuid::new_v4(), now));
+
+        // A request that got no answer makes way for the next peer
+        let later = now + Duration::seconds(SNAPSHOT_REQUEST_TIMEOUT_SECS + 1);
+        let retry = Uuid::new_v4();
+        assert!(bootstrap.start("carol", retry, later));
+        assert!(matches!(bootstrap.receive("bob", id, 0, 1, snapshot_page(&[])), SnapshotPage::Unrequested));
+        bootstrap.cancel(retry);
+        let retry = Uuid::new_v4();
+        assert!(bootstrap.start("dave", retry, later));
+
+        // A peer that can't send its snapshot makes way for the next one
+        assert!(!bootstrap.fail("carol", retry));
+        assert!(bootstrap.fail("dave", retry));
+        let retry = Uuid::new_v4();
+        assert!(bootstrap.start("dave", retry, later));
+
+        // Only the peer asked can answer, and only once; pages may arrive
+        // out of order
+        let page = |page, reputations| bootstrap.receive("dave", retry, page, 2, snapshot_page(reputations));
+        assert!(matches!(
+            bootstrap.receive("carol", retry, 0, 1, snapshot_page(&[])),
+            SnapshotPage::Unrequested
+        ));
+        assert!(matches!(page(2, &["x"]), SnapshotPage::Unrequested));
+        assert!(matches!(page(1, &["c"]), SnapshotPage::Partial));
+        match page(0, &["a", "b"]) {
+            SnapshotPage::Complete(snapshot) => {
+                let ids: Vec<_> = snapshot.reputations.iter().map(|(id, _)| id.as_str()).collect();
+                assert_eq!(ids, ["a", "b", "c"]);
+            }
+            other => panic!("snapshot not complete: {:?}", other),
+        }
+        assert!(matches!(page(0, &["a"]), SnapshotPage::Unrequested));
+        assert!(!bootstrap.start("erin", Uuid::new_v4(), later));
+    }
+}
@@ -1,4 +1,170 @@
-//! Message codec for framing
+//! Payload codecs for protocol messages
+//!
+//! [`JsonCodec`] is what every node has always sent and stays the default.
+//! [`BincodeCodec`] produces a much smaller encoding for storage and for
+//! bandwidth-heavy traffic. Bincode is not self-describing, so it can only
+//! decode types whose layout is fixed. The protocol structs such as
+//! [`CreditTransfer`](crate::CreditTransfer) qualify, and so do the envelope
+//! enums ([`CreditMessage`](crate::CreditMessage) and friends): they carry an
+//! internal `type` tag only in JSON and are keyed by variant index in
+//! bincode.
+//!
+//! [`PayloadCodec`] picks one of the two from configuration. Its bincode
+//! output starts with a format byte; a JSON document always starts with `{`,
+//! so [`PayloadCodec::decode`] accepts either regardless of which codec the
+//! sender was configured with.
 
-/// Placeholder for message codec implementation
-pub struct MessageCodec;
+use mycelial_core::{MycelialError, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Leading byte of bincode payloads encoded by [`PayloadCodec`]
+const FORMAT_BINCODE: u8 = 0x02;
+
+/// Encodes and decodes protocol messages
+pub trait MessageCodec {
+    /// Encode a message to bytes
+    fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>>;
+
+    /// Decode a message from bytes
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// JSON, readable and accepted by every node
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl MessageCodec for JsonCodec {
+    fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(message)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Bincode, compact but limited to types with a fixed layout
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl MessageCodec for BincodeCodec {
+    fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>> {
+        bincode::serialize(message).map_err(|e| MycelialError::Serialization(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        bincode::deserialize(bytes).map_err(|e| MycelialError::Serialization(e.to_string()))
+    }
+}
+
+/// Codec chosen by a node's configuration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadCodec {
+    /// Plain JSON, sent as-is
+    #[default]
+    Json,
+    /// Bincode, prefixed with a format byte
+    Bincode,
+}
+
+impl MessageCodec for PayloadCodec {
+    fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>> {
+        match self {
+            PayloadCodec::Json => JsonCodec.encode(message),
+            PayloadCodec::Bincode => {
+                let encoded = BincodeCodec.encode(message)?;
+                let mut data = Vec::with_capacity(encoded.len() + 1);
+                data.push(FORMAT_BINCODE);
+                data.extend_from_slice(&encoded);
+                Ok(data)
+            }
+        }
+    }
+
+    /// Decode either format, whichever codec this node encodes with
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match bytes.split_first() {
+            Some((&FORMAT_BINCODE, encoded)) => BincodeCodec.decode(encoded),
+            _ => JsonCodec.decode(bytes),
+        }
+    }
+}
+
+impl std::str::FromStr for PayloadCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(PayloadCodec::Json),
+            "bincode" => Ok(PayloadCodec::Bincode),
+            other => Err(format!("unknown payload codec '{}', expected json or bincode", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CreditMessage, CreditTransfer};
+    use uuid::Uuid;
+
+    fn sample_transfer() -> CreditTransfer {
+        CreditTransfer::new(Uuid::new_v4(), "alice".to_string(), "bob".to_string(), 12.5)
+            .with_memo("compute for batch 7")
+    }
+
+    fn assert_same(decoded: &CreditTransfer, original: &CreditTransfer) {
+        assert_eq!(decoded.id, original.id);
+        assert_eq!(decoded.line_id, original.line_id);
+        assert_eq!(decoded.from, original.from);
+        assert_eq!(decoded.to, original.to);
+        assert_eq!(decoded.amount, original.amount);
+        assert_eq!(decoded.memo, original.memo);
+        assert_eq!(decoded.timestamp, original.timestamp);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let transfer = sample_transfer();
+        let bytes = JsonCodec.encode(&transfer).unwrap();
+        assert_same(&JsonCodec.decode(&bytes).unwrap(), &transfer);
+
+        let envelope = CreditMessage::Transfer(transfer);
+        let bytes = JsonCodec.encode(&envelope).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["type"], "transfer");
+        assert!(matches!(JsonCodec.decode(&bytes).unwrap(), CreditMessage::Transfer(_)));
+    }
+
+    #[test]
+    fn test_bincode_round_trip() {
+        let transfer = sample_transfer();
+        let bytes = BincodeCodec.encode(&transfer).unwrap();
+        assert_same(&BincodeCodec.decode(&bytes).unwrap(), &transfer);
+        assert!(bytes.len() < JsonCodec.encode(&transfer).unwrap().len());
+
+        let envelope = CreditMessage::Transfer(transfer.clone());
+        let bytes = BincodeCodec.encode(&envelope).unwrap();
+        match BincodeCodec.decode(&bytes).unwrap() {
+            CreditMessage::Transfer(decoded) => assert_same(&decoded, &transfer),
+            other => panic!("Wrong variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_payload_codec_reads_both_formats() {
+        let transfer = sample_transfer();
+        let json = PayloadCodec::Json.encode(&transfer).unwrap();
+        let bincode = PayloadCodec::Bincode.encode(&transfer).unwrap();
+        assert_eq!(bincode[0], FORMAT_BINCODE);
+
+        for codec in [PayloadCodec::Json, PayloadCodec::Bincode] {
+            assert_same(&codec.decode(&json).unwrap(), &transfer);
+            assert_same(&codec.decode(&bincode).unwrap(), &transfer);
+        }
+
+        assert_eq!("Bincode".parse::<PayloadCodec>(), Ok(PayloadCodec::Bincode));
+        assert!("cbor".parse::<PayloadCodec>().is_err());
+    }
+}
@@ -1,6 +1,8 @@
 //! Mycelial Protocol - Message serialization and protocol definitions
 //!
 //! This crate handles the serialization and deserialization of network messages.
+//! Protocol payloads can be encoded as JSON or, more compactly, with bincode;
+//! see [`codec`].
 //!
 //! # Economics Protocol Messages
 //!
@@ -22,6 +24,8 @@
 pub mod codec;
 pub mod messages;
 
+pub use codec::{BincodeCodec, JsonCodec, MessageCodec, PayloadCodec};
+
 // Re-export message types for convenience
 pub use messages::{
     // Topics
@@ -19,13 +19,74 @@ pub mod topics {
     pub const RESOURCE: &str = "/mycelial/1.0.0/resource";
 }
 
+/// Serde for the envelope enums
+///
+/// Self-describing formats such as JSON get the variant name in a `type`
+/// field next to the payload's own fields, as every node has always sent.
+/// Bincode cannot decode that (it needs to know the layout up front), so
+/// compact formats get a plain enum keyed by variant index instead.
+macro_rules! envelope_serde {
+    ($envelope:ident { $($variant:ident($payload:ty)),+ $(,)? }) => {
+        impl Serialize for $envelope {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                #[derive(Serialize)]
+                #[serde(tag = "type", rename_all = "snake_case")]
+                enum Tagged<'a> {
+                    $($variant(&'a $payload)),+
+                }
+
+                #[derive(Serialize)]
+                enum Compact<'a> {
+                    $($variant(&'a $payload)),+
+                }
+
+                if serializer.is_human_readable() {
+                    match self {
+                        $($envelope::$variant(payload) => Tagged::$variant(payload)),+
+                    }
+                    .serialize(serializer)
+                } else {
+                    match self {
+                        $($envelope::$variant(payload) => Compact::$variant(payload)),+
+                    }
+                    .serialize(serializer)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $envelope {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #[derive(Deserialize)]
+                #[serde(tag = "type", rename_all = "snake_case")]
+                enum Tagged {
+                    $($variant($payload)),+
+                }
+
+                #[derive(Deserialize)]
+                enum Compact {
+                    $($variant($payload)),+
+                }
+
+                if deserializer.is_human_readable() {
+                    Ok(match Tagged::deserialize(deserializer)? {
+                        $(Tagged::$variant(payload) => $envelope::$variant(payload)),+
+                    })
+                } else {
+                    Ok(match Compact::deserialize(deserializer)? {
+                        $(Compact::$variant(payload) => $envelope::$variant(payload)),+
+                    })
+                }
+            }
+        }
+    };
+}
+
 // ============================================================================
 // VOUCH PROTOCOL MESSAGES
 // ============================================================================
 
 /// Messages for the vouch/reputation protocol
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[derive(Debug, Clone)]
 pub enum VouchMessage {
     /// Request to vouch for a peer
     VouchRequest(VouchRequest),
@@ -35,6 +96,12 @@ pub enum VouchMessage {
     ReputationUpdate(ReputationUpdate),
 }
 
+envelope_serde!(VouchMessage {
+    VouchRequest(VouchRequest),
+    VouchAck(VouchAck),
+    ReputationUpdate(ReputationUpdate),
+});
+
 /// A vouch request from one peer to another
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VouchRequest {
@@ -136,8 +203,7 @@ pub enum ReputationChangeReason {
 // ============================================================================
 
 /// Messages for the mutual credit protocol
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[derive(Debug, Clone)]
 pub enum CreditMessage {
     /// Create a new credit line
     CreateLine(CreateCreditLine),
@@ -151,6 +217,14 @@ pub enum CreditMessage {
     LineUpdate(CreditLineUpdate),
 }
 
+envelope_serde!(CreditMessage {
+    CreateLine(CreateCreditLine),
+    LineAck(CreditLineAck),
+    Transfer(CreditTransfer),
+    TransferAck(CreditTransferAck),
+    LineUpdate(CreditLineUpdate),
+});
+
 /// Request to create a credit line
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateCreditLine {
@@ -277,8 +351,7 @@ pub struct CreditLineUpdate {
 // ============================================================================
 
 /// Messages for the governance protocol
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[derive(Debug, Clone)]
 pub enum GovernanceMessage {
     /// Create a new proposal
     CreateProposal(CreateProposal),
@@ -290,6 +363,13 @@ pub enum GovernanceMessage {
     ProposalExecuted(ProposalExecuted),
 }
 
+envelope_serde!(GovernanceMessage {
+    CreateProposal(CreateProposal),
+    CastVote(CastVote),
+    ProposalUpdate(ProposalUpdate),
+    ProposalExecuted(ProposalExecuted),
+});
+
 /// Create a new governance proposal
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateProposal {
@@ -480,8 +560,7 @@ pub struct ProposalExecuted {
 // ============================================================================
 
 /// Messages for the resource sharing protocol
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[derive(Debug, Clone)]
 pub enum ResourceMessage {
     /// Report resource contribution
     Contribution(ResourceContribution),
@@ -491,6 +570,12 @@ pub enum ResourceMessage {
     PoolUpdate(ResourcePoolUpdate),
 }
 
+envelope_serde!(ResourceMessage {
+    Contribution(ResourceContribution),
+    Metrics(ResourceMetrics),
+    PoolUpdate(ResourcePoolUpdate),
+});
+
 /// Report of resource contribution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceContribution {
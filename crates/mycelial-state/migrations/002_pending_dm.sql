@@ -0,0 +1,15 @@
+-- Store-and-forward queue for direct messages to offline peers
+-- Version: 002
+
+-- Pending direct messages: held until the recipient reconnects or the TTL passes
+CREATE TABLE IF NOT EXISTS pending_dm (
+    id TEXT PRIMARY KEY,
+    recipient_peer_id TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    queued_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_dm_recipient ON pending_dm(recipient_peer_id);
+CREATE INDEX IF NOT EXISTS idx_pending_dm_expires ON pending_dm(expires_at);
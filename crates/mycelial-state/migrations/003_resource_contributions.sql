@@ -0,0 +1,15 @@
+-- Resource contribution ledger for the leaderboard
+-- Version: 003
+
+-- One row per reported contribution; totals are summed at query time so
+-- contributions accumulate instead of overwriting each other
+CREATE TABLE IF NOT EXISTS resource_contributions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    peer_id TEXT NOT NULL,
+    resource_type TEXT NOT NULL,
+    amount REAL NOT NULL,
+    recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_resource_contributions_type_peer
+    ON resource_contributions(resource_type, peer_id);
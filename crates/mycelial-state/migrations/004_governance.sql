@@ -0,0 +1,35 @@
+-- Governance proposals and votes
+-- Version: 004
+
+-- Proposals: status stays 'open' until the deadline, when the final tally
+-- is frozen into the row
+CREATE TABLE IF NOT EXISTS proposals (
+    id TEXT PRIMARY KEY,
+    proposer_peer_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    proposal_type_json TEXT NOT NULL,
+    quorum REAL NOT NULL,
+    threshold REAL NOT NULL,
+    deadline INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    votes_for REAL,
+    votes_against REAL,
+    votes_abstain REAL,
+    voter_count INTEGER,
+    eligible_weight REAL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proposals_status_deadline ON proposals(status, deadline);
+
+-- Votes: one row per voter, holding their latest vote
+CREATE TABLE IF NOT EXISTS proposal_votes (
+    proposal_id TEXT NOT NULL,
+    voter_peer_id TEXT NOT NULL,
+    vote TEXT NOT NULL,
+    reason TEXT,
+    timestamp INTEGER NOT NULL,
+    PRIMARY KEY (proposal_id, voter_peer_id),
+    FOREIGN KEY (proposal_id) REFERENCES proposals(id) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+-- Vouches between peers
+-- Version: 005
+
+-- A vouch stays 'pending' until the vouchee acknowledges it as 'accepted'
+-- or 'rejected'; only accepted, unexpired vouches carry trust
+CREATE TABLE IF NOT EXISTS vouches (
+    id TEXT PRIMARY KEY,
+    voucher_peer_id TEXT NOT NULL,
+    vouchee_peer_id TEXT NOT NULL,
+    stake REAL NOT NULL,
+    message TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_vouches_voucher ON vouches(voucher_peer_id, status);
@@ -0,0 +1,14 @@
+-- Operator-assigned peer tags
+-- Version: 006
+
+-- Free-form labels such as 'bootstrap' or 'friend'; a peer can carry many,
+-- and they go with the peer when it's deleted
+CREATE TABLE IF NOT EXISTS peer_tags (
+    peer_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    PRIMARY KEY (peer_id, tag),
+    FOREIGN KEY (peer_id) REFERENCES peers(peer_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_peer_tags_tag ON peer_tags(tag);
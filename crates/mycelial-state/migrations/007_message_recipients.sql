@@ -0,0 +1,6 @@
+-- Group direct messages
+-- Version: 007
+
+-- JSON array of recipient peer ids for messages sent to several peers at
+-- once; NULL for broadcasts and single-recipient messages
+ALTER TABLE messages ADD COLUMN recipients_json TEXT;
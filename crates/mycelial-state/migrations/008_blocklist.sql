@@ -0,0 +1,11 @@
+-- Blocked peers
+-- Version: 008
+
+-- Peers whose connections are refused and whose messages are dropped. Not
+-- tied to the peers table: unknown peers can be blocked, and a block outlives
+-- deleting the peer's data
+CREATE TABLE IF NOT EXISTS blocklist (
+    peer_id TEXT PRIMARY KEY,
+    reason TEXT,
+    blocked_at INTEGER NOT NULL
+);
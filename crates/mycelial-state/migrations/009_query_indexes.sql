@@ -0,0 +1,15 @@
+-- Indexes matching the common message and credit queries
+-- Version: 009
+
+-- Messages are listed per sender or per type, newest first. Composite
+-- indexes with the timestamp return them in order without a sort step, and
+-- make the single-column indexes from 001 redundant.
+CREATE INDEX IF NOT EXISTS idx_messages_sender_timestamp ON messages(sender_peer_id, timestamp);
+CREATE INDEX IF NOT EXISTS idx_messages_type_timestamp ON messages(message_type, timestamp);
+DROP INDEX IF EXISTS idx_messages_sender;
+DROP INDEX IF EXISTS idx_messages_type;
+
+-- Active credit lines are listed by, and deactivated on, last transaction
+CREATE INDEX IF NOT EXISTS idx_credit_active_last_transaction
+    ON credit_relationships(active, last_transaction);
+DROP INDEX IF EXISTS idx_credit_active;
@@ -0,0 +1,18 @@
+-- Per-node credit tallies
+-- Version: 010
+
+-- A credit balance kept as a PN-counter: every node that records transfers
+-- on a relationship has its own running totals of increments and
+-- decrements, and the balance is the sum of increments minus decrements
+-- over all nodes. Tallies only grow, so merging takes the larger of each.
+-- They go with the relationship when it's deleted
+CREATE TABLE IF NOT EXISTS credit_tallies (
+    creditor_peer_id TEXT NOT NULL,
+    debtor_peer_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    increments REAL NOT NULL DEFAULT 0.0,
+    decrements REAL NOT NULL DEFAULT 0.0,
+    PRIMARY KEY (creditor_peer_id, debtor_peer_id, node_id),
+    FOREIGN KEY (creditor_peer_id, debtor_peer_id)
+        REFERENCES credit_relationships(creditor_peer_id, debtor_peer_id) ON DELETE CASCADE
+);
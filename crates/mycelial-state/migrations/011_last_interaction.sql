@@ -0,0 +1,8 @@
+-- Time of each peer's last interaction
+-- Version: 011
+
+-- Reputation ages from when its interaction counters last changed. The
+-- row's updated_at moves on every write (last seen, name, addresses), so a
+-- peer that keeps reconnecting would never age.
+ALTER TABLE peers ADD COLUMN last_interaction_at INTEGER;
+UPDATE peers SET last_interaction_at = updated_at;
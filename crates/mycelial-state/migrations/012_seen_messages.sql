@@ -0,0 +1,11 @@
+-- Seen message IDs
+-- Version: 012
+
+-- IDs of messages the node accepted, so replay protection survives a
+-- restart. Rows older than the replay window are pruned.
+CREATE TABLE IF NOT EXISTS seen_messages (
+    id TEXT PRIMARY KEY,
+    seen_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_seen_messages_seen_at ON seen_messages(seen_at);
@@ -0,0 +1,17 @@
+-- Opening balances of tallied credit relationships
+-- Version: 013
+
+-- The balance a relationship had when its first transfer was tallied. It
+-- is a single last-write-wins value, newest timestamp first and ties going
+-- to the greater origin, rather than a tally of its own. Timestamps are in
+-- nanoseconds so the stamp survives a round trip exactly.
+CREATE TABLE IF NOT EXISTS credit_opening_balances (
+    creditor_peer_id TEXT NOT NULL,
+    debtor_peer_id TEXT NOT NULL,
+    amount REAL NOT NULL,
+    timestamp_ns INTEGER NOT NULL,
+    origin TEXT NOT NULL,
+    PRIMARY KEY (creditor_peer_id, debtor_peer_id),
+    FOREIGN KEY (creditor_peer_id, debtor_peer_id)
+        REFERENCES credit_relationships(creditor_peer_id, debtor_peer_id) ON DELETE CASCADE
+);
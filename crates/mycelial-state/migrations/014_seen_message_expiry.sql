@@ -0,0 +1,10 @@
+-- Expiry of seen message IDs
+-- Version: 014
+
+-- Direct messages to the node are remembered for a day, everything else
+-- only for the maximum message age, so each ID records when it may be
+-- forgotten. IDs stored before this were all kept for a day.
+ALTER TABLE seen_messages ADD COLUMN expires_at INTEGER NOT NULL DEFAULT 0;
+UPDATE seen_messages SET expires_at = seen_at + 86400;
+
+CREATE INDEX IF NOT EXISTS idx_seen_messages_expires_at ON seen_messages(expires_at);
@@ -0,0 +1,90 @@
+//! Peer blocklist
+//!
+//! Blocking is the hard counterpart to tagging a peer "blocked": the node
+//! refuses its connections and drops its messages. Blocks are kept
+//! separately from the peer's own data, so they survive
+//! [`SqliteStore::delete_peer`].
+
+use chrono::Utc;
+use sqlx::Row;
+use tracing::info;
+
+use crate::error::Result;
+use crate::storage::SqliteStore;
+
+impl SqliteStore {
+    /// Block a peer, returns false if it was already blocked
+    ///
+    /// Blocking again updates the reason.
+    pub async fn block_peer(&self, peer_id: &str, reason: Option<&str>) -> Result<bool> {
+        let already_blocked = self.is_blocked(peer_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO blocklist (peer_id, reason, blocked_at) VALUES (?, ?, ?)
+            ON CONFLICT(peer_id) DO UPDATE SET reason = excluded.reason
+            "#,
+        )
+        .bind(peer_id)
+        .bind(reason)
+        .bind(Utc::now().timestamp())
+        .execute(self.pool())
+        .await?;
+
+        if !already_blocked {
+            info!("Blocked peer {} ({})", peer_id, reason.unwrap_or("no reason given"));
+        }
+        Ok(!already_blocked)
+    }
+
+    /// Lift a block, returns false if the peer wasn't blocked
+    pub async fn unblock_peer(&self, peer_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM blocklist WHERE peer_id = ?")
+            .bind(peer_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether a peer is blocked
+    pub async fn is_blocked(&self, peer_id: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM blocklist WHERE peer_id = ?")
+            .bind(peer_id)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Every blocked peer ID, oldest block first
+    pub async fn blocked_peers(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT peer_id FROM blocklist ORDER BY blocked_at, peer_id")
+            .fetch_all(self.pool())
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get("peer_id")).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_block_and_unblock() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        assert!(!store.is_blocked("mallory").await.unwrap());
+
+        // Unknown peers can be blocked
+        assert!(store.block_peer("mallory", Some("spam")).await.unwrap());
+        assert!(!store.block_peer("mallory", Some("more spam")).await.unwrap());
+        assert!(store.block_peer("trudy", None).await.unwrap());
+        assert!(store.is_blocked("mallory").await.unwrap());
+        assert_eq!(store.blocked_peers().await.unwrap().len(), 2);
+
+        assert!(store.unblock_peer("mallory").await.unwrap());
+        assert!(!store.unblock_peer("mallory").await.unwrap());
+        assert!(!store.is_blocked("mallory").await.unwrap());
+        assert_eq!(store.blocked_peers().await.unwrap(), vec!["trudy"]);
+    }
+}
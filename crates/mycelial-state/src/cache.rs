@@ -1,7 +1,8 @@
 //! In-memory caching layer
 //!
 //! This module provides LRU caching for frequently accessed data like
-//! peer information, messages, and credit relationships.
+//! peer information, messages, and credit relationships. Caches can instead
+//! evict the least frequently used entry, see [`EvictionPolicy`].
 
 use lru::LruCache;
 use mycelial_core::{
@@ -10,13 +11,77 @@ use mycelial_core::{
     peer::PeerInfo,
     reputation::Reputation,
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
 use uuid::Uuid;
 
-/// Generic LRU cache for frequently accessed data
+use crate::error::Result;
+use crate::storage::SqliteStore;
+
+/// Hit, miss and eviction counters for a cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    /// Lookups that found a value
+    pub hits: u64,
+    /// Lookups that found nothing
+    pub misses: u64,
+    /// Entries dropped to make room for new ones
+    pub evictions: u64,
+    /// Fraction of lookups that were hits (0.0 when there were none)
+    pub hit_rate: f64,
+}
+
+impl CacheMetrics {
+    fn new(hits: u64, misses: u64, evictions: u64) -> Self {
+        let lookups = hits + misses;
+        let hit_rate = if lookups == 0 {
+            0.0
+        } else {
+            hits as f64 / lookups as f64
+        };
+        Self {
+            hits,
+            misses,
+            evictions,
+            hit_rate,
+        }
+    }
+}
+
+/// Which entry a full cache drops to make room for a new one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Least recently used
+    #[default]
+    Lru,
+    /// Least frequently used, the least recently used of those on a tie
+    ///
+    /// Keeps entries that are read often even when a burst of one-off
+    /// entries passes through, at the cost of a scan over the cache on
+    /// every eviction.
+    Lfu,
+}
+
+/// Called with each entry the eviction policy evicts
+pub type EvictCallback<K, V> = Box<dyn Fn(&K, &V) + Send + Sync>;
+
+/// Generic cache for frequently accessed data, LRU unless configured otherwise
 pub struct MemoryCache<K, V> {
     cache: RwLock<LruCache<K, V>>,
+    policy: EvictionPolicy,
+    /// Accesses per cached key, only tracked under [`EvictionPolicy::Lfu`]
+    frequencies: Mutex<HashMap<K, u64>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    on_evict: Option<EvictCallback<K, V>>,
 }
 
 impl<K: std::hash::Hash + Eq + Clone, V: Clone> MemoryCache<K, V> {
@@ -25,27 +90,140 @@ impl<K: std::hash::Hash + Eq + Clone, V: Clone> MemoryCache<K, V> {
         let cap = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::new(100).unwrap());
         Self {
             cache: RwLock::new(LruCache::new(cap)),
+            policy: EvictionPolicy::Lru,
+            frequencies: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            on_evict: None,
+        }
+    }
+
+    /// Run `callback` for every entry evicted to make room for another
+    ///
+    /// Only capacity evictions trigger it, not explicit removal or
+    /// replacing a key's value. It runs after the cache lock is released, so
+    /// it may use the cache.
+    pub fn with_on_evict(mut self, callback: impl Fn(&K, &V) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Box::new(callback));
+        self
+    }
+
+    /// Choose which entry is evicted when the cache is full
+    ///
+    /// Meant to be set at construction; entries already cached start with
+    /// no recorded accesses.
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self.frequencies.get_mut().clear();
+        self
+    }
+
+    /// Eviction policy in use
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Count an access to `key` towards LFU eviction
+    fn touch(&self, key: &K) {
+        if self.policy == EvictionPolicy::Lfu {
+            *self.frequencies.lock().entry(key.clone()).or_insert(0) += 1;
         }
     }
 
+    /// Stop tracking accesses to a key that left the cache
+    fn forget(&self, key: &K) {
+        if self.policy == EvictionPolicy::Lfu {
+            self.frequencies.lock().remove(key);
+        }
+    }
+
+    /// Pop the least frequently used entry, the least recently used on a tie
+    fn pop_least_frequent(&self, cache: &mut LruCache<K, V>) -> Option<(K, V)> {
+        let mut frequencies = self.frequencies.lock();
+        // Iterating from least to most recently used, `min_by_key` keeps the
+        // first of equally frequent keys
+        let victim = cache
+            .iter()
+            .rev()
+            .min_by_key(|(key, _)| frequencies.get(*key).copied().unwrap_or(0))
+            .map(|(key, _)| key.clone())?;
+        frequencies.remove(&victim);
+        cache.pop_entry(&victim)
+    }
+
     /// Get a value from the cache
     pub fn get(&self, key: &K) -> Option<V> {
-        self.cache.write().get(key).cloned()
+        let value = self.cache.write().get(key).cloned();
+        if value.is_some() {
+            self.touch(key);
+        }
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Get a value only if it passes `is_valid`, removing it otherwise
+    ///
+    /// A rejected value counts as a miss.
+    pub fn get_if(&self, key: &K, is_valid: impl Fn(&V) -> bool) -> Option<V> {
+        let mut cache = self.cache.write();
+        let value = match cache.get(key).cloned() {
+            Some(value) if !is_valid(&value) => {
+                cache.pop(key);
+                self.forget(key);
+                None
+            }
+            Some(value) => {
+                self.touch(key);
+                Some(value)
+            }
+            None => None,
+        };
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
     }
 
-    /// Peek a value without updating LRU order
+    /// Peek a value without counting it as an access
     pub fn peek(&self, key: &K) -> Option<V> {
         self.cache.read().peek(key).cloned()
     }
 
     /// Insert a value into the cache
     pub fn insert(&self, key: K, value: V) {
-        self.cache.write().put(key, value);
+        let evicted = {
+            let mut cache = self.cache.write();
+            // Under LFU make room first, so `push` never picks the victim
+            let lfu_victim = if self.policy == EvictionPolicy::Lfu
+                && !cache.contains(&key)
+                && cache.len() >= cache.cap().get()
+            {
+                self.pop_least_frequent(&mut cache)
+            } else {
+                None
+            };
+            // `push` hands back the displaced entry: the old value when the key
+            // was already present, otherwise the least recently used entry
+            let displaced = cache
+                .push(key.clone(), value)
+                .filter(|(old_key, _)| *old_key != key);
+            self.touch(&key);
+            lfu_victim.or(displaced)
+        };
+        if let Some((old_key, old_value)) = evicted {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            if let Some(on_evict) = &self.on_evict {
+                on_evict(&old_key, &old_value);
+            }
+        }
     }
 
     /// Remove a value from the cache
     pub fn remove(&self, key: &K) -> Option<V> {
-        self.cache.write().pop(key)
+        let value = self.cache.write().pop(key);
+        self.forget(key);
+        value
     }
 
     /// Check if key exists
@@ -63,20 +241,48 @@ impl<K: std::hash::Hash + Eq + Clone, V: Clone> MemoryCache<K, V> {
         self.cache.read().is_empty()
     }
 
+    /// Maximum number of entries held
+    pub fn capacity(&self) -> usize {
+        self.cache.read().cap().get()
+    }
+
     /// Clear all entries
     pub fn clear(&self) {
-        self.cache.write().clear();
+        let mut cache = self.cache.write();
+        cache.clear();
+        self.frequencies.lock().clear();
     }
 
     /// Get all keys
     pub fn keys(&self) -> Vec<K> {
         self.cache.read().iter().map(|(k, _)| k.clone()).collect()
     }
+
+    /// Get hit, miss and eviction counters
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics::new(
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+            self.evictions.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// A cached peer with the time it was inserted
+#[derive(Clone)]
+struct PeerEntry {
+    info: PeerInfo,
+    reputation: Reputation,
+    inserted_at: Instant,
 }
 
 /// Specialized cache for peer information with reputation
+///
+/// With a TTL, entries older than the TTL are treated as absent on lookup
+/// and dropped lazily; `purge_expired` removes them eagerly.
 pub struct PeerCache {
-    peers: MemoryCache<String, (PeerInfo, Reputation)>,
+    peers: MemoryCache<String, PeerEntry>,
+    ttl: Option<Duration>,
 }
 
 impl PeerCache {
@@ -84,33 +290,85 @@ impl PeerCache {
     pub fn new(capacity: usize) -> Self {
         Self {
             peers: MemoryCache::new(capacity),
+            ttl: None,
+        }
+    }
+
+    /// Create a peer cache whose entries expire after `ttl`
+    pub fn new_with_ttl(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            peers: MemoryCache::new(capacity),
+            ttl: Some(ttl),
         }
     }
 
+    /// Choose which peer is evicted when the cache is full
+    ///
+    /// [`EvictionPolicy::Lfu`] keeps peers that are looked up often cached
+    /// while many peers are seen once and never again.
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.peers = self.peers.with_eviction_policy(policy);
+        self
+    }
+
+    /// Check whether an entry has outlived the TTL
+    fn is_expired(&self, entry: &PeerEntry) -> bool {
+        self.ttl
+            .map(|ttl| entry.inserted_at.elapsed() >= ttl)
+            .unwrap_or(false)
+    }
+
+    /// Get a live entry, dropping it if expired
+    fn get_entry(&self, peer_id: &str) -> Option<PeerEntry> {
+        self.peers
+            .get_if(&peer_id.to_string(), |entry| !self.is_expired(entry))
+    }
+
+    /// Maximum number of peers held
+    pub fn capacity(&self) -> usize {
+        self.peers.capacity()
+    }
+
     /// Get peer info and reputation
     pub fn get(&self, peer_id: &str) -> Option<(PeerInfo, Reputation)> {
-        self.peers.get(&peer_id.to_string())
+        self.get_entry(peer_id)
+            .map(|entry| (entry.info, entry.reputation))
     }
 
     /// Get only peer info
     pub fn get_peer_info(&self, peer_id: &str) -> Option<PeerInfo> {
-        self.peers.get(&peer_id.to_string()).map(|(info, _)| info)
+        self.get_entry(peer_id).map(|entry| entry.info)
     }
 
     /// Get only reputation
     pub fn get_reputation(&self, peer_id: &str) -> Option<Reputation> {
-        self.peers.get(&peer_id.to_string()).map(|(_, rep)| rep)
+        self.get_entry(peer_id).map(|entry| entry.reputation)
     }
 
     /// Insert or update peer
     pub fn insert(&self, peer_info: PeerInfo, reputation: Reputation) {
-        self.peers.insert(peer_info.id.as_str().to_string(), (peer_info, reputation));
+        self.peers.insert(
+            peer_info.id.as_str().to_string(),
+            PeerEntry {
+                info: peer_info,
+                reputation,
+                inserted_at: Instant::now(),
+            },
+        );
     }
 
     /// Update reputation for existing peer
+    ///
+    /// Keeps the original insertion time, so this does not extend the TTL.
     pub fn update_reputation(&self, peer_id: &str, reputation: Reputation) -> bool {
-        if let Some((info, _)) = self.peers.get(&peer_id.to_string()) {
-            self.peers.insert(peer_id.to_string(), (info, reputation));
+        if let Some(entry) = self.get_entry(peer_id) {
+            self.peers.insert(
+                peer_id.to_string(),
+                PeerEntry {
+                    reputation,
+                    ..entry
+                },
+            );
             true
         } else {
             false
@@ -119,20 +377,32 @@ impl PeerCache {
 
     /// Remove peer from cache
     pub fn remove(&self, peer_id: &str) -> Option<(PeerInfo, Reputation)> {
-        self.peers.remove(&peer_id.to_string())
+        self.peers
+            .remove(&peer_id.to_string())
+            .map(|entry| (entry.info, entry.reputation))
     }
 
     /// Check if peer is cached
     pub fn contains(&self, peer_id: &str) -> bool {
-        self.peers.contains(&peer_id.to_string())
+        self.peers
+            .peek(&peer_id.to_string())
+            .map(|entry| !self.is_expired(&entry))
+            .unwrap_or(false)
     }
 
     /// Get all cached peer IDs
     pub fn peer_ids(&self) -> Vec<String> {
-        self.peers.keys()
+        let cache = self.peers.cache.read();
+        cache
+            .iter()
+            .filter(|(_, entry)| !self.is_expired(entry))
+            .map(|(id, _)| id.clone())
+            .collect()
     }
 
     /// Get cache size
+    ///
+    /// Includes expired entries that have not been purged yet.
     pub fn len(&self) -> usize {
         self.peers.len()
     }
@@ -147,13 +417,38 @@ impl PeerCache {
         self.peers.clear();
     }
 
+    /// Remove all expired entries, returning how many were dropped
+    pub fn purge_expired(&self) -> usize {
+        if self.ttl.is_none() {
+            return 0;
+        }
+
+        let expired: Vec<String> = self
+            .peers
+            .cache
+            .read()
+            .iter()
+            .filter(|(_, entry)| self.is_expired(entry))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            self.peers.remove(id);
+        }
+        expired.len()
+    }
+
+    /// Get cache metrics
+    pub fn metrics(&self) -> CacheMetrics {
+        self.peers.metrics()
+    }
+
     /// Get trusted peers (reputation >= threshold)
     pub fn get_trusted(&self, threshold: f64) -> Vec<(PeerInfo, Reputation)> {
         let cache = self.peers.cache.read();
         cache
             .iter()
-            .filter(|(_, (_, rep))| rep.score >= threshold)
-            .map(|(_, v)| v.clone())
+            .filter(|(_, entry)| entry.reputation.score >= threshold && !self.is_expired(entry))
+            .map(|(_, entry)| (entry.info.clone(), entry.reputation.clone()))
             .collect()
     }
 }
@@ -164,20 +459,46 @@ impl Default for PeerCache {
     }
 }
 
+/// Secondary index from a peer to the ids of its cached entries
+type PeerIndex = Arc<RwLock<HashMap<String, Vec<String>>>>;
+
+/// Drop `id` from a peer's index entry, and the entry once it's empty
+fn unindex(index: &mut HashMap<String, Vec<String>>, peer: &str, id: &str) {
+    if let Some(ids) = index.get_mut(peer) {
+        ids.retain(|i| i != id);
+        if ids.is_empty() {
+            index.remove(peer);
+        }
+    }
+}
+
 /// Specialized cache for messages
 pub struct MessageCache {
     messages: MemoryCache<String, Message>,
-    /// Index of messages by sender
-    by_sender: RwLock<std::collections::HashMap<String, Vec<String>>>,
+    /// Index of messages by sender, pruned as messages are evicted
+    by_sender: PeerIndex,
 }
 
 impl MessageCache {
     /// Create a new message cache with the given capacity
     pub fn new(capacity: usize) -> Self {
-        Self {
-            messages: MemoryCache::new(capacity),
-            by_sender: RwLock::new(std::collections::HashMap::new()),
-        }
+        let by_sender = PeerIndex::default();
+        let index = by_sender.clone();
+        let messages = MemoryCache::new(capacity).with_on_evict(move |id: &String, message: &Message| {
+            unindex(&mut index.write(), message.sender.as_str(), id);
+        });
+        Self { messages, by_sender }
+    }
+
+    /// Choose which message is evicted when the cache is full
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.messages = self.messages.with_eviction_policy(policy);
+        self
+    }
+
+    /// Maximum number of messages held
+    pub fn capacity(&self) -> usize {
+        self.messages.capacity()
     }
 
     /// Get a message by ID
@@ -196,7 +517,7 @@ impl MessageCache {
         let mut by_sender = self.by_sender.write();
         by_sender
             .entry(sender)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(id);
     }
 
@@ -204,10 +525,7 @@ impl MessageCache {
     pub fn remove(&self, id: &Uuid) -> Option<Message> {
         if let Some(msg) = self.messages.remove(&id.to_string()) {
             // Update sender index
-            let mut by_sender = self.by_sender.write();
-            if let Some(ids) = by_sender.get_mut(msg.sender.as_str()) {
-                ids.retain(|i| i != &id.to_string());
-            }
+            unindex(&mut self.by_sender.write(), msg.sender.as_str(), &id.to_string());
             Some(msg)
         } else {
             None
@@ -246,6 +564,11 @@ impl MessageCache {
         self.messages.clear();
         self.by_sender.write().clear();
     }
+
+    /// Get cache metrics
+    pub fn metrics(&self) -> CacheMetrics {
+        self.messages.metrics()
+    }
 }
 
 impl Default for MessageCache {
@@ -257,17 +580,28 @@ impl Default for MessageCache {
 /// Specialized cache for credit relationships
 pub struct CreditCache {
     relationships: MemoryCache<String, CreditRelationship>,
-    /// Index by peer (both creditor and debtor)
-    by_peer: RwLock<std::collections::HashMap<String, Vec<String>>>,
+    /// Index by peer (both creditor and debtor), pruned as relationships are evicted
+    by_peer: PeerIndex,
 }
 
 impl CreditCache {
     /// Create a new credit cache with the given capacity
     pub fn new(capacity: usize) -> Self {
-        Self {
-            relationships: MemoryCache::new(capacity),
-            by_peer: RwLock::new(std::collections::HashMap::new()),
-        }
+        let by_peer = PeerIndex::default();
+        let index = by_peer.clone();
+        let relationships =
+            MemoryCache::new(capacity).with_on_evict(move |id: &String, rel: &CreditRelationship| {
+                let mut index = index.write();
+                unindex(&mut index, rel.creditor.as_str(), id);
+                unindex(&mut index, rel.debtor.as_str(), id);
+            });
+        Self { relationships, by_peer }
+    }
+
+    /// Choose which relationship is evicted when the cache is full
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.relationships = self.relationships.with_eviction_policy(policy);
+        self
     }
 
     /// Generate relationship ID from peers
@@ -275,6 +609,11 @@ impl CreditCache {
         format!("{}_{}", creditor, debtor)
     }
 
+    /// Maximum number of relationships held
+    pub fn capacity(&self) -> usize {
+        self.relationships.capacity()
+    }
+
     /// Get a relationship by ID
     pub fn get(&self, id: &str) -> Option<CreditRelationship> {
         self.relationships.get(&id.to_string())
@@ -294,10 +633,14 @@ impl CreditCache {
 
         self.relationships.insert(id.clone(), relationship);
 
-        // Update peer index
+        // Update peer index (re-inserting the same relationship must not duplicate it)
         let mut by_peer = self.by_peer.write();
-        by_peer.entry(creditor).or_insert_with(Vec::new).push(id.clone());
-        by_peer.entry(debtor).or_insert_with(Vec::new).push(id);
+        for peer in [creditor, debtor] {
+            let ids = by_peer.entry(peer).or_default();
+            if !ids.contains(&id) {
+                ids.push(id.clone());
+            }
+        }
     }
 
     /// Remove a relationship
@@ -305,12 +648,8 @@ impl CreditCache {
         if let Some(rel) = self.relationships.remove(&id.to_string()) {
             // Update peer index
             let mut by_peer = self.by_peer.write();
-            if let Some(ids) = by_peer.get_mut(rel.creditor.as_str()) {
-                ids.retain(|i| i != id);
-            }
-            if let Some(ids) = by_peer.get_mut(rel.debtor.as_str()) {
-                ids.retain(|i| i != id);
-            }
+            unindex(&mut by_peer, rel.creditor.as_str(), id);
+            unindex(&mut by_peer, rel.debtor.as_str(), id);
             Some(rel)
         } else {
             None
@@ -354,6 +693,11 @@ impl CreditCache {
         self.relationships.clear();
         self.by_peer.write().clear();
     }
+
+    /// Get cache metrics
+    pub fn metrics(&self) -> CacheMetrics {
+        self.relationships.metrics()
+    }
 }
 
 impl Default for CreditCache {
@@ -398,12 +742,57 @@ impl StateCache {
         self.credits.clear();
     }
 
+    /// Preload the caches from the store, so early reads don't all miss
+    ///
+    /// Loads the most recently seen peers, the newest messages and the most
+    /// recently used active credit relationships, never more than a cache
+    /// holds. Entries are inserted least valuable first, so the most valuable
+    /// ones end up most recently used and are the last to be evicted.
+    pub async fn warm_from_store(
+        &self,
+        store: &SqliteStore,
+        peer_limit: usize,
+        msg_limit: usize,
+    ) -> Result<WarmedCounts> {
+        let peer_limit = peer_limit.min(self.peers.capacity());
+        let peers = store.list_recent_peers(peer_limit as i64).await?;
+        for (info, reputation) in peers.iter().rev() {
+            self.peers.insert(info.clone(), reputation.clone());
+        }
+
+        let msg_limit = msg_limit.min(self.messages.capacity());
+        let messages = store.list_recent_messages(msg_limit as i64).await?;
+        for message in messages.iter().rev() {
+            self.messages.insert(message.clone());
+        }
+
+        let mut credits = store.list_active_credit_relationships().await?;
+        credits.truncate(self.credits.capacity());
+        for relationship in credits.iter().rev() {
+            self.credits.insert(relationship.clone());
+        }
+
+        let counts = WarmedCounts {
+            peers: peers.len(),
+            messages: messages.len(),
+            credits: credits.len(),
+        };
+        info!(
+            "Warmed caches: {} peers, {} messages, {} credit relationships",
+            counts.peers, counts.messages, counts.credits
+        );
+        Ok(counts)
+    }
+
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
         CacheStats {
             peer_count: self.peers.len(),
             message_count: self.messages.len(),
             credit_count: self.credits.len(),
+            peers: self.peers.metrics(),
+            messages: self.messages.metrics(),
+            credits: self.credits.metrics(),
         }
     }
 }
@@ -414,12 +803,26 @@ impl Default for StateCache {
     }
 }
 
+/// Entries loaded into each cache by [`StateCache::warm_from_store`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WarmedCounts {
+    pub peers: usize,
+    pub messages: usize,
+    pub credits: usize,
+}
+
 /// Statistics about cache usage
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CacheStats {
     pub peer_count: usize,
     pub message_count: usize,
     pub credit_count: usize,
+    /// Peer cache hit/miss/eviction counters
+    pub peers: CacheMetrics,
+    /// Message cache hit/miss/eviction counters
+    pub messages: CacheMetrics,
+    /// Credit cache hit/miss/eviction counters
+    pub credits: CacheMetrics,
 }
 
 #[cfg(test)]
@@ -441,6 +844,82 @@ mod tests {
         assert!(!cache.contains(&"key1".to_string()));
     }
 
+    #[test]
+    fn test_memory_cache_metrics() {
+        let cache: MemoryCache<String, i32> = MemoryCache::new(2);
+
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        // Updating an existing key is not an eviction
+        cache.insert("b".to_string(), 3);
+        assert_eq!(cache.metrics().evictions, 0);
+
+        // Third distinct key pushes out the least recently used one
+        cache.insert("c".to_string(), 4);
+        assert_eq!(cache.metrics().evictions, 1);
+
+        assert_eq!(cache.get(&"a".to_string()), None);
+        assert_eq!(cache.get(&"b".to_string()), Some(3));
+        assert_eq!(cache.get(&"c".to_string()), Some(4));
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.hits, 2);
+        assert_eq!(metrics.misses, 1);
+        assert!((metrics.hit_rate - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_eviction_policies_differ() {
+        let lru: MemoryCache<String, i32> = MemoryCache::new(3);
+        let lfu: MemoryCache<String, i32> =
+            MemoryCache::new(3).with_eviction_policy(EvictionPolicy::Lfu);
+        assert_eq!(lru.eviction_policy(), EvictionPolicy::Lru);
+
+        for cache in [&lru, &lfu] {
+            cache.insert("hot".to_string(), 1);
+            cache.insert("warm".to_string(), 2);
+            cache.insert("cold".to_string(), 3);
+            // "hot" is read often but not lately, "cold" once and last
+            for _ in 0..5 {
+                cache.get(&"hot".to_string());
+            }
+            cache.get(&"warm".to_string());
+            cache.get(&"cold".to_string());
+
+            cache.insert("new".to_string(), 4);
+            assert_eq!(cache.metrics().evictions, 1);
+        }
+
+        // LRU drops the entry untouched the longest, LFU the one read least
+        assert!(!lru.contains(&"hot".to_string()));
+        assert!(lru.contains(&"warm".to_string()));
+        assert!(lfu.contains(&"hot".to_string()));
+        assert!(!lfu.contains(&"warm".to_string()));
+
+        // "cold" and "new" were both accessed twice now; the older one goes
+        lfu.get(&"new".to_string());
+        lfu.insert("newer".to_string(), 5);
+        assert!(!lfu.contains(&"cold".to_string()));
+        assert!(lfu.contains(&"new".to_string()));
+    }
+
+    #[test]
+    fn test_peer_cache_lfu_keeps_hot_peer() {
+        let cache = PeerCache::new(2).with_eviction_policy(EvictionPolicy::Lfu);
+        cache.insert(test_peer("hot"), Reputation::new(0.5));
+        for _ in 0..3 {
+            cache.get("hot");
+        }
+
+        // A stream of peers seen once only ever evicts each other
+        for id in ["a", "b", "c"] {
+            cache.insert(test_peer(id), Reputation::new(0.5));
+        }
+        assert!(cache.contains("hot"));
+        assert!(cache.contains("c"));
+        assert_eq!(cache.len(), 2);
+    }
+
     #[test]
     fn test_peer_cache() {
         let cache = PeerCache::new(10);
@@ -467,6 +946,53 @@ mod tests {
         assert_eq!(trusted.len(), 1);
     }
 
+    fn test_peer(id: &str) -> PeerInfo {
+        PeerInfo {
+            id: PeerId(id.to_string()),
+            public_key: id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_peer_cache_ttl_lazy_expiry() {
+        let cache = PeerCache::new_with_ttl(10, Duration::from_millis(50));
+        cache.insert(test_peer("peer1"), Reputation::new(0.9));
+
+        assert!(cache.contains("peer1"));
+        assert!(cache.get("peer1").is_some());
+        assert_eq!(cache.get_trusted(0.5).len(), 1);
+
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(!cache.contains("peer1"));
+        assert!(cache.get_trusted(0.5).is_empty());
+        assert!(cache.get("peer1").is_none());
+        // The lookup dropped the stale entry
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_peer_cache_purge_expired() {
+        let cache = PeerCache::new_with_ttl(10, Duration::from_millis(50));
+        cache.insert(test_peer("old"), Reputation::default());
+
+        std::thread::sleep(Duration::from_millis(60));
+        cache.insert(test_peer("fresh"), Reputation::default());
+
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.peer_ids(), vec!["fresh".to_string()]);
+
+        // Without a TTL nothing ever expires
+        let cache = PeerCache::new(10);
+        cache.insert(test_peer("peer1"), Reputation::default());
+        assert_eq!(cache.purge_expired(), 0);
+        assert!(cache.contains("peer1"));
+    }
+
     #[test]
     fn test_message_cache() {
         let cache = MessageCache::new(10);
@@ -512,6 +1038,41 @@ mod tests {
         assert_eq!(for_debtor.len(), 1);
     }
 
+    #[test]
+    fn test_credit_cache_eviction_prunes_index() {
+        let cache = CreditCache::new(2);
+        for debtor in ["d1", "d2", "d3", "d4"] {
+            cache.insert(CreditRelationship::new(
+                PeerId("creditor".to_string()),
+                PeerId(debtor.to_string()),
+                10.0,
+            ));
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.metrics().evictions, 2);
+
+        // Only the two surviving relationships are indexed
+        let by_peer = cache.by_peer.read();
+        assert_eq!(by_peer["creditor"], vec!["creditor_d3", "creditor_d4"]);
+        assert!(!by_peer.contains_key("d1"));
+        assert!(!by_peer.contains_key("d2"));
+        assert_eq!(by_peer.len(), 3);
+    }
+
+    #[test]
+    fn test_message_cache_eviction_prunes_index() {
+        let cache = MessageCache::new(1);
+        let first = Message::new(MessageType::Content, PeerId("alice".to_string()), vec![]);
+        let second = Message::new(MessageType::Content, PeerId("bob".to_string()), vec![]);
+        cache.insert(first);
+        cache.insert(second.clone());
+
+        assert!(cache.get_from_sender("alice").is_empty());
+        let by_sender = cache.by_sender.read();
+        assert!(!by_sender.contains_key("alice"));
+        assert_eq!(by_sender["bob"], vec![second.id.to_string()]);
+    }
+
     #[test]
     fn test_state_cache() {
         let cache = StateCache::new();
@@ -520,5 +1081,50 @@ mod tests {
         assert_eq!(stats.peer_count, 0);
         assert_eq!(stats.message_count, 0);
         assert_eq!(stats.credit_count, 0);
+        assert_eq!(stats.peers.hit_rate, 0.0);
+
+        cache.credits.get("missing");
+        assert_eq!(cache.stats().credits.misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_from_store() {
+        use mycelial_core::credit::CreditRelationship;
+
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let now = Utc::now();
+        for i in 0..5 {
+            let info = PeerInfo {
+                id: PeerId(format!("peer{}", i)),
+                public_key: format!("peer{}", i),
+                addresses: vec![],
+                first_seen: now,
+                last_seen: now + chrono::Duration::seconds(i),
+                name: None,
+            };
+            store.upsert_peer(&info, Some(&Reputation::default())).await.unwrap();
+        }
+        for i in 0..4 {
+            let mut message = Message::new(MessageType::Content, PeerId("peer0".to_string()), vec![i]);
+            message.timestamp = now + chrono::Duration::seconds(i as i64);
+            store.store_message(&message).await.unwrap();
+        }
+        for debtor in ["peer1", "peer2"] {
+            let rel = CreditRelationship::new(PeerId("peer0".to_string()), PeerId(debtor.to_string()), 100.0);
+            store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+
+        // Peer cache holds 3, so only the three most recently seen are loaded
+        let cache = StateCache::with_capacities(3, 100, 100);
+        let counts = cache.warm_from_store(&store, 10, 2).await.unwrap();
+        assert_eq!(counts, WarmedCounts { peers: 3, messages: 2, credits: 2 });
+
+        assert_eq!(cache.peers.len(), 3);
+        assert!(cache.peers.contains("peer4"));
+        assert!(cache.peers.contains("peer2"));
+        assert!(!cache.peers.contains("peer1"));
+        assert_eq!(cache.messages.len(), 2);
+        assert_eq!(cache.credits.len(), 2);
+        assert!(cache.credits.get_between("peer0", "peer2").is_some());
     }
 }
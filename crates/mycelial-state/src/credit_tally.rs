@@ -0,0 +1,351 @@
+//! Per-node credit tallies
+//!
+//! A balance synced last-write-wins loses one of two transfers made at the
+//! same time on different nodes. Relationships whose transfers are recorded
+//! with [`SqliteStore::apply_transfer`] keep their balance as a PN-counter
+//! instead: every node has its own grow-only totals of increments and
+//! decrements, merging takes the larger of each per node, and the balance is
+//! the opening balance plus the sum over all nodes. Concurrent transfers
+//! then both count, whatever order they arrive in.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqliteConnection};
+use std::collections::BTreeMap;
+
+use crate::error::Result;
+use crate::storage::SqliteStore;
+
+/// One node's running totals of transfers on a relationship
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreditTally {
+    /// Sum of the amounts added to the balance
+    pub increments: f64,
+    /// Sum of the amounts taken off the balance
+    pub decrements: f64,
+}
+
+impl CreditTally {
+    /// Contribution to the balance
+    pub fn net(&self) -> f64 {
+        self.increments - self.decrements
+    }
+
+    /// Record a transfer; positive amounts raise the balance
+    pub fn record(&mut self, amount: f64) {
+        if amount >= 0.0 {
+            self.increments += amount;
+        } else {
+            self.decrements -= amount;
+        }
+    }
+
+    /// Keep the larger of each total, returns true if either grew
+    pub fn merge(&mut self, other: &CreditTally) -> bool {
+        let grew = other.increments > self.increments || other.decrements > self.decrements;
+        self.increments = self.increments.max(other.increments);
+        self.decrements = self.decrements.max(other.decrements);
+        grew
+    }
+}
+
+/// Balance a relationship had before its first tallied transfer
+///
+/// Nodes converting a relationship at the same time may each record one, so
+/// it is last-write-wins: the newer timestamp wins, ties go to the greater
+/// origin, and only one of them is ever counted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpeningBalance {
+    pub amount: f64,
+    pub timestamp: DateTime<Utc>,
+    /// Node that recorded it
+    pub origin: String,
+}
+
+impl OpeningBalance {
+    /// Whether this one wins over `other`
+    fn supersedes(&self, other: &OpeningBalance) -> bool {
+        (self.timestamp, &self.origin) > (other.timestamp, &other.origin)
+    }
+}
+
+/// A relationship's balance as a PN-counter
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreditTallies {
+    /// Balance carried over when the first transfer was tallied
+    #[serde(default)]
+    pub opening: Option<OpeningBalance>,
+    /// Tallies of every node that recorded transfers, by node ID
+    #[serde(default)]
+    pub nodes: BTreeMap<String, CreditTally>,
+}
+
+impl CreditTallies {
+    /// Whether the balance isn't tallied at all
+    pub fn is_empty(&self) -> bool {
+        self.opening.is_none() && self.nodes.is_empty()
+    }
+
+    /// Only what `origin` recorded itself: its own tally, and the opening
+    /// balance if it set it
+    ///
+    /// A node speaks for its own transfers but not for anyone else's, so
+    /// tallies arriving in an update are cut down to these before merging.
+    pub fn recorded_by(&self, origin: &str) -> CreditTallies {
+        CreditTallies {
+            opening: self.opening.clone().filter(|opening| opening.origin == origin),
+            nodes: self.nodes.get(origin).map(|tally| (origin.to_string(), *tally)).into_iter().collect(),
+        }
+    }
+}
+
+/// Merge `remote` into `local`, returns true if anything changed
+pub fn merge_tallies(local: &mut CreditTallies, remote: &CreditTallies) -> bool {
+    let mut grew = false;
+    if let Some(opening) = &remote.opening {
+        if local.opening.as_ref().is_none_or(|current| opening.supersedes(current)) {
+            local.opening = Some(opening.clone());
+            grew = true;
+        }
+    }
+    for (node_id, tally) in &remote.nodes {
+        grew |= local.nodes.entry(node_id.clone()).or_default().merge(tally);
+    }
+    grew
+}
+
+/// Balance a set of tallies adds up to
+///
+/// Summed in node order, so every node holding the same tallies computes
+/// exactly the same balance.
+pub fn tallied_balance(tallies: &CreditTallies) -> f64 {
+    let opening = tallies.opening.as_ref().map_or(0.0, |opening| opening.amount);
+    opening + tallies.nodes.values().map(CreditTally::net).sum::<f64>()
+}
+
+/// Tallies stored for a relationship, read on an open connection
+pub(crate) async fn load_tallies(conn: &mut SqliteConnection, creditor: &str, debtor: &str) -> Result<CreditTallies> {
+    let rows = sqlx::query(
+        r#"
+        SELECT node_id, increments, decrements FROM credit_tallies
+        WHERE creditor_peer_id = ? AND debtor_peer_id = ?
+        "#,
+    )
+    .bind(creditor)
+    .bind(debtor)
+    .fetch_all(&mut *conn)
+    .await?;
+    let nodes = rows
+        .into_iter()
+        .map(|row| {
+            let tally = CreditTally {
+                increments: row.get("increments"),
+                decrements: row.get("decrements"),
+            };
+            (row.get("node_id"), tally)
+        })
+        .collect();
+
+    let opening = sqlx::query(
+        r#"
+        SELECT amount, timestamp_ns, origin FROM credit_opening_balances
+        WHERE creditor_peer_id = ? AND debtor_peer_id = ?
+        "#,
+    )
+    .bind(creditor)
+    .bind(debtor)
+    .fetch_optional(&mut *conn)
+    .await?
+    .map(|row| OpeningBalance {
+        amount: row.get("amount"),
+        timestamp: DateTime::from_timestamp_nanos(row.get("timestamp_ns")),
+        origin: row.get("origin"),
+    });
+
+    Ok(CreditTallies { opening, nodes })
+}
+
+/// Merge tallies into the stored ones on an open connection
+///
+/// Stored totals only grow and a stored opening balance is only replaced
+/// by a newer one, so this never loses anything already recorded.
+pub(crate) async fn store_tallies(
+    conn: &mut SqliteConnection,
+    creditor: &str,
+    debtor: &str,
+    tallies: &CreditTallies,
+) -> Result<()> {
+    for (node_id, tally) in &tallies.nodes {
+        sqlx::query(
+            r#"
+            INSERT INTO credit_tallies (creditor_peer_id, debtor_peer_id, node_id, increments, decrements)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(creditor_peer_id, debtor_peer_id, node_id) DO UPDATE SET
+                increments = MAX(increments, excluded.increments),
+                decrements = MAX(decrements, excluded.decrements)
+            "#,
+        )
+        .bind(creditor)
+        .bind(debtor)
+        .bind(node_id)
+        .bind(tally.increments)
+        .bind(tally.decrements)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    if let Some(opening) = &tallies.opening {
+        // Dates past 2262 don't fit; nothing that far ahead gets past the skew check
+        let timestamp_ns = opening.timestamp.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        sqlx::query(
+            r#"
+            INSERT INTO credit_opening_balances (creditor_peer_id, debtor_peer_id, amount, timestamp_ns, origin)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(creditor_peer_id, debtor_peer_id) DO UPDATE SET
+                amount = excluded.amount,
+                timestamp_ns = excluded.timestamp_ns,
+                origin = excluded.origin
+            WHERE (excluded.timestamp_ns, excluded.origin) > (timestamp_ns, origin)
+            "#,
+        )
+        .bind(creditor)
+        .bind(debtor)
+        .bind(opening.amount)
+        .bind(timestamp_ns)
+        .bind(&opening.origin)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+impl SqliteStore {
+    /// Tallies stored for a relationship, empty if its balance isn't tallied
+    pub async fn credit_tallies(&self, creditor: &str, debtor: &str) -> Result<CreditTallies> {
+        let mut conn = self.pool().acquire().await?;
+        load_tallies(&mut conn, creditor, debtor).await
+    }
+
+    /// Store a relationship's tallies, keeping the larger of each stored
+    /// total and the newer opening balance
+    ///
+    /// The relationship itself must already be stored.
+    pub async fn merge_credit_tallies(&self, creditor: &str, debtor: &str, tallies: &CreditTallies) -> Result<()> {
+        let mut tx = self.pool().begin().await?;
+        store_tallies(&mut tx, creditor, debtor, tallies).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use mycelial_core::{
+        credit::CreditRelationship,
+        peer::{PeerId, PeerInfo},
+    };
+
+    fn tally(increments: f64, decrements: f64) -> CreditTally {
+        CreditTally { increments, decrements }
+    }
+
+    fn opening(amount: f64, timestamp: DateTime<Utc>, origin: &str) -> Option<OpeningBalance> {
+        Some(OpeningBalance { amount, timestamp, origin: origin.to_string() })
+    }
+
+    #[test]
+    fn test_merge_keeps_larger_totals() {
+        let mut local = CreditTallies {
+            opening: None,
+            nodes: BTreeMap::from([("a".to_string(), tally(10.0, 2.0))]),
+        };
+        let remote = CreditTallies {
+            opening: None,
+            nodes: BTreeMap::from([("a".to_string(), tally(5.0, 4.0)), ("b".to_string(), tally(3.0, 0.0))]),
+        };
+
+        assert!(merge_tallies(&mut local, &remote));
+        assert_eq!(local.nodes["a"], tally(10.0, 4.0));
+        assert_eq!(tallied_balance(&local), 9.0);
+
+        // Merging again changes nothing
+        assert!(!merge_tallies(&mut local, &remote));
+    }
+
+    #[test]
+    fn test_opening_balance_is_last_write_wins() {
+        let now = Utc::now();
+        let mut local = CreditTallies { opening: opening(40.0, now, "a"), nodes: BTreeMap::new() };
+
+        // A larger but older opening doesn't replace the newer one
+        let older = CreditTallies { opening: opening(90.0, now - Duration::seconds(1), "b"), ..Default::default() };
+        assert!(!merge_tallies(&mut local, &older));
+        assert_eq!(tallied_balance(&local), 40.0);
+
+        // A smaller but newer one does, and only one is ever counted
+        let newer = CreditTallies { opening: opening(20.0, now, "b"), ..Default::default() };
+        assert!(merge_tallies(&mut local, &newer));
+        assert_eq!(tallied_balance(&local), 20.0);
+    }
+
+    #[test]
+    fn test_recorded_by_keeps_only_origin() {
+        let now = Utc::now();
+        let tallies = CreditTallies {
+            opening: opening(40.0, now, "a"),
+            nodes: BTreeMap::from([("a".to_string(), tally(10.0, 0.0)), ("b".to_string(), tally(99.0, 0.0))]),
+        };
+
+        let own = tallies.recorded_by("a");
+        assert_eq!(own.opening, tallies.opening);
+        assert_eq!(own.nodes, BTreeMap::from([("a".to_string(), tally(10.0, 0.0))]));
+
+        // Nothing b claims about a's tally or opening survives
+        let relayed = tallies.recorded_by("b");
+        assert_eq!(relayed.opening, None);
+        assert_eq!(relayed.nodes, BTreeMap::from([("b".to_string(), tally(99.0, 0.0))]));
+    }
+
+    #[tokio::test]
+    async fn test_tallies_round_trip() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        for id in ["alice", "bob"] {
+            let peer = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&peer, None).await.unwrap();
+        }
+        let relationship = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        store.upsert_credit_relationship(&relationship).await.unwrap();
+        assert!(store.credit_tallies("alice", "bob").await.unwrap().is_empty());
+
+        let mut node_a = CreditTally::default();
+        node_a.record(30.0);
+        node_a.record(-5.0);
+        let now = Utc::now();
+        let tallies = CreditTallies {
+            opening: opening(12.5, now, "node_a"),
+            nodes: BTreeMap::from([("node_a".to_string(), node_a)]),
+        };
+        store.merge_credit_tallies("alice", "bob", &tallies).await.unwrap();
+
+        // Smaller totals and older openings never overwrite stored ones
+        let older = CreditTallies {
+            opening: opening(50.0, now - Duration::seconds(1), "node_b"),
+            nodes: BTreeMap::from([("node_a".to_string(), tally(10.0, 0.0))]),
+        };
+        store.merge_credit_tallies("alice", "bob", &older).await.unwrap();
+        assert_eq!(store.credit_tallies("alice", "bob").await.unwrap(), tallies);
+
+        // Tallies go with the peer's relationships
+        store.delete_peer("bob").await.unwrap();
+        assert!(store.credit_tallies("alice", "bob").await.unwrap().is_empty());
+    }
+}
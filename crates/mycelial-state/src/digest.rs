@@ -0,0 +1,359 @@
+//! State digests for divergence detection
+//!
+//! A digest is a Merkle summary of the synced state, kept per record kind
+//! (peers, credit relationships, key-value entries). Each record is a leaf
+//! hash; leaves are spread over [`DIGEST_BUCKETS`] buckets by their key, and
+//! a kind's root hashes its bucket hashes. Nodes exchange only the roots and
+//! bucket hashes ([`DigestSummary`]), then fetch the leaves of the buckets
+//! that differ to tell which records do. Nothing is applied — a diff is a
+//! read-only report.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::Result;
+
+/// Hash of a single record, bucket or root
+pub type DigestHash = [u8; 32];
+
+/// Buckets the leaves of each record kind are spread over
+pub const DIGEST_BUCKETS: u16 = 64;
+
+/// Bucket a record key falls into
+fn bucket_of(key: &str) -> u16 {
+    let hash = Sha256::digest(key.as_bytes());
+    u16::from_be_bytes([hash[0], hash[1]]) % DIGEST_BUCKETS
+}
+
+/// Leaf hashes for one kind of record, keyed by record ID
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestTree {
+    leaves: BTreeMap<String, DigestHash>,
+}
+
+impl DigestTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a record, hashing its serialized content
+    pub fn insert(&mut self, key: impl Into<String>, content: &[u8]) {
+        self.leaves.insert(key.into(), Sha256::digest(content).into());
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Check if the tree has no records
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Hash of each bucket's leaves, in key order
+    pub fn bucket_hashes(&self) -> Vec<DigestHash> {
+        let mut hashers = vec![Sha256::new(); DIGEST_BUCKETS as usize];
+        for (key, leaf) in &self.leaves {
+            let hasher = &mut hashers[bucket_of(key) as usize];
+            hasher.update((key.len() as u64).to_be_bytes());
+            hasher.update(key.as_bytes());
+            hasher.update(leaf);
+        }
+        hashers.into_iter().map(|hasher| hasher.finalize().into()).collect()
+    }
+
+    /// Root hash over the bucket hashes
+    pub fn root(&self) -> DigestHash {
+        self.summary().root
+    }
+
+    /// Root and bucket hashes, to send to another node
+    pub fn summary(&self) -> TreeSummary {
+        let buckets = self.bucket_hashes();
+        let mut hasher = Sha256::new();
+        for bucket in &buckets {
+            hasher.update(bucket);
+        }
+        TreeSummary { root: hasher.finalize().into(), buckets }
+    }
+
+    /// The records in `buckets`
+    pub fn in_buckets(&self, buckets: &BTreeSet<u16>) -> DigestTree {
+        let leaves = self
+            .leaves
+            .iter()
+            .filter(|(key, _)| buckets.contains(&bucket_of(key)))
+            .map(|(key, leaf)| (key.clone(), *leaf))
+            .collect();
+        DigestTree { leaves }
+    }
+
+    /// Add another tree's records
+    pub fn extend(&mut self, other: DigestTree) {
+        self.leaves.extend(other.leaves);
+    }
+
+    /// Count records that differ from another tree
+    ///
+    /// A record differs if it is missing on either side or has a different hash.
+    pub fn diff_count(&self, other: &DigestTree) -> usize {
+        if self.leaves == other.leaves {
+            return 0;
+        }
+
+        let changed_or_missing = self
+            .leaves
+            .iter()
+            .filter(|(key, leaf)| other.leaves.get(*key) != Some(leaf))
+            .count();
+        let only_other = other
+            .leaves
+            .keys()
+            .filter(|key| !self.leaves.contains_key(*key))
+            .count();
+
+        changed_or_missing + only_other
+    }
+}
+
+/// Root and bucket hashes of one record kind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeSummary {
+    pub root: DigestHash,
+    pub buckets: Vec<DigestHash>,
+}
+
+impl TreeSummary {
+    /// Buckets whose hashes differ from another summary's
+    pub fn differing_buckets(&self, other: &TreeSummary) -> BTreeSet<u16> {
+        if self.root == other.root {
+            return BTreeSet::new();
+        }
+        (0..DIGEST_BUCKETS)
+            .filter(|bucket| self.buckets.get(*bucket as usize) != other.buckets.get(*bucket as usize))
+            .collect()
+    }
+}
+
+/// Digest of the full synced state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDigest {
+    /// Peer records
+    pub peers: DigestTree,
+    /// Credit relationships
+    pub credits: DigestTree,
+    /// Application key-value entries
+    pub kv: DigestTree,
+}
+
+impl StateDigest {
+    fn kinds(&self) -> [&DigestTree; 3] {
+        [&self.peers, &self.credits, &self.kv]
+    }
+
+    fn kinds_mut(&mut self) -> [&mut DigestTree; 3] {
+        [&mut self.peers, &mut self.credits, &mut self.kv]
+    }
+
+    /// Compare against another digest
+    pub fn diff(&self, other: &StateDigest) -> DigestDiff {
+        DigestDiff {
+            peers: self.peers.diff_count(&other.peers),
+            credits: self.credits.diff_count(&other.credits),
+            kv: self.kv.diff_count(&other.kv),
+        }
+    }
+
+    /// Root and bucket hashes of each kind, to send to another node
+    pub fn summary(&self) -> DigestSummary {
+        DigestSummary {
+            peers: self.peers.summary(),
+            credits: self.credits.summary(),
+            kv: self.kv.summary(),
+        }
+    }
+
+    /// Buckets of each kind that differ from another node's summary
+    pub fn differing_buckets(&self, other: &DigestSummary) -> DigestBuckets {
+        let summary = self.summary();
+        DigestBuckets {
+            peers: summary.peers.differing_buckets(&other.peers),
+            credits: summary.credits.differing_buckets(&other.credits),
+            kv: summary.kv.differing_buckets(&other.kv),
+        }
+    }
+
+    /// The records in `buckets`
+    pub fn in_buckets(&self, buckets: &DigestBuckets) -> StateDigest {
+        StateDigest {
+            peers: self.peers.in_buckets(&buckets.peers),
+            credits: self.credits.in_buckets(&buckets.credits),
+            kv: self.kv.in_buckets(&buckets.kv),
+        }
+    }
+
+    /// Add another digest's records
+    pub fn extend(&mut self, other: StateDigest) {
+        self.peers.extend(other.peers);
+        self.credits.extend(other.credits);
+        self.kv.extend(other.kv);
+    }
+
+    /// Compare the records in `buckets` against another node's leaves of them
+    pub fn diff_buckets(&self, buckets: &DigestBuckets, other: &StateDigest) -> DigestDiff {
+        self.in_buckets(buckets).diff(&other.in_buckets(buckets))
+    }
+
+    /// The records in `buckets`, split into pages of at most `max_bytes` encoded
+    ///
+    /// A page holds whole buckets, so one bucket larger than `max_bytes`
+    /// makes a page of its own.
+    pub fn leaf_pages(&self, buckets: &DigestBuckets, max_bytes: usize) -> Result<Vec<DigestPage>> {
+        let mut pages = Vec::new();
+        let mut page = DigestPage::default();
+        let mut page_bytes = 0;
+
+        for (kind, (tree, requested)) in self.kinds().into_iter().zip(buckets.kinds()).enumerate() {
+            for bucket in requested {
+                let leaves = tree.in_buckets(&BTreeSet::from([*bucket]));
+                let bytes = serde_json::to_vec(&leaves)?.len();
+                if page_bytes + bytes > max_bytes && !page.buckets.is_empty() {
+                    pages.push(std::mem::take(&mut page));
+                    page_bytes = 0;
+                }
+                page_bytes += bytes;
+                page.leaves.kinds_mut()[kind].extend(leaves);
+                page.buckets.kinds_mut()[kind].insert(*bucket);
+            }
+        }
+        if !page.buckets.is_empty() {
+            pages.push(page);
+        }
+        Ok(pages)
+    }
+}
+
+/// Root and bucket hashes of each record kind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestSummary {
+    pub peers: TreeSummary,
+    pub credits: TreeSummary,
+    pub kv: TreeSummary,
+}
+
+/// Bucket indexes of each record kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestBuckets {
+    pub peers: BTreeSet<u16>,
+    pub credits: BTreeSet<u16>,
+    pub kv: BTreeSet<u16>,
+}
+
+impl DigestBuckets {
+    fn kinds(&self) -> [&BTreeSet<u16>; 3] {
+        [&self.peers, &self.credits, &self.kv]
+    }
+
+    fn kinds_mut(&mut self) -> [&mut BTreeSet<u16>; 3] {
+        [&mut self.peers, &mut self.credits, &mut self.kv]
+    }
+
+    /// Check if no bucket of any kind is included
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty() && self.credits.is_empty() && self.kv.is_empty()
+    }
+
+    /// Drop the buckets `other` includes
+    pub fn remove(&mut self, other: &DigestBuckets) {
+        self.peers.retain(|bucket| !other.peers.contains(bucket));
+        self.credits.retain(|bucket| !other.credits.contains(bucket));
+        self.kv.retain(|bucket| !other.kv.contains(bucket));
+    }
+}
+
+/// The leaves of some buckets, as sent to a node that asked for them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestPage {
+    /// Buckets this page holds in full
+    pub buckets: DigestBuckets,
+    pub leaves: StateDigest,
+}
+
+/// Number of differing records per kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestDiff {
+    /// Differing peer records
+    pub peers: usize,
+    /// Differing credit relationships
+    pub credits: usize,
+    /// Differing key-value entries
+    pub kv: usize,
+}
+
+impl DigestDiff {
+    /// Total number of differing records
+    pub fn total(&self) -> usize {
+        self.peers + self.credits + self.kv
+    }
+
+    /// Check if the two states are identical
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_diff() {
+        let mut a = DigestTree::new();
+        a.insert("k1", b"v1");
+        a.insert("k2", b"v2");
+
+        let mut b = a.clone();
+        assert_eq!(a.root(), b.root());
+        assert_eq!(a.diff_count(&b), 0);
+
+        b.insert("k2", b"changed");
+        b.insert("k3", b"v3");
+        assert_ne!(a.root(), b.root());
+        assert_eq!(a.diff_count(&b), 2);
+        assert_eq!(b.diff_count(&a), 2);
+    }
+
+    #[test]
+    fn test_diff_by_buckets() {
+        let mut a = StateDigest::default();
+        for i in 0..500 {
+            a.peers.insert(format!("peer{}", i), b"same");
+            a.kv.insert(format!("key{}", i), b"same");
+        }
+        let mut b = a.clone();
+        b.peers.insert("peer7", b"changed");
+        b.kv.insert("key_only_b", b"new");
+
+        // Only the buckets holding the two records differ
+        let buckets = a.differing_buckets(&b.summary());
+        assert_eq!((buckets.peers.len(), buckets.credits.len(), buckets.kv.len()), (1, 0, 1));
+
+        // Their leaves arrive in pages, and only those need comparing
+        let pages = b.leaf_pages(&buckets, 1024).unwrap();
+        assert_eq!(pages.len(), 2);
+        let mut remaining = buckets.clone();
+        let mut remote = StateDigest::default();
+        for page in pages {
+            remaining.remove(&page.buckets);
+            remote.extend(page.leaves);
+        }
+        assert!(remaining.is_empty());
+        assert!(remote.peers.len() < 50);
+        let diff = a.diff_buckets(&buckets, &remote);
+        assert_eq!(diff, a.diff(&b));
+        assert_eq!((diff.peers, diff.kv), (1, 1));
+    }
+}
@@ -45,6 +45,18 @@ pub enum StateError {
     #[error("Sync error: {0}")]
     Sync(String),
 
+    /// Credit transfer would push the balance past the relationship's limit
+    #[error("Credit limit exceeded: requested {requested}, available {available}")]
+    CreditLimitExceeded { requested: f64, available: f64 },
+
+    /// State update signature missing or not from the claimed peer
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// State update timestamped too far ahead of the local clock
+    #[error("Clock skew: update is {ahead_secs}s in the future (max {max_secs}s)")]
+    ClockSkew { ahead_secs: i64, max_secs: i64 },
+
     /// Internal error
     #[error("Internal error: {0}")]
     Internal(String),
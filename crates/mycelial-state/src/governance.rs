@@ -0,0 +1,480 @@
+//! Governance proposal persistence and tallying
+//!
+//! Proposals and votes arrive over gossip as [`CreateProposal`] and
+//! [`CastVote`] messages. Each voter's latest vote is kept; its weight is the
+//! voter's reputation score in our store rather than the weight the voter
+//! claims, so peers can't inflate their own influence. Quorum is measured
+//! against the total reputation of every known peer.
+//!
+//! A proposal stays [`ProposalVerdict::Open`] until its deadline. The first
+//! tally after the deadline decides it and freezes the result, so later
+//! reputation changes don't rewrite history:
+//!
+//! - **Expired**: participating weight fell short of the quorum
+//! - **Passed**: quorum met and `for / (for + against)` reached the threshold
+//! - **Failed**: quorum met but the threshold was not
+//!
+//! Abstentions count towards quorum but not towards approval.
+
+use chrono::{DateTime, TimeZone, Utc};
+use mycelial_protocol::{CastVote, CreateProposal, Vote};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::error::{Result, StateError};
+use crate::storage::SqliteStore;
+
+/// Where a proposal stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalVerdict {
+    /// Voting is still open
+    Open,
+    /// Quorum and approval threshold met at the deadline
+    Passed,
+    /// Quorum met but not enough approval at the deadline
+    Failed,
+    /// Quorum not reached by the deadline
+    Expired,
+}
+
+impl ProposalVerdict {
+    /// Name the verdict is stored and reported under
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProposalVerdict::Open => "open",
+            ProposalVerdict::Passed => "passed",
+            ProposalVerdict::Failed => "failed",
+            ProposalVerdict::Expired => "expired",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "open" => Ok(ProposalVerdict::Open),
+            "passed" => Ok(ProposalVerdict::Passed),
+            "failed" => Ok(ProposalVerdict::Failed),
+            "expired" => Ok(ProposalVerdict::Expired),
+            other => Err(StateError::InvalidData(format!("unknown proposal status '{}'", other))),
+        }
+    }
+}
+
+/// Weighted tally of a proposal and the verdict it leads to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProposalOutcome {
+    pub proposal_id: Uuid,
+    pub verdict: ProposalVerdict,
+    /// Reputation-weighted votes in favour
+    pub votes_for: f64,
+    /// Reputation-weighted votes against
+    pub votes_against: f64,
+    /// Reputation-weighted abstentions
+    pub votes_abstain: f64,
+    /// Distinct voters
+    pub voter_count: u32,
+    /// Total reputation of all known peers, the quorum's denominator
+    pub eligible_weight: f64,
+}
+
+impl ProposalOutcome {
+    /// Share of the eligible weight that voted (0.0 when nobody is eligible)
+    pub fn participation(&self) -> f64 {
+        if self.eligible_weight <= 0.0 {
+            return 0.0;
+        }
+        (self.votes_for + self.votes_against + self.votes_abstain) / self.eligible_weight
+    }
+
+    /// Share of decisive (non-abstaining) weight in favour
+    pub fn approval(&self) -> f64 {
+        let decisive = self.votes_for + self.votes_against;
+        if decisive <= 0.0 {
+            return 0.0;
+        }
+        self.votes_for / decisive
+    }
+}
+
+fn vote_name(vote: &Vote) -> &'static str {
+    match vote {
+        Vote::For => "for",
+        Vote::Against => "against",
+        Vote::Abstain => "abstain",
+    }
+}
+
+impl SqliteStore {
+    /// Store a new proposal; re-announcements of a known proposal are ignored
+    pub async fn save_proposal(&self, proposal: &CreateProposal) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO proposals (
+                id, proposer_peer_id, title, description, proposal_type_json,
+                quorum, threshold, deadline, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO NOTHING
+            "#,
+        )
+        .bind(proposal.id.to_string())
+        .bind(&proposal.proposer)
+        .bind(&proposal.title)
+        .bind(&proposal.description)
+        .bind(serde_json::to_string(&proposal.proposal_type)?)
+        .bind(proposal.quorum.clamp(0.0, 1.0))
+        .bind(proposal.threshold.clamp(0.0, 1.0))
+        .bind(proposal.deadline.timestamp())
+        .bind(proposal.timestamp.timestamp())
+        .execute(self.pool())
+        .await?;
+
+        debug!("Saved proposal {}", proposal.id);
+        Ok(())
+    }
+
+    /// Record a vote, returns false if it was ignored
+    ///
+    /// A voter's newer vote replaces their earlier one. Older votes, votes
+    /// dated outside the proposal's window (before it was created or after
+    /// its deadline) and votes on decided proposals are ignored. Fails with
+    /// `NotFound` if the proposal is unknown.
+    pub async fn record_vote(&self, vote: &CastVote) -> Result<bool> {
+        let proposal_id = vote.proposal_id.to_string();
+        let proposal = sqlx::query("SELECT created_at, deadline, status FROM proposals WHERE id = ?")
+            .bind(&proposal_id)
+            .fetch_optional(self.pool())
+            .await?
+            .ok_or_else(|| StateError::NotFound {
+                entity: "proposal".to_string(),
+                id: proposal_id.clone(),
+            })?;
+
+        if proposal.get::<String, _>("status") != ProposalVerdict::Open.as_str() {
+            debug!("Ignoring vote by {} on decided proposal {}", vote.voter, proposal_id);
+            return Ok(false);
+        }
+        let timestamp = vote.timestamp.timestamp();
+        if timestamp < proposal.get::<i64, _>("created_at") || timestamp > proposal.get::<i64, _>("deadline") {
+            debug!("Ignoring vote by {} on {} dated outside its window", vote.voter, proposal_id);
+            return Ok(false);
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO proposal_votes (proposal_id, voter_peer_id, vote, reason, timestamp)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(proposal_id, voter_peer_id) DO UPDATE SET
+                vote = excluded.vote,
+                reason = excluded.reason,
+                timestamp = excluded.timestamp
+            WHERE excluded.timestamp >= proposal_votes.timestamp
+            "#,
+        )
+        .bind(&proposal_id)
+        .bind(&vote.voter)
+        .bind(vote_name(&vote.vote))
+        .bind(&vote.reason)
+        .bind(timestamp)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Open proposals whose deadline has passed by `now`
+    pub async fn due_proposals(&self, now: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query("SELECT id FROM proposals WHERE status = 'open' AND deadline <= ?")
+            .bind(now.timestamp())
+            .fetch_all(self.pool())
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let id: String = row.get("id");
+                Uuid::parse_str(&id).map_err(|e| StateError::InvalidData(format!("proposal ID {}: {}", id, e)))
+            })
+            .collect()
+    }
+
+    /// Tally a proposal, deciding it if its deadline has passed
+    pub async fn tally_proposal(&self, proposal_id: &Uuid) -> Result<ProposalOutcome> {
+        self.tally_proposal_at(proposal_id, Utc::now()).await
+    }
+
+    /// Tally a proposal as of `now`
+    pub async fn tally_proposal_at(&self, proposal_id: &Uuid, now: DateTime<Utc>) -> Result<ProposalOutcome> {
+        let id = proposal_id.to_string();
+        let row = sqlx::query(
+            r#"
+            SELECT quorum, threshold, deadline, status,
+                   votes_for, votes_against, votes_abstain, voter_count, eligible_weight
+            FROM proposals WHERE id = ?
+            "#,
+        )
+        .bind(&id)
+        .fetch_optional(self.pool())
+        .await?
+        .ok_or_else(|| StateError::NotFound {
+            entity: "proposal".to_string(),
+            id: id.clone(),
+        })?;
+
+        // Decided proposals keep their frozen tally
+        let status = ProposalVerdict::parse(row.get::<String, _>("status").as_str())?;
+        if status != ProposalVerdict::Open {
+            return Ok(ProposalOutcome {
+                proposal_id: *proposal_id,
+                verdict: status,
+                votes_for: row.get::<Option<f64>, _>("votes_for").unwrap_or_default(),
+                votes_against: row.get::<Option<f64>, _>("votes_against").unwrap_or_default(),
+                votes_abstain: row.get::<Option<f64>, _>("votes_abstain").unwrap_or_default(),
+                voter_count: row.get::<Option<i64>, _>("voter_count").unwrap_or_default() as u32,
+                eligible_weight: row.get::<Option<f64>, _>("eligible_weight").unwrap_or_default(),
+            });
+        }
+
+        let mut outcome = ProposalOutcome {
+            proposal_id: *proposal_id,
+            verdict: ProposalVerdict::Open,
+            votes_for: 0.0,
+            votes_against: 0.0,
+            votes_abstain: 0.0,
+            voter_count: 0,
+            eligible_weight: 0.0,
+        };
+
+        // Unknown voters carry no weight but still count as voters
+        let totals = sqlx::query(
+            r#"
+            SELECT v.vote, COUNT(*) AS voters, COALESCE(SUM(p.reputation_score), 0.0) AS weight
+            FROM proposal_votes v
+            LEFT JOIN peers p ON p.peer_id = v.voter_peer_id
+            WHERE v.proposal_id = ?
+            GROUP BY v.vote
+            "#,
+        )
+        .bind(&id)
+        .fetch_all(self.pool())
+        .await?;
+
+        for total in totals {
+            let weight: f64 = total.get("weight");
+            outcome.voter_count += total.get::<i64, _>("voters") as u32;
+            match total.get::<String, _>("vote").as_str() {
+                "for" => outcome.votes_for = weight,
+                "against" => outcome.votes_against = weight,
+                _ => outcome.votes_abstain = weight,
+            }
+        }
+
+        outcome.eligible_weight = sqlx::query("SELECT COALESCE(SUM(reputation_score), 0.0) AS total FROM peers")
+            .fetch_one(self.pool())
+            .await?
+            .get("total");
+
+        let deadline = Utc
+            .timestamp_opt(row.get("deadline"), 0)
+            .single()
+            .unwrap_or_else(Utc::now);
+        if now < deadline {
+            return Ok(outcome);
+        }
+
+        let quorum: f64 = row.get("quorum");
+        let threshold: f64 = row.get("threshold");
+        outcome.verdict = if outcome.participation() < quorum {
+            ProposalVerdict::Expired
+        } else if outcome.approval() >= threshold && outcome.votes_for > 0.0 {
+            ProposalVerdict::Passed
+        } else {
+            ProposalVerdict::Failed
+        };
+
+        // Only the first tally past the deadline decides the proposal
+        sqlx::query(
+            r#"
+            UPDATE proposals
+            SET status = ?, votes_for = ?, votes_against = ?, votes_abstain = ?,
+                voter_count = ?, eligible_weight = ?
+            WHERE id = ? AND status = 'open'
+            "#,
+        )
+        .bind(outcome.verdict.as_str())
+        .bind(outcome.votes_for)
+        .bind(outcome.votes_against)
+        .bind(outcome.votes_abstain)
+        .bind(outcome.voter_count as i64)
+        .bind(outcome.eligible_weight)
+        .bind(&id)
+        .execute(self.pool())
+        .await?;
+
+        info!(
+            "Proposal {} {:?}: {:.2} for, {:.2} against, {:.2} abstain of {:.2} eligible",
+            id,
+            outcome.verdict,
+            outcome.votes_for,
+            outcome.votes_against,
+            outcome.votes_abstain,
+            outcome.eligible_weight
+        );
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use mycelial_core::peer::{PeerId, PeerInfo};
+    use mycelial_core::reputation::Reputation;
+
+    async fn store_with_peers(scores: &[(&str, f64)]) -> SqliteStore {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        for (id, score) in scores {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            let reputation = Reputation {
+                score: *score,
+                ..Default::default()
+            };
+            store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+        }
+        store
+    }
+
+    fn proposal(deadline: DateTime<Utc>) -> CreateProposal {
+        let mut proposal = CreateProposal::new("alice".to_string(), "Raise limits".to_string(), String::new())
+            .with_quorum(0.5)
+            .with_threshold(0.5)
+            .with_deadline(deadline);
+        proposal.timestamp = deadline - Duration::hours(1);
+        proposal
+    }
+
+    fn vote_at(proposal: &CreateProposal, voter: &str, vote: Vote, at: DateTime<Utc>) -> CastVote {
+        // Claimed weights are ignored in favour of stored reputation
+        let mut vote = CastVote::new(proposal.id, voter.to_string(), vote, 1000.0);
+        vote.timestamp = at;
+        vote
+    }
+
+    #[tokio::test]
+    async fn test_proposal_passes() {
+        let store = store_with_peers(&[("alice", 0.8), ("bob", 0.6), ("carol", 0.4), ("dave", 0.2)]).await;
+        let deadline = Utc::now() + Duration::hours(1);
+        let proposal = proposal(deadline);
+        store.save_proposal(&proposal).await.unwrap();
+
+        let early = deadline - Duration::minutes(30);
+        let later = deadline - Duration::minutes(10);
+        assert!(store.record_vote(&vote_at(&proposal, "alice", Vote::For, early)).await.unwrap());
+        assert!(store.record_vote(&vote_at(&proposal, "carol", Vote::Against, early)).await.unwrap());
+        // Bob changes his mind; only the latest vote counts
+        assert!(store.record_vote(&vote_at(&proposal, "bob", Vote::Against, early)).await.unwrap());
+        assert!(store.record_vote(&vote_at(&proposal, "bob", Vote::For, later)).await.unwrap());
+        // A delayed copy of the older vote doesn't overwrite the newer one
+        assert!(!store.record_vote(&vote_at(&proposal, "bob", Vote::Against, early)).await.unwrap());
+
+        let open = store.tally_proposal_at(&proposal.id, later).await.unwrap();
+        assert_eq!(open.verdict, ProposalVerdict::Open);
+        assert_eq!(open.voter_count, 3);
+
+        let outcome = store.tally_proposal_at(&proposal.id, deadline).await.unwrap();
+        assert_eq!(outcome.verdict, ProposalVerdict::Passed);
+        assert!((outcome.votes_for - 1.4).abs() < 1e-9);
+        assert!((outcome.votes_against - 0.4).abs() < 1e-9);
+        assert!((outcome.eligible_weight - 2.0).abs() < 1e-9);
+
+        // Votes after the deadline are ignored and the verdict is frozen
+        let late = vote_at(&proposal, "dave", Vote::Against, deadline + Duration::minutes(1));
+        assert!(!store.record_vote(&late).await.unwrap());
+        assert_eq!(store.tally_proposal(&proposal.id).await.unwrap(), outcome);
+
+        // So are votes back-dated into the window once it is decided
+        let backdated = vote_at(&proposal, "dave", Vote::Against, early);
+        assert!(!store.record_vote(&backdated).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_vote_before_proposal_created() {
+        let store = store_with_peers(&[("alice", 0.5)]).await;
+        let deadline = Utc::now() + Duration::hours(1);
+        let proposal = proposal(deadline);
+        store.save_proposal(&proposal).await.unwrap();
+
+        let premature = vote_at(&proposal, "alice", Vote::For, proposal.timestamp - Duration::minutes(1));
+        assert!(!store.record_vote(&premature).await.unwrap());
+        assert_eq!(store.tally_proposal(&proposal.id).await.unwrap().voter_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_due_proposals() {
+        let store = store_with_peers(&[("alice", 0.5)]).await;
+        let now = Utc::now();
+        let due = proposal(now - Duration::minutes(1));
+        let open = proposal(now + Duration::hours(1));
+        store.save_proposal(&due).await.unwrap();
+        store.save_proposal(&open).await.unwrap();
+
+        assert_eq!(store.due_proposals(now).await.unwrap(), vec![due.id]);
+
+        // Decided proposals are no longer due
+        store.tally_proposal_at(&due.id, now).await.unwrap();
+        assert!(store.due_proposals(now).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_proposal_fails_without_approval() {
+        let store = store_with_peers(&[("alice", 0.5), ("bob", 0.5)]).await;
+        let deadline = Utc::now() - Duration::minutes(1);
+        let proposal = proposal(deadline);
+        store.save_proposal(&proposal).await.unwrap();
+
+        let before = deadline - Duration::minutes(5);
+        store.record_vote(&vote_at(&proposal, "alice", Vote::Against, before)).await.unwrap();
+        store.record_vote(&vote_at(&proposal, "bob", Vote::Abstain, before)).await.unwrap();
+
+        let outcome = store.tally_proposal(&proposal.id).await.unwrap();
+        assert_eq!(outcome.verdict, ProposalVerdict::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_without_quorum() {
+        let store = store_with_peers(&[("alice", 0.9), ("bob", 0.9), ("carol", 0.9), ("dave", 0.3)]).await;
+        let deadline = Utc::now() - Duration::minutes(1);
+        let proposal = proposal(deadline);
+        store.save_proposal(&proposal).await.unwrap();
+
+        // Unanimous, but only 0.9 + 0.3 of 3.0 eligible weight voted
+        let before = deadline - Duration::minutes(5);
+        store.record_vote(&vote_at(&proposal, "alice", Vote::For, before)).await.unwrap();
+        store.record_vote(&vote_at(&proposal, "dave", Vote::For, before)).await.unwrap();
+        // Unknown voters add nothing
+        store.record_vote(&vote_at(&proposal, "mallory", Vote::For, before)).await.unwrap();
+
+        let outcome = store.tally_proposal(&proposal.id).await.unwrap();
+        assert_eq!(outcome.verdict, ProposalVerdict::Expired);
+        assert_eq!(outcome.voter_count, 3);
+        assert!((outcome.participation() - 0.4).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_vote_on_unknown_proposal() {
+        let store = store_with_peers(&[]).await;
+        let proposal = proposal(Utc::now());
+        let err = store
+            .record_vote(&vote_at(&proposal, "alice", Vote::For, Utc::now()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StateError::NotFound { .. }));
+    }
+}
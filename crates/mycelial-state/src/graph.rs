@@ -0,0 +1,335 @@
+//! Credit graph export
+//!
+//! Renders the active credit network as a directed graph in standard
+//! formats (GraphML or Graphviz DOT) so it can be loaded into analysis tools.
+//! Peers are nodes and credit relationships are weighted edges pointing from
+//! creditor to debtor.
+
+use mycelial_core::{credit::CreditRelationship, peer::PeerInfo};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Supported graph serialization formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// GraphML (XML)
+    GraphMl,
+    /// Graphviz DOT
+    Dot,
+}
+
+impl GraphFormat {
+    /// Pick a format from an HTTP `Accept` header, defaulting to GraphML
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        match accept {
+            Some(value)
+                if value.contains("vnd.graphviz") || value.contains("text/plain") =>
+            {
+                GraphFormat::Dot
+            }
+            _ => GraphFormat::GraphMl,
+        }
+    }
+
+    /// MIME type of the rendered output
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            GraphFormat::GraphMl => "application/graphml+xml",
+            GraphFormat::Dot => "text/vnd.graphviz",
+        }
+    }
+}
+
+/// A peer in the credit graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    /// Peer ID
+    pub id: String,
+    /// Display label (peer name, or short ID when unnamed)
+    pub label: String,
+}
+
+/// A creditor -> debtor relationship in the credit graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    /// Creditor peer ID
+    pub source: String,
+    /// Debtor peer ID
+    pub target: String,
+    /// Credit limit extended
+    pub credit_limit: f64,
+    /// Current balance owed
+    pub balance: f64,
+}
+
+/// Snapshot of the credit network ready for export
+#[derive(Debug, Clone, Default)]
+pub struct CreditGraph {
+    /// Nodes, sorted by peer ID
+    pub nodes: Vec<GraphNode>,
+    /// Edges, in relationship order
+    pub edges: Vec<GraphEdge>,
+}
+
+impl CreditGraph {
+    /// Build a graph from credit relationships, labeling nodes from known peers
+    ///
+    /// Every peer referenced by a relationship becomes a node, even when no
+    /// peer record is known for it.
+    pub fn build(peers: &[PeerInfo], relationships: &[CreditRelationship]) -> Self {
+        let names: HashMap<&str, &str> = peers
+            .iter()
+            .filter_map(|p| p.name.as_deref().map(|name| (p.id.as_str(), name)))
+            .collect();
+
+        let mut nodes = BTreeMap::new();
+        let mut edges = Vec::with_capacity(relationships.len());
+
+        for rel in relationships {
+            for peer in [&rel.creditor, &rel.debtor] {
+                nodes.entry(peer.as_str().to_string()).or_insert_with(|| {
+                    names
+                        .get(peer.as_str())
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| peer.short().to_string())
+                });
+            }
+
+            edges.push(GraphEdge {
+                source: rel.creditor.as_str().to_string(),
+                target: rel.debtor.as_str().to_string(),
+                credit_limit: rel.credit_limit,
+                balance: rel.balance,
+            });
+        }
+
+        Self {
+            nodes: nodes
+                .into_iter()
+                .map(|(id, label)| GraphNode { id, label })
+                .collect(),
+            edges,
+        }
+    }
+
+    /// Render the whole graph into a single string
+    pub fn render(self, format: GraphFormat) -> String {
+        let mut writer = GraphWriter::new(format);
+        let mut out = writer.header().to_string();
+        out.push_str(&writer.page(&self.nodes, &self.edges));
+        out.push_str(writer.footer());
+        out
+    }
+}
+
+/// A batch of active credit relationships, for exporting the graph in pages
+#[derive(Debug, Clone, Default)]
+pub struct CreditGraphPage {
+    /// Nodes for the peers the edges reference
+    pub nodes: Vec<GraphNode>,
+    /// Edges, in storage order
+    pub edges: Vec<GraphEdge>,
+    /// Cursor for the following batch, or None once all are read
+    pub next_cursor: Option<i64>,
+}
+
+/// Renders a credit graph a page at a time
+///
+/// GraphML and DOT both allow nodes and edges to be interleaved, so each
+/// page's nodes are written right before its edges. Only the IDs of nodes
+/// already written are kept, to skip them in later pages.
+#[derive(Debug)]
+pub struct GraphWriter {
+    format: GraphFormat,
+    written: HashSet<String>,
+}
+
+impl GraphWriter {
+    /// Create a writer for `format`
+    pub fn new(format: GraphFormat) -> Self {
+        Self { format, written: HashSet::new() }
+    }
+
+    /// Opening of the document, written before any page
+    pub fn header(&self) -> &'static str {
+        match self.format {
+            GraphFormat::GraphMl => GRAPHML_HEADER,
+            GraphFormat::Dot => DOT_HEADER,
+        }
+    }
+
+    /// Close of the document, written after the last page
+    pub fn footer(&self) -> &'static str {
+        match self.format {
+            GraphFormat::GraphMl => GRAPHML_FOOTER,
+            GraphFormat::Dot => DOT_FOOTER,
+        }
+    }
+
+    /// Render nodes not written yet, followed by the edges
+    pub fn page(&mut self, nodes: &[GraphNode], edges: &[GraphEdge]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            if !self.written.insert(node.id.clone()) {
+                continue;
+            }
+            out.push_str(&match self.format {
+                GraphFormat::GraphMl => format!(
+                    "    <node id=\"{}\"><data key=\"label\">{}</data></node>\n",
+                    xml_escape(&node.id),
+                    xml_escape(&node.label)
+                ),
+                GraphFormat::Dot => format!(
+                    "  \"{}\" [label=\"{}\"];\n",
+                    dot_escape(&node.id),
+                    dot_escape(&node.label)
+                ),
+            });
+        }
+        for edge in edges {
+            out.push_str(&match self.format {
+                GraphFormat::GraphMl => format!(
+                    "    <edge source=\"{}\" target=\"{}\"><data key=\"limit\">{}</data><data key=\"balance\">{}</data></edge>\n",
+                    xml_escape(&edge.source),
+                    xml_escape(&edge.target),
+                    edge.credit_limit,
+                    edge.balance
+                ),
+                GraphFormat::Dot => format!(
+                    "  \"{}\" -> \"{}\" [weight={}, limit={}, balance={}];\n",
+                    dot_escape(&edge.source),
+                    dot_escape(&edge.target),
+                    edge.credit_limit,
+                    edge.credit_limit,
+                    edge.balance
+                ),
+            });
+        }
+        out
+    }
+}
+
+const GRAPHML_HEADER: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+    "  <key id=\"limit\" for=\"edge\" attr.name=\"limit\" attr.type=\"double\"/>\n",
+    "  <key id=\"balance\" for=\"edge\" attr.name=\"balance\" attr.type=\"double\"/>\n",
+    "  <graph id=\"credit\" edgedefault=\"directed\">\n",
+);
+
+const GRAPHML_FOOTER: &str = "  </graph>\n</graphml>\n";
+
+const DOT_HEADER: &str = "digraph credit {\n";
+
+const DOT_FOOTER: &str = "}\n";
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use mycelial_core::peer::PeerId;
+
+    fn peer(id: &str, name: Option<&str>) -> PeerInfo {
+        PeerInfo {
+            id: PeerId(id.to_string()),
+            public_key: id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: name.map(String::from),
+        }
+    }
+
+    fn small_network() -> CreditGraph {
+        let peers = vec![peer("alice_peer", Some("Alice")), peer("bob_peer", Some("Bob & Co"))];
+
+        let mut ab = CreditRelationship::new(
+            PeerId("alice_peer".to_string()),
+            PeerId("bob_peer".to_string()),
+            100.0,
+        );
+        ab.balance = 25.0;
+        let bc = CreditRelationship::new(
+            PeerId("bob_peer".to_string()),
+            PeerId("carol_peer".to_string()),
+            50.0,
+        );
+
+        CreditGraph::build(&peers, &[ab, bc])
+    }
+
+    #[test]
+    fn test_build_graph() {
+        let graph = small_network();
+
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[0].label, "Alice");
+        assert_eq!(graph.nodes[1].label, "Bob & Co");
+        // Unknown peers fall back to their short ID
+        assert_eq!(graph.nodes[2].id, "carol_peer");
+        assert_eq!(graph.nodes[2].label, "carol_pe");
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].source, "alice_peer");
+        assert_eq!(graph.edges[0].target, "bob_peer");
+        assert_eq!(graph.edges[0].credit_limit, 100.0);
+        assert_eq!(graph.edges[0].balance, 25.0);
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let dot = small_network().render(GraphFormat::Dot);
+
+        assert!(dot.starts_with("digraph credit {"));
+        assert!(dot.contains("\"alice_peer\" [label=\"Alice\"];"));
+        assert!(dot.contains("\"carol_peer\" [label=\"carol_pe\"];"));
+        assert!(dot.contains("\"alice_peer\" -> \"bob_peer\" [weight=100, limit=100, balance=25];"));
+        assert!(dot.contains("\"bob_peer\" -> \"carol_peer\" [weight=50, limit=50, balance=0];"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_render_graphml() {
+        let xml = small_network().render(GraphFormat::GraphMl);
+
+        assert!(xml.contains("edgedefault=\"directed\""));
+        assert_eq!(xml.matches("<node ").count(), 3);
+        assert_eq!(xml.matches("<edge ").count(), 2);
+        assert!(xml.contains("<data key=\"label\">Bob &amp; Co</data>"));
+        assert!(xml.contains("<edge source=\"alice_peer\" target=\"bob_peer\"><data key=\"limit\">100</data><data key=\"balance\">25</data></edge>"));
+        assert!(xml.ends_with("</graphml>\n"));
+    }
+
+    #[test]
+    fn test_writer_skips_written_nodes() {
+        let graph = small_network();
+        let mut writer = GraphWriter::new(GraphFormat::Dot);
+
+        // Bob is an endpoint of both edges but is only written once
+        let first = writer.page(&graph.nodes[..2], &graph.edges[..1]);
+        let second = writer.page(&graph.nodes[1..], &graph.edges[1..]);
+        assert_eq!(first.matches("[label=").count(), 2);
+        assert_eq!(second.matches("[label=").count(), 1);
+        assert!(second.contains("\"carol_peer\" [label=\"carol_pe\"];"));
+        assert!(second.contains("\"bob_peer\" -> \"carol_peer\""));
+    }
+
+    #[test]
+    fn test_format_from_accept() {
+        assert_eq!(GraphFormat::from_accept(Some("text/vnd.graphviz")), GraphFormat::Dot);
+        assert_eq!(GraphFormat::from_accept(Some("application/graphml+xml")), GraphFormat::GraphMl);
+        assert_eq!(GraphFormat::from_accept(None), GraphFormat::GraphMl);
+    }
+}
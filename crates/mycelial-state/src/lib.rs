@@ -7,6 +7,14 @@
 //! - **storage**: SQLite-based persistence with sqlx
 //! - **cache**: LRU in-memory caching for peers, messages, and credit relationships
 //! - **sync**: State synchronization with vector clocks and CRDT-style merge strategies
+//! - **graph**: Credit network export as GraphML or DOT
+//! - **digest**: Merkle state digests for divergence reports
+//! - **sync_keys**: Namespaced keys for the state_sync table
+//! - **governance**: Proposal and vote persistence with quorum tallying
+//! - **vouch**: Vouch persistence and transitive trust paths
+//! - **blocklist**: Peers whose connections and messages are refused
+//! - **credit_tally**: Per-node transfer tallies for conflict-free credit balances
+//! - **seen**: IDs of accepted messages, for replay protection across restarts
 //! - **error**: State-specific error types
 //!
 //! ## Example
@@ -34,9 +42,23 @@ pub mod error;
 pub mod storage;
 pub mod cache;
 pub mod sync;
+pub mod graph;
+pub mod digest;
+pub mod sync_keys;
+pub mod governance;
+pub mod vouch;
+pub mod tags;
+pub mod blocklist;
+pub mod credit_tally;
+pub mod seen;
 
 // Re-exports for convenience
 pub use error::{Result, StateError};
-pub use storage::SqliteStore;
-pub use cache::{StateCache, PeerCache, MessageCache, CreditCache, MemoryCache, CacheStats};
-pub use sync::{StateSync, StateUpdate, VectorClock, PeerInfoUpdate};
+pub use storage::{CreditSummary, MessagePage, MessageStats, PayloadSizeBuckets, PendingDirectMessage, SqliteStore, StoreOptions};
+pub use cache::{StateCache, PeerCache, MessageCache, CreditCache, MemoryCache, CacheStats, CacheMetrics, EvictionPolicy, WarmedCounts};
+pub use sync::{ClockOrdering, CompactionConfig, ConflictRecord, ConflictResolution, OverflowPolicy, SkipReason, StateSnapshot, StateSync, StateUpdate, SyncCodec, SyncResponse, UpdateEffect, VectorClock, PeerInfoUpdate};
+pub use graph::{CreditGraph, CreditGraphPage, GraphFormat, GraphWriter};
+pub use digest::{DigestBuckets, DigestDiff, DigestPage, DigestSummary, StateDigest};
+pub use credit_tally::{CreditTallies, CreditTally, OpeningBalance};
+pub use governance::{ProposalOutcome, ProposalVerdict};
+pub use seen::SeenMessage;
@@ -0,0 +1,129 @@
+//! Seen message IDs
+//!
+//! The node remembers which messages it accepted for as long as a replay
+//! could still pass its age check. Keeping the IDs here as well means a
+//! restart doesn't open a window in which old messages are accepted again.
+//! The node collects IDs in memory and writes them in batches.
+
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use tracing::info;
+
+use crate::error::Result;
+use crate::storage::SqliteStore;
+
+/// Rows per multi-row insert, keeping bind parameters (3 per row) under
+/// SQLite's default limit of 999
+const SEEN_BATCH_ROWS: usize = 300;
+
+/// A message ID the node accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeenMessage {
+    pub id: String,
+    pub seen_at: DateTime<Utc>,
+    /// When a replay can no longer pass the age check, so the ID may be forgotten
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SqliteStore {
+    /// Remember accepted message IDs, in one transaction
+    ///
+    /// An ID that is already stored takes the new times.
+    pub async fn record_seen_messages(&self, seen: &[SeenMessage]) -> Result<()> {
+        if seen.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.pool().begin().await?;
+        for chunk in seen.chunks(SEEN_BATCH_ROWS) {
+            let mut builder = QueryBuilder::<Sqlite>::new("INSERT INTO seen_messages (id, seen_at, expires_at) ");
+            builder.push_values(chunk, |mut row, message| {
+                row.push_bind(&message.id)
+                    .push_bind(message.seen_at.timestamp())
+                    .push_bind(message.expires_at.timestamp());
+            });
+            builder.push(
+                " ON CONFLICT(id) DO UPDATE SET seen_at = excluded.seen_at, expires_at = excluded.expires_at",
+            );
+            builder.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Message IDs that haven't expired at `now`, the soonest to expire first
+    pub async fn unexpired_seen_messages(&self, now: DateTime<Utc>) -> Result<Vec<SeenMessage>> {
+        let rows = sqlx::query(
+            "SELECT id, seen_at, expires_at FROM seen_messages WHERE expires_at > ? ORDER BY expires_at, id",
+        )
+        .bind(now.timestamp())
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                Some(SeenMessage {
+                    id: row.get("id"),
+                    seen_at: Utc.timestamp_opt(row.get("seen_at"), 0).single()?,
+                    expires_at: Utc.timestamp_opt(row.get("expires_at"), 0).single()?,
+                })
+            })
+            .collect())
+    }
+
+    /// Forget message IDs that expired by `now`, returns how many
+    pub async fn prune_seen_messages(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM seen_messages WHERE expires_at <= ?")
+            .bind(now.timestamp())
+            .execute(self.pool())
+            .await?;
+
+        let deleted = result.rows_affected();
+        if deleted > 0 {
+            info!("Pruned {} seen message IDs", deleted);
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_seen_messages() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let now = Utc::now();
+        let seen = |id: &str, expires_in: Duration| SeenMessage {
+            id: id.to_string(),
+            seen_at: now,
+            expires_at: now + expires_in,
+        };
+        store
+            .record_seen_messages(&[
+                seen("expired", Duration::minutes(-1)),
+                seen("dm", Duration::days(1)),
+                seen("broadcast", Duration::minutes(5)),
+            ])
+            .await
+            .unwrap();
+
+        let ids = |seen: Vec<SeenMessage>| seen.into_iter().map(|message| message.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.unexpired_seen_messages(now).await.unwrap()), ["broadcast", "dm"]);
+
+        // Seeing a message again extends it
+        store.record_seen_messages(&[seen("expired", Duration::hours(1))]).await.unwrap();
+        assert_eq!(ids(store.unexpired_seen_messages(now).await.unwrap()), ["broadcast", "expired", "dm"]);
+
+        assert_eq!(store.prune_seen_messages(now + Duration::minutes(10)).await.unwrap(), 1);
+        assert_eq!(ids(store.unexpired_seen_messages(now).await.unwrap()), ["expired", "dm"]);
+
+        // Batches larger than one insert are written whole
+        let many: Vec<_> = (0..SEEN_BATCH_ROWS + 5).map(|i| seen(&format!("m{}", i), Duration::hours(2))).collect();
+        store.record_seen_messages(&many).await.unwrap();
+        assert_eq!(store.unexpired_seen_messages(now).await.unwrap().len(), SEEN_BATCH_ROWS + 7);
+    }
+}
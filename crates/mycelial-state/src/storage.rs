@@ -4,27 +4,262 @@
 //! relationships using SQLite with sqlx.
 
 use async_trait::async_trait;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
+use multiaddr::Multiaddr;
 use mycelial_core::{
     credit::CreditRelationship,
     message::{Message, MessageType},
     peer::{PeerId, PeerInfo},
-    reputation::{Reputation, ReputationSnapshot},
+    reputation::{Reputation, ReputationPolicy, ReputationSnapshot},
     Result as CoreResult, StateStore,
 };
 use sqlx::{
-    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
-    Row,
+    sqlite::{
+        Sqlite, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+        SqliteSynchronous,
+    },
+    QueryBuilder, Row,
 };
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
 use std::str::FromStr;
-use tracing::{debug, info};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::cache::StateCache;
+use crate::credit_tally::{self, tallied_balance, OpeningBalance};
 use crate::error::{Result, StateError};
+use crate::digest::StateDigest;
+use crate::graph::{CreditGraphPage, GraphEdge, GraphNode};
+use crate::sync_keys;
+
+/// Bind parameters per row of the multi-row peer insert
+const PEER_ROW_PARAMS: usize = 11;
+
+/// Rows per multi-row peer insert, keeping bind parameters under SQLite's
+/// default limit of 999
+const PEER_BATCH_ROWS: usize = 999 / PEER_ROW_PARAMS;
+
+/// Default number of reputation snapshots kept per peer
+pub const DEFAULT_MAX_REPUTATION_HISTORY: usize = 100;
+
+/// Longest obligation cycle (in peers) searched for by credit netting
+pub const MAX_CREDIT_CYCLE_LEN: usize = 4;
+
+/// A numbered schema change, applied once and recorded in `schema_migrations`
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// Schema migrations in the order they apply
+///
+/// Append new migrations here; never edit or renumber one that has shipped,
+/// since databases record only its version.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "pending_dm",
+        sql: include_str!("../migrations/002_pending_dm.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "resource_contributions",
+        sql: include_str!("../migrations/003_resource_contributions.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "governance",
+        sql: include_str!("../migrations/004_governance.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "vouches",
+        sql: include_str!("../migrations/005_vouches.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "peer_tags",
+        sql: include_str!("../migrations/006_peer_tags.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "message_recipients",
+        sql: include_str!("../migrations/007_message_recipients.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "blocklist",
+        sql: include_str!("../migrations/008_blocklist.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "query_indexes",
+        sql: include_str!("../migrations/009_query_indexes.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "credit_tallies",
+        sql: include_str!("../migrations/010_credit_tallies.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "last_interaction",
+        sql: include_str!("../migrations/011_last_interaction.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "seen_messages",
+        sql: include_str!("../migrations/012_seen_messages.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "credit_opening_balances",
+        sql: include_str!("../migrations/013_credit_opening_balances.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "seen_message_expiry",
+        sql: include_str!("../migrations/014_seen_message_expiry.sql"),
+    },
+];
+
+/// Parse and dedupe a peer's addresses, dropping any that aren't multiaddrs
+///
+/// Addresses are stored in canonical form, so textual variants of the same
+/// multiaddr collapse into one entry. Order is preserved.
+fn valid_addresses(peer_id: &str, addresses: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    addresses
+        .iter()
+        .filter_map(|address| match address.parse::<Multiaddr>() {
+            Ok(addr) if !addr.is_empty() => Some(addr.to_string()),
+            Ok(_) => {
+                warn!("Dropping empty address for peer {}", peer_id);
+                None
+            }
+            Err(e) => {
+                warn!("Dropping invalid address {:?} for peer {}: {}", address, peer_id, e);
+                None
+            }
+        })
+        .filter(|address| seen.insert(address.clone()))
+        .collect()
+}
+
+/// Whether a database URL names an in-memory database
+///
+/// Covers `:memory:`, `sqlite::memory:` and URI filenames with `mode=memory`.
+fn is_in_memory(path: &str) -> bool {
+    path.contains(":memory:") || path.contains("mode=memory")
+}
+
+/// A direct message held for a recipient that is currently offline
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDirectMessage {
+    /// Message ID
+    pub id: String,
+    /// Recipient peer ID
+    pub recipient: String,
+    /// Serialized message, published as-is on delivery
+    pub payload: Vec<u8>,
+    /// When the message was queued
+    pub queued_at: DateTime<Utc>,
+    /// When the message is dropped if still undelivered
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A batch of messages in storage order, for walking the whole table
+#[derive(Debug, Clone)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,
+    /// Cursor for the following batch, or None once the table is exhausted
+    pub next_cursor: Option<i64>,
+}
+
+/// Message counts by type and payload size
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MessageStats {
+    /// All stored messages
+    pub total: u64,
+    /// Messages per type name, e.g. `Content`
+    pub by_type: BTreeMap<String, u64>,
+    /// Messages per payload size bucket
+    pub by_size: PayloadSizeBuckets,
+}
+
+/// Message counts by payload size
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PayloadSizeBuckets {
+    /// Under 256 bytes
+    pub under_256b: u64,
+    /// 256 bytes up to 1 KiB
+    pub under_1kb: u64,
+    /// 1 KiB up to 16 KiB
+    pub under_16kb: u64,
+    /// 16 KiB or more
+    pub larger: u64,
+}
+
+/// Totals across a peer's credit relationships
+///
+/// A relationship's balance is positive when the debtor owes the creditor and
+/// negative when the creditor owes the debtor. Amounts here are signed the same
+/// way from the peer's side: `total_extended` sums balances where the peer is
+/// creditor (what others owe it), `total_owed` sums balances where it is
+/// debtor (what it owes others), and `net = total_extended - total_owed` is
+/// positive when the peer is owed more than it owes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CreditSummary {
+    /// Balances of relationships where the peer is creditor
+    pub total_extended: f64,
+    /// Balances of relationships where the peer is debtor
+    pub total_owed: f64,
+    /// `total_extended - total_owed`
+    pub net: f64,
+    /// Relationships the peer is part of, active or not
+    pub relationship_count: u64,
+}
+
+/// Connection settings for [`SqliteStore`]
+#[derive(Debug, Clone, Copy)]
+pub struct StoreOptions {
+    /// Maximum pooled connections (at least 1)
+    pub max_connections: u32,
+    /// SQLite journal mode
+    pub journal_mode: SqliteJournalMode,
+    /// SQLite synchronous setting
+    pub synchronous: SqliteSynchronous,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            journal_mode: SqliteJournalMode::Wal,
+            synchronous: SqliteSynchronous::Normal,
+        }
+    }
+}
 
 /// SQLite-based storage backend
 pub struct SqliteStore {
     pool: SqlitePool,
+    /// Optional write-through cache for peers and credit relationships
+    cache: Option<Arc<StateCache>>,
+    /// Most recent reputation snapshots kept when writing a peer
+    max_reputation_history: usize,
+    /// How recomputed reputation scores are derived from the counters
+    reputation_policy: ReputationPolicy,
+    /// Refuse peer records whose id doesn't match their public key
+    strict_peer_ids: bool,
 }
 
 impl SqliteStore {
@@ -33,74 +268,258 @@ impl SqliteStore {
     /// # Arguments
     /// * `path` - Path to the SQLite database file (use ":memory:" for in-memory)
     pub async fn new(path: &str) -> Result<Self> {
-        info!("Initializing SQLite store at: {}", path);
+        Self::new_with_options(path, StoreOptions::default()).await
+    }
+
+    /// Create a new SQLite store with custom connection settings
+    pub async fn new_with_options(path: &str, store_options: StoreOptions) -> Result<Self> {
+        if store_options.max_connections < 1 {
+            return Err(StateError::Connection(
+                "max_connections must be at least 1".to_string(),
+            ));
+        }
+
+        info!(
+            "Initializing SQLite store at: {} (pool size {})",
+            path, store_options.max_connections
+        );
 
         let options = SqliteConnectOptions::from_str(path)
             .map_err(|e| StateError::Connection(e.to_string()))?
             .create_if_missing(true)
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal);
+            .foreign_keys(true)
+            .journal_mode(store_options.journal_mode)
+            .synchronous(store_options.synchronous);
+
+        let mut pool_options = SqlitePoolOptions::new().max_connections(store_options.max_connections);
+        if is_in_memory(path) {
+            // An in-memory database is dropped with its last connection, so
+            // never let the pool close them all
+            pool_options = pool_options
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None);
+        }
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+        let pool = pool_options
             .connect_with(options)
             .await
             .map_err(|e| StateError::Connection(e.to_string()))?;
 
-        let store = Self { pool };
+        let store = Self {
+            pool,
+            cache: None,
+            max_reputation_history: DEFAULT_MAX_REPUTATION_HISTORY,
+            reputation_policy: ReputationPolicy::default(),
+            strict_peer_ids: false,
+        };
         store.run_migrations().await?;
 
         info!("SQLite store initialized successfully");
         Ok(store)
     }
 
-    /// Run database migrations
+    /// Apply any migrations the database hasn't seen yet
+    ///
+    /// Each migration runs in its own transaction together with the row
+    /// recording it, so a failed migration leaves no trace and is retried on
+    /// the next start. Databases created before versions were tracked have
+    /// every migration re-run once; the early migrations only use
+    /// `IF NOT EXISTS`, so that is harmless.
     async fn run_migrations(&self) -> Result<()> {
         debug!("Running database migrations");
 
-        // Run the initial schema
-        sqlx::query(include_str!("../migrations/001_initial.sql"))
-            .execute(&self.pool)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StateError::Migration(e.to_string()))?;
+
+        let applied: HashSet<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+            .fetch_all(&self.pool)
             .await
-            .map_err(|e| StateError::Migration(e.to_string()))?;
+            .map_err(|e| StateError::Migration(e.to_string()))?
+            .into_iter()
+            .collect();
+
+        for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+            let migrate = |e: sqlx::Error| {
+                StateError::Migration(format!("{:03}_{}: {}", migration.version, migration.name, e))
+            };
+            let mut tx = self.pool.begin().await.map_err(migrate)?;
+            sqlx::query(migration.sql).execute(&mut *tx).await.map_err(migrate)?;
+            sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+                .bind(migration.version)
+                .bind(migration.name)
+                .execute(&mut *tx)
+                .await
+                .map_err(migrate)?;
+            tx.commit().await.map_err(migrate)?;
+            info!("Applied migration {:03}_{}", migration.version, migration.name);
+        }
 
         debug!("Migrations completed successfully");
         Ok(())
     }
 
+    /// Check that the database answers a trivial query
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Version of the newest applied migration (0 for an empty database)
+    pub async fn schema_version(&self) -> Result<i64> {
+        let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Keep a cache in sync with peer and credit writes, serving reads from it
+    ///
+    /// Writes populate or invalidate the cached entry; `get_peer` and
+    /// `get_credit_relationship` answer from the cache on a hit and fill it
+    /// on a miss.
+    pub fn with_cache(mut self, cache: Arc<StateCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set how many reputation snapshots are stored per peer
+    pub fn with_max_reputation_history(mut self, max: usize) -> Self {
+        self.max_reputation_history = max;
+        self
+    }
+
+    /// Reject peer records whose id isn't derived from their public key
+    ///
+    /// See [`PeerInfo::verify_id`]. Off by default.
+    pub fn with_strict_peer_ids(mut self, strict: bool) -> Self {
+        self.strict_peer_ids = strict;
+        self
+    }
+
+    /// Refuse a spoofed peer record when strict peer ids are on
+    fn check_peer_id(&self, info: &PeerInfo) -> Result<()> {
+        if self.strict_peer_ids && !info.verify_id() {
+            return Err(StateError::InvalidData(format!(
+                "peer id {} doesn't match its public key",
+                info.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Derive recomputed reputation scores with a custom policy
+    pub fn with_reputation_policy(mut self, policy: ReputationPolicy) -> Self {
+        self.reputation_policy = policy;
+        self
+    }
+
+    /// Copy a reputation with its history cut to the most recent snapshots
+    fn trimmed(&self, reputation: &Reputation) -> Reputation {
+        let mut reputation = reputation.clone();
+        let excess = reputation.history.len().saturating_sub(self.max_reputation_history);
+        reputation.history.drain(..excess);
+        reputation
+    }
+
+    /// Get the write-through cache, if one is attached
+    pub fn cache(&self) -> Option<&Arc<StateCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Drop a peer from the cache so the next read goes to the database
+    fn invalidate_peer(&self, peer_id: &str) {
+        if let Some(cache) = &self.cache {
+            cache.peers.remove(peer_id);
+        }
+    }
+
     /// Get a reference to the connection pool
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
 
+    /// Close the connection pool, waiting for in-flight queries to finish
+    ///
+    /// In WAL mode this checkpoints the log, so nothing is left for recovery
+    /// on the next start. Any later query on this store fails.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Copy the WAL into the database file and truncate it
+    ///
+    /// Readers still using old pages can keep the checkpoint from finishing;
+    /// it is then picked up again by the next one.
+    pub async fn checkpoint(&self) -> Result<()> {
+        let (busy, log_frames, checkpointed): (i64, i64, i64) =
+            sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+                .fetch_one(&self.pool)
+                .await?;
+
+        if busy != 0 {
+            warn!("WAL checkpoint incomplete ({} of {} frames)", checkpointed, log_frames);
+        } else {
+            debug!("WAL checkpoint done ({} frames)", checkpointed);
+        }
+        Ok(())
+    }
+
+    /// Rebuild the database file, reclaiming space left by deleted rows
+    ///
+    /// VACUUM rewrites the whole file and holds the write lock while doing
+    /// so, so writers block (or fail with SQLITE_BUSY) until it finishes.
+    /// Run it when the node is quiet, e.g. after pruning, not while a sync
+    /// or import is writing heavily.
+    pub async fn vacuum(&self) -> Result<()> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        info!("Vacuumed database");
+        Ok(())
+    }
+
     // ========== Peer Operations ==========
 
     /// Store or update a peer
     pub async fn upsert_peer(&self, info: &PeerInfo, reputation: Option<&Reputation>) -> Result<()> {
+        self.check_peer_id(info)?;
         let peer_id = info.id.as_str();
         let public_key = &info.public_key;
-        let addresses_json = serde_json::to_string(&info.addresses)?;
+        let addresses = valid_addresses(peer_id, &info.addresses);
+        let addresses_json = serde_json::to_string(&addresses)?;
         let first_seen = info.first_seen.timestamp();
         let last_seen = info.last_seen.timestamp();
         let display_name = info.name.as_deref();
+        let reputation = reputation.map(|rep| self.trimmed(rep));
 
-        let (reputation_score, successful, failed, history_json) = match reputation {
+        let (reputation_score, successful, failed, history_json, last_interaction) = match &reputation {
             Some(rep) => (
                 rep.score,
                 rep.successful_interactions as i64,
                 rep.failed_interactions as i64,
                 serde_json::to_string(&rep.history)?,
+                rep.last_updated.timestamp(),
             ),
-            None => (0.5, 0i64, 0i64, "[]".to_string()),
+            None => (0.5, 0i64, 0i64, "[]".to_string(), Utc::now().timestamp()),
         };
 
-        sqlx::query(
+        // The stored first_seen and name can differ from the incoming ones
+        let stored = sqlx::query(
             r#"
             INSERT INTO peers (
                 peer_id, public_key, display_name, addresses_json,
                 reputation_score, successful_interactions, failed_interactions,
-                reputation_history_json, first_seen, last_seen
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                reputation_history_json, first_seen, last_seen, last_interaction_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(peer_id) DO UPDATE SET
                 public_key = excluded.public_key,
                 display_name = COALESCE(excluded.display_name, peers.display_name),
@@ -110,7 +529,9 @@ impl SqliteStore {
                 failed_interactions = excluded.failed_interactions,
                 reputation_history_json = excluded.reputation_history_json,
                 last_seen = excluded.last_seen,
+                last_interaction_at = excluded.last_interaction_at,
                 updated_at = strftime('%s', 'now')
+            RETURNING first_seen, display_name
             "#,
         )
         .bind(peer_id)
@@ -123,20 +544,131 @@ impl SqliteStore {
         .bind(&history_json)
         .bind(first_seen)
         .bind(last_seen)
-        .execute(&self.pool)
+        .bind(last_interaction)
+        .fetch_one(&self.pool)
         .await?;
 
+        if let Some(cache) = &self.cache {
+            let first_seen: i64 = stored.get("first_seen");
+            let info = PeerInfo {
+                addresses,
+                first_seen: Utc.timestamp_opt(first_seen, 0).single().unwrap_or(info.first_seen),
+                name: stored.get("display_name"),
+                ..info.clone()
+            };
+            cache.peers.insert(info, reputation.unwrap_or_default());
+        }
+
         debug!("Upserted peer: {}", peer_id);
         Ok(())
     }
 
+    /// Store or update many peers in a single transaction
+    ///
+    /// Rows are written with multi-row inserts; if any row fails the whole
+    /// batch is rolled back. Returns the number of rows affected.
+    pub async fn upsert_peers_batch(&self, entries: &[(PeerInfo, Option<Reputation>)]) -> Result<u64> {
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        for (info, _) in entries {
+            self.check_peer_id(info)?;
+        }
+
+        let mut rows = Vec::with_capacity(entries.len());
+        for (info, reputation) in entries {
+            let reputation = reputation.as_ref().map(|rep| self.trimmed(rep));
+            let (reputation_score, successful, failed, history_json, last_interaction) = match &reputation {
+                Some(rep) => (
+                    rep.score,
+                    rep.successful_interactions as i64,
+                    rep.failed_interactions as i64,
+                    serde_json::to_string(&rep.history)?,
+                    rep.last_updated.timestamp(),
+                ),
+                None => (0.5, 0i64, 0i64, "[]".to_string(), Utc::now().timestamp()),
+            };
+            rows.push((
+                info,
+                serde_json::to_string(&valid_addresses(info.id.as_str(), &info.addresses))?,
+                reputation_score,
+                successful,
+                failed,
+                history_json,
+                last_interaction,
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut affected = 0;
+
+        for chunk in rows.chunks(PEER_BATCH_ROWS) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                r#"
+                INSERT INTO peers (
+                    peer_id, public_key, display_name, addresses_json,
+                    reputation_score, successful_interactions, failed_interactions,
+                    reputation_history_json, first_seen, last_seen, last_interaction_at
+                ) "#,
+            );
+            builder.push_values(
+                chunk,
+                |mut row, (info, addresses_json, score, successful, failed, history_json, last_interaction)| {
+                row.push_bind(info.id.as_str())
+                    .push_bind(&info.public_key)
+                    .push_bind(info.name.as_deref())
+                    .push_bind(addresses_json)
+                    .push_bind(*score)
+                    .push_bind(*successful)
+                    .push_bind(*failed)
+                    .push_bind(history_json)
+                    .push_bind(info.first_seen.timestamp())
+                    .push_bind(info.last_seen.timestamp())
+                    .push_bind(*last_interaction);
+                },
+            );
+            builder.push(
+                r#"
+                ON CONFLICT(peer_id) DO UPDATE SET
+                    public_key = excluded.public_key,
+                    display_name = COALESCE(excluded.display_name, peers.display_name),
+                    addresses_json = excluded.addresses_json,
+                    reputation_score = excluded.reputation_score,
+                    successful_interactions = excluded.successful_interactions,
+                    failed_interactions = excluded.failed_interactions,
+                    reputation_history_json = excluded.reputation_history_json,
+                    last_seen = excluded.last_seen,
+                    last_interaction_at = excluded.last_interaction_at,
+                    updated_at = strftime('%s', 'now')
+                "#,
+            );
+
+            // Dropping `tx` on error rolls the whole batch back
+            affected += builder.build().execute(&mut *tx).await?.rows_affected();
+        }
+
+        tx.commit().await?;
+
+        for (info, _) in entries {
+            self.invalidate_peer(info.id.as_str());
+        }
+
+        debug!("Upserted {} peers in batch", entries.len());
+        Ok(affected)
+    }
+
     /// Get a peer by ID
     pub async fn get_peer(&self, peer_id: &str) -> Result<Option<(PeerInfo, Reputation)>> {
+        if let Some(hit) = self.cache.as_ref().and_then(|cache| cache.peers.get(peer_id)) {
+            return Ok(Some(hit));
+        }
+
         let row = sqlx::query(
             r#"
             SELECT peer_id, public_key, display_name, addresses_json, location_json,
                    reputation_score, successful_interactions, failed_interactions,
-                   reputation_history_json, first_seen, last_seen
+                   reputation_history_json, first_seen, last_seen, last_interaction_at
             FROM peers WHERE peer_id = ?
             "#,
         )
@@ -148,6 +680,9 @@ impl SqliteStore {
             Some(row) => {
                 let peer_info = self.row_to_peer_info(&row)?;
                 let reputation = self.row_to_reputation(&row)?;
+                if let Some(cache) = &self.cache {
+                    cache.peers.insert(peer_info.clone(), reputation.clone());
+                }
                 Ok(Some((peer_info, reputation)))
             }
             None => Ok(None),
@@ -160,7 +695,7 @@ impl SqliteStore {
             r#"
             SELECT peer_id, public_key, display_name, addresses_json, location_json,
                    reputation_score, successful_interactions, failed_interactions,
-                   reputation_history_json, first_seen, last_seen
+                   reputation_history_json, first_seen, last_seen, last_interaction_at
             FROM peers ORDER BY last_seen DESC
             "#,
         )
@@ -177,13 +712,72 @@ impl SqliteStore {
         Ok(results)
     }
 
+    /// List the most recently seen peers, newest first
+    pub async fn list_recent_peers(&self, limit: i64) -> Result<Vec<(PeerInfo, Reputation)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT peer_id, public_key, display_name, addresses_json, location_json,
+                   reputation_score, successful_interactions, failed_interactions,
+                   reputation_history_json, first_seen, last_seen, last_interaction_at
+            FROM peers ORDER BY last_seen DESC LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            let peer_info = self.row_to_peer_info(&row)?;
+            let reputation = self.row_to_reputation(&row)?;
+            results.push((peer_info, reputation));
+        }
+
+        Ok(results)
+    }
+
+    /// Find peers whose display name starts with `prefix`, ignoring case
+    ///
+    /// Peers without a display name never match. `%` and `_` in the prefix
+    /// are matched literally.
+    pub async fn search_peers_by_name(&self, prefix: &str, limit: i64) -> Result<Vec<(PeerInfo, Reputation)>> {
+        let escaped = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let rows = sqlx::query(
+            r#"
+            SELECT peer_id, public_key, display_name, addresses_json, location_json,
+                   reputation_score, successful_interactions, failed_interactions,
+                   reputation_history_json, first_seen, last_seen, last_interaction_at
+            FROM peers
+            WHERE display_name IS NOT NULL AND display_name LIKE ? ESCAPE '\'
+            ORDER BY display_name COLLATE NOCASE, peer_id
+            LIMIT ?
+            "#,
+        )
+        .bind(format!("{}%", escaped))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            let peer_info = self.row_to_peer_info(&row)?;
+            let reputation = self.row_to_reputation(&row)?;
+            results.push((peer_info, reputation));
+        }
+
+        Ok(results)
+    }
+
     /// List peers with reputation above threshold
     pub async fn list_trusted_peers(&self, threshold: f64) -> Result<Vec<(PeerInfo, Reputation)>> {
         let rows = sqlx::query(
             r#"
             SELECT peer_id, public_key, display_name, addresses_json, location_json,
                    reputation_score, successful_interactions, failed_interactions,
-                   reputation_history_json, first_seen, last_seen
+                   reputation_history_json, first_seen, last_seen, last_interaction_at
             FROM peers WHERE reputation_score >= ? ORDER BY reputation_score DESC
             "#,
         )
@@ -203,6 +797,7 @@ impl SqliteStore {
 
     /// Update peer reputation
     pub async fn update_peer_reputation(&self, peer_id: &str, reputation: &Reputation) -> Result<()> {
+        let reputation = &self.trimmed(reputation);
         let history_json = serde_json::to_string(&reputation.history)?;
 
         let result = sqlx::query(
@@ -212,6 +807,7 @@ impl SqliteStore {
                 successful_interactions = ?,
                 failed_interactions = ?,
                 reputation_history_json = ?,
+                last_interaction_at = ?,
                 updated_at = strftime('%s', 'now')
             WHERE peer_id = ?
             "#,
@@ -220,6 +816,7 @@ impl SqliteStore {
         .bind(reputation.successful_interactions as i64)
         .bind(reputation.failed_interactions as i64)
         .bind(&history_json)
+        .bind(reputation.last_updated.timestamp())
         .bind(peer_id)
         .execute(&self.pool)
         .await?;
@@ -231,10 +828,95 @@ impl SqliteStore {
             });
         }
 
+        if let Some(cache) = &self.cache {
+            cache.peers.update_reputation(peer_id, reputation.clone());
+        }
+
         debug!("Updated reputation for peer: {}", peer_id);
         Ok(())
     }
 
+    /// Recalculate a peer's reputation score from its interaction counters
+    ///
+    /// The score is derived by the store's [`ReputationPolicy`] (by default
+    /// `successful / (successful + failed)`, or the neutral 0.5 for a peer
+    /// with no recorded interactions). The counters' age is measured from
+    /// the peer's last interaction, which neither recomputing nor other
+    /// writes to the peer touch. Returns the new score.
+    pub async fn recompute_reputation(&self, peer_id: &str) -> Result<f64> {
+        let row = sqlx::query(
+            "SELECT successful_interactions, failed_interactions, last_interaction_at FROM peers WHERE peer_id = ?",
+        )
+        .bind(peer_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| StateError::NotFound {
+            entity: "peer".to_string(),
+            id: peer_id.to_string(),
+        })?;
+
+        let score = self.policy_score(&row, Utc::now());
+        sqlx::query("UPDATE peers SET reputation_score = ? WHERE peer_id = ?")
+            .bind(score)
+            .bind(peer_id)
+            .execute(&self.pool)
+            .await?;
+
+        self.invalidate_peer(peer_id);
+        Ok(score)
+    }
+
+    /// Recalculate every peer's reputation score from its counters
+    ///
+    /// Repairs scores that drifted from the counters (or from a changed
+    /// policy); returns the number of peers whose score changed.
+    pub async fn recompute_all_reputations(&self) -> Result<u64> {
+        let rows = sqlx::query(
+            r#"
+            SELECT peer_id, reputation_score, successful_interactions, failed_interactions, last_interaction_at
+            FROM peers
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let mut changed = Vec::new();
+        for row in &rows {
+            let peer_id: String = row.get("peer_id");
+            let stored: f64 = row.get("reputation_score");
+            let score = self.policy_score(row, now);
+            if score == stored {
+                continue;
+            }
+            sqlx::query("UPDATE peers SET reputation_score = ? WHERE peer_id = ?")
+                .bind(score)
+                .bind(&peer_id)
+                .execute(&mut *tx)
+                .await?;
+            changed.push(peer_id);
+        }
+        tx.commit().await?;
+
+        for peer_id in &changed {
+            self.invalidate_peer(peer_id);
+        }
+        info!("Recomputed reputation for {} peers", changed.len());
+        Ok(changed.len() as u64)
+    }
+
+    /// Score a peers row's counters under the store's reputation policy
+    fn policy_score(&self, row: &sqlx::sqlite::SqliteRow, now: DateTime<Utc>) -> f64 {
+        let successful: i64 = row.get("successful_interactions");
+        let failed: i64 = row.get("failed_interactions");
+        let last_interaction: Option<i64> = row.get("last_interaction_at");
+        let age = last_interaction
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .map_or(chrono::Duration::zero(), |at| now - at);
+        self.reputation_policy.score(successful.max(0) as u64, failed.max(0) as u64, age)
+    }
+
     /// Update peer last seen timestamp
     pub async fn touch_peer(&self, peer_id: &str) -> Result<()> {
         let now = Utc::now().timestamp();
@@ -250,18 +932,67 @@ impl SqliteStore {
         .execute(&self.pool)
         .await?;
 
+        self.invalidate_peer(peer_id);
         Ok(())
     }
 
-    /// Delete a peer
-    pub async fn delete_peer(&self, peer_id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM peers WHERE peer_id = ?")
+    /// Delete a peer, returns false if it wasn't known
+    ///
+    /// Also deletes the peer's tags, the credit relationships it is a party
+    /// to along with their transactions, and the messages it sent.
+    pub async fn delete_peer(&self, peer_id: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Rows referencing the peer would otherwise block the delete; tags
+        // and credit tallies go with it through ON DELETE CASCADE
+        sqlx::query(
+            r#"
+            DELETE FROM credit_transactions WHERE relationship_id IN (
+                SELECT id FROM credit_relationships WHERE creditor_peer_id = ?1 OR debtor_peer_id = ?1
+            )
+            "#,
+        )
+        .bind(peer_id)
+        .execute(&mut *tx)
+        .await?;
+        let relationships: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM credit_relationships WHERE creditor_peer_id = ?1 OR debtor_peer_id = ?1 RETURNING id",
+        )
+        .bind(peer_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let messages: Vec<String> =
+            sqlx::query_scalar("DELETE FROM messages WHERE sender_peer_id = ? RETURNING id")
+                .bind(peer_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let result = sqlx::query("DELETE FROM peers WHERE peer_id = ?")
             .bind(peer_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
 
-        debug!("Deleted peer: {}", peer_id);
-        Ok(())
+        tx.commit().await?;
+
+        self.invalidate_peer(peer_id);
+        if let Some(cache) = &self.cache {
+            for id in &relationships {
+                cache.credits.remove(id);
+            }
+            for id in messages.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+                cache.messages.remove(&id);
+            }
+        }
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            debug!(
+                "Deleted peer {} with {} credit relationships and {} messages",
+                peer_id,
+                relationships.len(),
+                messages.len()
+            );
+        }
+        Ok(deleted)
     }
 
     /// Count peers
@@ -274,7 +1005,7 @@ impl SqliteStore {
     }
 
     // Helper to convert row to PeerInfo
-    fn row_to_peer_info(&self, row: &sqlx::sqlite::SqliteRow) -> Result<PeerInfo> {
+    pub(crate) fn row_to_peer_info(&self, row: &sqlx::sqlite::SqliteRow) -> Result<PeerInfo> {
         let peer_id: String = row.get("peer_id");
         // public_key is now stored as base58 string (TEXT), with fallback for legacy BLOB
         let public_key: String = row.try_get::<String, _>("public_key")
@@ -302,11 +1033,12 @@ impl SqliteStore {
     }
 
     // Helper to convert row to Reputation
-    fn row_to_reputation(&self, row: &sqlx::sqlite::SqliteRow) -> Result<Reputation> {
+    pub(crate) fn row_to_reputation(&self, row: &sqlx::sqlite::SqliteRow) -> Result<Reputation> {
         let score: f64 = row.get("reputation_score");
         let successful: i64 = row.get("successful_interactions");
         let failed: i64 = row.get("failed_interactions");
         let history_json: String = row.get("reputation_history_json");
+        let last_interaction: Option<i64> = row.get("last_interaction_at");
 
         let history: Vec<ReputationSnapshot> = serde_json::from_str(&history_json)
             .map_err(|e| StateError::Deserialization(e.to_string()))?;
@@ -315,11 +1047,47 @@ impl SqliteStore {
             score,
             successful_interactions: successful as u64,
             failed_interactions: failed as u64,
-            last_updated: Utc::now(),
+            last_updated: last_interaction
+                .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+                .unwrap_or_else(Utc::now),
             history,
         })
     }
 
+    /// Export every peer with its reputation as a JSON array
+    ///
+    /// Each element is a `[PeerInfo, Reputation]` pair, the format read by
+    /// [`import_peers`](Self::import_peers).
+    pub async fn export_peers(&self) -> Result<String> {
+        let peers = self.list_peers().await?;
+        Ok(serde_json::to_string(&peers)?)
+    }
+
+    /// Import peers exported with [`export_peers`](Self::export_peers)
+    ///
+    /// All entries are written in one transaction. An entry that doesn't
+    /// deserialize fails the whole import, unless `lenient` is set, in which
+    /// case it is skipped with a warning. Returns the number imported.
+    pub async fn import_peers(&self, json: &str, lenient: bool) -> Result<usize> {
+        let raw: Vec<serde_json::Value> = serde_json::from_str(json)
+            .map_err(|e| StateError::Deserialization(e.to_string()))?;
+
+        let mut entries = Vec::with_capacity(raw.len());
+        for (index, value) in raw.into_iter().enumerate() {
+            match serde_json::from_value::<(PeerInfo, Reputation)>(value) {
+                Ok((info, reputation)) => entries.push((info, Some(reputation))),
+                Err(e) if lenient => warn!("Skipping peer entry {}: {}", index, e),
+                Err(e) => {
+                    return Err(StateError::Deserialization(format!("peer entry {}: {}", index, e)))
+                }
+            }
+        }
+
+        self.upsert_peers_batch(&entries).await?;
+        info!("Imported {} peers", entries.len());
+        Ok(entries.len())
+    }
+
     // ========== Message Operations ==========
 
     /// Store a message
@@ -328,12 +1096,19 @@ impl SqliteStore {
         let message_type = format!("{:?}", message.message_type);
         let sender = message.sender.as_str();
         let recipient = message.recipient.as_ref().map(|p| p.as_str().to_string());
+        let recipients_json = if message.recipients.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&message.recipients)?)
+        };
         let timestamp = message.timestamp.timestamp();
 
         sqlx::query(
             r#"
-            INSERT INTO messages (id, message_type, sender_peer_id, recipient_peer_id, payload, signature, timestamp)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO messages (
+                id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(id) DO NOTHING
             "#,
         )
@@ -341,6 +1116,7 @@ impl SqliteStore {
         .bind(&message_type)
         .bind(sender)
         .bind(&recipient)
+        .bind(&recipients_json)
         .bind(&message.payload)
         .bind(&message.signature)
         .bind(timestamp)
@@ -355,7 +1131,7 @@ impl SqliteStore {
     pub async fn get_message(&self, id: &Uuid) -> Result<Option<Message>> {
         let row = sqlx::query(
             r#"
-            SELECT id, message_type, sender_peer_id, recipient_peer_id, payload, signature, timestamp
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
             FROM messages WHERE id = ?
             "#,
         )
@@ -373,7 +1149,7 @@ impl SqliteStore {
     pub async fn list_messages_from(&self, peer_id: &str, limit: i64) -> Result<Vec<Message>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, message_type, sender_peer_id, recipient_peer_id, payload, signature, timestamp
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
             FROM messages WHERE sender_peer_id = ?
             ORDER BY timestamp DESC LIMIT ?
             "#,
@@ -391,14 +1167,26 @@ impl SqliteStore {
         Ok(results)
     }
 
-    /// List recent messages
-    pub async fn list_recent_messages(&self, limit: i64) -> Result<Vec<Message>> {
+    /// List the latest `limit` messages a peer sent or received, oldest first
+    ///
+    /// Received covers direct messages to the peer and group messages naming
+    /// it; broadcasts have no recipient and only count for their sender.
+    pub async fn list_conversation(&self, peer_id: &str, limit: i64) -> Result<Vec<Message>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, message_type, sender_peer_id, recipient_peer_id, payload, signature, timestamp
-            FROM messages ORDER BY timestamp DESC LIMIT ?
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            FROM (
+                SELECT rowid AS seq, * FROM messages
+                WHERE sender_peer_id = ?1
+                   OR recipient_peer_id = ?1
+                   OR EXISTS (SELECT 1 FROM json_each(messages.recipients_json) WHERE json_each.value = ?1)
+                ORDER BY timestamp DESC, seq DESC
+                LIMIT ?2
+            )
+            ORDER BY timestamp ASC, seq ASC
             "#,
         )
+        .bind(peer_id)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
@@ -411,18 +1199,27 @@ impl SqliteStore {
         Ok(results)
     }
 
-    /// List messages by type
-    pub async fn list_messages_by_type(&self, message_type: &MessageType, limit: i64) -> Result<Vec<Message>> {
-        let type_str = format!("{:?}", message_type);
+    /// Count messages sent by a peer at or after `since`
+    pub async fn count_messages_from(&self, peer_id: &str, since: DateTime<Utc>) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) as count FROM messages WHERE sender_peer_id = ? AND timestamp >= ?",
+        )
+        .bind(peer_id)
+        .bind(since.timestamp())
+        .fetch_one(&self.pool)
+        .await?;
 
+        Ok(row.get("count"))
+    }
+
+    /// List recent messages
+    pub async fn list_recent_messages(&self, limit: i64) -> Result<Vec<Message>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, message_type, sender_peer_id, recipient_peer_id, payload, signature, timestamp
-            FROM messages WHERE message_type = ?
-            ORDER BY timestamp DESC LIMIT ?
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            FROM messages ORDER BY timestamp DESC LIMIT ?
             "#,
         )
-        .bind(&type_str)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
@@ -435,49 +1232,166 @@ impl SqliteStore {
         Ok(results)
     }
 
-    /// Delete old messages
-    pub async fn prune_messages(&self, older_than_secs: i64) -> Result<u64> {
-        let cutoff = Utc::now().timestamp() - older_than_secs;
-
-        let result = sqlx::query("DELETE FROM messages WHERE timestamp < ?")
-            .bind(cutoff)
-            .execute(&self.pool)
-            .await?;
+    /// List up to `limit` messages stored after `cursor`, oldest first
+    ///
+    /// Start from cursor 0 and follow [`MessagePage::next_cursor`]; each
+    /// batch is a keyset query on the rowid, so walking the table never holds
+    /// more than one batch in memory.
+    pub async fn list_messages_page(&self, cursor: i64, limit: i64) -> Result<MessagePage> {
+        let rows = sqlx::query(
+            r#"
+            SELECT rowid, id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            FROM messages WHERE rowid > ?
+            ORDER BY rowid LIMIT ?
+            "#,
+        )
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
 
-        let deleted = result.rows_affected();
-        if deleted > 0 {
-            info!("Pruned {} old messages", deleted);
+        let full = !rows.is_empty() && rows.len() as i64 >= limit;
+        let mut last_rowid = cursor;
+        let mut messages = Vec::with_capacity(rows.len());
+        for row in rows {
+            last_rowid = row.get("rowid");
+            messages.push(self.row_to_message(&row)?);
         }
 
-        Ok(deleted)
+        Ok(MessagePage {
+            messages,
+            next_cursor: full.then_some(last_rowid),
+        })
     }
 
-    // Helper to convert row to Message
+    /// List messages of one type from a specific sender
+    pub async fn list_messages_from_by_type(
+        &self,
+        peer_id: &str,
+        message_type: &MessageType,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        let type_str = format!("{:?}", message_type);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            FROM messages WHERE sender_peer_id = ? AND message_type = ?
+            ORDER BY timestamp DESC LIMIT ?
+            "#,
+        )
+        .bind(peer_id)
+        .bind(&type_str)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            results.push(self.row_to_message(&row)?);
+        }
+
+        Ok(results)
+    }
+
+    /// List messages by type
+    pub async fn list_messages_by_type(&self, message_type: &MessageType, limit: i64) -> Result<Vec<Message>> {
+        let type_str = format!("{:?}", message_type);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, message_type, sender_peer_id, recipient_peer_id, recipients_json, payload, signature, timestamp
+            FROM messages WHERE message_type = ?
+            ORDER BY timestamp DESC LIMIT ?
+            "#,
+        )
+        .bind(&type_str)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            results.push(self.row_to_message(&row)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Count stored messages by type and payload size bucket
+    pub async fn message_stats(&self) -> Result<MessageStats> {
+        let rows = sqlx::query(
+            r#"
+            SELECT message_type,
+                   CASE
+                       WHEN length(payload) < 256 THEN 0
+                       WHEN length(payload) < 1024 THEN 1
+                       WHEN length(payload) < 16384 THEN 2
+                       ELSE 3
+                   END AS bucket,
+                   COUNT(*) AS count
+            FROM messages
+            GROUP BY message_type, bucket
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut stats = MessageStats::default();
+        for row in rows {
+            let count = row.get::<i64, _>("count") as u64;
+            stats.total += count;
+            *stats.by_type.entry(row.get("message_type")).or_default() += count;
+            let bucket = match row.get::<i64, _>("bucket") {
+                0 => &mut stats.by_size.under_256b,
+                1 => &mut stats.by_size.under_1kb,
+                2 => &mut stats.by_size.under_16kb,
+                _ => &mut stats.by_size.larger,
+            };
+            *bucket += count;
+        }
+        Ok(stats)
+    }
+
+    /// Delete old messages
+    pub async fn prune_messages(&self, older_than_secs: i64) -> Result<u64> {
+        let cutoff = Utc::now().timestamp() - older_than_secs;
+
+        let result = sqlx::query("DELETE FROM messages WHERE timestamp < ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        let deleted = result.rows_affected();
+        if deleted > 0 {
+            info!("Pruned {} old messages", deleted);
+        }
+
+        Ok(deleted)
+    }
+
+    // Helper to convert row to Message
     fn row_to_message(&self, row: &sqlx::sqlite::SqliteRow) -> Result<Message> {
         let id: String = row.get("id");
         let message_type_str: String = row.get("message_type");
         let sender: String = row.get("sender_peer_id");
         let recipient: Option<String> = row.get("recipient_peer_id");
+        let recipients: Vec<PeerId> = match row.get::<Option<String>, _>("recipients_json") {
+            Some(json) => serde_json::from_str(&json)?,
+            None => Vec::new(),
+        };
         let payload: Vec<u8> = row.get("payload");
         let signature: Option<Vec<u8>> = row.get("signature");
         let timestamp: i64 = row.get("timestamp");
 
-        let message_type = match message_type_str.as_str() {
-            "Discovery" => MessageType::Discovery,
-            "Content" => MessageType::Content,
-            "Reputation" => MessageType::Reputation,
-            "Credit" => MessageType::Credit,
-            "Governance" => MessageType::Governance,
-            "Direct" => MessageType::Direct,
-            "System" => MessageType::System,
-            _ => MessageType::System,
-        };
+        let message_type = message_type_str.parse().unwrap_or(MessageType::System);
 
         Ok(Message {
             id: Uuid::parse_str(&id).map_err(|e| StateError::Deserialization(e.to_string()))?,
             message_type,
             sender: PeerId(sender),
             recipient: recipient.map(PeerId),
+            recipients,
             payload,
             timestamp: Utc.timestamp_opt(timestamp, 0).single().unwrap_or_else(Utc::now),
             signature,
@@ -520,12 +1434,21 @@ impl SqliteStore {
         .execute(&self.pool)
         .await?;
 
+        // `established` survives a conflict, so re-read rather than trust `rel`
+        if let Some(cache) = &self.cache {
+            cache.credits.remove(&id);
+        }
+
         debug!("Upserted credit relationship: {}", id);
         Ok(id)
     }
 
     /// Get a credit relationship by ID
     pub async fn get_credit_relationship(&self, id: &str) -> Result<Option<CreditRelationship>> {
+        if let Some(hit) = self.cache.as_ref().and_then(|cache| cache.credits.get(id)) {
+            return Ok(Some(hit));
+        }
+
         let row = sqlx::query(
             r#"
             SELECT id, creditor_peer_id, debtor_peer_id, credit_limit, balance,
@@ -538,7 +1461,13 @@ impl SqliteStore {
         .await?;
 
         match row {
-            Some(row) => Ok(Some(self.row_to_credit_relationship(&row)?)),
+            Some(row) => {
+                let relationship = self.row_to_credit_relationship(&row)?;
+                if let Some(cache) = &self.cache {
+                    cache.credits.insert(relationship.clone());
+                }
+                Ok(Some(relationship))
+            }
             None => Ok(None),
         }
     }
@@ -592,6 +1521,36 @@ impl SqliteStore {
         Ok(results)
     }
 
+    /// Sum a peer's credit balances as creditor and as debtor
+    ///
+    /// Inactive relationships are included, since their balances still stand.
+    /// See [`CreditSummary`] for the sign conventions.
+    pub async fn credit_summary(&self, peer_id: &str) -> Result<CreditSummary> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN creditor_peer_id = ?1 THEN balance ELSE 0.0 END), 0.0) AS extended,
+                COALESCE(SUM(CASE WHEN debtor_peer_id = ?1 THEN balance ELSE 0.0 END), 0.0) AS owed,
+                COUNT(*) AS relationships
+            FROM credit_relationships
+            WHERE creditor_peer_id = ?1 OR debtor_peer_id = ?1
+            "#,
+        )
+        .bind(peer_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let total_extended: f64 = row.get("extended");
+        let total_owed: f64 = row.get("owed");
+        let relationship_count: i64 = row.get("relationships");
+        Ok(CreditSummary {
+            total_extended,
+            total_owed,
+            net: total_extended - total_owed,
+            relationship_count: relationship_count as u64,
+        })
+    }
+
     /// List all active credit relationships
     pub async fn list_active_credit_relationships(&self) -> Result<Vec<CreditRelationship>> {
         let rows = sqlx::query(
@@ -613,6 +1572,65 @@ impl SqliteStore {
         Ok(results)
     }
 
+    /// Count active credit relationships
+    pub async fn count_active_credit_relationships(&self) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM credit_relationships WHERE active = 1")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    /// List up to `limit` active credit relationships after `cursor` as graph edges
+    ///
+    /// Start from cursor 0 and follow [`CreditGraphPage::next_cursor`]. Each
+    /// page carries nodes for the peers its edges reference, labeled by peer
+    /// name or short ID, so exporting the graph holds one page at a time.
+    pub async fn credit_graph_page(&self, cursor: i64, limit: i64) -> Result<CreditGraphPage> {
+        let rows = sqlx::query(
+            r#"
+            SELECT r.rowid, r.creditor_peer_id, r.debtor_peer_id, r.credit_limit, r.balance,
+                   c.display_name AS creditor_name, d.display_name AS debtor_name
+            FROM credit_relationships r
+            LEFT JOIN peers c ON c.peer_id = r.creditor_peer_id
+            LEFT JOIN peers d ON d.peer_id = r.debtor_peer_id
+            WHERE r.active = 1 AND r.rowid > ?
+            ORDER BY r.rowid LIMIT ?
+            "#,
+        )
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let full = !rows.is_empty() && rows.len() as i64 >= limit;
+        let mut last_rowid = cursor;
+        let mut page = CreditGraphPage::default();
+        for row in rows {
+            last_rowid = row.get("rowid");
+            let source: String = row.get("creditor_peer_id");
+            let target: String = row.get("debtor_peer_id");
+            for (id, name) in [(&source, "creditor_name"), (&target, "debtor_name")] {
+                if page.nodes.iter().any(|node| &node.id == id) {
+                    continue;
+                }
+                let label = row
+                    .get::<Option<String>, _>(name)
+                    .unwrap_or_else(|| PeerId(id.clone()).short().to_string());
+                page.nodes.push(GraphNode { id: id.clone(), label });
+            }
+            page.edges.push(GraphEdge {
+                source,
+                target,
+                credit_limit: row.get("credit_limit"),
+                balance: row.get("balance"),
+            });
+        }
+        page.next_cursor = full.then_some(last_rowid);
+
+        Ok(page)
+    }
+
     /// Record a credit transaction
     pub async fn record_credit_transaction(
         &self,
@@ -643,6 +1661,310 @@ impl SqliteStore {
         Ok(())
     }
 
+    /// Move `amount` of credit from creditor to debtor on behalf of `node_id`,
+    /// returning the new balance
+    ///
+    /// The transfer goes into the node's tally (see [`crate::credit_tally`]);
+    /// a relationship tallied for the first time opens with the balance it
+    /// had so far. The tallies, the balance they add up to and the
+    /// transaction row are written in one SQL transaction. Its first
+    /// statement checks the limit and takes SQLite's write lock, so
+    /// concurrent transfers are serialized and can't overdraw. Only positive
+    /// amounts move credit; anything else is rejected with
+    /// [`StateError::InvalidData`].
+    pub async fn apply_transfer(&self, creditor: &str, debtor: &str, amount: f64, node_id: &str) -> Result<f64> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(StateError::InvalidData(format!(
+                "transfer amount must be positive, got {}",
+                amount
+            )));
+        }
+
+        let id = format!("{}_{}", creditor, debtor);
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let checked = sqlx::query(
+            r#"
+            UPDATE credit_relationships
+            SET last_transaction = ?2,
+                updated_at = strftime('%s', 'now')
+            WHERE creditor_peer_id = ?3 AND debtor_peer_id = ?4
+              AND active = 1 AND balance + ?1 <= credit_limit
+            RETURNING balance
+            "#,
+        )
+        .bind(amount)
+        .bind(now.timestamp())
+        .bind(creditor)
+        .bind(debtor)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let previous: f64 = match checked {
+            Some(row) => row.get("balance"),
+            None => {
+                // Nothing matched: work out why for the caller
+                let row = sqlx::query(
+                    r#"
+                    SELECT credit_limit, balance, active FROM credit_relationships
+                    WHERE creditor_peer_id = ? AND debtor_peer_id = ?
+                    "#,
+                )
+                .bind(creditor)
+                .bind(debtor)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                return Err(match row {
+                    None => StateError::NotFound {
+                        entity: "credit relationship".to_string(),
+                        id,
+                    },
+                    Some(row) if row.get::<i32, _>("active") == 0 => {
+                        StateError::InvalidData(format!("credit relationship {} is inactive", id))
+                    }
+                    Some(row) => StateError::CreditLimitExceeded {
+                        requested: amount,
+                        available: (row.get::<f64, _>("credit_limit") - row.get::<f64, _>("balance"))
+                            .max(0.0),
+                    },
+                });
+            }
+        };
+
+        let mut tallies = credit_tally::load_tallies(&mut tx, creditor, debtor).await?;
+        if tallies.is_empty() {
+            tallies.opening = Some(OpeningBalance {
+                amount: previous,
+                timestamp: now,
+                origin: node_id.to_string(),
+            });
+        }
+        tallies.nodes.entry(node_id.to_string()).or_default().record(amount);
+        credit_tally::store_tallies(&mut tx, creditor, debtor, &tallies).await?;
+        let balance = tallied_balance(&tallies);
+
+        sqlx::query(
+            r#"
+            UPDATE credit_relationships SET balance = ?
+            WHERE creditor_peer_id = ? AND debtor_peer_id = ?
+            "#,
+        )
+        .bind(balance)
+        .bind(creditor)
+        .bind(debtor)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO credit_transactions (id, relationship_id, amount, balance_after, description, timestamp)
+            VALUES (?, ?, ?, ?, NULL, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&id)
+        .bind(amount)
+        .bind(balance)
+        .bind(now.timestamp())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if let Some(cache) = &self.cache {
+            cache.credits.remove(&id);
+        }
+
+        debug!("Transferred {} on {} (balance {})", amount, id, balance);
+        Ok(balance)
+    }
+
+    /// Find obligation cycles through a peer, up to [`MAX_CREDIT_CYCLE_LEN`] peers
+    ///
+    /// A cycle `[a, b, c]` means a owes b, b owes c and c owes a, each over
+    /// an active relationship with a positive balance. Every cycle starts at
+    /// `peer_id` and is reported once.
+    pub async fn find_credit_cycles(&self, peer_id: &str) -> Result<Vec<Vec<String>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT creditor_peer_id, debtor_peer_id FROM credit_relationships
+            WHERE active = 1 AND balance > 0
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // debtor -> creditors it owes, sorted for deterministic output
+        let mut owes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for row in rows {
+            let creditor: String = row.get("creditor_peer_id");
+            let debtor: String = row.get("debtor_peer_id");
+            owes.entry(debtor).or_default().push(creditor);
+        }
+        for creditors in owes.values_mut() {
+            creditors.sort();
+        }
+
+        let mut cycles = Vec::new();
+        let mut path = vec![peer_id.to_string()];
+        collect_cycles(&owes, &mut path, MAX_CREDIT_CYCLE_LEN, &mut cycles);
+        Ok(cycles)
+    }
+
+    /// Clear `amount` of debt around an obligation cycle
+    ///
+    /// Each edge of the cycle (as returned by [`Self::find_credit_cycles`])
+    /// has its balance reduced by `amount` and a transaction recorded, all in
+    /// one SQL transaction: if any edge is missing, inactive or owes less
+    /// than `amount`, nothing is changed.
+    pub async fn apply_netting(&self, cycle: &[String], amount: f64) -> Result<()> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(StateError::InvalidData(format!(
+                "netting amount must be positive, got {}",
+                amount
+            )));
+        }
+        if cycle.len() < 2 || cycle.len() > MAX_CREDIT_CYCLE_LEN {
+            return Err(StateError::InvalidData(format!(
+                "netting cycle must have 2 to {} peers, got {}",
+                MAX_CREDIT_CYCLE_LEN,
+                cycle.len()
+            )));
+        }
+
+        let now = Utc::now().timestamp();
+        let mut ids = Vec::with_capacity(cycle.len());
+        let mut tx = self.pool.begin().await?;
+
+        for (i, debtor) in cycle.iter().enumerate() {
+            let creditor = &cycle[(i + 1) % cycle.len()];
+            let id = format!("{}_{}", creditor, debtor);
+
+            let updated = sqlx::query(
+                r#"
+                UPDATE credit_relationships
+                SET balance = balance - ?1,
+                    last_transaction = ?2,
+                    updated_at = strftime('%s', 'now')
+                WHERE creditor_peer_id = ?3 AND debtor_peer_id = ?4
+                  AND active = 1 AND balance >= ?1
+                RETURNING balance
+                "#,
+            )
+            .bind(amount)
+            .bind(now)
+            .bind(creditor)
+            .bind(debtor)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            // Returning early drops `tx`, rolling back the edges already netted
+            let Some(row) = updated else {
+                let exists = sqlx::query(
+                    "SELECT 1 FROM credit_relationships WHERE creditor_peer_id = ? AND debtor_peer_id = ?",
+                )
+                .bind(creditor)
+                .bind(debtor)
+                .fetch_optional(&mut *tx)
+                .await?
+                .is_some();
+
+                return Err(if exists {
+                    StateError::InvalidData(format!(
+                        "credit relationship {} is inactive or owes less than {}",
+                        id, amount
+                    ))
+                } else {
+                    StateError::NotFound {
+                        entity: "credit relationship".to_string(),
+                        id,
+                    }
+                });
+            };
+            let balance: f64 = row.get("balance");
+
+            sqlx::query(
+                r#"
+                INSERT INTO credit_transactions (id, relationship_id, amount, balance_after, description, timestamp)
+                VALUES (?, ?, ?, ?, 'netting', ?)
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&id)
+            .bind(-amount)
+            .bind(balance)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            ids.push(id);
+        }
+
+        tx.commit().await?;
+
+        if let Some(cache) = &self.cache {
+            for id in &ids {
+                cache.credits.remove(id);
+            }
+        }
+
+        debug!("Netted {} around cycle {}", amount, cycle.join(" -> "));
+        Ok(())
+    }
+
+    /// Delete old credit transactions
+    ///
+    /// Only the transaction log is pruned; relationship rows and their
+    /// balances are kept.
+    pub async fn prune_credit_transactions(&self, older_than_secs: i64) -> Result<u64> {
+        let cutoff = Utc::now().timestamp() - older_than_secs;
+
+        let result = sqlx::query("DELETE FROM credit_transactions WHERE timestamp < ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        let deleted = result.rows_affected();
+        if deleted > 0 {
+            info!("Pruned {} old credit transactions", deleted);
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deactivate credit relationships with no transaction in the given time
+    ///
+    /// Returns the number of relationships deactivated. Balances are kept, so
+    /// a line can be reactivated later.
+    pub async fn deactivate_stale_credit(&self, older_than_secs: i64) -> Result<u64> {
+        let cutoff = Utc::now().timestamp() - older_than_secs;
+
+        let ids: Vec<String> = sqlx::query_scalar(
+            r#"
+            UPDATE credit_relationships
+            SET active = 0, updated_at = strftime('%s', 'now')
+            WHERE active = 1 AND last_transaction < ?
+            RETURNING id
+            "#,
+        )
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if let Some(cache) = &self.cache {
+            for id in &ids {
+                cache.credits.remove(id);
+            }
+        }
+        if !ids.is_empty() {
+            info!("Deactivated {} stale credit relationships", ids.len());
+        }
+
+        Ok(ids.len() as u64)
+    }
+
     // Helper to convert row to CreditRelationship
     fn row_to_credit_relationship(&self, row: &sqlx::sqlite::SqliteRow) -> Result<CreditRelationship> {
         let creditor: String = row.get("creditor_peer_id");
@@ -664,10 +1986,70 @@ impl SqliteStore {
         })
     }
 
+    // ========== Resource Contribution Operations ==========
+
+    /// Record a resource contribution, adding to the peer's running total
+    pub async fn record_contribution(&self, peer_id: &str, resource_type: &str, amount: f64) -> Result<()> {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(StateError::InvalidData(format!(
+                "contribution amount must be a non-negative number, got {}",
+                amount
+            )));
+        }
+
+        sqlx::query(
+            "INSERT INTO resource_contributions (peer_id, resource_type, amount) VALUES (?, ?, ?)",
+        )
+        .bind(peer_id)
+        .bind(resource_type)
+        .bind(amount)
+        .execute(&self.pool)
+        .await?;
+
+        debug!("Recorded {} {} contribution from {}", amount, resource_type, peer_id);
+        Ok(())
+    }
+
+    /// Peers with the largest total contribution of a resource type, largest first
+    pub async fn top_contributors(&self, resource_type: &str, limit: u32) -> Result<Vec<(String, f64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT peer_id, SUM(amount) AS total
+            FROM resource_contributions
+            WHERE resource_type = ?
+            GROUP BY peer_id
+            ORDER BY total DESC, peer_id
+            LIMIT ?
+            "#,
+        )
+        .bind(resource_type)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("peer_id"), row.get("total")))
+            .collect())
+    }
+
     // ========== State Sync Operations ==========
 
     /// Store a sync key-value pair
+    ///
+    /// Keys in the reserved internal namespace are rejected; see [`sync_keys`].
     pub async fn set_sync_value(&self, key: &str, value: &[u8]) -> Result<()> {
+        sync_keys::validate_public(key)?;
+        self.write_sync_value(key, value).await
+    }
+
+    /// Store a node-internal sync value under a reserved key
+    pub async fn set_internal_sync_value(&self, key: &str, value: &[u8]) -> Result<()> {
+        sync_keys::validate_internal(key)?;
+        self.write_sync_value(key, value).await
+    }
+
+    async fn write_sync_value(&self, key: &str, value: &[u8]) -> Result<()> {
         sqlx::query(
             r#"
             INSERT INTO state_sync (key, value, version)
@@ -703,8 +2085,34 @@ impl SqliteStore {
         }
     }
 
+    /// List sync keys starting with a prefix, in key order
+    pub async fn list_sync_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            "SELECT key FROM state_sync WHERE substr(key, 1, ?) = ? ORDER BY key",
+        )
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("key")).collect())
+    }
+
     /// Delete a sync key
+    ///
+    /// Keys in the reserved internal namespace are rejected.
     pub async fn delete_sync_value(&self, key: &str) -> Result<()> {
+        sync_keys::validate_public(key)?;
+        self.remove_sync_value(key).await
+    }
+
+    /// Delete a node-internal sync key
+    pub async fn delete_internal_sync_value(&self, key: &str) -> Result<()> {
+        sync_keys::validate_internal(key)?;
+        self.remove_sync_value(key).await
+    }
+
+    async fn remove_sync_value(&self, key: &str) -> Result<()> {
         sqlx::query("DELETE FROM state_sync WHERE key = ?")
             .bind(key)
             .execute(&self.pool)
@@ -712,6 +2120,250 @@ impl SqliteStore {
 
         Ok(())
     }
+
+    /// Persist the topics the node is subscribed to, replacing the saved set
+    pub async fn save_subscriptions(&self, topics: &[String]) -> Result<()> {
+        let json = serde_json::to_vec(topics)?;
+        self.set_internal_sync_value(sync_keys::SUBSCRIPTIONS, &json).await
+    }
+
+    /// Load the saved subscriptions
+    ///
+    /// A missing or unreadable entry yields an empty list so a node always
+    /// starts; blank topics are dropped.
+    pub async fn load_subscriptions(&self) -> Result<Vec<String>> {
+        let Some((value, _)) = self.get_sync_value(sync_keys::SUBSCRIPTIONS).await? else {
+            return Ok(Vec::new());
+        };
+
+        match serde_json::from_slice::<Vec<String>>(&value) {
+            Ok(topics) => Ok(topics
+                .into_iter()
+                .filter(|topic| !topic.trim().is_empty())
+                .collect()),
+            Err(e) => {
+                warn!("Ignoring unreadable saved subscriptions: {}", e);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Persist the node's lifetime received message count
+    pub async fn save_message_count(&self, count: u64) -> Result<()> {
+        let json = serde_json::to_vec(&count)?;
+        self.set_internal_sync_value(sync_keys::MESSAGE_COUNT, &json).await
+    }
+
+    /// Load the saved lifetime message count, 0 if none was saved
+    ///
+    /// Like [`load_subscriptions`](Self::load_subscriptions), an unreadable
+    /// entry is ignored rather than preventing startup.
+    pub async fn load_message_count(&self) -> Result<u64> {
+        let Some((value, _)) = self.get_sync_value(sync_keys::MESSAGE_COUNT).await? else {
+            return Ok(0);
+        };
+
+        match serde_json::from_slice::<u64>(&value) {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                warn!("Ignoring unreadable saved message count: {}", e);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Persist the reputation compaction epoch and when it started
+    pub async fn save_compaction_epoch(&self, epoch: u64, started_at: DateTime<Utc>) -> Result<()> {
+        let json = serde_json::to_vec(&(epoch, started_at))?;
+        self.set_internal_sync_value(sync_keys::COMPACTION, &json).await
+    }
+
+    /// Load the saved compaction epoch and its start, if one was saved
+    ///
+    /// An unreadable entry is ignored, as for the message count.
+    pub async fn load_compaction_epoch(&self) -> Result<Option<(u64, DateTime<Utc>)>> {
+        let Some((value, _)) = self.get_sync_value(sync_keys::COMPACTION).await? else {
+            return Ok(None);
+        };
+
+        match serde_json::from_slice::<(u64, DateTime<Utc>)>(&value) {
+            Ok(saved) => Ok(Some(saved)),
+            Err(e) => {
+                warn!("Ignoring unreadable saved compaction epoch: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Compute a digest of the synced state for divergence checks
+    ///
+    /// Covers peers, all credit relationships and application key-value
+    /// entries; reserved internal keys are local-only and left out.
+    pub async fn state_digest(&self) -> Result<StateDigest> {
+        let mut digest = StateDigest::default();
+
+        let peers = sqlx::query(
+            r#"
+            SELECT peer_id, public_key, display_name, addresses_json,
+                   successful_interactions, failed_interactions
+            FROM peers
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for row in peers {
+            let peer_id: String = row.get("peer_id");
+            let public_key: Vec<u8> = row.get("public_key");
+            let name: Option<String> = row.get("display_name");
+            let addresses: String = row.get("addresses_json");
+            let successful: i64 = row.get("successful_interactions");
+            let failed: i64 = row.get("failed_interactions");
+            let content = serde_json::to_vec(&(public_key, name, addresses, successful, failed))?;
+            digest.peers.insert(peer_id, &content);
+        }
+
+        let credits = sqlx::query("SELECT id, credit_limit, balance, active FROM credit_relationships")
+            .fetch_all(&self.pool)
+            .await?;
+        for row in credits {
+            let id: String = row.get("id");
+            let credit_limit: f64 = row.get("credit_limit");
+            let balance: f64 = row.get("balance");
+            let active: i32 = row.get("active");
+            let content = serde_json::to_vec(&(credit_limit, balance, active))?;
+            digest.credits.insert(id, &content);
+        }
+
+        let entries = sqlx::query("SELECT key, value FROM state_sync")
+            .fetch_all(&self.pool)
+            .await?;
+        for row in entries {
+            let key: String = row.get("key");
+            if sync_keys::is_reserved(&key) {
+                continue;
+            }
+            let value: Vec<u8> = row.get("value");
+            digest.kv.insert(key, &value);
+        }
+
+        Ok(digest)
+    }
+
+    // ========== Pending Direct Message Operations ==========
+
+    /// Queue a direct message for an offline recipient
+    ///
+    /// The queue is bounded per recipient; once `max_per_recipient` messages
+    /// are waiting, further messages are rejected. The count and the insert
+    /// are one statement, so concurrent senders can't overfill the queue.
+    pub async fn enqueue_pending_dm(
+        &self,
+        dm: &PendingDirectMessage,
+        max_per_recipient: usize,
+    ) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO pending_dm (id, recipient_peer_id, payload, queued_at, expires_at)
+            SELECT ?, ?, ?, ?, ?
+            WHERE (SELECT COUNT(*) FROM pending_dm WHERE recipient_peer_id = ?) < ?
+            "#,
+        )
+        .bind(&dm.id)
+        .bind(&dm.recipient)
+        .bind(&dm.payload)
+        .bind(dm.queued_at.timestamp())
+        .bind(dm.expires_at.timestamp())
+        .bind(&dm.recipient)
+        .bind(max_per_recipient as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StateError::InvalidData(format!(
+                "pending DM queue full for {} ({} messages)",
+                dm.recipient, max_per_recipient
+            )));
+        }
+
+        debug!("Queued DM {} for offline peer {}", dm.id, dm.recipient);
+        Ok(())
+    }
+
+    /// List unexpired pending messages for a recipient, oldest first
+    pub async fn list_pending_dms(&self, recipient: &str) -> Result<Vec<PendingDirectMessage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, recipient_peer_id, payload, queued_at, expires_at
+            FROM pending_dm
+            WHERE recipient_peer_id = ? AND expires_at > ?
+            ORDER BY queued_at ASC
+            "#,
+        )
+        .bind(recipient)
+        .bind(Utc::now().timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| self.row_to_pending_dm(row)).collect())
+    }
+
+    /// Count pending messages for a recipient
+    pub async fn count_pending_dms(&self, recipient: &str) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM pending_dm WHERE recipient_peer_id = ?")
+            .bind(recipient)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    /// Remove a pending message once its delivery is acknowledged
+    ///
+    /// Returns false if no such message was queued, e.g. because an earlier
+    /// acknowledgement already removed it.
+    pub async fn delete_pending_dm(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM pending_dm WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Drop pending messages past their TTL, returning what was dropped
+    pub async fn expire_pending_dms(&self) -> Result<Vec<PendingDirectMessage>> {
+        let now = Utc::now().timestamp();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, recipient_peer_id, payload, queued_at, expires_at
+            FROM pending_dm WHERE expires_at <= ?
+            "#,
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM pending_dm WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| self.row_to_pending_dm(row)).collect())
+    }
+
+    fn row_to_pending_dm(&self, row: &sqlx::sqlite::SqliteRow) -> PendingDirectMessage {
+        let queued_at: i64 = row.get("queued_at");
+        let expires_at: i64 = row.get("expires_at");
+
+        PendingDirectMessage {
+            id: row.get("id"),
+            recipient: row.get("recipient_peer_id"),
+            payload: row.get("payload"),
+            queued_at: Utc.timestamp_opt(queued_at, 0).single().unwrap_or_else(Utc::now),
+            expires_at: Utc.timestamp_opt(expires_at, 0).single().unwrap_or_else(Utc::now),
+        }
+    }
 }
 
 // Implement the core StateStore trait
@@ -743,20 +2395,244 @@ impl StateStore for SqliteStore {
             .await
             .map_err(|e| mycelial_core::MycelialError::Storage(e.to_string()))
     }
+
+    async fn store_credit_relationship(&self, relationship: &CreditRelationship) -> CoreResult<()> {
+        self.upsert_credit_relationship(relationship)
+            .await
+            .map(|_| ())
+            .map_err(|e| mycelial_core::MycelialError::Storage(e.to_string()))
+    }
+
+    async fn get_credit_relationship_between(
+        &self,
+        creditor: &PeerId,
+        debtor: &PeerId,
+    ) -> CoreResult<Option<CreditRelationship>> {
+        SqliteStore::get_credit_relationship_between(self, creditor.as_str(), debtor.as_str())
+            .await
+            .map_err(|e| mycelial_core::MycelialError::Storage(e.to_string()))
+    }
+
+    async fn list_credit_relationships_for(&self, id: &PeerId) -> CoreResult<Vec<CreditRelationship>> {
+        SqliteStore::list_credit_relationships_for(self, id.as_str())
+            .await
+            .map_err(|e| mycelial_core::MycelialError::Storage(e.to_string()))
+    }
+}
+
+/// Depth-first search for debt paths leading back to `path[0]`
+///
+/// `path` holds the peers visited so far; a cycle is recorded whenever the
+/// last peer owes the first. Peers already on the path are skipped, so only
+/// simple cycles are reported.
+fn collect_cycles(
+    owes: &BTreeMap<String, Vec<String>>,
+    path: &mut Vec<String>,
+    max_len: usize,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    let Some(creditors) = owes.get(path.last().expect("path starts with a peer")) else {
+        return;
+    };
+
+    for creditor in creditors {
+        if *creditor == path[0] {
+            if path.len() >= 2 {
+                cycles.push(path.clone());
+            }
+        } else if path.len() < max_len && !path.contains(creditor) {
+            path.push(creditor.clone());
+            collect_cycles(owes, path, max_len, cycles);
+            path.pop();
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::credit_tally::CreditTally;
 
     async fn create_test_store() -> SqliteStore {
         SqliteStore::new(":memory:").await.unwrap()
     }
 
+    /// Query plan steps SQLite picks for a statement
+    async fn query_plan(store: &SqliteStore, sql: &str) -> String {
+        let rows = sqlx::query(&format!("EXPLAIN QUERY PLAN {}", sql))
+            .fetch_all(store.pool())
+            .await
+            .unwrap();
+        rows.iter().map(|row| row.get::<String, _>("detail")).collect::<Vec<_>>().join("; ")
+    }
+
+    #[tokio::test]
+    async fn test_queries_use_indexes() {
+        let store = create_test_store().await;
+
+        // Timings from the same queries on 1000 peers, 200k messages and 50k
+        // credit lines (after ANALYZE), before and after migration 009:
+        //   messages from a sender, newest 50:      0.18 ms -> 0.09 ms
+        //   messages of a type, newest 50:          0.15 ms -> 0.08 ms
+        //   stale active credit lines:              3.0 ms  -> 0.76 ms
+        //   all active credit lines, sorted (~10k): 23.9 ms -> 23.4 ms
+        //   peers above a reputation score:         unchanged (indexed in 001)
+        let cases = [
+            (
+                "SELECT * FROM messages WHERE sender_peer_id = 'a' ORDER BY timestamp DESC LIMIT 50",
+                "idx_messages_sender_timestamp",
+            ),
+            (
+                "SELECT COUNT(*) FROM messages WHERE sender_peer_id = 'a' AND timestamp >= 0",
+                "idx_messages_sender_timestamp",
+            ),
+            (
+                "SELECT * FROM messages WHERE message_type = 'Content' ORDER BY timestamp DESC LIMIT 50",
+                "idx_messages_type_timestamp",
+            ),
+            (
+                "SELECT * FROM credit_relationships WHERE active = 1 ORDER BY last_transaction DESC",
+                "idx_credit_active_last_transaction",
+            ),
+            (
+                "SELECT id FROM credit_relationships WHERE active = 1 AND last_transaction < 0",
+                "idx_credit_active_last_transaction",
+            ),
+            (
+                "SELECT * FROM peers WHERE reputation_score >= 0.5 ORDER BY reputation_score DESC",
+                "idx_peers_reputation",
+            ),
+        ];
+        for (sql, index) in cases {
+            let plan = query_plan(&store, sql).await;
+            assert!(plan.contains(index), "{} should use {}: {}", sql, index, plan);
+            assert!(!plan.contains("TEMP B-TREE"), "{} should not sort: {}", sql, plan);
+        }
+
+        // The upserts rely on unique constraints, which are untouched
+        let info = PeerInfo {
+            id: PeerId("a".to_string()),
+            public_key: "a".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&info, None).await.unwrap();
+        store.upsert_peer(&info, None).await.unwrap();
+        assert_eq!(store.count_peers().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_are_idempotent() {
+        let store = create_test_store().await;
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert_eq!(store.schema_version().await.unwrap(), latest);
+
+        // A second run applies nothing and leaves existing data alone
+        let info = PeerInfo {
+            id: PeerId("alice".to_string()),
+            public_key: "alice".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&info, None).await.unwrap();
+        store.run_migrations().await.unwrap();
+
+        assert_eq!(store.schema_version().await.unwrap(), latest);
+        let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_migrations")
+            .fetch_one(store.pool())
+            .await
+            .unwrap();
+        assert_eq!(recorded, MIGRATIONS.len() as i64);
+        assert_eq!(store.count_peers().await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1, "{}", migration.name);
+        }
+    }
+
     #[tokio::test]
     async fn test_peer_crud() {
+        check_peer_crud(&create_test_store().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_search_peers_by_name() {
         let store = create_test_store().await;
+        let names = [
+            ("p1", Some("Alice")),
+            ("p2", Some("alicia")),
+            ("p3", Some("Bob")),
+            ("p4", None),
+            ("p5", Some("al_bert")),
+            ("p6", Some("Malice")),
+        ];
+        for (id, name) in names {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: name.map(str::to_string),
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+
+        let found = |peers: Vec<(PeerInfo, Reputation)>| -> Vec<String> {
+            peers.into_iter().map(|(info, _)| info.id.to_string()).collect()
+        };
+
+        // Case-insensitive prefix match only
+        assert_eq!(found(store.search_peers_by_name("ALI", 10).await.unwrap()), vec!["p1", "p2"]);
+        assert_eq!(found(store.search_peers_by_name("bo", 10).await.unwrap()), vec!["p3"]);
+        assert_eq!(found(store.search_peers_by_name("ALI", 1).await.unwrap()), vec!["p1"]);
+        assert!(store.search_peers_by_name("lice", 10).await.unwrap().is_empty());
+
+        // Wildcards in the prefix are literal
+        assert_eq!(found(store.search_peers_by_name("al_", 10).await.unwrap()), vec!["p5"]);
+        assert!(store.search_peers_by_name("%", 10).await.unwrap().is_empty());
+
+        // Unnamed peers never match, even an empty prefix
+        assert_eq!(store.search_peers_by_name("", 10).await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_memory_url() {
+        assert!(is_in_memory(":memory:"));
+        assert!(is_in_memory("sqlite::memory:"));
+        assert!(is_in_memory("sqlite:file:state?mode=memory&cache=shared"));
+        assert!(!is_in_memory("sqlite:mycelial.db?mode=rwc"));
+
+        // Every pooled connection sees the same database
+        let store = SqliteStore::new("sqlite::memory:").await.unwrap();
+        check_peer_crud(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_single_connection_pool() {
+        let options = StoreOptions {
+            max_connections: 1,
+            ..Default::default()
+        };
+        let store = SqliteStore::new_with_options(":memory:", options).await.unwrap();
+        check_peer_crud(&store).await;
+
+        let options = StoreOptions {
+            max_connections: 0,
+            ..Default::default()
+        };
+        let result = SqliteStore::new_with_options(":memory:", options).await;
+        assert!(matches!(result, Err(StateError::Connection(msg)) if msg.contains("at least 1")));
+    }
 
+    async fn check_peer_crud(store: &SqliteStore) {
         // Create peer info
         let peer_id = PeerId("test_peer_123".to_string());
         let peer_info = PeerInfo {
@@ -788,6 +2664,94 @@ mod tests {
         assert!(store.get_peer("test_peer_123").await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_delete_peer_cascades() {
+        let cache = Arc::new(StateCache::new());
+        let store = create_test_store().await.with_cache(cache.clone());
+        for id in ["alice", "bob"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        let rel = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        let rel_id = store.upsert_credit_relationship(&rel).await.unwrap();
+        store.record_credit_transaction(&rel_id, 10.0, 10.0, None).await.unwrap();
+        let message = Message::new(MessageType::Content, PeerId("bob".to_string()), vec![1]);
+        store.store_message(&message).await.unwrap();
+        store.add_tag("bob", "blocked").await.unwrap();
+        assert!(store.get_credit_relationship(&rel_id).await.unwrap().is_some());
+
+        assert!(store.delete_peer("bob").await.unwrap());
+        assert!(store.get_peer("bob").await.unwrap().is_none());
+        assert!(store.get_credit_relationship(&rel_id).await.unwrap().is_none());
+        assert!(store.get_message(&message.id).await.unwrap().is_none());
+        assert!(store.list_peers_by_tag("blocked").await.unwrap().is_empty());
+        assert!(store.get_peer("alice").await.unwrap().is_some());
+
+        assert!(!store.delete_peer("bob").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_strict_peer_ids() {
+        let store = create_test_store().await.with_strict_peer_ids(true);
+
+        let (genuine, _) = PeerInfo::generate(vec![]);
+        store.upsert_peer(&genuine, None).await.unwrap();
+
+        let (other, _) = PeerInfo::generate(vec![]);
+        let spoofed = PeerInfo { id: other.id, ..genuine.clone() };
+        assert!(matches!(
+            store.upsert_peer(&spoofed, None).await,
+            Err(StateError::InvalidData(_))
+        ));
+        assert!(matches!(
+            store.upsert_peers_batch(&[(genuine.clone(), None), (spoofed.clone(), None)]).await,
+            Err(StateError::InvalidData(_))
+        ));
+        assert!(store.get_peer(spoofed.id.as_str()).await.unwrap().is_none());
+
+        // Lenient stores accept anything, as before
+        let store = store.with_strict_peer_ids(false);
+        store.upsert_peer(&spoofed, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upsert_peers_batch() {
+        let store = create_test_store().await;
+
+        let entries: Vec<(PeerInfo, Option<Reputation>)> = (0..200)
+            .map(|i| {
+                let info = PeerInfo {
+                    id: PeerId(format!("batch_peer_{}", i)),
+                    public_key: format!("key_{}", i),
+                    addresses: vec![format!("/ip4/10.0.0.{}/tcp/9000", i % 250)],
+                    first_seen: Utc::now(),
+                    last_seen: Utc::now(),
+                    name: None,
+                };
+                let reputation = (i % 2 == 0).then(|| Reputation::new(0.7));
+                (info, reputation)
+            })
+            .collect();
+
+        let affected = store.upsert_peers_batch(&entries).await.unwrap();
+        assert_eq!(affected, 200);
+        assert_eq!(store.count_peers().await.unwrap(), 200);
+
+        let (_, rep) = store.get_peer("batch_peer_42").await.unwrap().unwrap();
+        assert!((rep.score - 0.7).abs() < 0.001);
+
+        // Re-running the batch updates in place
+        store.upsert_peers_batch(&entries[..10]).await.unwrap();
+        assert_eq!(store.count_peers().await.unwrap(), 200);
+    }
+
     #[tokio::test]
     async fn test_message_crud() {
         let store = create_test_store().await;
@@ -822,6 +2786,124 @@ mod tests {
         assert_eq!(messages.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_group_message_roundtrip() {
+        let store = create_test_store().await;
+        let sender = PeerInfo {
+            id: PeerId("sender_peer".to_string()),
+            public_key: "sender_peer".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&sender, None).await.unwrap();
+
+        let recipients = vec![PeerId("alice".to_string()), PeerId("bob".to_string()), PeerId("carol".to_string())];
+        let message = Message::group(sender.id.clone(), recipients.clone(), b"hi all".to_vec());
+        store.store_message(&message).await.unwrap();
+        let direct = Message::direct(sender.id.clone(), PeerId("alice".to_string()), b"hi".to_vec());
+        store.store_message(&direct).await.unwrap();
+
+        // Recorded once, with every recipient
+        let messages = store.list_messages_from("sender_peer", 10).await.unwrap();
+        assert_eq!(messages.len(), 2);
+        let retrieved = store.get_message(&message.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.recipients, recipients);
+        assert!(retrieved.recipient.is_none());
+
+        let retrieved = store.get_message(&direct.id).await.unwrap().unwrap();
+        assert!(retrieved.recipients.is_empty());
+        assert_eq!(retrieved.recipient, Some(PeerId("alice".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_list_messages_page() {
+        let store = create_test_store().await;
+        let sender = PeerInfo {
+            id: PeerId("sender_peer".to_string()),
+            public_key: "sender_peer".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&sender, None).await.unwrap();
+        for i in 0..5u8 {
+            let message = Message::new(MessageType::Content, sender.id.clone(), vec![i]);
+            store.store_message(&message).await.unwrap();
+        }
+
+        // Walk the table two at a time, in insertion order
+        let mut payloads = Vec::new();
+        let mut cursor = Some(0);
+        while let Some(after) = cursor {
+            let page = store.list_messages_page(after, 2).await.unwrap();
+            assert!(page.messages.len() <= 2);
+            payloads.extend(page.messages.into_iter().map(|m| m.payload[0]));
+            cursor = page.next_cursor;
+        }
+        assert_eq!(payloads, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_message_from_unknown_sender_rejected() {
+        let store = create_test_store().await;
+
+        let message = Message::new(
+            MessageType::Content,
+            PeerId("nobody".to_string()),
+            b"orphan".to_vec(),
+        );
+        let err = store.store_message(&message).await.unwrap_err();
+        assert!(matches!(err, StateError::Database(msg) if msg.contains("FOREIGN KEY")));
+        assert!(store.get_message(&message.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_message_stats() {
+        let store = create_test_store().await;
+        assert_eq!(store.message_stats().await.unwrap(), MessageStats::default());
+
+        let sender = PeerId("sender_peer".to_string());
+        let sender_info = PeerInfo {
+            id: sender.clone(),
+            public_key: "sender_peer".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&sender_info, None).await.unwrap();
+
+        for (message_type, size) in [
+            (MessageType::Content, 10),
+            (MessageType::Content, 255),
+            (MessageType::Content, 256),
+            (MessageType::Credit, 1023),
+            (MessageType::Credit, 1024),
+            (MessageType::Direct, 16 * 1024),
+        ] {
+            let message = Message::new(message_type, sender.clone(), vec![0; size]);
+            store.store_message(&message).await.unwrap();
+        }
+
+        let stats = store.message_stats().await.unwrap();
+        assert_eq!(stats.total, 6);
+        assert_eq!(
+            stats.by_type,
+            BTreeMap::from([
+                ("Content".to_string(), 3),
+                ("Credit".to_string(), 2),
+                ("Direct".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            stats.by_size,
+            PayloadSizeBuckets { under_256b: 2, under_1kb: 2, under_16kb: 1, larger: 1 }
+        );
+    }
+
     #[tokio::test]
     async fn test_credit_relationship_crud() {
         let store = create_test_store().await;
@@ -877,11 +2959,684 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_sync_values() {
-        let store = create_test_store().await;
-
-        // Set value
-        store.set_sync_value("test_key", b"test_value").await.unwrap();
+    async fn test_credit_through_state_store_trait() {
+        let store: Box<dyn StateStore> = Box::new(create_test_store().await);
+        let (alice, bob) = (PeerId("alice".to_string()), PeerId("bob".to_string()));
+        for id in [&alice, &bob] {
+            let info = PeerInfo {
+                id: id.clone(),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.store_peer(&info).await.unwrap();
+        }
+
+        let mut rel = CreditRelationship::new(alice.clone(), bob.clone(), 100.0);
+        store.store_credit_relationship(&rel).await.unwrap();
+        rel.balance = 30.0;
+        store.store_credit_relationship(&rel).await.unwrap();
+
+        let stored = store.get_credit_relationship_between(&alice, &bob).await.unwrap().unwrap();
+        assert_eq!(stored.balance, 30.0);
+        assert!(store.get_credit_relationship_between(&bob, &alice).await.unwrap().is_none());
+
+        assert_eq!(store.list_credit_relationships_for(&bob).await.unwrap().len(), 1);
+        assert!(store
+            .list_credit_relationships_for(&PeerId("carol".to_string()))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_contribution_leaderboard() {
+        let store = create_test_store().await;
+
+        store.record_contribution("alice", "bandwidth", 50.0).await.unwrap();
+        store.record_contribution("bob", "bandwidth", 80.0).await.unwrap();
+        store.record_contribution("carol", "bandwidth", 30.0).await.unwrap();
+        // Contributions accumulate: alice overtakes bob
+        store.record_contribution("alice", "bandwidth", 45.0).await.unwrap();
+        // Other resource types don't count
+        store.record_contribution("carol", "storage", 500.0).await.unwrap();
+
+        let top = store.top_contributors("bandwidth", 2).await.unwrap();
+        assert_eq!(top, vec![("alice".to_string(), 95.0), ("bob".to_string(), 80.0)]);
+
+        assert_eq!(store.top_contributors("storage", 10).await.unwrap().len(), 1);
+        assert!(store.record_contribution("bob", "bandwidth", f64::NAN).await.is_err());
+        assert!(store.record_contribution("bob", "bandwidth", -1.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_peer_export_import() {
+        let source = create_test_store().await;
+        for i in 0..3 {
+            let peer_info = PeerInfo {
+                id: PeerId(format!("export_peer_{}", i)),
+                public_key: format!("key{}", i),
+                addresses: vec![format!("/ip4/10.0.0.{}/tcp/4001", i)],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(format!("Peer {}", i)),
+            };
+            let mut reputation = Reputation::new(0.5 + i as f64 / 10.0);
+            reputation.successful_interactions = i;
+            source.upsert_peer(&peer_info, Some(&reputation)).await.unwrap();
+        }
+
+        let json = source.export_peers().await.unwrap();
+        let target = create_test_store().await;
+        assert_eq!(target.import_peers(&json, false).await.unwrap(), 3);
+
+        for i in 0..3 {
+            let id = format!("export_peer_{}", i);
+            let (info, reputation) = target.get_peer(&id).await.unwrap().unwrap();
+            assert_eq!(info.name, Some(format!("Peer {}", i)));
+            assert_eq!(info.addresses, vec![format!("/ip4/10.0.0.{}/tcp/4001", i)]);
+            assert!((reputation.score - (0.5 + i as f64 / 10.0)).abs() < 1e-9);
+            assert_eq!(reputation.successful_interactions, i);
+        }
+
+        // A malformed entry fails the batch unless lenient
+        let mut entries: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        entries.push(serde_json::json!({"not": "a peer"}));
+        let mixed = serde_json::to_string(&entries).unwrap();
+
+        let strict = create_test_store().await;
+        assert!(matches!(
+            strict.import_peers(&mixed, false).await,
+            Err(StateError::Deserialization(_))
+        ));
+        assert_eq!(strict.count_peers().await.unwrap(), 0);
+        assert_eq!(strict.import_peers(&mixed, true).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_validates_addresses() {
+        let store = create_test_store().await;
+
+        let peer_info = PeerInfo {
+            id: PeerId("addr_peer".to_string()),
+            public_key: "5pLr9CqWZz4Ysf7u1Q6Fh".to_string(), // base58 encoded
+            addresses: vec![
+                "/ip4/127.0.0.1/tcp/4001".to_string(),
+                "not a multiaddr".to_string(),
+                "/ip4/127.0.0.1/tcp/4001".to_string(),
+                "/ip4/10.0.0.1/udp/4002/quic-v1".to_string(),
+                "/ip4/999.0.0.1/tcp/1".to_string(),
+                "".to_string(),
+                "/ip4/10.0.0.1/udp/4002/quic-v1".to_string(),
+            ],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+
+        store.upsert_peer(&peer_info, None).await.unwrap();
+        let (stored, _) = store.get_peer("addr_peer").await.unwrap().unwrap();
+        assert_eq!(
+            stored.addresses,
+            vec![
+                "/ip4/127.0.0.1/tcp/4001".to_string(),
+                "/ip4/10.0.0.1/udp/4002/quic-v1".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reputation_history_trimmed() {
+        let store = create_test_store().await;
+
+        let peer_info = PeerInfo {
+            id: PeerId("history_peer".to_string()),
+            public_key: "4nKq8BpVYy3Xre6t9P5Eg".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+
+        let mut reputation = Reputation::new(0.5);
+        reputation.history = (0..150)
+            .map(|i| ReputationSnapshot {
+                score: i as f64 / 1000.0,
+                timestamp: Utc::now(),
+            })
+            .collect();
+
+        store.upsert_peer(&peer_info, Some(&reputation)).await.unwrap();
+        let (_, stored) = store.get_peer("history_peer").await.unwrap().unwrap();
+        assert_eq!(stored.history.len(), 100);
+        assert_eq!(stored.history[0].score, 0.050);
+        assert_eq!(stored.history[99].score, 0.149);
+
+        store.update_peer_reputation("history_peer", &reputation).await.unwrap();
+        let (_, stored) = store.get_peer("history_peer").await.unwrap().unwrap();
+        assert_eq!(stored.history.len(), 100);
+        assert_eq!(stored.history[0].score, 0.050);
+
+        // The cap is configurable
+        let store = store.with_max_reputation_history(10);
+        store.update_peer_reputation("history_peer", &reputation).await.unwrap();
+        let (_, stored) = store.get_peer("history_peer").await.unwrap().unwrap();
+        assert_eq!(stored.history.len(), 10);
+        assert_eq!(stored.history[0].score, 0.140);
+    }
+
+    #[tokio::test]
+    async fn test_recompute_reputation() {
+        let store = create_test_store().await;
+        for (id, successful, failed) in [("steady", 3, 1), ("drifted", 1, 3), ("new", 0, 0)] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            let reputation = Reputation {
+                score: 0.99, // inconsistent with the counters
+                successful_interactions: successful,
+                failed_interactions: failed,
+                ..Default::default()
+            };
+            store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+        }
+
+        assert_eq!(store.recompute_reputation("steady").await.unwrap(), 0.75);
+        let (_, stored) = store.get_peer("steady").await.unwrap().unwrap();
+        assert_eq!(stored.score, 0.75);
+        assert!(matches!(
+            store.recompute_reputation("nobody").await,
+            Err(StateError::NotFound { .. })
+        ));
+
+        // "steady" is already repaired
+        assert_eq!(store.recompute_all_reputations().await.unwrap(), 2);
+        assert_eq!(store.get_peer("drifted").await.unwrap().unwrap().1.score, 0.25);
+        assert_eq!(store.get_peer("new").await.unwrap().unwrap().1.score, 0.5);
+        assert_eq!(store.recompute_all_reputations().await.unwrap(), 0);
+
+        // A stricter policy lowers every score backed by failures
+        let store = store.with_reputation_policy(ReputationPolicy {
+            failure_weight: 3.0,
+            ..Default::default()
+        });
+        assert_eq!(store.recompute_all_reputations().await.unwrap(), 2);
+        assert_eq!(store.recompute_reputation("steady").await.unwrap(), 0.5);
+        assert_eq!(store.get_peer("drifted").await.unwrap().unwrap().1.score, 0.1);
+        assert_eq!(store.get_peer("new").await.unwrap().unwrap().1.score, 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_reputation_ages_from_last_interaction() {
+        let store = create_test_store().await.with_reputation_policy(ReputationPolicy {
+            recency_half_life: Some(chrono::Duration::hours(1)),
+            ..Default::default()
+        });
+        let info = PeerInfo {
+            id: PeerId("quiet".to_string()),
+            public_key: "quiet".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        let interacted = Utc::now() - chrono::Duration::hours(1);
+        let reputation = Reputation {
+            score: 0.75,
+            successful_interactions: 3,
+            failed_interactions: 1,
+            last_updated: interacted,
+            ..Default::default()
+        };
+        store.upsert_peer(&info, Some(&reputation)).await.unwrap();
+
+        // Seeing the peer again doesn't make its counters any more recent
+        store.touch_peer("quiet").await.unwrap();
+        let (_, stored) = store.get_peer("quiet").await.unwrap().unwrap();
+        assert_eq!(stored.last_updated.timestamp(), interacted.timestamp());
+        let score = store.recompute_reputation("quiet").await.unwrap();
+        assert!((score - 0.625).abs() < 1e-3, "{}", score);
+    }
+
+    #[tokio::test]
+    async fn test_write_through_cache() {
+        let cache = Arc::new(StateCache::new());
+        let store = create_test_store().await.with_cache(cache.clone());
+
+        let peer_info = PeerInfo {
+            id: PeerId("cached_peer".to_string()),
+            public_key: "2wMHpFAjZbL9GkXP8n3E1".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: Some("Cached".to_string()),
+        };
+        store.upsert_peer(&peer_info, Some(&Reputation::new(0.7))).await.unwrap();
+
+        // Served straight from the cache populated by the write
+        let (info, rep) = store.get_peer("cached_peer").await.unwrap().unwrap();
+        assert_eq!(info.name, Some("Cached".to_string()));
+        assert!((rep.score - 0.7).abs() < 0.001);
+        assert_eq!(cache.peers.metrics().hits, 1);
+        assert_eq!(cache.peers.metrics().misses, 0);
+
+        // The cached copy matches the stored row, which keeps the original
+        // first_seen and the known name
+        let reconnect = PeerInfo {
+            first_seen: peer_info.first_seen + chrono::Duration::hours(1),
+            name: None,
+            ..peer_info.clone()
+        };
+        store.upsert_peer(&reconnect, Some(&Reputation::new(0.7))).await.unwrap();
+        let (info, _) = store.get_peer("cached_peer").await.unwrap().unwrap();
+        assert_eq!(info.first_seen.timestamp(), peer_info.first_seen.timestamp());
+        assert_eq!(info.name, Some("Cached".to_string()));
+        assert_eq!(cache.peers.metrics().misses, 0);
+
+        // Reputation writes update the cached copy
+        store.update_peer_reputation("cached_peer", &Reputation::new(0.9)).await.unwrap();
+        let (_, rep) = store.get_peer("cached_peer").await.unwrap().unwrap();
+        assert!((rep.score - 0.9).abs() < 0.001);
+
+        // A miss falls through to the database and fills the cache
+        cache.clear_all();
+        assert!(store.get_peer("cached_peer").await.unwrap().is_some());
+        assert!(cache.peers.contains("cached_peer"));
+
+        // Deleting invalidates
+        store.delete_peer("cached_peer").await.unwrap();
+        assert!(store.get_peer("cached_peer").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prune_credit_transactions() {
+        let store = create_test_store().await;
+
+        for (id, name) in [("creditor_peer", "Creditor"), ("debtor_peer", "Debtor")] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(name.to_string()),
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        let rel = CreditRelationship::new(
+            PeerId("creditor_peer".to_string()),
+            PeerId("debtor_peer".to_string()),
+            100.0,
+        );
+        let rel_id = store.upsert_credit_relationship(&rel).await.unwrap();
+
+        for (i, description) in ["old-1", "old-2", "old-3", "recent-1", "recent-2"].iter().enumerate() {
+            store
+                .record_credit_transaction(&rel_id, 10.0, 10.0 * (i + 1) as f64, Some(description))
+                .await
+                .unwrap();
+        }
+
+        // Backdate the "old" transactions by ten days
+        let ten_days_ago = Utc::now().timestamp() - 10 * 24 * 60 * 60;
+        sqlx::query("UPDATE credit_transactions SET timestamp = ? WHERE description LIKE 'old-%'")
+            .bind(ten_days_ago)
+            .execute(store.pool())
+            .await
+            .unwrap();
+
+        // Cutoff at one week: only the backdated rows go
+        let deleted = store.prune_credit_transactions(7 * 24 * 60 * 60).await.unwrap();
+        assert_eq!(deleted, 3);
+
+        let remaining: Vec<String> = sqlx::query("SELECT description FROM credit_transactions ORDER BY description")
+            .fetch_all(store.pool())
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get("description"))
+            .collect();
+        assert_eq!(remaining, vec!["recent-1", "recent-2"]);
+
+        // The relationship itself is untouched
+        assert!(store.get_credit_relationship(&rel_id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_deactivate_stale_credit() {
+        let store = create_test_store().await;
+        for id in ["alice", "bob", "carol"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        let ten_days_ago = Utc::now() - chrono::Duration::days(10);
+        for (creditor, debtor, stale) in [("alice", "bob", true), ("bob", "carol", false), ("carol", "alice", true)] {
+            let mut rel = CreditRelationship::new(
+                PeerId(creditor.to_string()),
+                PeerId(debtor.to_string()),
+                100.0,
+            );
+            if stale {
+                rel.established = ten_days_ago;
+                rel.last_transaction = ten_days_ago;
+            }
+            store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+
+        assert_eq!(store.deactivate_stale_credit(7 * 24 * 60 * 60).await.unwrap(), 2);
+        let active = store.list_active_credit_relationships().await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].creditor.as_str(), "bob");
+
+        // Deactivated lines are kept, and not counted twice
+        let stale = store.get_credit_relationship_between("alice", "bob").await.unwrap().unwrap();
+        assert!(!stale.active);
+        assert_eq!(store.deactivate_stale_credit(7 * 24 * 60 * 60).await.unwrap(), 0);
+    }
+
+    /// Store creditor and debtor peers and a credit line between them
+    async fn create_credit_line(store: &SqliteStore, credit_limit: f64) {
+        for id in ["creditor_peer", "debtor_peer"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        let rel = CreditRelationship::new(
+            PeerId("creditor_peer".to_string()),
+            PeerId("debtor_peer".to_string()),
+            credit_limit,
+        );
+        store.upsert_credit_relationship(&rel).await.unwrap();
+    }
+
+    async fn transaction_count(store: &SqliteStore) -> i64 {
+        sqlx::query("SELECT COUNT(*) as count FROM credit_transactions")
+            .fetch_one(store.pool())
+            .await
+            .unwrap()
+            .get("count")
+    }
+
+    #[tokio::test]
+    async fn test_apply_transfer() {
+        let store = create_test_store().await;
+        create_credit_line(&store, 100.0).await;
+
+        assert_eq!(store.apply_transfer("creditor_peer", "debtor_peer", 40.0, "node").await.unwrap(), 40.0);
+        assert_eq!(store.apply_transfer("creditor_peer", "debtor_peer", 25.0, "node").await.unwrap(), 65.0);
+
+        let rel = store
+            .get_credit_relationship_between("creditor_peer", "debtor_peer")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rel.balance, 65.0);
+        assert_eq!(transaction_count(&store).await, 2);
+
+        // Both went into the node's tally, on top of the balance the
+        // relationship opened with
+        let tallies = store.credit_tallies("creditor_peer", "debtor_peer").await.unwrap();
+        assert_eq!(tallies.opening.map(|opening| (opening.amount, opening.origin)), Some((0.0, "node".to_string())));
+        assert_eq!(tallies.nodes["node"], CreditTally { increments: 65.0, decrements: 0.0 });
+
+        // Unknown relationships are reported as missing
+        let err = store.apply_transfer("debtor_peer", "creditor_peer", 1.0, "node").await.unwrap_err();
+        assert!(matches!(err, StateError::NotFound { .. }));
+
+        // Zero and negative amounts would move nothing or run the balance back
+        for amount in [0.0, -10.0, f64::NAN] {
+            let err = store.apply_transfer("creditor_peer", "debtor_peer", amount, "node").await.unwrap_err();
+            assert!(matches!(err, StateError::InvalidData(_)), "{}", amount);
+        }
+        assert_eq!(transaction_count(&store).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_transfer_rejects_overdraft() {
+        let store = create_test_store().await;
+        create_credit_line(&store, 100.0).await;
+        store.apply_transfer("creditor_peer", "debtor_peer", 80.0, "node").await.unwrap();
+
+        let err = store.apply_transfer("creditor_peer", "debtor_peer", 30.0, "node").await.unwrap_err();
+        match err {
+            StateError::CreditLimitExceeded { requested, available } => {
+                assert_eq!(requested, 30.0);
+                assert_eq!(available, 20.0);
+            }
+            other => panic!("expected CreditLimitExceeded, got {:?}", other),
+        }
+
+        // The rejected transfer left no trace
+        let rel = store
+            .get_credit_relationship_between("creditor_peer", "debtor_peer")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rel.balance, 80.0);
+        assert_eq!(transaction_count(&store).await, 1);
+        let tallies = store.credit_tallies("creditor_peer", "debtor_peer").await.unwrap();
+        assert_eq!(tallies.nodes["node"].increments, 80.0);
+    }
+
+    #[tokio::test]
+    async fn test_credit_cycle_netting() {
+        let store = create_test_store().await;
+        for id in ["a", "b", "c", "d"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        // a owes b 50, b owes c 30, c owes a 40; d owes a but nobody owes d
+        let debts = [("a", "b", 50.0), ("b", "c", 30.0), ("c", "a", 40.0), ("d", "a", 10.0)];
+        for (debtor, creditor, balance) in debts {
+            let mut rel = CreditRelationship::new(
+                PeerId(creditor.to_string()),
+                PeerId(debtor.to_string()),
+                100.0,
+            );
+            rel.balance = balance;
+            store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+
+        let cycles = store.find_credit_cycles("a").await.unwrap();
+        assert_eq!(cycles, vec![vec!["a".to_string(), "b".to_string(), "c".to_string()]]);
+        assert!(store.find_credit_cycles("d").await.unwrap().is_empty());
+
+        store.apply_netting(&cycles[0], 30.0).await.unwrap();
+        let balance = |creditor: &'static str, debtor: &'static str| {
+            let store = &store;
+            async move {
+                store
+                    .get_credit_relationship_between(creditor, debtor)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .balance
+            }
+        };
+        assert_eq!(balance("b", "a").await, 20.0);
+        assert_eq!(balance("c", "b").await, 0.0);
+        assert_eq!(balance("a", "c").await, 10.0);
+        assert_eq!(transaction_count(&store).await, 3);
+
+        // b -> c is settled, so the cycle is gone
+        assert!(store.find_credit_cycles("a").await.unwrap().is_empty());
+
+        // Netting more than an edge owes changes nothing
+        let err = store.apply_netting(&cycles[0], 5.0).await.unwrap_err();
+        assert!(matches!(err, StateError::InvalidData(_)));
+        assert_eq!(balance("b", "a").await, 20.0);
+        assert_eq!(balance("a", "c").await, 10.0);
+        assert_eq!(transaction_count(&store).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_credit_summary() {
+        let store = create_test_store().await;
+        for id in ["a", "b", "c"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        // b owes a 70; a owes c 25
+        for (creditor, debtor, balance) in [("a", "b", 70.0), ("c", "a", 25.0)] {
+            let mut rel = CreditRelationship::new(
+                PeerId(creditor.to_string()),
+                PeerId(debtor.to_string()),
+                100.0,
+            );
+            rel.balance = balance;
+            store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+
+        let summary = store.credit_summary("a").await.unwrap();
+        assert_eq!(summary.total_extended, 70.0);
+        assert_eq!(summary.total_owed, 25.0);
+        assert_eq!(summary.net, 45.0);
+        assert_eq!(summary.relationship_count, 2);
+
+        // Debtor-only and creditor-only peers come out negative and positive
+        assert_eq!(store.credit_summary("b").await.unwrap().net, -70.0);
+        assert_eq!(store.credit_summary("c").await.unwrap().net, 25.0);
+        assert_eq!(store.credit_summary("nobody").await.unwrap(), CreditSummary::default());
+    }
+
+    #[tokio::test]
+    async fn test_credit_graph_pages() {
+        let store = create_test_store().await;
+        for (id, name) in [("alice_peer", Some("Alice")), ("bob_peer", None), ("carol_peer", Some("Carol"))] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: name.map(String::from),
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        for (creditor, debtor) in [("alice_peer", "bob_peer"), ("bob_peer", "carol_peer"), ("carol_peer", "alice_peer")] {
+            let rel = CreditRelationship::new(PeerId(creditor.to_string()), PeerId(debtor.to_string()), 100.0);
+            store.upsert_credit_relationship(&rel).await.unwrap();
+        }
+        let mut inactive = CreditRelationship::new(PeerId("bob_peer".to_string()), PeerId("alice_peer".to_string()), 10.0);
+        inactive.active = false;
+        store.upsert_credit_relationship(&inactive).await.unwrap();
+
+        let first = store.credit_graph_page(0, 2).await.unwrap();
+        assert_eq!(first.edges.len(), 2);
+        assert_eq!(first.edges[0].source, "alice_peer");
+        assert_eq!(first.edges[0].target, "bob_peer");
+        let labels: Vec<_> = first.nodes.iter().map(|node| node.label.as_str()).collect();
+        assert_eq!(labels, ["Alice", "bob_peer", "Carol"]);
+
+        let second = store.credit_graph_page(first.next_cursor.unwrap(), 2).await.unwrap();
+        assert_eq!(second.edges.len(), 1);
+        assert_eq!(second.edges[0].source, "carol_peer");
+        assert_eq!(second.nodes.len(), 2);
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_vacuum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = format!("sqlite:{}", dir.path().join("maintenance.db").display());
+        let store = SqliteStore::new(&path).await.unwrap();
+        for i in 0..50 {
+            let info = PeerInfo {
+                id: PeerId(format!("peer_{}", i)),
+                public_key: format!("peer_{}", i),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        for i in 0..25 {
+            store.delete_peer(&format!("peer_{}", i)).await.unwrap();
+        }
+
+        store.checkpoint().await.unwrap();
+        store.vacuum().await.unwrap();
+        store.checkpoint().await.unwrap();
+        assert_eq!(store.count_peers().await.unwrap(), 25);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_transfers_do_not_double_spend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = format!("sqlite:{}", dir.path().join("transfers.db").display());
+        let store = Arc::new(SqliteStore::new(&path).await.unwrap());
+        create_credit_line(&store, 100.0).await;
+
+        // Twenty transfers of 10 race for a limit of 100: exactly ten fit
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store.apply_transfer("creditor_peer", "debtor_peer", 10.0, "node").await
+                })
+            })
+            .collect();
+
+        let mut accepted = 0;
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(_) => accepted += 1,
+                Err(StateError::CreditLimitExceeded { .. }) => {}
+                Err(e) => panic!("unexpected transfer error: {}", e),
+            }
+        }
+
+        assert_eq!(accepted, 10);
+        let rel = store
+            .get_credit_relationship_between("creditor_peer", "debtor_peer")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rel.balance, 100.0);
+        assert_eq!(transaction_count(&store).await, 10);
+    }
+
+    #[tokio::test]
+    async fn test_sync_values() {
+        let store = create_test_store().await;
+
+        // Set value
+        store.set_sync_value("test_key", b"test_value").await.unwrap();
 
         // Get value
         let (value, version) = store.get_sync_value("test_key").await.unwrap().unwrap();
@@ -899,6 +3654,186 @@ mod tests {
         assert!(store.get_sync_value("test_key").await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_reserved_sync_keys() {
+        let store = create_test_store().await;
+
+        // Public API can't touch the reserved namespace
+        let result = store.set_sync_value(sync_keys::SUBSCRIPTIONS, b"[]").await;
+        assert!(matches!(result, Err(StateError::InvalidData(_))));
+        assert!(store.delete_sync_value(sync_keys::SUBSCRIPTIONS).await.is_err());
+
+        // Internal writes succeed and are readable
+        store.set_internal_sync_value(sync_keys::SUBSCRIPTIONS, b"[]").await.unwrap();
+        let (value, _) = store.get_sync_value(sync_keys::SUBSCRIPTIONS).await.unwrap().unwrap();
+        assert_eq!(value, b"[]");
+
+        // Internal API only writes reserved keys
+        assert!(store.set_internal_sync_value("app:key", b"x").await.is_err());
+
+        store.set_sync_value("app:a", b"1").await.unwrap();
+        store.set_sync_value("app:b", b"2").await.unwrap();
+        assert_eq!(store.list_sync_keys("app:").await.unwrap(), vec!["app:a", "app:b"]);
+        assert_eq!(
+            store.list_sync_keys(sync_keys::RESERVED_PREFIX).await.unwrap(),
+            vec![sync_keys::SUBSCRIPTIONS]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_roundtrip() {
+        let store = create_test_store().await;
+        assert!(store.load_subscriptions().await.unwrap().is_empty());
+
+        let topics = vec![
+            "/mycelial/1.0.0/chat".to_string(),
+            "/mycelial/1.0.0/reputation".to_string(),
+            "custom".to_string(),
+        ];
+        store.save_subscriptions(&topics).await.unwrap();
+        assert_eq!(store.load_subscriptions().await.unwrap(), topics);
+
+        // Saving replaces the previous set
+        store.save_subscriptions(&topics[..1]).await.unwrap();
+        assert_eq!(store.load_subscriptions().await.unwrap(), topics[..1].to_vec());
+
+        // Corrupt entries don't prevent startup
+        store.set_internal_sync_value(sync_keys::SUBSCRIPTIONS, b"not json").await.unwrap();
+        assert!(store.load_subscriptions().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_conversation() {
+        let store = create_test_store().await;
+        for id in ["alice", "bob", "carol"] {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        let alice = PeerId("alice".to_string());
+        let bob = PeerId("bob".to_string());
+        let carol = PeerId("carol".to_string());
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let messages = [
+            Message::direct(alice.clone(), bob.clone(), b"1".to_vec()),
+            Message::direct(bob.clone(), alice.clone(), b"2".to_vec()),
+            Message::group(carol.clone(), vec![alice.clone(), bob.clone()], b"3".to_vec()),
+            Message::direct(bob.clone(), carol.clone(), b"not alice".to_vec()),
+            Message::new(MessageType::Content, alice.clone(), b"4".to_vec()),
+        ];
+        for (i, mut message) in messages.into_iter().enumerate() {
+            message.timestamp = start + chrono::Duration::minutes(i as i64);
+            store.store_message(&message).await.unwrap();
+        }
+
+        let payloads = |messages: Vec<Message>| -> Vec<String> {
+            messages.into_iter().map(|m| String::from_utf8(m.payload).unwrap()).collect()
+        };
+        let conversation = store.list_conversation("alice", 10).await.unwrap();
+        assert_eq!(payloads(conversation), ["1", "2", "3", "4"]);
+
+        // The limit keeps the latest messages
+        let latest = store.list_conversation("alice", 2).await.unwrap();
+        assert_eq!(payloads(latest), ["3", "4"]);
+    }
+
+    #[tokio::test]
+    async fn test_message_count_roundtrip() {
+        let store = create_test_store().await;
+        assert_eq!(store.load_message_count().await.unwrap(), 0);
+
+        store.save_message_count(42).await.unwrap();
+        store.save_message_count(1_000_000_007).await.unwrap();
+        assert_eq!(store.load_message_count().await.unwrap(), 1_000_000_007);
+
+        store.set_internal_sync_value(sync_keys::MESSAGE_COUNT, b"-1").await.unwrap();
+        assert_eq!(store.load_message_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_state_digest_diff() {
+        let node_a = create_test_store().await;
+        let node_b = create_test_store().await;
+
+        let peer = |id: &str, name: &str| PeerInfo {
+            id: PeerId(id.to_string()),
+            public_key: "2wMHpFAjZbL9GkXP8n3E1".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: Some(name.to_string()),
+        };
+
+        // Shared state
+        for store in [&node_a, &node_b] {
+            store.upsert_peer(&peer("peer_1", "One"), None).await.unwrap();
+            store.upsert_peer(&peer("peer_2", "Two"), None).await.unwrap();
+            store.set_sync_value("app:shared", b"same").await.unwrap();
+        }
+        assert!(node_a.state_digest().await.unwrap()
+            .diff(&node_b.state_digest().await.unwrap())
+            .is_empty());
+
+        // Diverge: a renamed peer, a peer only on A, a credit line only on B,
+        // and a kv entry only on A. Internal keys never count.
+        node_a.upsert_peer(&peer("peer_1", "Renamed"), None).await.unwrap();
+        node_a.upsert_peer(&peer("peer_3", "Three"), None).await.unwrap();
+        let rel = CreditRelationship::new(PeerId("peer_1".into()), PeerId("peer_2".into()), 10.0);
+        node_b.upsert_credit_relationship(&rel).await.unwrap();
+        node_a.set_sync_value("app:only_a", b"x").await.unwrap();
+        node_a.set_internal_sync_value(sync_keys::SUBSCRIPTIONS, b"[]").await.unwrap();
+
+        let diff = node_a.state_digest().await.unwrap()
+            .diff(&node_b.state_digest().await.unwrap());
+        assert_eq!(diff.peers, 2);
+        assert_eq!(diff.credits, 1);
+        assert_eq!(diff.kv, 1);
+        assert_eq!(diff.total(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_pending_dm_queue() {
+        let store = create_test_store().await;
+        let now = Utc::now();
+
+        let dm = |id: &str, ttl_secs: i64| PendingDirectMessage {
+            id: id.to_string(),
+            recipient: "offline_peer".to_string(),
+            payload: id.as_bytes().to_vec(),
+            queued_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl_secs),
+        };
+
+        // Queue while the peer is offline, bounded per recipient
+        store.enqueue_pending_dm(&dm("dm-1", 3600), 2).await.unwrap();
+        store.enqueue_pending_dm(&dm("dm-2", 3600), 2).await.unwrap();
+        assert!(store.enqueue_pending_dm(&dm("dm-3", 3600), 2).await.is_err());
+
+        // Removed once the recipient acknowledges it, and only once
+        let pending = store.list_pending_dms("offline_peer").await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].payload, b"dm-1");
+        for dm in &pending {
+            assert!(store.delete_pending_dm(&dm.id).await.unwrap());
+        }
+        assert!(!store.delete_pending_dm("dm-1").await.unwrap());
+        assert_eq!(store.count_pending_dms("offline_peer").await.unwrap(), 0);
+
+        // Expires if the peer never returns
+        store.enqueue_pending_dm(&dm("dm-4", -1), 2).await.unwrap();
+        assert!(store.list_pending_dms("offline_peer").await.unwrap().is_empty());
+        let expired = store.expire_pending_dms().await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "dm-4");
+        assert_eq!(store.count_pending_dms("offline_peer").await.unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn test_trusted_peers() {
         let store = create_test_store().await;
@@ -911,11 +3846,11 @@ mod tests {
             "2wMHpFAjZbL9GkXP8n3E3",
             "2wMHpFAjZbL9GkXP8n3E4",
         ];
-        for i in 0..5 {
+        for (i, key) in test_keys.iter().enumerate() {
             let peer_id = PeerId(format!("peer_{}", i));
             let peer_info = PeerInfo {
                 id: peer_id.clone(),
-                public_key: test_keys[i].to_string(), // base58 encoded
+                public_key: key.to_string(), // base58 encoded
                 addresses: vec![],
                 first_seen: Utc::now(),
                 last_seen: Utc::now(),
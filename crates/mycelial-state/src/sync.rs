@@ -2,32 +2,58 @@
 //!
 //! This module provides mechanisms for synchronizing state between peers
 //! using gossipsub messaging with last-write-wins semantics for simple
-//! fields and grow-only counters for reputation.
+//! fields, grow-only counters for reputation, and per-node tallies for
+//! credit balances that transfers are recorded against (see
+//! [`credit_tally`](crate::credit_tally)).
+//!
+//! Gossip only carries updates made while a peer is listening. For catch-up,
+//! every applied update is also appended to a local log stamped with this
+//! node's clock. A peer sends its [`VectorClock`]; the responder replies with
+//! the logged updates past the requester's view of it plus its own clock
+//! (a [`SyncResponse`]), and the requester applies them and merges the clock.
+//!
+//! Because a reply carries the responder's clock, the requester can tell when
+//! a record was written on both sides without either having seen the other's
+//! write. Last-write-wins still picks the winner, but such conflicts are kept
+//! in a bounded log ([`StateSync::recent_conflicts`]) for debugging divergence.
 
 use chrono::{DateTime, Utc};
 use mycelial_core::{
+    identity::{Keypair, KeypairExt, PublicKeyExt, SignatureBytes},
     peer::{PeerId, PeerInfo},
-    reputation::Reputation,
+    reputation::{Reputation, ReputationPolicy},
     credit::CreditRelationship,
 };
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, info, warn};
 
 use crate::cache::StateCache;
+use crate::credit_tally::{merge_tallies, tallied_balance, CreditTallies};
 use crate::error::{Result, StateError};
 use crate::storage::SqliteStore;
+use crate::sync_keys;
 
 /// State update types that can be synced across the network
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StateUpdate {
     /// Peer information update
+    ///
+    /// Only the peer itself may announce its info, so this must be signed by
+    /// the key behind `peer_id`.
     PeerUpdate {
         peer_id: String,
         info: PeerInfoUpdate,
         timestamp: DateTime<Utc>,
+        /// Node that created the update, breaks timestamp ties
+        #[serde(default)]
+        origin: String,
+        /// Signature over the update by the peer's own key
+        #[serde(default)]
+        signature: Option<SignatureBytes>,
     },
     /// Reputation update (grow-only counters)
     ReputationUpdate {
@@ -35,6 +61,12 @@ pub enum StateUpdate {
         successful_interactions: u64,
         failed_interactions: u64,
         timestamp: DateTime<Utc>,
+        /// Compaction epoch the counters belong to
+        #[serde(default)]
+        epoch: u64,
+        /// Node that reported the counters, as it claims; not authenticated
+        #[serde(default)]
+        origin: String,
     },
     /// Credit relationship update
     CreditUpdate {
@@ -44,6 +76,16 @@ pub enum StateUpdate {
         balance: f64,
         active: bool,
         timestamp: DateTime<Utc>,
+        /// Node that created the update, breaks timestamp ties
+        #[serde(default)]
+        origin: String,
+        /// Every node's transfer tallies, for balances kept as a PN-counter
+        /// (None means `balance` is last-write-wins)
+        ///
+        /// Only the origin's own tally and opening balance are merged; the
+        /// rest come from the updates of the nodes that recorded them.
+        #[serde(default)]
+        tallies: Option<CreditTallies>,
     },
     /// Generic key-value update
     KeyValueUpdate {
@@ -54,6 +96,52 @@ pub enum StateUpdate {
     },
 }
 
+impl StateUpdate {
+    /// Bytes covered by the update's signature
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            StateUpdate::PeerUpdate { peer_id, info, timestamp, origin, .. } => {
+                Ok(serde_json::to_vec(&(peer_id, info, timestamp, origin))?)
+            }
+            _ => Err(StateError::InvalidData("update kind is not signed".to_string())),
+        }
+    }
+
+    /// Sign a peer update with the peer's keypair
+    ///
+    /// Other update kinds carry no signature and are left unchanged.
+    pub fn sign(&mut self, keypair: &Keypair) -> Result<()> {
+        if matches!(self, StateUpdate::PeerUpdate { .. }) {
+            let signed = keypair.sign_bytes(&self.signing_bytes()?);
+            if let StateUpdate::PeerUpdate { signature, .. } = self {
+                *signature = Some(signed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify that a peer update is signed by the key behind its `peer_id`
+    ///
+    /// Peer IDs are base58-encoded public keys, so the key is recovered from
+    /// the claimed ID. Other update kinds always pass.
+    pub fn verify_signature(&self) -> Result<()> {
+        let StateUpdate::PeerUpdate { peer_id, signature, .. } = self else {
+            return Ok(());
+        };
+
+        let signature = signature
+            .as_ref()
+            .ok_or_else(|| StateError::InvalidSignature(format!("unsigned peer update for {}", peer_id)))?;
+        let public_key = PeerId(peer_id.clone())
+            .to_public_key()
+            .map_err(|_| StateError::InvalidSignature(format!("peer ID {} is not a public key", peer_id)))?;
+
+        public_key
+            .verify_bytes(&self.signing_bytes()?, signature)
+            .map_err(|_| StateError::InvalidSignature(format!("peer update for {} not signed by that peer", peer_id)))
+    }
+}
+
 /// Peer information that can be synced
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfoUpdate {
@@ -64,9 +152,12 @@ pub struct PeerInfoUpdate {
 }
 
 /// Vector clock for tracking causality
+///
+/// Entries are kept sorted by peer ID, so a clock always serializes to the
+/// same bytes however it was built and can be hashed or signed.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VectorClock {
-    clocks: HashMap<String, u64>,
+    clocks: BTreeMap<String, u64>,
 }
 
 impl VectorClock {
@@ -94,44 +185,415 @@ impl VectorClock {
         }
     }
 
+    /// Compare causal order with another clock
+    ///
+    /// Walks the union of peer IDs once; a peer missing from one clock
+    /// counts as zero there.
+    pub fn compare(&self, other: &VectorClock) -> ClockOrdering {
+        let mut less = false;
+        let mut greater = false;
+
+        let ours = self.clocks.iter().map(|(peer_id, &value)| (value, other.get(peer_id)));
+        let theirs_only = other
+            .clocks
+            .iter()
+            .filter(|(peer_id, _)| !self.clocks.contains_key(*peer_id))
+            .map(|(_, &value)| (0, value));
+
+        for (mine, theirs) in ours.chain(theirs_only) {
+            less |= mine < theirs;
+            greater |= mine > theirs;
+            if less && greater {
+                return ClockOrdering::Concurrent;
+            }
+        }
+
+        match (less, greater) {
+            (false, false) => ClockOrdering::Equal,
+            (true, false) => ClockOrdering::Before,
+            (false, true) => ClockOrdering::After,
+            (true, true) => ClockOrdering::Concurrent,
+        }
+    }
+
     /// Check if this clock is concurrent with another
     pub fn is_concurrent(&self, other: &VectorClock) -> bool {
-        !self.happens_before(other) && !other.happens_before(self)
+        self.compare(other) == ClockOrdering::Concurrent
     }
 
     /// Check if this clock happens-before another
     pub fn happens_before(&self, other: &VectorClock) -> bool {
-        let mut dominated = false;
-        for (peer_id, &value) in &self.clocks {
-            let other_value = other.get(peer_id);
-            if value > other_value {
-                return false;
-            }
-            if value < other_value {
-                dominated = true;
+        self.compare(other) == ClockOrdering::Before
+    }
+}
+
+/// Causal relationship between two vector clocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockOrdering {
+    /// Both clocks have seen exactly the same events
+    Equal,
+    /// This clock happened before the other
+    Before,
+    /// This clock happened after the other
+    After,
+    /// Neither clock dominates the other
+    Concurrent,
+}
+
+/// Schedule for compacting reputation counters across epochs
+///
+/// Grow-only counters eventually get so large that recent behavior no longer
+/// moves the ratio. Each compaction scales both counters down by `factor`,
+/// preserving their ratio and the ordering between peers.
+#[derive(Debug, Clone)]
+pub struct CompactionConfig {
+    /// Time between compactions
+    pub interval: chrono::Duration,
+    /// Factor applied to both counters (0.0 - 1.0)
+    pub factor: f64,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            interval: chrono::Duration::days(7),
+            factor: 0.5,
+        }
+    }
+}
+
+/// Which update to give up when the pending queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Evict the oldest queued update to make room (newer state wins)
+    #[default]
+    DropOldest,
+    /// Discard the update being queued
+    DropNewest,
+}
+
+/// Default cap on updates waiting to be sent
+pub const DEFAULT_MAX_PENDING_UPDATES: usize = 10_000;
+
+/// Default cap on updates kept in the log for lagging peers
+pub const DEFAULT_MAX_UPDATE_LOG: usize = 10_000;
+
+/// Prefix byte marking a zstd-compressed JSON update
+const FORMAT_JSON_ZSTD: u8 = 0x01;
+
+/// Prefix byte marking a postcard-encoded update
+const FORMAT_POSTCARD: u8 = 0x02;
+
+/// zstd compression level for state updates
+const ZSTD_LEVEL: i32 = 3;
+
+/// Largest update accepted after decompression, to refuse zstd bombs
+const MAX_DECOMPRESSED_UPDATE: usize = 16 * 1024 * 1024;
+
+/// Default tolerance for last-write-wins timestamps ahead of the local clock
+pub const DEFAULT_MAX_FUTURE_SKEW_SECS: i64 = 300;
+
+/// Default window in which one reporter gets one reputation update per peer
+pub const DEFAULT_REPUTATION_REPORT_WINDOW_SECS: i64 = 10;
+
+/// Tracked reporter/subject pairs before expired ones are swept
+const REPUTATION_REPORTS_SWEEP_AT: usize = 4096;
+
+/// Wire encoding for state updates
+///
+/// Plain JSON is sent as-is, exactly as before codecs existed, so older nodes
+/// can still read it and it stays easy to debug. Other encodings start with a
+/// format byte; a JSON document always starts with `{`, so they can't be
+/// confused and [`StateSync::deserialize_update`] accepts any of them.
+///
+/// Postcard is a compact binary encoding. Timestamps keep their RFC 3339 text
+/// and byte fields (signatures, key-value payloads) are written raw, so a
+/// decoded update is identical to the original. A signed `PeerUpdate` with two
+/// addresses takes about 300 bytes instead of about 580 as JSON, mostly
+/// because JSON spells the 64 signature bytes out as decimal numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncCodec {
+    /// Uncompressed JSON
+    #[default]
+    Json,
+    /// JSON compressed with zstd, prefixed with a format byte
+    JsonZstd,
+    /// Postcard binary encoding, prefixed with a format byte
+    Postcard,
+}
+
+impl SyncCodec {
+    /// Encode an update for network transmission
+    pub fn encode(self, update: &StateUpdate) -> Result<Vec<u8>> {
+        let json = || serde_json::to_vec(update).map_err(|e| StateError::Serialization(e.to_string()));
+
+        match self {
+            SyncCodec::Json => json(),
+            SyncCodec::JsonZstd => {
+                let json = json()?;
+                let compressed = zstd::bulk::compress(&json, ZSTD_LEVEL)
+                    .map_err(|e| StateError::Serialization(format!("zstd: {}", e)))?;
+                let mut data = Vec::with_capacity(compressed.len() + 1);
+                data.push(FORMAT_JSON_ZSTD);
+                data.extend_from_slice(&compressed);
+                Ok(data)
             }
+            SyncCodec::Postcard => postcard::to_extend(update, vec![FORMAT_POSTCARD])
+                .map_err(|e| StateError::Serialization(format!("postcard: {}", e))),
         }
-        for (peer_id, &value) in &other.clocks {
-            if self.get(peer_id) < value {
-                dominated = true;
+    }
+}
+
+/// Reply to an anti-entropy request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncResponse {
+    /// Responder's clock at the time of the reply
+    pub clock: VectorClock,
+    /// Updates the requester hasn't seen, oldest first
+    pub updates: Vec<StateUpdate>,
+    /// The responder's log no longer reaches back to what the requester
+    /// has seen, so `updates` is incomplete; import a snapshot instead
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Full copy of a node's syncable state, for bootstrapping a new node
+///
+/// Applying a snapshot follows the same rules as individual updates: peer
+/// info and credit relationships are last-write-wins, reputation counters
+/// and credit tallies are merged, so a snapshot never rolls back anything
+/// newer locally.
+/// Peer info travels as the signed updates the exporter received, so the
+/// exporter can't make it up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Node that exported the snapshot, breaks last-write-wins ties
+    pub origin: String,
+    /// Exporter's clock when the snapshot was taken
+    pub clock: VectorClock,
+    /// Exporter's reputation compaction epoch
+    pub epoch: u64,
+    /// Latest signed peer update for every known peer that has one
+    pub peer_updates: Vec<StateUpdate>,
+    /// Reputation counters of every known peer
+    pub reputations: Vec<(String, Reputation)>,
+    /// Active credit relationships
+    pub credit_relationships: Vec<CreditRelationship>,
+    /// Tallies of the active relationships whose balance is tallied, as
+    /// (creditor, debtor, tallies)
+    #[serde(default)]
+    pub credit_tallies: Vec<(String, String, CreditTallies)>,
+    /// When the snapshot was taken
+    pub taken_at: DateTime<Utc>,
+}
+
+impl StateSnapshot {
+    /// Split into pages whose JSON encoding stays within `max_bytes`
+    ///
+    /// Every page carries the snapshot's origin, clock and epoch, and
+    /// [`StateSnapshot::join`] puts them back together. Fails if a single
+    /// record doesn't fit in a page.
+    pub fn into_pages(self, max_bytes: usize) -> Result<Vec<StateSnapshot>> {
+        let empty = StateSnapshot {
+            origin: self.origin,
+            clock: self.clock,
+            epoch: self.epoch,
+            peer_updates: Vec::new(),
+            reputations: Vec::new(),
+            credit_relationships: Vec::new(),
+            credit_tallies: Vec::new(),
+            taken_at: self.taken_at,
+        };
+        let mut pages = Pages::new(empty, max_bytes)?;
+        pages.fill(self.peer_updates, |page| &mut page.peer_updates)?;
+        pages.fill(self.reputations, |page| &mut page.reputations)?;
+        pages.fill(self.credit_relationships, |page| &mut page.credit_relationships)?;
+        pages.fill(self.credit_tallies, |page| &mut page.credit_tallies)?;
+        Ok(pages.pages)
+    }
+
+    /// Put pages made by [`StateSnapshot::into_pages`] back together
+    pub fn join(pages: impl IntoIterator<Item = StateSnapshot>) -> Option<StateSnapshot> {
+        let mut pages = pages.into_iter();
+        let mut snapshot = pages.next()?;
+        for page in pages {
+            snapshot.peer_updates.extend(page.peer_updates);
+            snapshot.reputations.extend(page.reputations);
+            snapshot.credit_relationships.extend(page.credit_relationships);
+            snapshot.credit_tallies.extend(page.credit_tallies);
+        }
+        Some(snapshot)
+    }
+}
+
+/// Pages being filled by [`StateSnapshot::into_pages`]
+struct Pages {
+    pages: Vec<StateSnapshot>,
+    /// A page without records, and its encoded length
+    empty: StateSnapshot,
+    empty_len: usize,
+    max_bytes: usize,
+    /// Encoded length of the last page so far
+    len: usize,
+}
+
+impl Pages {
+    fn new(empty: StateSnapshot, max_bytes: usize) -> Result<Self> {
+        let empty_len = serde_json::to_vec(&empty)?.len();
+        Ok(Self { pages: vec![empty.clone()], empty, empty_len, max_bytes, len: empty_len })
+    }
+
+    /// Add records to the last page, starting a new one whenever it's full
+    fn fill<T: Serialize>(
+        &mut self,
+        records: Vec<T>,
+        field: fn(&mut StateSnapshot) -> &mut Vec<T>,
+    ) -> Result<()> {
+        for record in records {
+            // The record plus the comma separating it from the previous one
+            let len = serde_json::to_vec(&record)?.len() + 1;
+            if self.empty_len + len > self.max_bytes {
+                return Err(StateError::InvalidData(format!(
+                    "snapshot record of {} bytes doesn't fit in a {} byte page",
+                    len, self.max_bytes
+                )));
             }
+            if self.len + len > self.max_bytes {
+                self.pages.push(self.empty.clone());
+                self.len = self.empty_len;
+            }
+            field(self.pages.last_mut().expect("there is always a page")).push(record);
+            self.len += len;
+        }
+        Ok(())
+    }
+}
+
+/// Most conflicts kept by [`StateSync::recent_conflicts`]
+pub const MAX_CONFLICT_RECORDS: usize = 256;
+
+/// Which side of a concurrent write last-write-wins kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The local version was newer and stayed
+    KeptLocal,
+    /// The remote version was newer and replaced the local one
+    AppliedRemote,
+}
+
+/// A record written by two nodes that hadn't seen each other's write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictRecord {
+    /// Record written, e.g. `credit:<creditor>:<debtor>`
+    pub key: String,
+    /// Local clock when the local version was applied
+    pub local_clock: VectorClock,
+    /// Clock the remote version arrived with
+    pub remote_clock: VectorClock,
+    /// Which version won
+    pub resolution: ConflictResolution,
+    /// When the conflict was noticed
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Why an update would not change local state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Peer update not signed by the peer it describes
+    InvalidSignature(String),
+    /// The record already holds this version or a newer one
+    Stale,
+    /// Reputation update for a peer we have no record of
+    UnknownPeer,
+    /// Key-value update targeting a node-internal key
+    ReservedKey,
+    /// Reputation counters are no higher than what's stored
+    NoChange,
+    /// The reporter already had a reputation update for this peer accepted
+    /// within the rate-limit window
+    RateLimited,
+    /// Last-write-wins timestamp this far beyond the local clock's tolerance
+    ClockSkew(chrono::Duration),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::InvalidSignature(reason) => write!(f, "invalid signature: {}", reason),
+            SkipReason::Stale => write!(f, "stale"),
+            SkipReason::UnknownPeer => write!(f, "unknown peer"),
+            SkipReason::ReservedKey => write!(f, "reserved key"),
+            SkipReason::NoChange => write!(f, "no change"),
+            SkipReason::RateLimited => write!(f, "rate limited"),
+            SkipReason::ClockSkew(ahead) => write!(f, "timestamp {}s in the future", ahead.num_seconds()),
         }
-        dominated
     }
 }
 
+/// What applying an update would do, as reported by [`StateSync::validate_update`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateEffect {
+    /// The update would change local state
+    Apply,
+    /// The update would be ignored
+    Skip(SkipReason),
+}
+
+/// Last-write-wins version: newer timestamp wins, ties go to the greater origin
+///
+/// Ordering on the origin as well makes concurrent writes with the same
+/// timestamp resolve identically on every node, whatever the arrival order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct LwwStamp {
+    timestamp: DateTime<Utc>,
+    origin: String,
+}
+
 /// State synchronization manager
 pub struct StateSync {
     /// Local peer ID
     local_peer_id: String,
     /// Vector clock for this peer
     clock: RwLock<VectorClock>,
-    /// Last seen timestamps for updates (for LWW)
-    last_seen: RwLock<HashMap<String, DateTime<Utc>>>,
+    /// Last applied version per record (for LWW)
+    last_seen: RwLock<HashMap<String, LwwStamp>>,
     /// Pending updates to be sent
-    pending_updates: RwLock<Vec<StateUpdate>>,
+    pending_updates: RwLock<VecDeque<StateUpdate>>,
+    /// Most updates held in the pending queue
+    max_pending_updates: usize,
+    /// What gives way when the pending queue is full
+    overflow_policy: OverflowPolicy,
+    /// Updates dropped because the pending queue was full
+    dropped_updates: AtomicU64,
+    /// Encoding used for outgoing updates
+    codec: SyncCodec,
+    /// Applied updates, keyed by this node's clock position when applied
+    update_log: RwLock<BTreeMap<u64, StateUpdate>>,
+    /// Most updates kept in the log; the oldest are dropped first
+    max_update_log: usize,
     /// Cache reference for quick lookups
     cache: Arc<StateCache>,
+    /// Reputation counter compaction schedule
+    compaction: CompactionConfig,
+    /// Current compaction epoch
+    epoch: RwLock<u64>,
+    /// When counters were last compacted
+    last_compaction: RwLock<DateTime<Utc>>,
+    /// Half-life for reputation decay on merge (`None` disables decay)
+    decay_half_life: Option<chrono::Duration>,
+    /// How far ahead of the local clock a last-write-wins timestamp may be
+    max_future_skew: chrono::Duration,
+    /// How merged reputation counters are turned into a score
+    reputation_policy: ReputationPolicy,
+    /// Shortest gap between accepted reputation updates per source and peer
+    reputation_report_window: chrono::Duration,
+    /// When each (source, peer) pair last had a reputation update accepted
+    reputation_reports: RwLock<HashMap<(String, String), DateTime<Utc>>>,
+    /// Local clock when each last-write-wins record was last written
+    record_clocks: RwLock<HashMap<String, VectorClock>>,
+    /// Recently detected concurrent writes, oldest first
+    conflicts: RwLock<VecDeque<ConflictRecord>>,
 }
 
 impl StateSync {
@@ -141,15 +603,194 @@ impl StateSync {
             local_peer_id,
             clock: RwLock::new(VectorClock::new()),
             last_seen: RwLock::new(HashMap::new()),
-            pending_updates: RwLock::new(Vec::new()),
+            pending_updates: RwLock::new(VecDeque::new()),
+            max_pending_updates: DEFAULT_MAX_PENDING_UPDATES,
+            overflow_policy: OverflowPolicy::default(),
+            dropped_updates: AtomicU64::new(0),
+            codec: SyncCodec::default(),
+            update_log: RwLock::new(BTreeMap::new()),
+            max_update_log: DEFAULT_MAX_UPDATE_LOG,
             cache,
+            compaction: CompactionConfig::default(),
+            epoch: RwLock::new(0),
+            last_compaction: RwLock::new(Utc::now()),
+            decay_half_life: None,
+            max_future_skew: chrono::Duration::seconds(DEFAULT_MAX_FUTURE_SKEW_SECS),
+            reputation_policy: ReputationPolicy::default(),
+            reputation_report_window: chrono::Duration::seconds(DEFAULT_REPUTATION_REPORT_WINDOW_SECS),
+            reputation_reports: RwLock::new(HashMap::new()),
+            record_clocks: RwLock::new(HashMap::new()),
+            conflicts: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Use a custom reputation compaction schedule
+    pub fn with_compaction(mut self, compaction: CompactionConfig) -> Self {
+        self.compaction = compaction;
+        self
+    }
+
+    /// Encode outgoing updates with the given codec
+    pub fn with_codec(mut self, codec: SyncCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Decay reputations toward neutral with the given half-life when merging
+    pub fn with_decay_half_life(mut self, half_life: chrono::Duration) -> Self {
+        self.decay_half_life = Some(half_life);
+        self
+    }
+
+    /// Tolerate last-write-wins timestamps up to `skew` ahead of local time
+    pub fn with_max_future_skew(mut self, skew: chrono::Duration) -> Self {
+        self.max_future_skew = skew;
+        self
+    }
+
+    /// Hold at most `max` pending updates, dropping per `policy` beyond that
+    pub fn with_pending_limit(mut self, max: usize, policy: OverflowPolicy) -> Self {
+        self.max_pending_updates = max;
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Keep at most `max` updates in the log served to lagging peers
+    pub fn with_update_log_limit(mut self, max: usize) -> Self {
+        self.max_update_log = max;
+        self
+    }
+
+    /// Score merged reputation counters with a custom policy
+    ///
+    /// Merged counters are current, so only the failure weight applies here;
+    /// ageing on merge is governed by [`Self::with_decay_half_life`].
+    pub fn with_reputation_policy(mut self, policy: ReputationPolicy) -> Self {
+        self.reputation_policy = policy;
+        self
+    }
+
+    /// Accept one reputation update per source and peer every `window`
+    ///
+    /// The source is the peer that delivered the update, see
+    /// [`Self::apply_update_from`]. A zero window turns the limit off.
+    /// Updates applied with [`Self::apply_update`] are never limited.
+    pub fn with_reputation_rate_limit(mut self, window: chrono::Duration) -> Self {
+        self.reputation_report_window = window;
+        self
+    }
+
+    /// Current reputation compaction epoch
+    pub fn epoch(&self) -> u64 {
+        *self.epoch.read()
+    }
+
+    /// Check if a compaction is due under the configured schedule
+    pub fn compaction_due(&self, now: DateTime<Utc>) -> bool {
+        now - *self.last_compaction.read() >= self.compaction.interval
+    }
+
+    /// Pick up the compaction epoch saved by a previous run
+    ///
+    /// Stored counters are on that epoch's scale, so this must run before
+    /// any update is merged.
+    pub async fn restore_compaction(&self, store: &SqliteStore) -> Result<()> {
+        if let Some((epoch, started_at)) = store.load_compaction_epoch().await? {
+            *self.epoch.write() = epoch;
+            *self.last_compaction.write() = started_at;
+            debug!("Restored compaction epoch {}", epoch);
+        }
+        Ok(())
+    }
+
+    /// Compact reputation counters if the schedule says so
+    ///
+    /// Returns the number of peers compacted, or `None` if not yet due.
+    pub async fn maybe_compact_reputations(&self, store: &SqliteStore) -> Result<Option<usize>> {
+        if !self.compaction_due(Utc::now()) {
+            return Ok(None);
+        }
+        self.compact_reputations(store).await.map(Some)
+    }
+
+    /// Scale every peer's interaction counters down by the compaction factor
+    ///
+    /// The score and the success/failure ratio are left as they are; only
+    /// the counter magnitudes shrink. Starts a new epoch.
+    pub async fn compact_reputations(&self, store: &SqliteStore) -> Result<usize> {
+        let epoch = {
+            let mut epoch = self.epoch.write();
+            *epoch += 1;
+            *epoch
+        };
+        let compacted = self.start_epoch(epoch, store).await?;
+
+        info!(
+            "Compacted reputation counters for {} peers by {} (epoch {})",
+            compacted, self.compaction.factor, epoch
+        );
+        Ok(compacted)
+    }
+
+    /// Catch up with a newer compaction epoch seen in a peer's update
+    ///
+    /// Counters from a newer epoch are on a smaller scale than ours, and
+    /// max-merging them would ignore them until ours are compacted as well.
+    /// Nothing vouches for a peer's epoch, so one update moves us one epoch
+    /// at most, and only once half the compaction interval has passed since
+    /// the last one; a peer claiming a far-off epoch can't wipe the counters.
+    /// Returns true if a new epoch was started.
+    async fn adopt_epoch(&self, remote: u64, store: &SqliteStore) -> Result<bool> {
+        let epoch = {
+            let mut epoch = self.epoch.write();
+            let since_last = Utc::now() - *self.last_compaction.read();
+            if remote <= *epoch || since_last < self.compaction.interval / 2 {
+                return Ok(false);
+            }
+            *epoch += 1;
+            *epoch
+        };
+        let compacted = self.start_epoch(epoch, store).await?;
+
+        info!(
+            "Adopted compaction epoch {} from a peer, compacting {} peers",
+            epoch, compacted
+        );
+        Ok(true)
+    }
+
+    /// Scale every stored counter into a new epoch and persist it
+    ///
+    /// The epoch has already been bumped; returns the number of peers whose
+    /// counters changed.
+    async fn start_epoch(&self, epoch: u64, store: &SqliteStore) -> Result<usize> {
+        let now = Utc::now();
+        *self.last_compaction.write() = now;
+
+        let factor = self.compaction.factor;
+        let peers = store.list_peers().await?;
+        let mut compacted = 0;
+
+        for (peer_info, mut reputation) in peers {
+            let successful = scale_counter(reputation.successful_interactions, factor);
+            let failed = scale_counter(reputation.failed_interactions, factor);
+            if successful == reputation.successful_interactions && failed == reputation.failed_interactions {
+                continue;
+            }
+
+            reputation.successful_interactions = successful;
+            reputation.failed_interactions = failed;
+            store.update_peer_reputation(peer_info.id.as_str(), &reputation).await?;
+            self.cache.peers.insert(peer_info, reputation);
+            compacted += 1;
         }
+
+        store.save_compaction_epoch(epoch, now).await?;
+        Ok(compacted)
     }
 
     /// Create a peer update
     pub fn create_peer_update(&self, peer_info: &PeerInfo) -> StateUpdate {
-        self.clock.write().increment(&self.local_peer_id);
-
         StateUpdate::PeerUpdate {
             peer_id: peer_info.id.as_str().to_string(),
             info: PeerInfoUpdate {
@@ -158,25 +799,32 @@ impl StateSync {
                 name: peer_info.name.clone(),
             },
             timestamp: Utc::now(),
+            origin: self.local_peer_id.clone(),
+            signature: None,
         }
     }
 
+    /// Create a peer update signed with the peer's own keypair
+    pub fn create_signed_peer_update(&self, peer_info: &PeerInfo, keypair: &Keypair) -> Result<StateUpdate> {
+        let mut update = self.create_peer_update(peer_info);
+        update.sign(keypair)?;
+        Ok(update)
+    }
+
     /// Create a reputation update (grow-only counters)
     pub fn create_reputation_update(&self, peer_id: &str, reputation: &Reputation) -> StateUpdate {
-        self.clock.write().increment(&self.local_peer_id);
-
         StateUpdate::ReputationUpdate {
             peer_id: peer_id.to_string(),
             successful_interactions: reputation.successful_interactions,
             failed_interactions: reputation.failed_interactions,
             timestamp: Utc::now(),
+            epoch: self.epoch(),
+            origin: self.local_peer_id.clone(),
         }
     }
 
     /// Create a credit update
     pub fn create_credit_update(&self, relationship: &CreditRelationship) -> StateUpdate {
-        self.clock.write().increment(&self.local_peer_id);
-
         StateUpdate::CreditUpdate {
             creditor: relationship.creditor.as_str().to_string(),
             debtor: relationship.debtor.as_str().to_string(),
@@ -184,13 +832,52 @@ impl StateSync {
             balance: relationship.balance,
             active: relationship.active,
             timestamp: Utc::now(),
+            origin: self.local_peer_id.clone(),
+            tallies: None,
         }
     }
 
+    /// Record a transfer of `amount` from creditor to debtor made on this node
+    ///
+    /// The transfer is checked against the credit limit and added to this
+    /// node's tally by [`SqliteStore::apply_transfer`], then applied like any
+    /// other update. Returns the update to send to other nodes: it carries
+    /// every node's tallies along with the balance they add up to, so
+    /// transfers made concurrently on other nodes are merged rather than
+    /// overwritten.
+    pub async fn record_credit_transfer(
+        &self,
+        creditor: &str,
+        debtor: &str,
+        amount: f64,
+        store: &SqliteStore,
+    ) -> Result<StateUpdate> {
+        store.apply_transfer(creditor, debtor, amount, &self.local_peer_id).await?;
+        let relationship = store
+            .get_credit_relationship_between(creditor, debtor)
+            .await?
+            .ok_or_else(|| StateError::NotFound {
+                entity: "credit relationship".to_string(),
+                id: format!("{}:{}", creditor, debtor),
+            })?;
+        let tallies = store.credit_tallies(creditor, debtor).await?;
+
+        let update = StateUpdate::CreditUpdate {
+            creditor: creditor.to_string(),
+            debtor: debtor.to_string(),
+            credit_limit: relationship.credit_limit,
+            balance: tallied_balance(&tallies),
+            active: relationship.active,
+            timestamp: Utc::now(),
+            origin: self.local_peer_id.clone(),
+            tallies: Some(tallies),
+        };
+        self.apply_update(&update, store).await?;
+        Ok(update)
+    }
+
     /// Create a key-value update
     pub fn create_kv_update(&self, key: &str, value: Vec<u8>, version: u64) -> StateUpdate {
-        self.clock.write().increment(&self.local_peer_id);
-
         StateUpdate::KeyValueUpdate {
             key: key.to_string(),
             value,
@@ -199,22 +886,506 @@ impl StateSync {
         }
     }
 
-    /// Apply an update received from the network
+    /// Apply an update this node made itself
+    ///
+    /// Runs the same checks as [`Self::apply_update_from`], but reputation
+    /// updates aren't rate-limited.
     pub async fn apply_update(&self, update: &StateUpdate, store: &SqliteStore) -> Result<bool> {
+        self.apply_update_from(update, &self.local_peer_id, store).await
+    }
+
+    /// Apply an update delivered by `source`
+    ///
+    /// `source` is the peer the transport says the update came from (the
+    /// gossip source or sync responder), not a peer the update names itself:
+    /// reputation updates are rate-limited per source and subject, and a
+    /// field in the update could be set to anything.
+    ///
+    /// Peer updates that aren't signed by the peer they describe are rejected
+    /// with [`StateError::InvalidSignature`], and last-write-wins updates
+    /// dated too far in the future with [`StateError::ClockSkew`] (otherwise
+    /// a peer with a fast clock could pin a value). Updates that change state are
+    /// appended to the update log so they can be served to lagging peers.
+    pub async fn apply_update_from(&self, update: &StateUpdate, source: &str, store: &SqliteStore) -> Result<bool> {
+        // Counters from a newer epoch only merge once ours are on its scale
+        if let StateUpdate::ReputationUpdate { epoch, .. } = update {
+            self.adopt_epoch(*epoch, store).await?;
+        }
+
+        match self.validate_update_from(update, source, store).await? {
+            UpdateEffect::Apply => {}
+            UpdateEffect::Skip(SkipReason::InvalidSignature(reason)) => {
+                warn!("Rejecting state update: {}", reason);
+                return Err(StateError::InvalidSignature(reason));
+            }
+            UpdateEffect::Skip(SkipReason::ClockSkew(ahead)) => {
+                warn!("Rejecting {} update dated {}s ahead", update_kind(update), ahead.num_seconds());
+                return Err(StateError::ClockSkew {
+                    ahead_secs: ahead.num_seconds(),
+                    max_secs: self.max_future_skew.num_seconds(),
+                });
+            }
+            UpdateEffect::Skip(reason) => {
+                debug!("Skipping {} update: {}", update_kind(update), reason);
+                return Ok(false);
+            }
+        }
+
+        // Validation only peeks at the limit; claim the slot before applying
+        if let StateUpdate::ReputationUpdate { peer_id, .. } = update {
+            if !self.admit_reputation_report(source, peer_id, Utc::now()) {
+                debug!("Skipping reputation update: {}", SkipReason::RateLimited);
+                return Ok(false);
+            }
+        }
+
+        let applied = self.apply_verified(update, store).await?;
+        if applied {
+            self.keep_signed_peer_update(update, store).await?;
+            let mut clock = self.clock.write();
+            clock.increment(&self.local_peer_id);
+            if let Some((key, _)) = lww_entry(update) {
+                self.record_clocks.write().insert(key, clock.clone());
+            }
+            let mut log = self.update_log.write();
+            log.insert(clock.get(&self.local_peer_id), update.clone());
+            while log.len() > self.max_update_log {
+                log.pop_first();
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Work out whether an update this node made would be applied
+    pub async fn validate_update(&self, update: &StateUpdate, store: &SqliteStore) -> Result<UpdateEffect> {
+        self.validate_update_from(update, &self.local_peer_id, store).await
+    }
+
+    /// Work out whether an update delivered by `source` would be applied,
+    /// without changing anything
+    ///
+    /// Runs the same checks as [`Self::apply_update_from`] (signature, clock skew,
+    /// staleness, known peer, reserved keys, rate limit, counter growth) but only
+    /// reads the store, so updates from untrusted peers can be inspected before applying.
+    pub async fn validate_update_from(
+        &self,
+        update: &StateUpdate,
+        source: &str,
+        store: &SqliteStore,
+    ) -> Result<UpdateEffect> {
+        match update.verify_signature() {
+            Ok(()) => {}
+            Err(StateError::InvalidSignature(reason)) => {
+                return Ok(UpdateEffect::Skip(SkipReason::InvalidSignature(reason)));
+            }
+            Err(e) => return Err(e),
+        }
+
+        let dated = match update {
+            StateUpdate::PeerUpdate { timestamp, .. } => Some(*timestamp),
+            // An opening balance dated ahead would win over every later one
+            StateUpdate::CreditUpdate { timestamp, tallies, .. } => Some(
+                tallies
+                    .as_ref()
+                    .and_then(|tallies| tallies.opening.as_ref())
+                    .map_or(*timestamp, |opening| opening.timestamp.max(*timestamp)),
+            ),
+            _ => None,
+        };
+        if let Some(timestamp) = dated {
+            let ahead = timestamp - Utc::now();
+            if ahead > self.max_future_skew {
+                return Ok(UpdateEffect::Skip(SkipReason::ClockSkew(ahead)));
+            }
+        }
+
+        let skip = match update {
+            StateUpdate::PeerUpdate { peer_id, timestamp, origin, .. } => {
+                let stamp = LwwStamp { timestamp: *timestamp, origin: origin.clone() };
+                (!self.supersedes(&format!("peer:{}", peer_id), &stamp)).then_some(SkipReason::Stale)
+            }
+            StateUpdate::ReputationUpdate { peer_id, .. }
+                if self.reputation_rate_limited(source, peer_id, Utc::now()) =>
+            {
+                Some(SkipReason::RateLimited)
+            }
+            StateUpdate::ReputationUpdate {
+                peer_id,
+                successful_interactions,
+                failed_interactions,
+                timestamp,
+                epoch,
+                ..
+            } => match store.get_peer(peer_id).await? {
+                None => Some(SkipReason::UnknownPeer),
+                Some((_, reputation)) => self
+                    .merge_reputation(
+                        reputation,
+                        *successful_interactions,
+                        *failed_interactions,
+                        *epoch,
+                        timestamp,
+                    )
+                    .is_none()
+                    .then_some(SkipReason::NoChange),
+            },
+            StateUpdate::CreditUpdate { creditor, debtor, timestamp, origin, tallies, .. } => {
+                let stamp = LwwStamp { timestamp: *timestamp, origin: origin.clone() };
+                let update_key = format!("credit:{}:{}", creditor, debtor);
+                // Tallies that grow the local ones count however old the update is
+                let grows = match tallies {
+                    Some(tallies) => {
+                        let mut local = store.credit_tallies(creditor, debtor).await?;
+                        merge_tallies(&mut local, &tallies.recorded_by(origin))
+                    }
+                    None => false,
+                };
+                (!grows && !self.supersedes(&update_key, &stamp)).then_some(SkipReason::Stale)
+            }
+            StateUpdate::KeyValueUpdate { key, version, .. } => {
+                // Peers never get to write node-internal keys
+                if sync_keys::is_reserved(key) {
+                    Some(SkipReason::ReservedKey)
+                } else {
+                    match store.get_sync_value(key).await? {
+                        Some((_, existing_version)) if existing_version as u64 >= *version => {
+                            Some(SkipReason::Stale)
+                        }
+                        _ => None,
+                    }
+                }
+            }
+        };
+
+        Ok(skip.map_or(UpdateEffect::Apply, UpdateEffect::Skip))
+    }
+
+    /// Logged updates a peer with the given clock hasn't seen, oldest first
+    ///
+    /// The log is stamped with this node's own clock entry, so everything
+    /// past the remote's view of that entry is returned. Updates the remote
+    /// already has through other peers are included too; applying them again
+    /// is a no-op.
+    pub fn updates_since(&self, remote: &VectorClock) -> Vec<StateUpdate> {
+        let seen = remote.get(&self.local_peer_id);
+        self.update_log
+            .read()
+            .range(seen.saturating_add(1)..)
+            .map(|(_, update)| update.clone())
+            .collect()
+    }
+
+    /// Answer an anti-entropy request from a peer
+    ///
+    /// If updates the peer hasn't seen were already dropped from the log,
+    /// the reply is marked truncated.
+    pub fn handle_sync_request(&self, remote: &VectorClock) -> SyncResponse {
+        // Read the clock first so the reply never claims more than it carries
+        let clock = self.get_clock();
+        let seen = remote.get(&self.local_peer_id);
+        let truncated = self
+            .update_log
+            .read()
+            .keys()
+            .next()
+            .map_or(clock.get(&self.local_peer_id) > seen, |oldest| *oldest > seen.saturating_add(1));
+        SyncResponse {
+            updates: self.updates_since(remote),
+            clock,
+            truncated,
+        }
+    }
+
+    /// Apply a peer's anti-entropy reply, returns the number of updates applied
+    ///
+    /// Invalid updates are skipped. Updates that conflict with a concurrent
+    /// local write are logged, see [`Self::recent_conflicts`]. The
+    /// responder's clock is merged afterwards, recording that everything it
+    /// had logged has been seen, unless the reply is truncated: then some of
+    /// the responder's updates never arrived and a snapshot is needed.
+    /// `responder` is the peer the reply came from, see
+    /// [`Self::apply_update_from`].
+    pub async fn apply_sync_response(
+        &self,
+        response: &SyncResponse,
+        responder: &str,
+        store: &SqliteStore,
+    ) -> Result<usize> {
+        let mut applied = 0;
+        for update in &response.updates {
+            let conflict = self.concurrent_write(update, &response.clock);
+            let outcome = match self.apply_update_from(update, responder, store).await {
+                Ok(outcome) => outcome,
+                Err(StateError::InvalidSignature(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            if outcome {
+                applied += 1;
+            }
+            if let Some((key, local_clock)) = conflict {
+                self.record_conflict(ConflictRecord {
+                    key,
+                    local_clock,
+                    remote_clock: response.clock.clone(),
+                    resolution: if outcome {
+                        ConflictResolution::AppliedRemote
+                    } else {
+                        ConflictResolution::KeptLocal
+                    },
+                    detected_at: Utc::now(),
+                });
+            }
+        }
+
+        if response.truncated {
+            warn!("Sync response is missing updates dropped from the responder's log; import a snapshot");
+        } else {
+            self.merge_clock(&response.clock);
+        }
+        debug!(
+            "Applied {} of {} updates from sync response",
+            applied,
+            response.updates.len()
+        );
+        Ok(applied)
+    }
+
+    /// Export peers, reputations and active credit relationships
+    ///
+    /// Peers this node never received a signed update for are left out of
+    /// `peer_updates`, but their reputations are still exported.
+    pub async fn export_snapshot(&self, store: &SqliteStore) -> Result<StateSnapshot> {
+        // Read the clock first so the snapshot never claims more than it carries
+        let clock = self.get_clock();
+        let peers = store.list_peers().await?;
+        let credit_relationships = store.list_active_credit_relationships().await?;
+
+        let mut peer_updates = Vec::new();
+        for (info, _) in &peers {
+            if let Some(update) = self.signed_peer_update(info.id.as_str(), store).await? {
+                peer_updates.push(update);
+            }
+        }
+        let reputations = peers
+            .into_iter()
+            .map(|(info, reputation)| (info.id.as_str().to_string(), reputation))
+            .collect();
+
+        let mut credit_tallies = Vec::new();
+        for relationship in &credit_relationships {
+            let (creditor, debtor) = (relationship.creditor.as_str(), relationship.debtor.as_str());
+            let tallies = store.credit_tallies(creditor, debtor).await?;
+            if !tallies.is_empty() {
+                credit_tallies.push((creditor.to_string(), debtor.to_string(), tallies));
+            }
+        }
+
+        Ok(StateSnapshot {
+            origin: self.local_peer_id.clone(),
+            clock,
+            epoch: self.epoch(),
+            peer_updates,
+            reputations,
+            credit_relationships,
+            credit_tallies,
+            taken_at: Utc::now(),
+        })
+    }
+
+    /// Apply another node's snapshot, returns the number of records changed
+    ///
+    /// Peer updates are applied exactly like gossiped ones, so unsigned,
+    /// forged or future-dated ones are dropped. Credit relationships only
+    /// replace local records that are older by `last_transaction`, and are
+    /// dropped if dated too far ahead like credit updates. Reputation
+    /// counters are max-merged for peers known once the peer updates are in,
+    /// and credit tallies are merged for relationships known by then, so a
+    /// tallied balance never counts the same transfers twice. The exporter's
+    /// clock is merged afterwards.
+    pub async fn import_snapshot(&self, snapshot: &StateSnapshot, store: &SqliteStore) -> Result<usize> {
+        self.adopt_epoch(snapshot.epoch, store).await?;
+        let mut changed = 0;
+        let mut rejected = 0;
+
+        for update in &snapshot.peer_updates {
+            if !matches!(update, StateUpdate::PeerUpdate { .. }) {
+                rejected += 1;
+                continue;
+            }
+            match self.apply_update_from(update, &snapshot.origin, store).await {
+                Ok(applied) => changed += usize::from(applied),
+                Err(StateError::InvalidSignature(_) | StateError::ClockSkew { .. }) => rejected += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        for (peer_id, reputation) in &snapshot.reputations {
+            let Some((peer_info, current)) = store.get_peer(peer_id).await? else {
+                continue;
+            };
+            if let Some(merged) = self.merge_reputation(
+                current,
+                reputation.successful_interactions,
+                reputation.failed_interactions,
+                snapshot.epoch,
+                &reputation.last_updated,
+            ) {
+                store.update_peer_reputation(peer_id, &merged).await?;
+                self.cache.peers.insert(peer_info, merged);
+                changed += 1;
+            }
+        }
+
+        let now = Utc::now();
+        for relationship in &snapshot.credit_relationships {
+            if relationship.last_transaction - now > self.max_future_skew {
+                rejected += 1;
+                continue;
+            }
+            let creditor = relationship.creditor.as_str();
+            let debtor = relationship.debtor.as_str();
+            let update_key = format!("credit:{}:{}", creditor, debtor);
+            let stamp = LwwStamp { timestamp: relationship.last_transaction, origin: snapshot.origin.clone() };
+            let newer_locally = store
+                .get_credit_relationship_between(creditor, debtor)
+                .await?
+                .is_some_and(|existing| existing.last_transaction >= relationship.last_transaction);
+            if newer_locally || !self.supersedes(&update_key, &stamp) {
+                continue;
+            }
+
+            // A tallied balance stays what the tallies add up to
+            let mut relationship = relationship.clone();
+            let tallies = store.credit_tallies(creditor, debtor).await?;
+            if !tallies.is_empty() {
+                relationship.balance = tallied_balance(&tallies);
+            }
+
+            store.upsert_credit_relationship(&relationship).await?;
+            self.last_seen.write().insert(update_key, stamp);
+            self.cache.credits.insert(relationship);
+            changed += 1;
+        }
+
+        for (creditor, debtor, tallies) in &snapshot.credit_tallies {
+            let opening_ahead = tallies
+                .opening
+                .as_ref()
+                .is_some_and(|opening| opening.timestamp - now > self.max_future_skew);
+            if opening_ahead {
+                rejected += 1;
+                continue;
+            }
+            let Some(mut relationship) = store.get_credit_relationship_between(creditor, debtor).await? else {
+                continue;
+            };
+            let mut merged = store.credit_tallies(creditor, debtor).await?;
+            if !merge_tallies(&mut merged, tallies) {
+                continue;
+            }
+            relationship.balance = tallied_balance(&merged);
+            store.upsert_credit_relationship(&relationship).await?;
+            store.merge_credit_tallies(creditor, debtor, &merged).await?;
+            self.cache.credits.insert(relationship);
+            changed += 1;
+        }
+
+        self.merge_clock(&snapshot.clock);
+        info!(
+            "Imported snapshot from {}: {} of {} records changed, {} rejected",
+            snapshot.origin,
+            changed,
+            snapshot.peer_updates.len()
+                + snapshot.reputations.len()
+                + snapshot.credit_relationships.len()
+                + snapshot.credit_tallies.len(),
+            rejected
+        );
+        Ok(changed)
+    }
+
+    /// Keep the signed peer update just applied, for snapshots to pass on
+    async fn keep_signed_peer_update(&self, update: &StateUpdate, store: &SqliteStore) -> Result<()> {
+        if let StateUpdate::PeerUpdate { peer_id, signature: Some(_), .. } = update {
+            let key = format!("{}{}", sync_keys::SIGNED_PEER_PREFIX, peer_id);
+            store.set_internal_sync_value(&key, &serde_json::to_vec(update)?).await?;
+        }
+        Ok(())
+    }
+
+    /// Latest signed update applied for a peer, if any
+    ///
+    /// An unreadable entry counts as none.
+    async fn signed_peer_update(&self, peer_id: &str, store: &SqliteStore) -> Result<Option<StateUpdate>> {
+        let key = format!("{}{}", sync_keys::SIGNED_PEER_PREFIX, peer_id);
+        let Some((value, _)) = store.get_sync_value(&key).await? else {
+            return Ok(None);
+        };
+        Ok(serde_json::from_slice(&value).ok())
+    }
+
+    /// Number of updates in the update log
+    pub fn update_log_len(&self) -> usize {
+        self.update_log.read().len()
+    }
+
+    /// Concurrent writes detected so far, newest first
+    ///
+    /// Holds at most [`MAX_CONFLICT_RECORDS`]; older ones are dropped.
+    pub fn recent_conflicts(&self) -> Vec<ConflictRecord> {
+        self.conflicts.read().iter().rev().cloned().collect()
+    }
+
+    /// Find a local write that `update` conflicts with, returning its key and clock
+    ///
+    /// A conflict needs the current local version to come from another node
+    /// than the update and its clock to be concurrent with `remote_clock`.
+    /// Re-deliveries of the same write and a node's own successive writes
+    /// are not conflicts.
+    fn concurrent_write(&self, update: &StateUpdate, remote_clock: &VectorClock) -> Option<(String, VectorClock)> {
+        // Tallied balances merge, so concurrent transfers don't conflict
+        if let StateUpdate::CreditUpdate { tallies: Some(_), .. } = update {
+            return None;
+        }
+        let (key, stamp) = lww_entry(update)?;
+        let local = self.last_seen.read().get(&key).cloned()?;
+        if local.origin == stamp.origin {
+            return None;
+        }
+        let local_clock = self.record_clocks.read().get(&key).cloned()?;
+        local_clock.is_concurrent(remote_clock).then_some((key, local_clock))
+    }
+
+    /// Append to the conflict log, dropping the oldest record when full
+    fn record_conflict(&self, record: ConflictRecord) {
+        info!("Concurrent writes to {}, resolved as {:?}", record.key, record.resolution);
+        let mut conflicts = self.conflicts.write();
+        if conflicts.len() >= MAX_CONFLICT_RECORDS {
+            conflicts.pop_front();
+        }
+        conflicts.push_back(record);
+    }
+
+    /// Apply an update that passed [`Self::validate_update`]
+    async fn apply_verified(&self, update: &StateUpdate, store: &SqliteStore) -> Result<bool> {
         match update {
-            StateUpdate::PeerUpdate { peer_id, info, timestamp } => {
-                self.apply_peer_update(peer_id, info, timestamp, store).await
+            StateUpdate::PeerUpdate { peer_id, info, timestamp, origin, .. } => {
+                let stamp = LwwStamp { timestamp: *timestamp, origin: origin.clone() };
+                self.apply_peer_update(peer_id, info, stamp, store).await
             }
             StateUpdate::ReputationUpdate {
                 peer_id,
                 successful_interactions,
                 failed_interactions,
                 timestamp,
+                epoch,
+                ..
             } => {
                 self.apply_reputation_update(
                     peer_id,
                     *successful_interactions,
                     *failed_interactions,
+                    *epoch,
                     timestamp,
                     store,
                 )
@@ -227,22 +1398,61 @@ impl StateSync {
                 balance,
                 active,
                 timestamp,
+                origin,
+                tallies,
             } => {
-                self.apply_credit_update(
-                    creditor,
-                    debtor,
-                    *credit_limit,
-                    *balance,
-                    *active,
-                    timestamp,
-                    store,
-                )
-                .await
-            }
-            StateUpdate::KeyValueUpdate { key, value, version, timestamp } => {
-                self.apply_kv_update(key, value, *version, timestamp, store).await
+                let stamp = LwwStamp { timestamp: *timestamp, origin: origin.clone() };
+                let incoming = CreditRelationship {
+                    creditor: PeerId(creditor.clone()),
+                    debtor: PeerId(debtor.clone()),
+                    credit_limit: *credit_limit,
+                    balance: *balance,
+                    active: *active,
+                    established: *timestamp,
+                    last_transaction: *timestamp,
+                };
+                let tallies = tallies.as_ref().map(|tallies| tallies.recorded_by(origin));
+                self.apply_credit_update(incoming, tallies.as_ref(), stamp, store).await
             }
+            StateUpdate::KeyValueUpdate { key, value, .. } => self.apply_kv_update(key, value, store).await,
+        }
+    }
+
+    /// Check if an update about a peer from a source falls inside the rate-limit window
+    fn reputation_rate_limited(&self, source: &str, peer_id: &str, now: DateTime<Utc>) -> bool {
+        if source == self.local_peer_id || self.reputation_report_window <= chrono::Duration::zero() {
+            return false;
+        }
+        self.reputation_reports
+            .read()
+            .get(&(source.to_string(), peer_id.to_string()))
+            .is_some_and(|last| now - *last < self.reputation_report_window)
+    }
+
+    /// Record a reputation update from a source, returns false if rate-limited
+    fn admit_reputation_report(&self, source: &str, peer_id: &str, now: DateTime<Utc>) -> bool {
+        if source == self.local_peer_id || self.reputation_report_window <= chrono::Duration::zero() {
+            return true;
+        }
+        let window = self.reputation_report_window;
+        let mut reports = self.reputation_reports.write();
+        if reports.len() >= REPUTATION_REPORTS_SWEEP_AT {
+            reports.retain(|_, last| now - *last < window);
+        }
+        let key = (source.to_string(), peer_id.to_string());
+        if reports.get(&key).is_some_and(|last| now - *last < window) {
+            return false;
         }
+        reports.insert(key, now);
+        true
+    }
+
+    /// Check if an update is newer than the last one applied to a record
+    fn supersedes(&self, update_key: &str, stamp: &LwwStamp) -> bool {
+        self.last_seen
+            .read()
+            .get(update_key)
+            .is_none_or(|last| stamp > last)
     }
 
     /// Apply a peer update using last-write-wins
@@ -250,53 +1460,42 @@ impl StateSync {
         &self,
         peer_id: &str,
         info: &PeerInfoUpdate,
-        timestamp: &DateTime<Utc>,
+        stamp: LwwStamp,
         store: &SqliteStore,
     ) -> Result<bool> {
         let update_key = format!("peer:{}", peer_id);
 
-        // Check if we have a newer update
-        {
-            let last_seen = self.last_seen.read();
-            if let Some(last_ts) = last_seen.get(&update_key) {
-                if last_ts >= timestamp {
-                    debug!("Skipping stale peer update for {}", peer_id);
-                    return Ok(false);
-                }
-            }
-        }
-
-        // Get existing peer or create new one
-        let peer_info = match store.get_peer(peer_id).await? {
-            Some((mut existing, _)) => {
+        // Get existing peer or create new one; its counters stay as they are
+        let (peer_info, reputation) = match store.get_peer(peer_id).await? {
+            Some((mut existing, reputation)) => {
                 // Update existing peer
                 existing.public_key = info.public_key.clone();
                 existing.addresses = info.addresses.clone();
                 existing.name = info.name.clone();
                 existing.last_seen = Utc::now();
-                existing
+                (existing, Some(reputation))
             }
             None => {
                 // Create new peer
-                PeerInfo {
+                let peer_info = PeerInfo {
                     id: PeerId(peer_id.to_string()),
                     public_key: info.public_key.clone(),
                     addresses: info.addresses.clone(),
                     first_seen: Utc::now(),
                     last_seen: Utc::now(),
                     name: info.name.clone(),
-                }
+                };
+                (peer_info, None)
             }
         };
 
-        store.upsert_peer(&peer_info, None).await?;
+        store.upsert_peer(&peer_info, reputation.as_ref()).await?;
 
-        // Update last seen timestamp
-        self.last_seen.write().insert(update_key, *timestamp);
+        // Update last seen version
+        self.last_seen.write().insert(update_key, stamp);
 
         // Update cache
-        let reputation = Reputation::default();
-        self.cache.peers.insert(peer_info, reputation);
+        self.cache.peers.insert(peer_info, reputation.unwrap_or_default());
 
         debug!("Applied peer update for {}", peer_id);
         Ok(true)
@@ -308,81 +1507,140 @@ impl StateSync {
         peer_id: &str,
         successful: u64,
         failed: u64,
-        _timestamp: &DateTime<Utc>,
+        epoch: u64,
+        timestamp: &DateTime<Utc>,
         store: &SqliteStore,
     ) -> Result<bool> {
         // Get existing reputation
-        let (peer_info, mut reputation) = match store.get_peer(peer_id).await? {
-            Some((info, rep)) => (info, rep),
-            None => {
-                debug!("Skipping reputation update for unknown peer {}", peer_id);
-                return Ok(false);
-            }
+        let Some((peer_info, reputation)) = store.get_peer(peer_id).await? else {
+            debug!("Skipping reputation update for unknown peer {}", peer_id);
+            return Ok(false);
         };
 
-        // Grow-only counter merge: take the max
-        let updated = successful > reputation.successful_interactions
-            || failed > reputation.failed_interactions;
+        let Some(reputation) = self.merge_reputation(reputation, successful, failed, epoch, timestamp) else {
+            return Ok(false);
+        };
+
+        store.update_peer_reputation(peer_id, &reputation).await?;
+
+        // Update cache
+        self.cache.peers.insert(peer_info, reputation);
 
-        if updated {
-            reputation.successful_interactions = reputation.successful_interactions.max(successful);
-            reputation.failed_interactions = reputation.failed_interactions.max(failed);
+        debug!("Applied reputation update for {}", peer_id);
+        Ok(true)
+    }
 
-            // Recalculate score
-            let total = reputation.successful_interactions + reputation.failed_interactions;
-            if total > 0 {
-                reputation.score = reputation.successful_interactions as f64 / total as f64;
+    /// Merge incoming reputation counters into a local reputation
+    ///
+    /// Returns the merged reputation, or `None` if nothing would change.
+    /// With a decay half-life configured, both sides are aged to the present
+    /// before the max is taken: the local counters by their `last_updated`,
+    /// the incoming ones by the update timestamp (see [`decay_counters`]).
+    fn merge_reputation(
+        &self,
+        mut reputation: Reputation,
+        successful: u64,
+        failed: u64,
+        epoch: u64,
+        timestamp: &DateTime<Utc>,
+    ) -> Option<Reputation> {
+        // Bring counters from older epochs down to our scale before merging
+        let local_epoch = self.epoch();
+        let (successful, failed) = if epoch < local_epoch {
+            let factor = self.compaction.factor.powi((local_epoch - epoch).min(64) as i32);
+            (scale_counter(successful, factor), scale_counter(failed, factor))
+        } else {
+            (successful, failed)
+        };
+
+        // Age both sides before merging so silent peers drift back to neutral
+        let mut decayed = false;
+        let (successful, failed) = match self.decay_half_life {
+            Some(half_life) => {
+                let now = Utc::now();
+                let local = (reputation.successful_interactions, reputation.failed_interactions);
+                let aged = decay_counters(local.0, local.1, now - reputation.last_updated, half_life);
+                if aged != local {
+                    reputation.successful_interactions = aged.0;
+                    reputation.failed_interactions = aged.1;
+                    decayed = true;
+                }
+                decay_counters(successful, failed, now - *timestamp, half_life)
             }
+            None => (successful, failed),
+        };
 
-            store.update_peer_reputation(peer_id, &reputation).await?;
+        // Grow-only counter merge: take the max
+        let updated = decayed
+            || successful > reputation.successful_interactions
+            || failed > reputation.failed_interactions;
 
-            // Update cache
-            self.cache.peers.insert(peer_info, reputation);
+        if !updated {
+            return None;
+        }
+
+        reputation.successful_interactions = reputation.successful_interactions.max(successful);
+        reputation.failed_interactions = reputation.failed_interactions.max(failed);
+        // Decayed counters are current as of now; otherwise the merged ones
+        // are as recent as the newer side
+        reputation.last_updated = match self.decay_half_life {
+            Some(_) => Utc::now(),
+            None => reputation.last_updated.max(*timestamp),
+        };
 
-            debug!("Applied reputation update for {}", peer_id);
+        // Recalculate score
+        let total = reputation.successful_interactions + reputation.failed_interactions;
+        if total > 0 {
+            reputation.score = self.reputation_policy.score(
+                reputation.successful_interactions,
+                reputation.failed_interactions,
+                chrono::Duration::zero(),
+            );
         }
 
-        Ok(updated)
+        Some(reputation)
     }
 
-    /// Apply a credit update using last-write-wins
+    /// Apply a credit update carrying the `incoming` relationship
+    ///
+    /// The limit and active flag are last-write-wins, and so is the balance
+    /// unless the relationship has tallies: those are merged, and the
+    /// balance is what the merged tallies add up to.
     async fn apply_credit_update(
         &self,
-        creditor: &str,
-        debtor: &str,
-        credit_limit: f64,
-        balance: f64,
-        active: bool,
-        timestamp: &DateTime<Utc>,
+        incoming: CreditRelationship,
+        tallies: Option<&CreditTallies>,
+        stamp: LwwStamp,
         store: &SqliteStore,
     ) -> Result<bool> {
+        let (creditor, debtor) = (incoming.creditor.0.clone(), incoming.debtor.0.clone());
+        let (creditor, debtor) = (creditor.as_str(), debtor.as_str());
         let update_key = format!("credit:{}:{}", creditor, debtor);
+        let newer = self.supersedes(&update_key, &stamp);
 
-        // Check if we have a newer update
-        {
-            let last_seen = self.last_seen.read();
-            if let Some(last_ts) = last_seen.get(&update_key) {
-                if last_ts >= timestamp {
-                    debug!("Skipping stale credit update for {}:{}", creditor, debtor);
-                    return Ok(false);
-                }
-            }
-        }
-
-        let relationship = CreditRelationship {
-            creditor: PeerId(creditor.to_string()),
-            debtor: PeerId(debtor.to_string()),
-            credit_limit,
-            balance,
-            active,
-            established: *timestamp,
-            last_transaction: *timestamp,
+        let mut relationship = match store.get_credit_relationship_between(creditor, debtor).await? {
+            Some(existing) if !newer => existing,
+            _ => incoming,
         };
 
+        let mut merged = store.credit_tallies(creditor, debtor).await?;
+        if let Some(tallies) = tallies {
+            merge_tallies(&mut merged, tallies);
+        }
+        if !merged.is_empty() {
+            relationship.balance = tallied_balance(&merged);
+            relationship.last_transaction = relationship.last_transaction.max(stamp.timestamp);
+        }
+
         store.upsert_credit_relationship(&relationship).await?;
+        if !merged.is_empty() {
+            store.merge_credit_tallies(creditor, debtor, &merged).await?;
+        }
 
-        // Update last seen timestamp
-        self.last_seen.write().insert(update_key, *timestamp);
+        // Update last seen version
+        if newer {
+            self.last_seen.write().insert(update_key, stamp);
+        }
 
         // Update cache
         self.cache.credits.insert(relationship);
@@ -391,23 +1649,8 @@ impl StateSync {
         Ok(true)
     }
 
-    /// Apply a key-value update using version numbers
-    async fn apply_kv_update(
-        &self,
-        key: &str,
-        value: &[u8],
-        version: u64,
-        _timestamp: &DateTime<Utc>,
-        store: &SqliteStore,
-    ) -> Result<bool> {
-        // Get existing version
-        if let Some((_, existing_version)) = store.get_sync_value(key).await? {
-            if existing_version as u64 >= version {
-                debug!("Skipping stale key-value update for {}", key);
-                return Ok(false);
-            }
-        }
-
+    /// Apply a key-value update whose version is newer than the stored one
+    async fn apply_kv_update(&self, key: &str, value: &[u8], store: &SqliteStore) -> Result<bool> {
         store.set_sync_value(key, value).await?;
 
         debug!("Applied key-value update for {}", key);
@@ -415,26 +1658,65 @@ impl StateSync {
     }
 
     /// Queue an update to be sent
+    ///
+    /// Once the queue holds the configured maximum, an update is dropped
+    /// according to the overflow policy and counted in
+    /// [`Self::dropped_updates`].
     pub fn queue_update(&self, update: StateUpdate) {
-        self.pending_updates.write().push(update);
+        let mut pending = self.pending_updates.write();
+        if pending.len() >= self.max_pending_updates {
+            self.dropped_updates.fetch_add(1, Ordering::Relaxed);
+            match self.overflow_policy {
+                OverflowPolicy::DropOldest => {
+                    pending.pop_front();
+                }
+                OverflowPolicy::DropNewest => {
+                    debug!("Pending update queue full, dropping new update");
+                    return;
+                }
+            }
+            debug!("Pending update queue full, dropped oldest update");
+        }
+        if self.max_pending_updates > 0 {
+            pending.push_back(update);
+        }
     }
 
     /// Get and clear pending updates
     pub fn drain_pending_updates(&self) -> Vec<StateUpdate> {
         let mut pending = self.pending_updates.write();
-        std::mem::take(&mut *pending)
+        std::mem::take(&mut *pending).into()
     }
 
-    /// Serialize an update for network transmission
+    /// Number of updates dropped because the pending queue was full
+    pub fn dropped_updates(&self) -> u64 {
+        self.dropped_updates.load(Ordering::Relaxed)
+    }
+
+    /// Serialize an update as plain JSON for network transmission
     pub fn serialize_update(update: &StateUpdate) -> Result<Vec<u8>> {
-        serde_json::to_vec(update)
-            .map_err(|e| StateError::Serialization(e.to_string()))
+        SyncCodec::Json.encode(update)
     }
 
-    /// Deserialize an update from network data
+    /// Encode an update with this node's configured codec
+    pub fn encode_update(&self, update: &StateUpdate) -> Result<Vec<u8>> {
+        self.codec.encode(update)
+    }
+
+    /// Deserialize an update from network data, whichever codec produced it
     pub fn deserialize_update(data: &[u8]) -> Result<StateUpdate> {
-        serde_json::from_slice(data)
-            .map_err(|e| StateError::Deserialization(e.to_string()))
+        match data.split_first() {
+            Some((&FORMAT_JSON_ZSTD, compressed)) => {
+                let json = zstd::bulk::decompress(compressed, MAX_DECOMPRESSED_UPDATE)
+                    .map_err(|e| StateError::Deserialization(format!("zstd: {}", e)))?;
+                serde_json::from_slice(&json)
+                    .map_err(|e| StateError::Deserialization(e.to_string()))
+            }
+            Some((&FORMAT_POSTCARD, encoded)) => postcard::from_bytes(encoded)
+                .map_err(|e| StateError::Deserialization(format!("postcard: {}", e))),
+            _ => serde_json::from_slice(data)
+                .map_err(|e| StateError::Deserialization(e.to_string())),
+        }
     }
 
     /// Get the current vector clock
@@ -448,9 +1730,65 @@ impl StateSync {
     }
 }
 
+/// Short name of an update's kind, for logs
+fn update_kind(update: &StateUpdate) -> &'static str {
+    match update {
+        StateUpdate::PeerUpdate { .. } => "peer",
+        StateUpdate::ReputationUpdate { .. } => "reputation",
+        StateUpdate::CreditUpdate { .. } => "credit",
+        StateUpdate::KeyValueUpdate { .. } => "key-value",
+    }
+}
+
+/// Record key and stamp of a last-write-wins update
+fn lww_entry(update: &StateUpdate) -> Option<(String, LwwStamp)> {
+    match update {
+        StateUpdate::PeerUpdate { peer_id, timestamp, origin, .. } => Some((
+            format!("peer:{}", peer_id),
+            LwwStamp { timestamp: *timestamp, origin: origin.clone() },
+        )),
+        StateUpdate::CreditUpdate { creditor, debtor, timestamp, origin, .. } => Some((
+            format!("credit:{}:{}", creditor, debtor),
+            LwwStamp { timestamp: *timestamp, origin: origin.clone() },
+        )),
+        StateUpdate::ReputationUpdate { .. } | StateUpdate::KeyValueUpdate { .. } => None,
+    }
+}
+
+/// Scale a counter by a factor, rounding to the nearest integer
+fn scale_counter(value: u64, factor: f64) -> u64 {
+    (value as f64 * factor.clamp(0.0, 1.0)).round() as u64
+}
+
+/// Decay interaction counters toward the neutral 50/50 split
+///
+/// With `w = 2^(-age / half_life)` and `n = (s + f) / 2`, each counter moves
+/// toward `n` by `c' = n + (c - n) * w`. The total is preserved, so the score
+/// `s / (s + f)` moves from its current value toward 0.5 by the same weight:
+/// after one half-life it is halfway there, after two it keeps a quarter of
+/// its distance. Future-dated or zero ages leave the counters untouched.
+fn decay_counters(
+    successful: u64,
+    failed: u64,
+    age: chrono::Duration,
+    half_life: chrono::Duration,
+) -> (u64, u64) {
+    let half_life_ms = half_life.num_milliseconds();
+    let age_ms = age.num_milliseconds();
+    if half_life_ms <= 0 || age_ms <= 0 {
+        return (successful, failed);
+    }
+
+    let weight = 0.5f64.powf(age_ms as f64 / half_life_ms as f64);
+    let neutral = (successful + failed) as f64 / 2.0;
+    let decay = |count: u64| (neutral + (count as f64 - neutral) * weight).round() as u64;
+    (decay(successful), decay(failed))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::credit_tally::CreditTally;
 
     #[test]
     fn test_vector_clock() {
@@ -470,6 +1808,187 @@ mod tests {
         assert_eq!(clock1.get("peer2"), 1);
     }
 
+    #[test]
+    fn test_vector_clock_compare() {
+        let mut a = VectorClock::new();
+        a.increment("peer1");
+        let mut b = a.clone();
+
+        // Identical clocks
+        assert_eq!(a.compare(&b), ClockOrdering::Equal);
+        assert!(!a.is_concurrent(&b));
+        assert!(!a.happens_before(&b));
+
+        // Strict dominance in both directions
+        b.increment("peer1");
+        assert_eq!(a.compare(&b), ClockOrdering::Before);
+        assert_eq!(b.compare(&a), ClockOrdering::After);
+        assert!(a.happens_before(&b));
+        assert!(!b.happens_before(&a));
+
+        // A key only the other clock has still dominates
+        let mut c = a.clone();
+        c.increment("peer2");
+        assert_eq!(a.compare(&c), ClockOrdering::Before);
+        assert_eq!(c.compare(&a), ClockOrdering::After);
+
+        // Divergent: each side has an event the other lacks
+        assert_eq!(b.compare(&c), ClockOrdering::Concurrent);
+        assert_eq!(c.compare(&b), ClockOrdering::Concurrent);
+        assert!(b.is_concurrent(&c));
+
+        // An explicit zero entry is the same as a missing key
+        let mut zero = VectorClock::new();
+        zero.clocks.insert("peer3".to_string(), 0);
+        assert_eq!(zero.compare(&VectorClock::new()), ClockOrdering::Equal);
+    }
+
+    #[test]
+    fn test_vector_clock_serialization_is_deterministic() {
+        let peers: Vec<String> = (0..32).map(|i| format!("peer{}", i)).collect();
+
+        let mut forward = VectorClock::new();
+        for (i, peer) in peers.iter().enumerate() {
+            for _ in 0..=i {
+                forward.increment(peer);
+            }
+        }
+        let mut backward = VectorClock::new();
+        for (i, peer) in peers.iter().enumerate().rev() {
+            for _ in 0..=i {
+                backward.increment(peer);
+            }
+        }
+        let mut merged = VectorClock::new();
+        merged.merge(&backward);
+
+        let bytes = serde_json::to_vec(&forward).unwrap();
+        assert_eq!(serde_json::to_vec(&backward).unwrap(), bytes);
+        assert_eq!(serde_json::to_vec(&merged).unwrap(), bytes);
+
+        // Still the same after a round trip
+        let decoded: VectorClock = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(serde_json::to_vec(&decoded).unwrap(), bytes);
+    }
+
+    fn large_peer_update() -> StateUpdate {
+        StateUpdate::PeerUpdate {
+            peer_id: "test_peer".to_string(),
+            info: PeerInfoUpdate {
+                public_key: "3mJr7AoUXx2Wqd5s8N4Df".to_string(),
+                addresses: (0..200).map(|i| format!("/ip4/10.0.{}.{}/tcp/4001", i / 256, i % 256)).collect(),
+                name: Some("Test".to_string()),
+            },
+            timestamp: Utc::now(),
+            origin: "test_peer".to_string(),
+            signature: None,
+        }
+    }
+
+    fn addresses(update: &StateUpdate) -> &[String] {
+        match update {
+            StateUpdate::PeerUpdate { info, .. } => &info.addresses,
+            _ => panic!("Wrong update type"),
+        }
+    }
+
+    #[test]
+    fn test_codec_round_trip() {
+        let update = large_peer_update();
+        for codec in [SyncCodec::Json, SyncCodec::JsonZstd, SyncCodec::Postcard] {
+            let sync = StateSync::new("local".to_string(), Arc::new(StateCache::new())).with_codec(codec);
+            let encoded = sync.encode_update(&update).unwrap();
+            let decoded = StateSync::deserialize_update(&encoded).unwrap();
+            assert_eq!(addresses(&decoded), addresses(&update), "{:?}", codec);
+        }
+
+        let json = SyncCodec::Json.encode(&update).unwrap();
+        let zstd = SyncCodec::JsonZstd.encode(&update).unwrap();
+        assert_eq!(zstd[0], FORMAT_JSON_ZSTD);
+        assert!(zstd.len() < json.len() / 2, "{} vs {} bytes", zstd.len(), json.len());
+    }
+
+    #[test]
+    fn test_cross_codec_decode() {
+        let update = large_peer_update();
+
+        // Plain JSON from a node predating codecs is identical to the Json codec
+        let legacy = serde_json::to_vec(&update).unwrap();
+        assert_eq!(SyncCodec::Json.encode(&update).unwrap(), legacy);
+        assert_eq!(addresses(&StateSync::deserialize_update(&legacy).unwrap()), addresses(&update));
+
+        // A Json-configured node reads compressed updates and vice versa
+        let zstd = StateSync::new("a".to_string(), Arc::new(StateCache::new()))
+            .with_codec(SyncCodec::JsonZstd)
+            .encode_update(&update)
+            .unwrap();
+        let json = StateSync::new("b".to_string(), Arc::new(StateCache::new()))
+            .encode_update(&update)
+            .unwrap();
+        assert_eq!(
+            addresses(&StateSync::deserialize_update(&zstd).unwrap()),
+            addresses(&StateSync::deserialize_update(&json).unwrap()),
+        );
+
+        // Corrupt compressed payloads are rejected, not misread as JSON
+        let err = StateSync::deserialize_update(&[FORMAT_JSON_ZSTD, 0xde, 0xad]).unwrap_err();
+        assert!(matches!(err, StateError::Deserialization(_)));
+    }
+
+    #[test]
+    fn test_postcard_codec() {
+        // A representative signed peer update
+        let owner = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&owner.public_key());
+        let mut update = StateUpdate::PeerUpdate {
+            peer_id: peer_id.to_string(),
+            info: PeerInfoUpdate {
+                public_key: peer_id.to_string(),
+                addresses: vec![
+                    "/ip4/192.168.1.20/tcp/9000".to_string(),
+                    "/ip4/192.168.1.20/udp/9000/quic-v1".to_string(),
+                ],
+                name: Some("Alice".to_string()),
+            },
+            timestamp: Utc::now(),
+            origin: peer_id.to_string(),
+            signature: None,
+        };
+        update.sign(&owner).unwrap();
+
+        let json = SyncCodec::Json.encode(&update).unwrap();
+        let binary = SyncCodec::Postcard.encode(&update).unwrap();
+        assert_eq!(binary[0], FORMAT_POSTCARD);
+        assert!(binary.len() * 10 < json.len() * 6, "{} vs {} bytes", binary.len(), json.len());
+
+        // Timestamps and signature bytes survive exactly, so it still verifies
+        let decoded = StateSync::deserialize_update(&binary).unwrap();
+        decoded.verify_signature().unwrap();
+        assert_eq!(serde_json::to_vec(&decoded).unwrap(), json);
+
+        // Decoding goes by the format byte, whatever this node sends
+        let kv = StateUpdate::KeyValueUpdate {
+            key: "app:key".to_string(),
+            value: vec![0, 1, 2, 255],
+            version: 3,
+            timestamp: Utc::now(),
+        };
+        for codec in [SyncCodec::Json, SyncCodec::JsonZstd, SyncCodec::Postcard] {
+            let sync = StateSync::new("a".to_string(), Arc::new(StateCache::new())).with_codec(codec);
+            let encoded = sync.encode_update(&kv).unwrap();
+            match StateSync::deserialize_update(&encoded).unwrap() {
+                StateUpdate::KeyValueUpdate { value, version, .. } => {
+                    assert_eq!(value, vec![0, 1, 2, 255], "{:?}", codec);
+                    assert_eq!(version, 3);
+                }
+                _ => panic!("Wrong update type"),
+            }
+        }
+
+        let err = StateSync::deserialize_update(&[FORMAT_POSTCARD, 0x09]).unwrap_err();
+        assert!(matches!(err, StateError::Deserialization(_)));
+    }
+
     #[test]
     fn test_state_update_serialization() {
         let update = StateUpdate::PeerUpdate {
@@ -480,6 +1999,8 @@ mod tests {
                 name: Some("Test".to_string()),
             },
             timestamp: Utc::now(),
+            origin: "test_peer".to_string(),
+            signature: None,
         };
 
         let serialized = StateSync::serialize_update(&update).unwrap();
@@ -517,7 +2038,933 @@ mod tests {
             _ => panic!("Wrong update type"),
         }
 
-        // Check vector clock was incremented
-        assert_eq!(sync.get_clock().get("local_peer"), 1);
+        // Creating an update doesn't tick the clock, applying it does
+        assert_eq!(sync.get_clock().get("local_peer"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_validate_update() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()));
+
+        let owner = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&owner.public_key());
+        let peer_info = PeerInfo {
+            id: peer_id.clone(),
+            public_key: peer_id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+
+        // Accepted updates leave the store untouched until applied
+        let kv = sync.create_kv_update("app:key", b"v1".to_vec(), 1);
+        assert_eq!(sync.validate_update(&kv, &store).await.unwrap(), UpdateEffect::Apply);
+        assert!(store.get_sync_value("app:key").await.unwrap().is_none());
+
+        // Unsigned peer update
+        let unsigned = sync.create_peer_update(&peer_info);
+        assert!(matches!(
+            sync.validate_update(&unsigned, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::InvalidSignature(_))
+        ));
+
+        // Reputation for a peer we've never seen
+        let reputation = Reputation {
+            successful_interactions: 3,
+            ..Default::default()
+        };
+        let rep_update = sync.create_reputation_update(peer_id.as_str(), &reputation);
+        assert_eq!(
+            sync.validate_update(&rep_update, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::UnknownPeer)
+        );
+
+        // Stale: the peer update was already applied
+        let signed = sync.create_signed_peer_update(&peer_info, &owner).unwrap();
+        assert_eq!(sync.validate_update(&signed, &store).await.unwrap(), UpdateEffect::Apply);
+        assert!(sync.apply_update(&signed, &store).await.unwrap());
+        assert_eq!(
+            sync.validate_update(&signed, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::Stale)
+        );
+
+        // Counters no higher than what's stored
+        assert_eq!(sync.validate_update(&rep_update, &store).await.unwrap(), UpdateEffect::Apply);
+        assert!(sync.apply_update(&rep_update, &store).await.unwrap());
+        assert_eq!(
+            sync.validate_update(&rep_update, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::NoChange)
+        );
+
+        // Node-internal keys are off limits
+        let reserved = sync.create_kv_update(sync_keys::SUBSCRIPTIONS, b"[]".to_vec(), 1);
+        assert_eq!(
+            sync.validate_update(&reserved, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::ReservedKey)
+        );
+
+        // Key-value versions must move forward
+        assert!(sync.apply_update(&kv, &store).await.unwrap());
+        assert_eq!(
+            sync.validate_update(&kv, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::Stale)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_future_timestamps_rejected() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()))
+            .with_max_future_skew(chrono::Duration::minutes(1));
+
+        let owner = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&owner.public_key());
+        let peer_info = PeerInfo {
+            id: peer_id.clone(),
+            public_key: peer_id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        let dated = |ahead: chrono::Duration| {
+            let mut update = sync.create_peer_update(&peer_info);
+            if let StateUpdate::PeerUpdate { timestamp, .. } = &mut update {
+                *timestamp = Utc::now() + ahead;
+            }
+            update.sign(&owner).unwrap();
+            update
+        };
+
+        let pinned = dated(chrono::Duration::days(1));
+        assert!(matches!(
+            sync.validate_update(&pinned, &store).await.unwrap(),
+            UpdateEffect::Skip(SkipReason::ClockSkew(_))
+        ));
+        assert!(matches!(
+            sync.apply_update(&pinned, &store).await,
+            Err(StateError::ClockSkew { max_secs: 60, .. })
+        ));
+        assert!(store.get_peer(peer_id.as_str()).await.unwrap().is_none());
+
+        // Small drift is tolerated, and the past is always fine
+        assert!(sync.apply_update(&dated(chrono::Duration::seconds(1)), &store).await.unwrap());
+        let mut old = sync.create_credit_update(&CreditRelationship::new(
+            PeerId("a".to_string()),
+            PeerId("b".to_string()),
+            10.0,
+        ));
+        if let StateUpdate::CreditUpdate { timestamp, .. } = &mut old {
+            *timestamp = Utc::now() - chrono::Duration::days(365);
+        }
+        assert_eq!(sync.validate_update(&old, &store).await.unwrap(), UpdateEffect::Apply);
+    }
+
+    #[tokio::test]
+    async fn test_peer_update_signature() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()));
+
+        let owner = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&owner.public_key());
+        let peer_info = PeerInfo {
+            id: peer_id.clone(),
+            public_key: peer_id.to_string(),
+            addresses: vec!["/ip4/127.0.0.1/tcp/4001".to_string()],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: Some("Owner".to_string()),
+        };
+
+        // Forged: claims the owner's ID but signed by someone else
+        let forger = Keypair::generate();
+        let forged = sync.create_signed_peer_update(&peer_info, &forger).unwrap();
+        assert!(matches!(
+            sync.apply_update(&forged, &store).await,
+            Err(StateError::InvalidSignature(_))
+        ));
+        assert!(store.get_peer(peer_id.as_str()).await.unwrap().is_none());
+
+        // Unsigned updates are rejected too
+        let unsigned = sync.create_peer_update(&peer_info);
+        assert!(matches!(
+            sync.apply_update(&unsigned, &store).await,
+            Err(StateError::InvalidSignature(_))
+        ));
+
+        // The origin breaks last-write-wins ties, so it is signed as well
+        let mut relabelled = sync.create_signed_peer_update(&peer_info, &owner).unwrap();
+        if let StateUpdate::PeerUpdate { origin, .. } = &mut relabelled {
+            *origin = "zzz_forger".to_string();
+        }
+        assert!(matches!(
+            sync.apply_update(&relabelled, &store).await,
+            Err(StateError::InvalidSignature(_))
+        ));
+
+        // Signed by the owner: applied
+        let genuine = sync.create_signed_peer_update(&peer_info, &owner).unwrap();
+        assert!(sync.apply_update(&genuine, &store).await.unwrap());
+        let (stored, _) = store.get_peer(peer_id.as_str()).await.unwrap().unwrap();
+        assert_eq!(stored.name, Some("Owner".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_reputation_compaction() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let cache = Arc::new(StateCache::new());
+        let sync = StateSync::new("local_peer".to_string(), cache);
+
+        let peer_info = PeerInfo {
+            id: PeerId("busy_peer".to_string()),
+            public_key: "3mJr7AoUXx2Wqd5s8N4Df".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        let mut reputation = Reputation::new(0.8);
+        reputation.successful_interactions = 800;
+        reputation.failed_interactions = 200;
+        store.upsert_peer(&peer_info, Some(&reputation)).await.unwrap();
+
+        assert!(!sync.compaction_due(Utc::now()));
+        assert_eq!(sync.compact_reputations(&store).await.unwrap(), 1);
+        assert_eq!(sync.epoch(), 1);
+
+        let (_, compacted) = store.get_peer("busy_peer").await.unwrap().unwrap();
+        assert_eq!(compacted.successful_interactions, 400);
+        assert_eq!(compacted.failed_interactions, 100);
+        assert!((compacted.score - 0.8).abs() < 1e-9);
+
+        // Updates from the previous epoch are scaled before the max-merge
+        let stale = StateUpdate::ReputationUpdate {
+            peer_id: "busy_peer".to_string(),
+            successful_interactions: 810,
+            failed_interactions: 200,
+            timestamp: Utc::now(),
+            epoch: 0,
+            origin: "remote_peer".to_string(),
+        };
+        assert!(sync.apply_update(&stale, &store).await.unwrap());
+        let (_, merged) = store.get_peer("busy_peer").await.unwrap().unwrap();
+        assert_eq!(merged.successful_interactions, 405);
+        assert_eq!(merged.failed_interactions, 100);
+
+        // The epoch survives a restart
+        let restarted = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()));
+        restarted.restore_compaction(&store).await.unwrap();
+        assert_eq!(restarted.epoch(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_newer_epoch_adopted() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let compaction = CompactionConfig { interval: chrono::Duration::zero(), factor: 0.5 };
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new())).with_compaction(compaction);
+
+        let peer_info = PeerInfo {
+            id: PeerId("busy_peer".to_string()),
+            public_key: "3mJr7AoUXx2Wqd5s8N4Df".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        let mut reputation = Reputation::new(0.8);
+        reputation.successful_interactions = 800;
+        reputation.failed_interactions = 200;
+        store.upsert_peer(&peer_info, Some(&reputation)).await.unwrap();
+
+        // A peer a few epochs ahead moves us one epoch, compacting our counters
+        // so its smaller ones can win the merge
+        let update = |successful, epoch| StateUpdate::ReputationUpdate {
+            peer_id: "busy_peer".to_string(),
+            successful_interactions: successful,
+            failed_interactions: 100,
+            timestamp: Utc::now(),
+            epoch,
+            origin: "remote_peer".to_string(),
+        };
+        assert!(sync.apply_update(&update(420, 5), &store).await.unwrap());
+        assert_eq!(sync.epoch(), 1);
+        let (_, merged) = store.get_peer("busy_peer").await.unwrap().unwrap();
+        assert_eq!((merged.successful_interactions, merged.failed_interactions), (420, 100));
+        assert_eq!(store.load_compaction_epoch().await.unwrap().map(|(epoch, _)| epoch), Some(1));
+
+        // Within half an interval of the last compaction nothing is adopted
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()));
+        sync.restore_compaction(&store).await.unwrap();
+        sync.apply_update(&update(430, 5), &store).await.unwrap();
+        assert_eq!(sync.epoch(), 1);
+    }
+
+    #[test]
+    fn test_decay_counters() {
+        let half_life = chrono::Duration::hours(1);
+
+        // Aged by two half-lives, 900/100 (0.9) keeps a quarter of its lead
+        let (successful, failed) = decay_counters(900, 100, half_life * 2, half_life);
+        assert_eq!((successful, failed), (600, 400));
+        let score = successful as f64 / (successful + failed) as f64;
+        assert!((score - 0.6).abs() < 1e-9);
+
+        // Bad reputations recover toward neutral as well
+        let (successful, failed) = decay_counters(100, 900, half_life * 2, half_life);
+        assert_eq!((successful, failed), (400, 600));
+
+        // No age, no decay
+        assert_eq!(decay_counters(900, 100, chrono::Duration::zero(), half_life), (900, 100));
+    }
+
+    #[tokio::test]
+    async fn test_reputation_decay_on_merge() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let half_life = chrono::Duration::hours(1);
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()))
+            .with_decay_half_life(half_life);
+
+        let peer_info = PeerInfo {
+            id: PeerId("quiet_peer".to_string()),
+            public_key: "3mJr7AoUXx2Wqd5s8N4Df".to_string(), // base58 encoded
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&peer_info, None).await.unwrap();
+
+        // A report last refreshed two half-lives ago
+        let update = StateUpdate::ReputationUpdate {
+            peer_id: "quiet_peer".to_string(),
+            successful_interactions: 900,
+            failed_interactions: 100,
+            timestamp: Utc::now() - half_life * 2,
+            epoch: 0,
+            origin: "remote_peer".to_string(),
+        };
+        assert!(sync.apply_update(&update, &store).await.unwrap());
+
+        let (_, merged) = store.get_peer("quiet_peer").await.unwrap().unwrap();
+        assert!(merged.score < 0.9);
+        assert!((merged.score - 0.6).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pending_queue_overflow() {
+        let update = |peer: &str| StateUpdate::ReputationUpdate {
+            peer_id: peer.to_string(),
+            successful_interactions: 1,
+            failed_interactions: 0,
+            timestamp: Utc::now(),
+            epoch: 0,
+            origin: "remote_peer".to_string(),
+        };
+        let queued = |updates: Vec<StateUpdate>| -> Vec<String> {
+            updates
+                .into_iter()
+                .map(|u| match u {
+                    StateUpdate::ReputationUpdate { peer_id, .. } => peer_id,
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        for (policy, kept) in [
+            (OverflowPolicy::DropOldest, ["b", "c"]),
+            (OverflowPolicy::DropNewest, ["a", "b"]),
+        ] {
+            let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()))
+                .with_pending_limit(2, policy);
+            for peer in ["a", "b", "c"] {
+                sync.queue_update(update(peer));
+            }
+            assert_eq!(sync.dropped_updates(), 1, "{:?}", policy);
+            assert_eq!(queued(sync.drain_pending_updates()), kept, "{:?}", policy);
+
+            // Draining frees the queue again
+            sync.queue_update(update("d"));
+            assert_eq!(queued(sync.drain_pending_updates()), ["d"]);
+            assert_eq!(sync.dropped_updates(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reputation_updates_rate_limited() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()))
+            .with_reputation_rate_limit(chrono::Duration::milliseconds(200));
+        let peer_info = PeerInfo {
+            id: PeerId("subject".to_string()),
+            public_key: "subject".to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: None,
+        };
+        store.upsert_peer(&peer_info, None).await.unwrap();
+        let report = |origin: &str, successful: u64| StateUpdate::ReputationUpdate {
+            peer_id: "subject".to_string(),
+            successful_interactions: successful,
+            failed_interactions: 0,
+            timestamp: Utc::now(),
+            epoch: 0,
+            origin: origin.to_string(),
+        };
+
+        // A flood from one peer: only the first lands
+        assert!(sync.apply_update_from(&report("spammer", 10), "spammer", &store).await.unwrap());
+        for successful in 11..20 {
+            let update = report("spammer", successful);
+            assert_eq!(
+                sync.validate_update_from(&update, "spammer", &store).await.unwrap(),
+                UpdateEffect::Skip(SkipReason::RateLimited)
+            );
+            assert!(!sync.apply_update_from(&update, "spammer", &store).await.unwrap());
+        }
+        let (_, reputation) = store.get_peer("subject").await.unwrap().unwrap();
+        assert_eq!(reputation.successful_interactions, 10);
+
+        // Claiming a fresh origin for every update doesn't get around it
+        for origin in ["a", "b", "c"] {
+            assert!(!sync.apply_update_from(&report(origin, 15), "spammer", &store).await.unwrap());
+        }
+
+        // Other peers, and this node's own updates, aren't held back
+        assert!(sync.apply_update_from(&report("honest", 20), "honest", &store).await.unwrap());
+        assert!(sync.apply_update(&report("local_peer", 21), &store).await.unwrap());
+        assert!(sync.apply_update(&report("local_peer", 22), &store).await.unwrap());
+
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        assert!(sync.apply_update_from(&report("spammer", 30), "spammer", &store).await.unwrap());
+        let (_, reputation) = store.get_peer("subject").await.unwrap().unwrap();
+        assert_eq!(reputation.successful_interactions, 30);
+    }
+
+    #[tokio::test]
+    async fn test_reputation_policy_on_merge() {
+        let policies = [
+            ReputationPolicy::default(),
+            ReputationPolicy {
+                failure_weight: 4.0,
+                ..Default::default()
+            },
+        ];
+
+        let mut scores = Vec::new();
+        for policy in policies {
+            let store = SqliteStore::new(":memory:").await.unwrap();
+            let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()))
+                .with_reputation_policy(policy);
+            let peer_info = PeerInfo {
+                id: PeerId("flaky_peer".to_string()),
+                public_key: "flaky_peer".to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&peer_info, None).await.unwrap();
+
+            let update = StateUpdate::ReputationUpdate {
+                peer_id: "flaky_peer".to_string(),
+                successful_interactions: 8,
+                failed_interactions: 2,
+                timestamp: Utc::now(),
+                epoch: 0,
+                origin: "remote_peer".to_string(),
+            };
+            assert!(sync.apply_update(&update, &store).await.unwrap());
+            scores.push(store.get_peer("flaky_peer").await.unwrap().unwrap().1.score);
+        }
+
+        // 8 / (8 + 2), then 8 / (8 + 2 * 4)
+        assert_eq!(scores, vec![0.8, 0.5]);
+    }
+
+    #[tokio::test]
+    async fn test_anti_entropy_reconciles() {
+        let store_a = SqliteStore::new(":memory:").await.unwrap();
+        let store_b = SqliteStore::new(":memory:").await.unwrap();
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+
+        // Node A learns about a peer and a key while B isn't listening
+        let owner = Keypair::generate();
+        let peer_id = PeerId::from_public_key(&owner.public_key());
+        let peer_info = PeerInfo {
+            id: peer_id.clone(),
+            public_key: peer_id.to_string(),
+            addresses: vec!["/ip4/127.0.0.1/tcp/4001".to_string()],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: Some("Owner".to_string()),
+        };
+        let peer_update = node_a.create_signed_peer_update(&peer_info, &owner).unwrap();
+        assert!(node_a.apply_update(&peer_update, &store_a).await.unwrap());
+        let kv_a = node_a.create_kv_update("app:a", b"from a".to_vec(), 1);
+        assert!(node_a.apply_update(&kv_a, &store_a).await.unwrap());
+
+        // Node B has a key of its own
+        let kv_b = node_b.create_kv_update("app:b", b"from b".to_vec(), 1);
+        assert!(node_b.apply_update(&kv_b, &store_b).await.unwrap());
+
+        let diff = store_a.state_digest().await.unwrap().diff(&store_b.state_digest().await.unwrap());
+        assert_eq!(diff.total(), 3);
+
+        // A asks B, then B asks A
+        let response = node_b.handle_sync_request(&node_a.get_clock());
+        assert_eq!(response.updates.len(), 1);
+        assert_eq!(node_a.apply_sync_response(&response, "node_b", &store_a).await.unwrap(), 1);
+
+        let response = node_a.handle_sync_request(&node_b.get_clock());
+        assert_eq!(node_b.apply_sync_response(&response, "node_a", &store_b).await.unwrap(), 2);
+
+        let diff = store_a.state_digest().await.unwrap().diff(&store_b.state_digest().await.unwrap());
+        assert!(diff.is_empty());
+
+        // A's log is fully seen by B; anything B still offers is a no-op for A
+        assert!(node_a.updates_since(&node_b.get_clock()).is_empty());
+        let response = node_b.handle_sync_request(&node_a.get_clock());
+        assert_eq!(node_a.apply_sync_response(&response, "node_b", &store_a).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_log_bounded() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let node = StateSync::new("node_a".to_string(), Arc::new(StateCache::new())).with_update_log_limit(3);
+
+        // Each local update moves the clock once
+        for version in 1..=5 {
+            let update = node.create_kv_update("app:key", vec![version as u8], version);
+            assert_eq!(node.get_clock().get("node_a"), version - 1);
+            assert!(node.apply_update(&update, &store).await.unwrap());
+            assert_eq!(node.get_clock().get("node_a"), version);
+        }
+        assert_eq!(node.update_log_len(), 3);
+
+        // A peer that saw the first two gets the rest in full
+        let mut seen_two = VectorClock::new();
+        seen_two.increment("node_a");
+        seen_two.increment("node_a");
+        let response = node.handle_sync_request(&seen_two);
+        assert!(!response.truncated);
+        assert_eq!(response.updates.len(), 3);
+
+        // One that saw less can't catch up from the log, and doesn't pretend to
+        let fresh = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+        let response = node.handle_sync_request(&fresh.get_clock());
+        assert!(response.truncated);
+        let fresh_store = SqliteStore::new(":memory:").await.unwrap();
+        fresh.apply_sync_response(&response, "node_a", &fresh_store).await.unwrap();
+        assert_eq!(fresh.get_clock().get("node_a"), 0);
+        assert!(!node.handle_sync_request(&node.get_clock()).truncated);
+    }
+
+    #[tokio::test]
+    async fn test_lww_tie_breaks_by_origin() {
+        let timestamp = Utc::now();
+        let credit_update = |origin: &str, balance: f64| StateUpdate::CreditUpdate {
+            creditor: "alice".to_string(),
+            debtor: "bob".to_string(),
+            credit_limit: 100.0,
+            balance,
+            active: true,
+            timestamp,
+            origin: origin.to_string(),
+            tallies: None,
+        };
+        let from_a = credit_update("node_a", 10.0);
+        let from_b = credit_update("node_b", 20.0);
+
+        let mut balances = Vec::new();
+        for order in [[&from_a, &from_b], [&from_b, &from_a]] {
+            let store = SqliteStore::new(":memory:").await.unwrap();
+            for id in ["alice", "bob"] {
+                let peer = PeerInfo {
+                    id: PeerId(id.to_string()),
+                    public_key: id.to_string(),
+                    addresses: vec![],
+                    first_seen: timestamp,
+                    last_seen: timestamp,
+                    name: None,
+                };
+                store.upsert_peer(&peer, None).await.unwrap();
+            }
+
+            let sync = StateSync::new("local_peer".to_string(), Arc::new(StateCache::new()));
+            for update in order {
+                sync.apply_update(update, &store).await.unwrap();
+            }
+
+            let relationship = store
+                .get_credit_relationship_between("alice", "bob")
+                .await
+                .unwrap()
+                .unwrap();
+            balances.push(relationship.balance);
+        }
+
+        // The greater origin wins regardless of arrival order
+        assert_eq!(balances, vec![20.0, 20.0]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_logged() {
+        let start = Utc::now();
+        let credit_update = |origin: &str, balance: f64, secs: i64| StateUpdate::CreditUpdate {
+            creditor: "alice".to_string(),
+            debtor: "bob".to_string(),
+            credit_limit: 100.0,
+            balance,
+            active: true,
+            timestamp: start + chrono::Duration::seconds(secs),
+            origin: origin.to_string(),
+            tallies: None,
+        };
+        let mut stores = Vec::new();
+        for _ in 0..2 {
+            let store = SqliteStore::new(":memory:").await.unwrap();
+            for id in ["alice", "bob"] {
+                let peer = PeerInfo {
+                    id: PeerId(id.to_string()),
+                    public_key: id.to_string(),
+                    addresses: vec![],
+                    first_seen: start,
+                    last_seen: start,
+                    name: None,
+                };
+                store.upsert_peer(&peer, None).await.unwrap();
+            }
+            stores.push(store);
+        }
+        let (store_a, store_b) = (&stores[0], &stores[1]);
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+
+        // Both nodes write the relationship without hearing of the other
+        assert!(node_a.apply_update(&credit_update("node_a", 10.0, 0), store_a).await.unwrap());
+        assert!(node_b.apply_update(&credit_update("node_b", 20.0, 1), store_b).await.unwrap());
+
+        let response = node_b.handle_sync_request(&node_a.get_clock());
+        assert_eq!(node_a.apply_sync_response(&response, "node_b", store_a).await.unwrap(), 1);
+        let conflicts = node_a.recent_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "credit:alice:bob");
+        assert_eq!(conflicts[0].resolution, ConflictResolution::AppliedRemote);
+        assert!(conflicts[0].local_clock.is_concurrent(&conflicts[0].remote_clock));
+
+        // Once B has caught up, its next write follows A's and isn't a conflict
+        assert!(node_a.apply_update(&credit_update("node_a", 30.0, 2), store_a).await.unwrap());
+        let response = node_a.handle_sync_request(&node_b.get_clock());
+        node_b.apply_sync_response(&response, "node_a", store_b).await.unwrap();
+        assert!(node_b.recent_conflicts().is_empty());
+        assert!(node_b.apply_update(&credit_update("node_b", 40.0, 3), store_b).await.unwrap());
+
+        let response = node_b.handle_sync_request(&node_a.get_clock());
+        assert_eq!(node_a.apply_sync_response(&response, "node_b", store_a).await.unwrap(), 1);
+        assert_eq!(node_a.recent_conflicts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_transfers_converge() {
+        let mut stores = Vec::new();
+        for _ in 0..2 {
+            let store = SqliteStore::new(":memory:").await.unwrap();
+            for id in ["alice", "bob"] {
+                let peer = PeerInfo {
+                    id: PeerId(id.to_string()),
+                    public_key: id.to_string(),
+                    addresses: vec![],
+                    first_seen: Utc::now(),
+                    last_seen: Utc::now(),
+                    name: None,
+                };
+                store.upsert_peer(&peer, None).await.unwrap();
+            }
+            let mut relationship =
+                CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+            relationship.balance = 5.0;
+            store.upsert_credit_relationship(&relationship).await.unwrap();
+            stores.push(store);
+        }
+        let (store_a, store_b) = (&stores[0], &stores[1]);
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+
+        // Each node records a transfer without hearing of the other's, and
+        // opens the tallies with the balance it had
+        let transfer_a = node_a.record_credit_transfer("alice", "bob", 30.0, store_a).await.unwrap();
+        let transfer_b = node_b.record_credit_transfer("alice", "bob", 10.0, store_b).await.unwrap();
+
+        let response = node_b.handle_sync_request(&node_a.get_clock());
+        assert_eq!(node_a.apply_sync_response(&response, "node_b", store_a).await.unwrap(), 1);
+        let response = node_a.handle_sync_request(&node_b.get_clock());
+        assert_eq!(node_b.apply_sync_response(&response, "node_a", store_b).await.unwrap(), 1);
+
+        // Both transfers count on both nodes, on top of one opening balance
+        for store in [store_a, store_b] {
+            let relationship = store
+                .get_credit_relationship_between("alice", "bob")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(relationship.balance, 45.0);
+        }
+        assert_eq!(
+            store_a.credit_tallies("alice", "bob").await.unwrap(),
+            store_b.credit_tallies("alice", "bob").await.unwrap()
+        );
+        assert!(node_a.recent_conflicts().is_empty());
+
+        // Re-delivering a transfer doesn't count it twice
+        assert!(!node_b.apply_update(&transfer_a, store_b).await.unwrap());
+        assert!(!node_a.apply_update(&transfer_b, store_a).await.unwrap());
+        assert_eq!(tallied_balance(&store_b.credit_tallies("alice", "bob").await.unwrap()), 45.0);
+
+        // A node can't grow another node's tally for it
+        let mut inflated = transfer_b.clone();
+        if let StateUpdate::CreditUpdate { tallies: Some(tallies), timestamp, .. } = &mut inflated {
+            tallies.nodes.insert("node_c".to_string(), CreditTally { increments: 50.0, decrements: 0.0 });
+            *timestamp = Utc::now();
+        }
+        node_a.apply_update_from(&inflated, "node_b", store_a).await.unwrap();
+        let tallies = store_a.credit_tallies("alice", "bob").await.unwrap();
+        assert!(!tallies.nodes.contains_key("node_c"));
+        assert_eq!(tallied_balance(&tallies), 45.0);
+
+        // Transfers past the limit are refused, whoever records them
+        let err = node_b.record_credit_transfer("alice", "bob", 60.0, store_b).await.unwrap_err();
+        assert!(matches!(err, StateError::CreditLimitExceeded { .. }));
+        assert_eq!(tallied_balance(&store_b.credit_tallies("alice", "bob").await.unwrap()), 45.0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_carries_tallies() {
+        let mut stores = Vec::new();
+        for _ in 0..2 {
+            let store = SqliteStore::new(":memory:").await.unwrap();
+            for id in ["alice", "bob"] {
+                let peer = PeerInfo {
+                    id: PeerId(id.to_string()),
+                    public_key: id.to_string(),
+                    addresses: vec![],
+                    first_seen: Utc::now(),
+                    last_seen: Utc::now(),
+                    name: None,
+                };
+                store.upsert_peer(&peer, None).await.unwrap();
+            }
+            stores.push(store);
+        }
+        let (store_a, store_b) = (&stores[0], &stores[1]);
+        let mut relationship = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        relationship.balance = 5.0;
+        store_a.upsert_credit_relationship(&relationship).await.unwrap();
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        node_a.record_credit_transfer("alice", "bob", 30.0, store_a).await.unwrap();
+
+        let snapshot = node_a.export_snapshot(store_a).await.unwrap();
+        assert_eq!(snapshot.credit_tallies.len(), 1);
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+        node_b.import_snapshot(&snapshot, store_b).await.unwrap();
+        assert_eq!(
+            store_b.credit_tallies("alice", "bob").await.unwrap(),
+            store_a.credit_tallies("alice", "bob").await.unwrap()
+        );
+
+        // The bootstrapped node's own transfer adds to the tallies instead of
+        // opening them again with a balance that already counts node A's
+        let transfer = node_b.record_credit_transfer("alice", "bob", 10.0, store_b).await.unwrap();
+        assert!(node_a.apply_update(&transfer, store_a).await.unwrap());
+        for store in [store_a, store_b] {
+            let relationship = store.get_credit_relationship_between("alice", "bob").await.unwrap().unwrap();
+            assert_eq!(relationship.balance, 45.0);
+        }
+
+        // An opening balance dated ahead is refused
+        let mut future = snapshot.clone();
+        let (_, _, tallies) = &mut future.credit_tallies[0];
+        if let Some(opening) = &mut tallies.opening {
+            opening.amount = 500.0;
+            opening.timestamp = Utc::now() + chrono::Duration::hours(1);
+        }
+        assert_eq!(node_b.import_snapshot(&future, store_b).await.unwrap(), 0);
+        let relationship = store_b.get_credit_relationship_between("alice", "bob").await.unwrap().unwrap();
+        assert_eq!(relationship.balance, 45.0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_bootstrap() {
+        let store_a = SqliteStore::new(":memory:").await.unwrap();
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let (alice_key, bob_key) = (Keypair::generate(), Keypair::generate());
+        let peer = |key: &Keypair, name: &str| {
+            let id = PeerId::from_public_key(&key.public_key());
+            PeerInfo {
+                public_key: id.to_string(),
+                id,
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(name.to_string()),
+            }
+        };
+        let counters = |successful: u64, failed: u64| {
+            let mut reputation = Reputation::new(0.5);
+            reputation.successful_interactions = successful;
+            reputation.failed_interactions = failed;
+            reputation
+        };
+        let (alice, bob) = (peer(&alice_key, "Alice"), peer(&bob_key, "Bob"));
+        for (info, key, reputation) in [(&alice, &alice_key, counters(8, 2)), (&bob, &bob_key, counters(3, 1))] {
+            let update = node_a.create_signed_peer_update(info, key).unwrap();
+            assert!(node_a.apply_update_from(&update, info.id.as_str(), &store_a).await.unwrap());
+            store_a.update_peer_reputation(info.id.as_str(), &reputation).await.unwrap();
+        }
+        // A peer this node never got a signed update for keeps only its counters
+        let carol = peer(&Keypair::generate(), "Carol");
+        store_a.upsert_peer(&carol, Some(&counters(1, 0))).await.unwrap();
+        let mut credit = CreditRelationship::new(alice.id.clone(), bob.id.clone(), 100.0);
+        credit.balance = 25.0;
+        store_a.upsert_credit_relationship(&credit).await.unwrap();
+        let kv = node_a.create_kv_update("app:a", b"x".to_vec(), 1);
+        assert!(node_a.apply_update(&kv, &store_a).await.unwrap());
+
+        let snapshot = node_a.export_snapshot(&store_a).await.unwrap();
+        assert_eq!(snapshot.peer_updates.len(), 2);
+        assert_eq!(snapshot.reputations.len(), 3);
+        assert_eq!(snapshot.credit_relationships.len(), 1);
+
+        // Survives the wire
+        let snapshot: StateSnapshot = serde_json::from_slice(&serde_json::to_vec(&snapshot).unwrap()).unwrap();
+
+        // An empty node catches up in one exchange
+        let store_b = SqliteStore::new(":memory:").await.unwrap();
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+        assert_eq!(node_b.import_snapshot(&snapshot, &store_b).await.unwrap(), 5);
+        let (imported, reputation) = store_b.get_peer(alice.id.as_str()).await.unwrap().unwrap();
+        assert_eq!(imported.name, Some("Alice".to_string()));
+        assert_eq!((reputation.successful_interactions, reputation.failed_interactions), (8, 2));
+        assert!((reputation.score - 0.8).abs() < 1e-9);
+        assert!(store_b.get_peer(carol.id.as_str()).await.unwrap().is_none());
+        let imported = store_b.get_credit_relationship_between(alice.id.as_str(), bob.id.as_str()).await.unwrap().unwrap();
+        assert_eq!(imported.balance, 25.0);
+        assert_eq!(node_b.get_clock().get("node_a"), 3);
+
+        // ... and can pass the snapshot on itself
+        assert_eq!(node_b.export_snapshot(&store_b).await.unwrap().peer_updates.len(), 2);
+
+        // Importing again changes nothing
+        assert_eq!(node_b.import_snapshot(&snapshot, &store_b).await.unwrap(), 0);
+
+        // Newer local values survive
+        let store_c = SqliteStore::new(":memory:").await.unwrap();
+        let node_c = StateSync::new("node_c".to_string(), Arc::new(StateCache::new()));
+        let renamed = node_c.create_signed_peer_update(&peer(&alice_key, "Alice (renamed)"), &alice_key).unwrap();
+        assert!(node_c.apply_update_from(&renamed, alice.id.as_str(), &store_c).await.unwrap());
+        store_c.update_peer_reputation(alice.id.as_str(), &counters(20, 1)).await.unwrap();
+        store_c.upsert_peer(&bob, Some(&counters(1, 5))).await.unwrap();
+        let mut settled = credit.clone();
+        settled.balance = 0.0;
+        settled.last_transaction = Utc::now() + chrono::Duration::seconds(5);
+        store_c.upsert_credit_relationship(&settled).await.unwrap();
+
+        node_c.import_snapshot(&snapshot, &store_c).await.unwrap();
+        let (kept, reputation) = store_c.get_peer(alice.id.as_str()).await.unwrap().unwrap();
+        assert_eq!(kept.name, Some("Alice (renamed)".to_string()));
+        assert_eq!((reputation.successful_interactions, reputation.failed_interactions), (20, 2));
+        let (_, reputation) = store_c.get_peer(bob.id.as_str()).await.unwrap().unwrap();
+        assert_eq!((reputation.successful_interactions, reputation.failed_interactions), (3, 5));
+        let kept = store_c.get_credit_relationship_between(alice.id.as_str(), bob.id.as_str()).await.unwrap().unwrap();
+        assert_eq!(kept.balance, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_pages() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let node = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            let id = PeerId::from_public_key(&Keypair::generate().public_key());
+            ids.push(id.clone());
+            let info = PeerInfo {
+                public_key: id.to_string(),
+                id: id.clone(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: Some(format!("peer {}", i)),
+            };
+            store.upsert_peer(&info, Some(&Reputation::new(0.5))).await.unwrap();
+        }
+        for (creditor, debtor) in ids.iter().zip(ids.iter().cycle().skip(1)) {
+            let credit = CreditRelationship::new(creditor.clone(), debtor.clone(), 10.0);
+            store.upsert_credit_relationship(&credit).await.unwrap();
+        }
+        let snapshot = node.export_snapshot(&store).await.unwrap();
+
+        let pages = snapshot.clone().into_pages(2048).unwrap();
+        assert!(pages.len() > 1);
+        for page in &pages {
+            assert!(serde_json::to_vec(page).unwrap().len() <= 2048);
+            assert_eq!(page.origin, "node_a");
+        }
+        let joined = StateSnapshot::join(pages).unwrap();
+        assert_eq!(joined.reputations.len(), 20);
+        assert_eq!(joined.credit_relationships.len(), 20);
+        assert_eq!(
+            serde_json::to_value(&joined).unwrap(),
+            serde_json::to_value(&snapshot).unwrap()
+        );
+
+        // Everything fits in one page when there's room
+        assert_eq!(snapshot.clone().into_pages(1024 * 1024).unwrap().len(), 1);
+        // A record bigger than a page can't be sent at all
+        assert!(snapshot.into_pages(300).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_records_checked() {
+        let node_a = StateSync::new("node_a".to_string(), Arc::new(StateCache::new()));
+        let alice_key = Keypair::generate();
+        let alice_id = PeerId::from_public_key(&alice_key.public_key());
+        let alice = PeerInfo {
+            id: alice_id.clone(),
+            public_key: alice_id.to_string(),
+            addresses: vec![],
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            name: Some("Alice".to_string()),
+        };
+
+        // Peer info the exporter made up, or signed by someone else
+        let unsigned = node_a.create_peer_update(&alice);
+        let forged = node_a.create_signed_peer_update(&alice, &Keypair::generate()).unwrap();
+        let mut future = node_a.create_signed_peer_update(&alice, &alice_key).unwrap();
+        if let StateUpdate::PeerUpdate { timestamp, .. } = &mut future {
+            *timestamp = Utc::now() + chrono::Duration::hours(1);
+        }
+        future.sign(&alice_key).unwrap();
+        let mut credit = CreditRelationship::new(PeerId("alice".to_string()), PeerId("bob".to_string()), 100.0);
+        credit.last_transaction = Utc::now() + chrono::Duration::hours(1);
+        let snapshot = StateSnapshot {
+            origin: "node_a".to_string(),
+            clock: VectorClock::new(),
+            epoch: 0,
+            peer_updates: vec![unsigned, forged, future],
+            reputations: vec![(alice_id.to_string(), Reputation::new(0.5))],
+            credit_relationships: vec![credit],
+            credit_tallies: vec![],
+            taken_at: Utc::now(),
+        };
+
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let node_b = StateSync::new("node_b".to_string(), Arc::new(StateCache::new()));
+        assert_eq!(node_b.import_snapshot(&snapshot, &store).await.unwrap(), 0);
+        assert!(store.get_peer(alice_id.as_str()).await.unwrap().is_none());
+        assert!(store.get_credit_relationship_between("alice", "bob").await.unwrap().is_none());
     }
 }
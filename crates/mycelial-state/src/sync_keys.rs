@@ -0,0 +1,98 @@
+//! Key namespace for the `state_sync` key-value table
+//!
+//! Keys starting with [`RESERVED_PREFIX`] hold node-internal state and can
+//! only be written through the internal API on [`SqliteStore`]. Everything
+//! else is free for application data.
+//!
+//! [`SqliteStore`]: crate::SqliteStore
+
+use crate::error::{Result, StateError};
+
+/// Prefix reserved for node-internal keys
+pub const RESERVED_PREFIX: &str = "_sys:";
+
+/// Topics the node is subscribed to
+pub const SUBSCRIPTIONS: &str = "_sys:subscriptions";
+
+/// Externally observed address of the node
+pub const EXTERNAL_ADDRESS: &str = "_sys:external_address";
+
+/// Messages received over the node's lifetime
+pub const MESSAGE_COUNT: &str = "_sys:message_count";
+
+/// Reputation compaction epoch and when it started
+pub const COMPACTION: &str = "_sys:compaction";
+
+/// Prefix for per-peer last-seen timestamps
+pub const LAST_SEEN_PREFIX: &str = "_sys:last_seen:";
+
+/// Prefix for the latest signed update received for each peer
+pub const SIGNED_PEER_PREFIX: &str = "_sys:signed_peer:";
+
+/// Maximum key length in bytes
+pub const MAX_KEY_LEN: usize = 256;
+
+/// Check if a key lives in the reserved internal namespace
+pub fn is_reserved(key: &str) -> bool {
+    key.starts_with(RESERVED_PREFIX)
+}
+
+/// Validate a key written through the public API
+pub fn validate_public(key: &str) -> Result<()> {
+    validate(key)?;
+    if is_reserved(key) {
+        return Err(StateError::InvalidData(format!(
+            "sync key '{}' uses reserved prefix '{}'",
+            key, RESERVED_PREFIX
+        )));
+    }
+    Ok(())
+}
+
+/// Validate a key written through the internal API
+pub fn validate_internal(key: &str) -> Result<()> {
+    validate(key)?;
+    if !is_reserved(key) {
+        return Err(StateError::InvalidData(format!(
+            "internal sync key '{}' must start with '{}'",
+            key, RESERVED_PREFIX
+        )));
+    }
+    Ok(())
+}
+
+fn validate(key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Err(StateError::InvalidData("sync key must not be empty".into()));
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err(StateError::InvalidData(format!(
+            "sync key exceeds {} bytes",
+            MAX_KEY_LEN
+        )));
+    }
+    if key.chars().any(char::is_control) {
+        return Err(StateError::InvalidData(
+            "sync key must not contain control characters".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_validation() {
+        assert!(validate_public("app:settings").is_ok());
+        assert!(validate_public(SUBSCRIPTIONS).is_err());
+        assert!(validate_public("").is_err());
+        assert!(validate_public("bad\nkey").is_err());
+        assert!(validate_public(&"k".repeat(MAX_KEY_LEN + 1)).is_err());
+
+        assert!(validate_internal(SUBSCRIPTIONS).is_ok());
+        assert!(validate_internal(&format!("{}peer_1", LAST_SEEN_PREFIX)).is_ok());
+        assert!(validate_internal("app:settings").is_err());
+    }
+}
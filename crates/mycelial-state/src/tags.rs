@@ -0,0 +1,157 @@
+//! Peer tags
+//!
+//! Operators group peers with free-form tags such as "bootstrap", "blocked"
+//! or "friend". A peer can carry any number of tags, and its tags are
+//! removed along with it.
+
+use mycelial_core::{peer::PeerInfo, reputation::Reputation};
+use sqlx::Row;
+use tracing::debug;
+
+use crate::error::{Result, StateError};
+use crate::storage::SqliteStore;
+
+impl SqliteStore {
+    /// Tag a peer, returns false if it already had the tag
+    pub async fn add_tag(&self, peer_id: &str, tag: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO peer_tags (peer_id, tag)
+            SELECT peer_id, ? FROM peers WHERE peer_id = ?
+            ON CONFLICT(peer_id, tag) DO NOTHING
+            "#,
+        )
+        .bind(tag)
+        .bind(peer_id)
+        .execute(self.pool())
+        .await?;
+
+        if result.rows_affected() > 0 {
+            debug!("Tagged peer {} as {}", peer_id, tag);
+            return Ok(true);
+        }
+
+        let known = sqlx::query("SELECT 1 FROM peers WHERE peer_id = ?")
+            .bind(peer_id)
+            .fetch_optional(self.pool())
+            .await?;
+        match known {
+            Some(_) => Ok(false),
+            None => Err(StateError::NotFound {
+                entity: "peer".to_string(),
+                id: peer_id.to_string(),
+            }),
+        }
+    }
+
+    /// Remove a tag from a peer, returns false if it didn't have it
+    pub async fn remove_tag(&self, peer_id: &str, tag: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM peer_tags WHERE peer_id = ? AND tag = ?")
+            .bind(peer_id)
+            .bind(tag)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Tags on a peer, alphabetically
+    pub async fn peer_tags(&self, peer_id: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT tag FROM peer_tags WHERE peer_id = ? ORDER BY tag")
+            .bind(peer_id)
+            .fetch_all(self.pool())
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get("tag")).collect())
+    }
+
+    /// Peers carrying a tag, most recently seen first
+    pub async fn list_peers_by_tag(&self, tag: &str) -> Result<Vec<(PeerInfo, Reputation)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT p.peer_id, p.public_key, p.display_name, p.addresses_json, p.location_json,
+                   p.reputation_score, p.successful_interactions, p.failed_interactions,
+                   p.reputation_history_json, p.first_seen, p.last_seen, p.last_interaction_at
+            FROM peers p
+            JOIN peer_tags t ON t.peer_id = p.peer_id
+            WHERE t.tag = ?
+            ORDER BY p.last_seen DESC
+            "#,
+        )
+        .bind(tag)
+        .fetch_all(self.pool())
+        .await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            let peer_info = self.row_to_peer_info(&row)?;
+            let reputation = self.row_to_reputation(&row)?;
+            results.push((peer_info, reputation));
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use mycelial_core::peer::PeerId;
+
+    async fn store_with_peers(ids: &[&str]) -> SqliteStore {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        for id in ids {
+            let info = PeerInfo {
+                id: PeerId(id.to_string()),
+                public_key: id.to_string(),
+                addresses: vec![],
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+                name: None,
+            };
+            store.upsert_peer(&info, None).await.unwrap();
+        }
+        store
+    }
+
+    fn ids(peers: Vec<(PeerInfo, Reputation)>) -> Vec<String> {
+        let mut ids: Vec<String> = peers.into_iter().map(|(info, _)| info.id.0).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_tag_and_query() {
+        let store = store_with_peers(&["alice", "bob", "carol"]).await;
+
+        assert!(store.add_tag("alice", "friend").await.unwrap());
+        assert!(store.add_tag("bob", "friend").await.unwrap());
+        assert!(store.add_tag("bob", "bootstrap").await.unwrap());
+        assert!(!store.add_tag("bob", "friend").await.unwrap());
+
+        assert_eq!(ids(store.list_peers_by_tag("friend").await.unwrap()), vec!["alice", "bob"]);
+        assert_eq!(ids(store.list_peers_by_tag("bootstrap").await.unwrap()), vec!["bob"]);
+        assert!(store.list_peers_by_tag("blocked").await.unwrap().is_empty());
+        assert_eq!(store.peer_tags("bob").await.unwrap(), vec!["bootstrap", "friend"]);
+
+        assert!(store.remove_tag("alice", "friend").await.unwrap());
+        assert!(!store.remove_tag("alice", "friend").await.unwrap());
+        assert_eq!(ids(store.list_peers_by_tag("friend").await.unwrap()), vec!["bob"]);
+
+        assert!(matches!(
+            store.add_tag("nobody", "friend").await,
+            Err(StateError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tags_deleted_with_peer() {
+        let store = store_with_peers(&["alice"]).await;
+        store.add_tag("alice", "friend").await.unwrap();
+
+        store.delete_peer("alice").await.unwrap();
+        assert!(store.list_peers_by_tag("friend").await.unwrap().is_empty());
+        assert!(store.peer_tags("alice").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Vouch persistence and transitive trust
+//!
+//! A [`VouchRequest`] is stored as pending until the vouchee answers with a
+//! [`VouchAck`]. Accepted, unexpired vouches form a directed trust graph
+//! (voucher → vouchee). A peer nobody has interacted with yet can still be
+//! given some trust if a short chain of vouches leads to it from a peer we
+//! trust; [`SqliteStore::trust_path`] finds the shortest such chain and
+//! [`SqliteStore::transitive_trust_score`] turns it into a score.
+
+use chrono::Utc;
+use mycelial_protocol::{VouchAck, VouchRequest};
+use sqlx::Row;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use tracing::debug;
+
+use crate::error::Result;
+use crate::storage::SqliteStore;
+
+/// Longest vouch chain followed by [`SqliteStore::transitive_trust_score`]
+pub const MAX_TRUST_DEPTH: usize = 3;
+
+/// Factor applied to trust for every hop beyond a direct vouch
+pub const TRUST_DECAY_PER_HOP: f64 = 0.5;
+
+/// Trust graph: voucher -> (vouchee -> strongest stake)
+type TrustGraph = BTreeMap<String, BTreeMap<String, f64>>;
+
+impl SqliteStore {
+    /// Store a vouch request; re-announcements of a known vouch are ignored
+    pub async fn save_vouch(&self, vouch: &VouchRequest) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO vouches (
+                id, voucher_peer_id, vouchee_peer_id, stake, message, created_at, expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO NOTHING
+            "#,
+        )
+        .bind(vouch.id.to_string())
+        .bind(&vouch.voucher)
+        .bind(&vouch.vouchee)
+        .bind(vouch.stake.clamp(0.0, 1.0))
+        .bind(&vouch.message)
+        .bind(vouch.timestamp.timestamp())
+        .bind(vouch.expires_at.map(|at| at.timestamp()))
+        .execute(self.pool())
+        .await?;
+
+        debug!("Saved vouch {} from {} for {}", vouch.id, vouch.voucher, vouch.vouchee);
+        Ok(())
+    }
+
+    /// Apply a vouchee's answer to a pending vouch, returns false if ignored
+    ///
+    /// Only the vouchee can accept or reject a vouch, and only once.
+    pub async fn record_vouch_ack(&self, ack: &VouchAck) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE vouches SET status = ?
+            WHERE id = ? AND vouchee_peer_id = ? AND status = 'pending'
+            "#,
+        )
+        .bind(if ack.accepted { "accepted" } else { "rejected" })
+        .bind(ack.vouch_id.to_string())
+        .bind(&ack.from)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Shortest chain of accepted vouches from `from` to `to`, both included
+    ///
+    /// Returns None if `to` can't be reached within `max_depth` vouches.
+    pub async fn trust_path(&self, from: &str, to: &str, max_depth: usize) -> Result<Option<Vec<String>>> {
+        let graph = self.trust_graph().await?;
+        Ok(shortest_path(&graph, from, to, max_depth))
+    }
+
+    /// Trust `from` places in `to` through vouches, from 0.0 to 1.0
+    ///
+    /// The stakes along the shortest vouch chain are multiplied, then
+    /// discounted by [`TRUST_DECAY_PER_HOP`] for every hop beyond the first,
+    /// so a direct vouch is worth its stake and longer chains quickly fade.
+    /// Unreachable peers (within [`MAX_TRUST_DEPTH`]) score 0.0.
+    pub async fn transitive_trust_score(&self, from: &str, to: &str) -> Result<f64> {
+        let graph = self.trust_graph().await?;
+        let Some(path) = shortest_path(&graph, from, to, MAX_TRUST_DEPTH) else {
+            return Ok(0.0);
+        };
+        if path.len() < 2 {
+            return Ok(1.0);
+        }
+
+        let stakes: f64 = path
+            .windows(2)
+            .map(|hop| graph[&hop[0]][&hop[1]])
+            .product();
+        let hops = (path.len() - 1) as i32;
+        Ok(stakes * TRUST_DECAY_PER_HOP.powi(hops - 1))
+    }
+
+    /// Load accepted, unexpired vouches, keeping the strongest per pair
+    async fn trust_graph(&self) -> Result<TrustGraph> {
+        let rows = sqlx::query(
+            r#"
+            SELECT voucher_peer_id, vouchee_peer_id, MAX(stake) AS stake
+            FROM vouches
+            WHERE status = 'accepted' AND (expires_at IS NULL OR expires_at > ?)
+            GROUP BY voucher_peer_id, vouchee_peer_id
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .fetch_all(self.pool())
+        .await?;
+
+        let mut graph = TrustGraph::new();
+        for row in rows {
+            graph
+                .entry(row.get("voucher_peer_id"))
+                .or_default()
+                .insert(row.get("vouchee_peer_id"), row.get("stake"));
+        }
+        Ok(graph)
+    }
+}
+
+/// Breadth-first search for the shortest path of at most `max_depth` edges
+fn shortest_path(graph: &TrustGraph, from: &str, to: &str, max_depth: usize) -> Option<Vec<String>> {
+    if from == to {
+        return Some(vec![from.to_string()]);
+    }
+
+    // Each visited peer maps to the peer it was reached from
+    let mut parents: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([(from, 0)]);
+    while let Some((peer, depth)) = queue.pop_front() {
+        if depth == max_depth {
+            continue;
+        }
+        for next in graph.get(peer).into_iter().flat_map(|edges| edges.keys()) {
+            let next = next.as_str();
+            if next == from || parents.contains_key(next) {
+                continue;
+            }
+            parents.insert(next, peer);
+            if next == to {
+                let mut path = vec![to.to_string()];
+                let mut current = to;
+                while let Some(&parent) = parents.get(current) {
+                    path.push(parent.to_string());
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            queue.push_back((next, depth + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    async fn vouch(store: &SqliteStore, voucher: &str, vouchee: &str, stake: f64) -> VouchRequest {
+        let request = VouchRequest::new(voucher.to_string(), vouchee.to_string(), stake);
+        store.save_vouch(&request).await.unwrap();
+        request
+    }
+
+    fn ack(request: &VouchRequest, from: &str, accepted: bool) -> VouchAck {
+        VouchAck {
+            vouch_id: request.id,
+            from: from.to_string(),
+            accepted,
+            reason: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn accepted(store: &SqliteStore, voucher: &str, vouchee: &str, stake: f64) {
+        let request = vouch(store, voucher, vouchee, stake).await;
+        assert!(store.record_vouch_ack(&ack(&request, vouchee, true)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_direct_vouch() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+
+        // Pending vouches carry no trust, and only the vouchee can accept
+        let request = vouch(&store, "alice", "bob", 0.8).await;
+        assert_eq!(store.trust_path("alice", "bob", 3).await.unwrap(), None);
+        assert!(!store.record_vouch_ack(&ack(&request, "mallory", true)).await.unwrap());
+        assert!(store.record_vouch_ack(&ack(&request, "bob", true)).await.unwrap());
+        assert!(!store.record_vouch_ack(&ack(&request, "bob", false)).await.unwrap());
+
+        assert_eq!(
+            store.trust_path("alice", "bob", 3).await.unwrap(),
+            Some(vec!["alice".to_string(), "bob".to_string()])
+        );
+        // Vouches are directed
+        assert_eq!(store.trust_path("bob", "alice", 3).await.unwrap(), None);
+        assert!((store.transitive_trust_score("alice", "bob").await.unwrap() - 0.8).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_two_hop_trust_path() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        accepted(&store, "alice", "bob", 0.8).await;
+        accepted(&store, "bob", "carol", 0.5).await;
+        accepted(&store, "alice", "dave", 0.9).await;
+        accepted(&store, "dave", "erin", 0.9).await;
+        accepted(&store, "erin", "carol", 0.9).await;
+
+        // The shortest chain wins over a longer, stronger one
+        assert_eq!(
+            store.trust_path("alice", "carol", 3).await.unwrap(),
+            Some(vec!["alice".to_string(), "bob".to_string(), "carol".to_string()])
+        );
+        assert_eq!(store.trust_path("alice", "carol", 1).await.unwrap(), None);
+
+        // 0.8 * 0.5, halved for the extra hop
+        let score = store.transitive_trust_score("alice", "carol").await.unwrap();
+        assert!((score - 0.2).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_target() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        accepted(&store, "alice", "bob", 0.8).await;
+
+        let rejected = vouch(&store, "bob", "carol", 0.8).await;
+        store.record_vouch_ack(&ack(&rejected, "carol", false)).await.unwrap();
+        let expired = VouchRequest::new("bob".to_string(), "dave".to_string(), 0.8)
+            .with_expiration(Utc::now() - Duration::hours(1));
+        store.save_vouch(&expired).await.unwrap();
+        store.record_vouch_ack(&ack(&expired, "dave", true)).await.unwrap();
+
+        for target in ["carol", "dave", "nobody"] {
+            assert_eq!(store.trust_path("alice", target, 3).await.unwrap(), None);
+            assert_eq!(store.transitive_trust_score("alice", target).await.unwrap(), 0.0);
+        }
+    }
+}
@@ -3,7 +3,7 @@ import type { ChatMessage, Conversation } from '@/types';
 
 interface ChatPanelProps {
   messages: ChatMessage[];
-  onSendMessage: (content: string, to?: string, roomId?: string) => void;
+  onSendMessage: (content: string, to?: string | string[], roomId?: string) => void;
   activeConversation?: Conversation;
   selectedPeer?: string | null;
 }
@@ -22,12 +22,14 @@ export function ChatPanel({ messages, onSendMessage, activeConversation, selecte
     if (!input.trim()) return;
 
     // Determine target based on active conversation
-    let to: string | undefined;
+    let to: string | string[] | undefined;
     let roomId: string | undefined;
 
     if (activeConversation) {
       if (activeConversation.type === 'dm' && activeConversation.peerId) {
         to = activeConversation.peerId;
+      } else if (activeConversation.type === 'group' && activeConversation.memberIds) {
+        to = activeConversation.memberIds;
       } else if (activeConversation.type === 'room' && activeConversation.roomId) {
         roomId = activeConversation.roomId;
       }
@@ -57,6 +59,8 @@ export function ChatPanel({ messages, onSendMessage, activeConversation, selecte
           return { title: 'Community Chat', subtitle: 'Broadcasting to all peers' };
         case 'dm':
           return { title: activeConversation.name, subtitle: 'Direct message' };
+        case 'group':
+          return { title: activeConversation.name, subtitle: 'Group message' };
         case 'room':
           return { title: activeConversation.name, subtitle: 'Room chat' };
       }
@@ -77,6 +81,8 @@ export function ChatPanel({ messages, onSendMessage, activeConversation, selecte
           return 'Broadcast to community...';
         case 'dm':
           return `Message ${activeConversation.name}...`;
+        case 'group':
+          return 'Message the group...';
         case 'room':
           return `Message in ${activeConversation.name}...`;
       }
@@ -115,9 +121,9 @@ export function ChatPanel({ messages, onSendMessage, activeConversation, selecte
                 <span className="text-xs text-soft-gray font-mono">
                   {formatTime(msg.timestamp)}
                 </span>
-                {msg.to && activeConversation?.type !== 'dm' && (
+                {msg.to && activeConversation?.type !== 'dm' && activeConversation?.type !== 'group' && (
                   <span className="text-xs text-spore-purple font-mono">
-                    → {msg.to.slice(0, 8)}
+                    → {Array.isArray(msg.to) ? `${msg.to.length} members` : msg.to.slice(0, 8)}
                   </span>
                 )}
                 {msg.room_id && activeConversation?.type !== 'room' && (
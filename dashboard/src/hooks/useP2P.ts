@@ -13,6 +13,7 @@ import type {
   ResourceContribution,
   ResourcePool,
   Conversation,
+  ConversationType,
   Room,
 } from '@/types';
 
@@ -49,6 +50,10 @@ function getConversationId(msg: ChatMessage, _localPeerId: string | null): strin
   if (msg.room_id) {
     return `room:${msg.room_id}`;
   }
+  if (Array.isArray(msg.to)) {
+    // Group conversation - every member, sender included, sorted
+    return `group:${groupMembers(msg).join(',')}`;
+  }
   if (msg.to) {
     // DM conversation - use sorted peer IDs to ensure consistency
     const peers = [msg.from, msg.to].sort();
@@ -58,6 +63,12 @@ function getConversationId(msg: ChatMessage, _localPeerId: string | null): strin
   return COMMUNITY_CONVERSATION_ID;
 }
 
+// Every member of a group message's group, sorted
+function groupMembers(msg: ChatMessage): string[] {
+  const recipients = Array.isArray(msg.to) ? msg.to : [];
+  return [...new Set([msg.from, ...recipients])].sort();
+}
+
 // Helper to create or update conversation from message
 function updateConversation(
   conversations: Map<string, Conversation>,
@@ -71,13 +82,18 @@ function updateConversation(
 
   let name = 'Community';
   let peerId: string | undefined;
+  let memberIds: string[] | undefined;
   let roomId: string | undefined;
-  let type: 'community' | 'dm' | 'room' = 'community';
+  let type: ConversationType = 'community';
 
   if (msg.room_id) {
     type = 'room';
     roomId = msg.room_id;
     name = `Room: ${msg.room_id}`;
+  } else if (Array.isArray(msg.to)) {
+    type = 'group';
+    memberIds = groupMembers(msg).filter(id => id !== localPeerId);
+    name = `Group: ${memberIds.map(id => peers.get(id)?.name || id.slice(0, 8)).join(', ')}`;
   } else if (msg.to) {
     type = 'dm';
     // Get the other peer's ID (not our own)
@@ -92,6 +108,7 @@ function updateConversation(
     type,
     name,
     peerId,
+    memberIds,
     roomId,
     lastMessage: msg,
     unreadCount: existing ? (isActive ? 0 : existing.unreadCount + 1) : (isActive ? 0 : 1),
@@ -393,6 +410,27 @@ export function useP2P(options: UseP2POptions = {}) {
         break;
       }
 
+      case 'proposal_decided': {
+        // Only the outcome is sent; keep the rest of the proposal as it is
+        const decided = message as {
+          id: string;
+          status: 'passed' | 'failed' | 'expired';
+          votes_for: number;
+          votes_against: number;
+        };
+        const status: Proposal['status'] = decided.status === 'failed' ? 'rejected' : decided.status;
+        setState(s => ({
+          ...s,
+          proposals: s.proposals.map(p =>
+            p.id === decided.id
+              ? { ...p, status, votesFor: decided.votes_for, votesAgainst: decided.votes_against }
+              : p
+          ),
+        }));
+        console.log('Proposal decided:', decided);
+        break;
+      }
+
       case 'vote_cast': {
         const vote = (message.data || message) as Vote;
         // Update the proposal's vote counts
@@ -517,7 +555,7 @@ export function useP2P(options: UseP2POptions = {}) {
   }, [fetchInfo, fetchPeers, handleMessage]);
 
   // Send chat message (to DM, room, or community)
-  const sendChat = useCallback((content: string, to?: string, roomId?: string) => {
+  const sendChat = useCallback((content: string, to?: string | string[], roomId?: string) => {
     console.log('sendChat called:', { content, to, roomId, readyState: wsRef.current?.readyState });
 
     if (wsRef.current?.readyState !== WebSocket.OPEN) {
@@ -670,6 +708,11 @@ export function useP2P(options: UseP2POptions = {}) {
       );
     }
 
+    if (convId.startsWith('group:')) {
+      // Group: show messages within the same set of members
+      return state.messages.filter(m => getConversationId(m, state.localPeerId) === convId);
+    }
+
     if (convId.startsWith('room:')) {
       // Room: show messages with matching room_id
       const roomId = convId.replace('room:', '');
@@ -677,7 +720,7 @@ export function useP2P(options: UseP2POptions = {}) {
     }
 
     return [];
-  }, [state.activeConversationId, state.messages]);
+  }, [state.activeConversationId, state.messages, state.localPeerId]);
 
   // Send vouch request
   const sendVouch = useCallback((request: VouchRequest) => {
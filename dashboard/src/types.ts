@@ -35,20 +35,22 @@ export interface ChatMessage {
   id: string;
   from: string;
   from_name?: string;
-  to?: string;
+  // One peer ID for a direct message, every other member's for a group
+  to?: string | string[];
   room_id?: string;
   content: string;
   timestamp: number;
 }
 
 // Conversation types for enhanced chat
-export type ConversationType = 'community' | 'dm' | 'room';
+export type ConversationType = 'community' | 'dm' | 'group' | 'room';
 
 export interface Conversation {
   id: string;
   type: ConversationType;
   name: string;
   peerId?: string; // For DMs - the other peer's ID
+  memberIds?: string[]; // For groups - every member but us
   roomId?: string; // For rooms - the room ID
   lastMessage?: ChatMessage;
   unreadCount: number;